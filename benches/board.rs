@@ -0,0 +1,112 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use glyphon::FontSystem;
+use rust_whiteboard::{
+    board::Board, smoothing, text_layout, Action, BlendMode, Brush, Stroke, StrokeLod, TextEntries,
+    Vertex,
+};
+use tao::dpi::PhysicalSize;
+
+const SIZE: PhysicalSize<u32> = PhysicalSize::new(1920, 1080);
+
+// A long wavy stroke, sampled the way a fast pen reports it.
+fn wave(points: usize) -> Vec<Vertex> {
+    (0..points)
+        .map(|index| {
+            let t = index as f32 / points as f32;
+            Vertex {
+                position: [t * 1.8 - 0.9, (t * 40.0).sin() * 0.5],
+                color: [0.0, 0.0, 0.0, 1.0],
+            }
+        })
+        .collect()
+}
+
+fn stroke_tessellation(c: &mut Criterion) {
+    let points = wave(1_000);
+    let timestamps: Vec<u64> = (0..points.len() as u64).collect();
+    c.bench_function("smooth 1k points", |b| {
+        b.iter(|| smoothing::smooth(black_box(&points), &timestamps, SIZE))
+    });
+}
+
+fn ribbon_generation(c: &mut Criterion) {
+    let points = wave(10_000);
+    let mut vertices = Vec::new();
+    c.bench_function("ribbon 10k points", |b| {
+        b.iter(|| {
+            vertices.clear();
+            Stroke::ribbon_vertices(
+                black_box(&points),
+                4.0,
+                [20.0; 2],
+                None,
+                SIZE,
+                &mut vertices,
+            );
+        })
+    });
+    c.bench_function("calligraphy ribbon 10k points", |b| {
+        b.iter(|| {
+            vertices.clear();
+            Stroke::ribbon_vertices(
+                black_box(&points),
+                4.0,
+                [0.0; 2],
+                Some(0.8),
+                SIZE,
+                &mut vertices,
+            );
+        })
+    });
+}
+
+fn text_shaping(c: &mut Criterion) {
+    let mut font_system = FontSystem::new();
+    let mut text = TextEntries::null([0, 0, 0, 255], 24);
+    text.text = "The quick brown fox jumps over the lazy dog. ".repeat(20);
+    text.wrap_width = Some(600.0);
+    c.bench_function("shape a wrapped paragraph", |b| {
+        b.iter(|| text_layout(&mut font_system, black_box(&text), SIZE))
+    });
+    text.markdown = true;
+    text.text = "# Notes\n\n- **bold** point\n- *italic* point\n\n".repeat(10);
+    c.bench_function("shape markdown", |b| {
+        b.iter(|| text_layout(&mut font_system, black_box(&text), SIZE))
+    });
+}
+
+fn scene_serialization(c: &mut Criterion) {
+    let mut board = Board::new(SIZE);
+    for index in 0..200 {
+        let mut points = wave(100);
+        for point in &mut points {
+            point.position[1] += index as f32 / 200.0 - 0.5;
+        }
+        board.push_action(Action::Stroke(Stroke {
+            timestamps: (0..points.len() as u64).collect(),
+            points,
+            blend: BlendMode::Normal,
+            brush: Brush::Pen,
+            width: 4.0,
+            layer: 0,
+            lod: StrokeLod::default(),
+        }));
+    }
+    let pages = board.page_scenes();
+    let json = serde_json::to_string(&pages).unwrap();
+    c.bench_function("serialize 200 strokes", |b| {
+        b.iter(|| serde_json::to_string(black_box(&pages)).unwrap())
+    });
+    c.bench_function("deserialize 200 strokes", |b| {
+        b.iter(|| serde_json::from_str::<Vec<Vec<Action>>>(black_box(&json)).unwrap())
+    });
+}
+
+criterion_group!(
+    benches,
+    stroke_tessellation,
+    ribbon_generation,
+    text_shaping,
+    scene_serialization
+);
+criterion_main!(benches);
@@ -0,0 +1,71 @@
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
+
+pub const CARET_BLINK_INTERVAL: Duration = Duration::from_millis(500);
+pub const LASER_FRAME: Duration = Duration::from_millis(16);
+pub const PAGE_TURN_FRAME: Duration = Duration::from_millis(16);
+pub const PAGE_TURN_DURATION: Duration = Duration::from_millis(250);
+pub const AUTO_SCROLL_FRAME: Duration = Duration::from_millis(16);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Animation {
+    CaretBlink,
+    Interface,
+    Replay,
+    SessionTimer,
+    LecturePage,
+    Flipbook,
+    BrushIndicator,
+    FontScan,
+    Laser,
+    PageTurn,
+    AutoScroll,
+    StrokeHold,
+    StylusTap,
+    Ocr,
+    Bridge,
+    #[cfg(feature = "remote-api")]
+    Remote,
+}
+
+#[derive(Default)]
+pub struct AnimationClock {
+    deadlines: HashMap<Animation, Instant>,
+}
+
+impl AnimationClock {
+    pub fn schedule(&mut self, animation: Animation, delay: Duration) {
+        let deadline = Instant::now() + delay;
+        self.deadlines
+            .entry(animation)
+            .and_modify(|current| *current = (*current).min(deadline))
+            .or_insert(deadline);
+    }
+
+    pub fn cancel(&mut self, animation: Animation) {
+        self.deadlines.remove(&animation);
+    }
+
+    pub fn is_scheduled(&self, animation: Animation) -> bool {
+        self.deadlines.contains_key(&animation)
+    }
+
+    pub fn take_due(&mut self, now: Instant) -> Vec<Animation> {
+        let due: Vec<_> = self
+            .deadlines
+            .iter()
+            .filter(|(_, deadline)| **deadline <= now)
+            .map(|(animation, _)| *animation)
+            .collect();
+        for animation in &due {
+            self.deadlines.remove(animation);
+        }
+        due
+    }
+
+    pub fn next_deadline(&self) -> Option<Instant> {
+        self.deadlines.values().min().copied()
+    }
+}
@@ -0,0 +1,132 @@
+use serde::{Deserialize, Serialize};
+
+pub const PRESETS: [(&str, [u8; 4]); 3] = [
+    ("White", [255, 255, 255, 255]),
+    ("Black", [0, 0, 0, 255]),
+    ("Dark gray", [48, 48, 48, 255]),
+];
+// Board pixels between grid lines or dots at 100% zoom.
+const SPACING: f32 = 32.0;
+// Zoomed out further than this, every other line is skipped.
+const MIN_SCREEN_SPACING: f32 = 12.0;
+const PATTERN_ALPHA: f32 = 0.15;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Paper {
+    #[default]
+    Plain,
+    Grid,
+    Dots,
+}
+
+impl Paper {
+    pub const ALL: [Paper; 3] = [Paper::Plain, Paper::Grid, Paper::Dots];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Paper::Plain => "Plain",
+            Paper::Grid => "Grid",
+            Paper::Dots => "Dotted",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Background {
+    pub color: [u8; 4],
+    pub paper: Paper,
+    // The board is an endless roll of paper that only scrolls up and down.
+    pub roll: bool,
+}
+
+impl Default for Background {
+    fn default() -> Self {
+        Background {
+            color: PRESETS[0].1,
+            paper: Paper::Plain,
+            roll: false,
+        }
+    }
+}
+
+impl Background {
+    pub fn normalized(&self) -> [f32; 4] {
+        self.color.map(|channel| channel as f32 / 255.0)
+    }
+
+    // The paper pattern in the color that contrasts with the background, faded
+    // so it stays behind the ink.
+    pub fn pattern_color(&self) -> [f32; 4] {
+        let [red, green, blue, _] = crate::contrasting_color(self.color);
+        let [red, green, blue] = [red, green, blue].map(|channel| channel as f32 / 255.0);
+        [red, green, blue, PATTERN_ALPHA]
+    }
+
+    // Triangles in board pixels that draw the grid lines or dots over `area`,
+    // one window pixel thick at `zoom`.
+    pub fn pattern(&self, area: [[f32; 2]; 2], zoom: f32) -> Vec<[f32; 2]> {
+        if self.paper == Paper::Plain {
+            return Vec::new();
+        }
+        let mut spacing = SPACING;
+        while spacing * zoom < MIN_SCREEN_SPACING {
+            spacing *= 2.0;
+        }
+        let ticks = |from: f32, to: f32| {
+            let first = (from / spacing).floor() as i64;
+            let last = (to / spacing).ceil() as i64;
+            (first..=last).map(move |tick| tick as f32 * spacing)
+        };
+        let quad = |x1: f32, y1: f32, x2: f32, y2: f32| {
+            [[x1, y1], [x2, y1], [x2, y2], [x1, y1], [x2, y2], [x1, y2]]
+        };
+        let [[left, top], [right, bottom]] = area;
+        let width = 1.0 / zoom;
+
+        let mut triangles = Vec::new();
+        match self.paper {
+            Paper::Grid => {
+                for x in ticks(left, right) {
+                    triangles.extend(quad(x, top, x + width, bottom));
+                }
+                for y in ticks(top, bottom) {
+                    triangles.extend(quad(left, y, right, y + width));
+                }
+            }
+            Paper::Dots => {
+                let size = width * 2.0;
+                for y in ticks(top, bottom) {
+                    for x in ticks(left, right) {
+                        triangles.extend(quad(x, y, x + size, y + size));
+                    }
+                }
+            }
+            Paper::Plain => {}
+        }
+        triangles
+    }
+}
+
+// Color presets, a custom color and the paper pattern.
+pub fn menu(ui: &mut egui::Ui, background: &mut Background) {
+    for (name, color) in PRESETS {
+        if ui
+            .selectable_label(background.color == color, name)
+            .clicked()
+        {
+            background.color = color;
+        }
+    }
+    ui.horizontal(|ui| {
+        ui.label("Custom");
+        ui.color_edit_button_srgba_unmultiplied(&mut background.color);
+    });
+    ui.separator();
+    for paper in Paper::ALL {
+        ui.radio_value(&mut background.paper, paper, paper.label());
+    }
+    ui.separator();
+    ui.checkbox(&mut background.roll, "Paper roll")
+        .on_hover_text("Scroll only up and down, with the board as wide as the window");
+}
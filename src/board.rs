@@ -0,0 +1,1271 @@
+use crate::{
+    camera::Camera,
+    layers::{self, Layer},
+    ndc_to_screen, normalized_to_rgba,
+    pages::{self, Page, PageLabel},
+    points_bounds, redact_code, redact_image, redact_stroke, redact_table, redact_text,
+    screen_to_ndc,
+    settings::{HISTORY_MAX_BYTES, HISTORY_MAX_ENTRIES},
+    Action, BoardImage, CodeBlock, Connector, Erased, Fill, ImageAdjustment, ImageEdit, LabelEdit,
+    LayerEdit, ObjectRef, ObjectStyle, Rect, Rectangle, Stencil, Stroke, StrokeLod, Table,
+    TextEntries,
+};
+use tao::dpi::PhysicalSize;
+
+// The drawing model: what is on the page being shown, the undo history that
+// built it and the pages that are not on screen. It needs no window, so it can
+// be driven from tests and tools as well as from the event loop.
+pub struct Board {
+    // Strokes and rectangles are kept in NDC of this size.
+    pub size: PhysicalSize<u32>,
+    pub camera: Camera,
+    pub strokes: Vec<Stroke>,
+    pub texts: Vec<TextEntries>,
+    pub shapes: Vec<Rectangle>,
+    pub images: Vec<BoardImage>,
+    pub fills: Vec<Fill>,
+    pub tables: Vec<Table>,
+    pub code_blocks: Vec<CodeBlock>,
+    pub stencils: Vec<Stencil>,
+    pub connectors: Vec<Connector>,
+    pub redactions: Vec<Rect>,
+    pub layers: Vec<Layer>,
+    pub current_layer: u32,
+    pub label: PageLabel,
+    pub actions: Vec<Action>,
+    pub redo_actions: Vec<Action>,
+    pub pages: Vec<Page>,
+    pub current_page: usize,
+    pub history_max_entries: usize,
+    pub history_max_bytes: usize,
+}
+
+impl Board {
+    pub fn new(size: PhysicalSize<u32>) -> Self {
+        Board {
+            size,
+            camera: Camera::default(),
+            strokes: Vec::new(),
+            texts: Vec::new(),
+            shapes: Vec::new(),
+            images: Vec::new(),
+            fills: Vec::new(),
+            tables: Vec::new(),
+            code_blocks: Vec::new(),
+            stencils: Vec::new(),
+            connectors: Vec::new(),
+            redactions: Vec::new(),
+            layers: layers::default_layers(),
+            current_layer: 0,
+            label: PageLabel::default(),
+            actions: Vec::new(),
+            redo_actions: Vec::new(),
+            pages: vec![Page::default()],
+            current_page: 0,
+            history_max_entries: HISTORY_MAX_ENTRIES,
+            history_max_bytes: HISTORY_MAX_BYTES,
+        }
+    }
+
+    pub fn push_action(&mut self, action: Action) {
+        self.redo_actions.clear();
+        self.apply_action(action);
+    }
+
+    // Adds a new object to the board on the layer being drawn on.
+    pub fn push_object(&mut self, mut action: Action) {
+        action.set_layer(self.current_layer);
+        self.push_action(action);
+    }
+
+    pub fn set_layers(&mut self, layers: Vec<Layer>) {
+        self.layers = match layers.is_empty() {
+            true => layers::default_layers(),
+            false => layers,
+        };
+        if !self
+            .layers
+            .iter()
+            .any(|layer| layer.id == self.current_layer)
+        {
+            self.current_layer = self.layers[self.layers.len() - 1].id;
+        }
+    }
+
+    pub fn edit_layers(&mut self, layers: Vec<Layer>) {
+        if layers != self.layers {
+            let from = self.layers.clone();
+            self.push_action(Action::Layers(LayerEdit { from, to: layers }));
+        }
+    }
+
+    pub fn edit_label(&mut self, label: PageLabel) {
+        if label != self.label {
+            let from = self.label.clone();
+            self.push_action(Action::Label(LabelEdit { from, to: label }));
+        }
+    }
+
+    // The label of every page, the shown one included.
+    pub fn page_labels(&self) -> Vec<PageLabel> {
+        self.pages
+            .iter()
+            .enumerate()
+            .map(|(index, page)| match index == self.current_page {
+                true => self.label.clone(),
+                false => pages::label(&page.scene),
+            })
+            .collect()
+    }
+
+    pub fn object_layer(&self, object: ObjectRef) -> Option<u32> {
+        Some(match object {
+            ObjectRef::Stroke(index) => self.strokes.get(index)?.layer,
+            ObjectRef::Shape(index) => self.shapes.get(index)?.layer,
+            ObjectRef::Text(index) => self.texts.get(index)?.layer,
+            ObjectRef::Table(index) => self.tables.get(index)?.layer,
+            ObjectRef::Code(index) => self.code_blocks.get(index)?.layer,
+            ObjectRef::Stencil(index) => self.stencils.get(index)?.layer,
+            ObjectRef::Image(index) => self.images.get(index)?.layer,
+            ObjectRef::Fill(index) => self.fills.get(index)?.layer,
+            // Connectors sit on the layer of the stencil they start from.
+            ObjectRef::Connector(index) => {
+                self.stencils.get(self.connectors.get(index)?.from)?.layer
+            }
+        })
+    }
+
+    pub fn set_object_layer(&mut self, object: ObjectRef, layer: u32) {
+        let current = match object {
+            ObjectRef::Stroke(index) => self.strokes.get_mut(index).map(|o| &mut o.layer),
+            ObjectRef::Shape(index) => self.shapes.get_mut(index).map(|o| &mut o.layer),
+            ObjectRef::Text(index) => self.texts.get_mut(index).map(|o| &mut o.layer),
+            ObjectRef::Table(index) => self.tables.get_mut(index).map(|o| &mut o.layer),
+            ObjectRef::Code(index) => self.code_blocks.get_mut(index).map(|o| &mut o.layer),
+            ObjectRef::Stencil(index) => self.stencils.get_mut(index).map(|o| &mut o.layer),
+            ObjectRef::Image(index) => self.images.get_mut(index).map(|o| &mut o.layer),
+            ObjectRef::Fill(index) => self.fills.get_mut(index).map(|o| &mut o.layer),
+            ObjectRef::Connector(_) => None,
+        };
+        if let Some(current) = current {
+            *current = layer;
+        }
+    }
+
+    // Where the object's layer sits in the stack, or None while it is hidden.
+    // Objects left on a deleted layer stay in the scene, hidden, so undoing
+    // the deletion brings them back.
+    pub fn layer_rank(&self, object: ObjectRef) -> Option<usize> {
+        let layer = self.object_layer(object)?;
+        self.layers
+            .iter()
+            .position(|candidate| candidate.id == layer)
+            .filter(|&rank| self.layers[rank].visible)
+    }
+
+    // Applies several edits as one undo step, objects keeping their layers,
+    // and returns the objects they added.
+    pub fn push_group(&mut self, actions: Vec<Action>) -> Vec<ObjectRef> {
+        self.redo_actions.clear();
+        let mut added = Vec::new();
+        let mut applied = Vec::new();
+        for action in actions {
+            let object = self.added_object(&action);
+            if let Some(action) = self.apply(action) {
+                added.extend(object);
+                applied.push(action);
+            }
+        }
+        if !applied.is_empty() {
+            self.actions.push(Action::Group(applied));
+            self.compact_history();
+        }
+        added
+    }
+
+    pub fn apply_action(&mut self, action: Action) {
+        if let Some(action) = self.apply(action) {
+            self.actions.push(action);
+            self.compact_history();
+        }
+    }
+
+    // Applies the action and returns what undo needs to take it back, or None
+    // when it stays out of the history.
+    fn apply(&mut self, mut action: Action) -> Option<Action> {
+        match &mut action {
+            Action::Stroke(stroke) => {
+                let mut stroke = stroke.clone();
+                stroke.lod = StrokeLod::new(&stroke.points, self.size);
+                self.strokes.push(stroke);
+            }
+            Action::Text(text) => self.texts.push(text.clone()),
+            Action::Shapes(rectangle) => self.shapes.push(*rectangle),
+            Action::Table(table) => self.tables.push(table.clone()),
+            Action::Code(code_block) => self.code_blocks.push(code_block.clone()),
+            Action::Stencil(stencil) => self.stencils.push(stencil.clone()),
+            Action::Connector(connector) => self.connectors.push(connector.clone()),
+            Action::Arrange(moves) => {
+                for stencil_move in moves {
+                    if let Some(stencil) = self.stencils.get_mut(stencil_move.index) {
+                        stencil.position = stencil_move.to;
+                    }
+                }
+            }
+            Action::Restyle(restyle) => self.apply_style(restyle.object, &restyle.to),
+            Action::Erase(erased) => match self.remove_object(erased.object) {
+                Some(content) => erased.content = Some(Box::new(content)),
+                None => return None,
+            },
+            Action::Move(object_move) => {
+                for &object in &object_move.objects {
+                    self.translate_object(object, object_move.delta);
+                }
+            }
+            Action::Resize(resize) => self.set_object_corners(resize.object, resize.to),
+            Action::Image(image) => self.images.push(image.clone()),
+            Action::Layers(edit) => self.set_layers(edit.to.clone()),
+            Action::Label(edit) => self.label = edit.to.clone(),
+            Action::Retext(edit) => self.set_object_text(edit.object, &edit.to),
+            Action::Relayer(layer_move) => {
+                for &object in &layer_move.objects {
+                    self.set_object_layer(object, layer_move.to);
+                }
+            }
+            Action::Retouch(edit) => self.swap_image_data(edit),
+            Action::Adjust(adjustment) => self.swap_image_adjust(adjustment),
+            Action::Fill(fill) => self.fills.push(fill.clone()),
+            Action::Redact(area) => {
+                // The box is drawn over content that was cut when the redaction
+                // was made, so replaying a scene only puts the box back and
+                // leaves anything added since alone. Redactions are permanent
+                // and never enter the undo history.
+                self.redactions.push(area.clone());
+                return None;
+            }
+            Action::Group(parts) => {
+                let applied: Vec<Action> = std::mem::take(parts)
+                    .into_iter()
+                    .filter_map(|part| self.apply(part))
+                    .collect();
+                if applied.is_empty() {
+                    return None;
+                }
+                *parts = applied;
+            }
+        }
+        Some(action)
+    }
+
+    pub fn scene_actions(&self) -> Vec<Action> {
+        let layers = Action::Layers(LayerEdit {
+            from: Vec::new(),
+            to: self.layers.clone(),
+        });
+        // Unlabeled pages leave the label out, so blank pages stay blank.
+        let label = (self.label != PageLabel::default()).then(|| {
+            Action::Label(LabelEdit {
+                from: PageLabel::default(),
+                to: self.label.clone(),
+            })
+        });
+        std::iter::once(layers)
+            .chain(label)
+            .chain(self.strokes.iter().cloned().map(Action::Stroke))
+            .chain(self.texts.iter().cloned().map(Action::Text))
+            .chain(self.shapes.iter().copied().map(Action::Shapes))
+            .chain(self.images.iter().cloned().map(Action::Image))
+            .chain(self.fills.iter().cloned().map(Action::Fill))
+            .chain(self.tables.iter().cloned().map(Action::Table))
+            .chain(self.code_blocks.iter().cloned().map(Action::Code))
+            .chain(self.stencils.iter().cloned().map(Action::Stencil))
+            .chain(self.connectors.iter().cloned().map(Action::Connector))
+            .chain(self.redactions.iter().cloned().map(Action::Redact))
+            .collect()
+    }
+
+    pub fn replace_pages(&mut self, pages: Vec<Vec<Action>>) {
+        self.pages = pages.into_iter().map(Page::from_scene).collect();
+        self.current_page = 0;
+        let first = std::mem::take(&mut self.pages[0]);
+        self.restore_page(first);
+    }
+
+    // Recovered pages replace a blank board and otherwise follow its pages.
+    pub fn recover_pages(&mut self, pages: Vec<Vec<Action>>) {
+        let blank = self
+            .page_scenes()
+            .iter()
+            .flatten()
+            .all(|action| matches!(action, Action::Layers(_)));
+        if blank {
+            self.replace_pages(pages);
+            return;
+        }
+        let first = self.pages.len();
+        self.pages.extend(pages.into_iter().map(Page::from_scene));
+        self.show_page(first);
+    }
+
+    pub fn page_scenes(&self) -> Vec<Vec<Action>> {
+        self.pages
+            .iter()
+            .enumerate()
+            .map(|(index, page)| match index == self.current_page {
+                true => self.scene_actions(),
+                false => page.scene.clone(),
+            })
+            .collect()
+    }
+
+    pub fn stash_page(&mut self) -> Page {
+        Page {
+            scene: self.scene_actions(),
+            actions: std::mem::take(&mut self.actions),
+            redo_actions: std::mem::take(&mut self.redo_actions),
+            camera: self.camera,
+        }
+    }
+
+    pub fn restore_page(&mut self, page: Page) {
+        self.replace_board(page.scene);
+        self.actions = page.actions;
+        self.redo_actions = page.redo_actions;
+        self.camera = page.camera;
+    }
+
+    // Returns whether another page is now shown.
+    pub fn show_page(&mut self, index: usize) -> bool {
+        if index == self.current_page || index >= self.pages.len() {
+            return false;
+        }
+        self.pages[self.current_page] = self.stash_page();
+        let page = std::mem::take(&mut self.pages[index]);
+        self.current_page = index;
+        self.restore_page(page);
+        true
+    }
+
+    pub fn new_page(&mut self) {
+        self.pages[self.current_page] = self.stash_page();
+        self.current_page += 1;
+        self.pages.insert(self.current_page, Page::default());
+        self.restore_page(Page::default());
+    }
+
+    pub fn replace_board(&mut self, actions: Vec<Action>) {
+        self.strokes.clear();
+        self.texts.clear();
+        self.shapes.clear();
+        self.images.clear();
+        self.fills.clear();
+        self.tables.clear();
+        self.code_blocks.clear();
+        self.stencils.clear();
+        self.connectors.clear();
+        self.redactions.clear();
+        self.layers = layers::default_layers();
+        self.current_layer = 0;
+        self.label = PageLabel::default();
+        self.actions.clear();
+        self.redo_actions.clear();
+        for action in actions {
+            self.push_action(action);
+        }
+    }
+
+    pub fn object_action(&self, object: ObjectRef) -> Option<Action> {
+        Some(match object {
+            ObjectRef::Stroke(index) => Action::Stroke(self.strokes.get(index)?.clone()),
+            ObjectRef::Shape(index) => Action::Shapes(*self.shapes.get(index)?),
+            ObjectRef::Text(index) => Action::Text(self.texts.get(index)?.clone()),
+            ObjectRef::Table(index) => Action::Table(self.tables.get(index)?.clone()),
+            ObjectRef::Code(index) => Action::Code(self.code_blocks.get(index)?.clone()),
+            ObjectRef::Stencil(index) => Action::Stencil(self.stencils.get(index)?.clone()),
+            ObjectRef::Image(index) => Action::Image(self.images.get(index)?.clone()),
+            ObjectRef::Fill(index) => Action::Fill(self.fills.get(index)?.clone()),
+            // A connector means nothing away from the stencils it joins.
+            ObjectRef::Connector(_) => return None,
+        })
+    }
+
+    // The object an action adding one would create, if it was applied now.
+    pub fn added_object(&self, action: &Action) -> Option<ObjectRef> {
+        Some(match action {
+            Action::Stroke(_) => ObjectRef::Stroke(self.strokes.len()),
+            Action::Shapes(_) => ObjectRef::Shape(self.shapes.len()),
+            Action::Text(_) => ObjectRef::Text(self.texts.len()),
+            Action::Table(_) => ObjectRef::Table(self.tables.len()),
+            Action::Code(_) => ObjectRef::Code(self.code_blocks.len()),
+            Action::Stencil(_) => ObjectRef::Stencil(self.stencils.len()),
+            Action::Image(_) => ObjectRef::Image(self.images.len()),
+            Action::Fill(_) => ObjectRef::Fill(self.fills.len()),
+            _ => return None,
+        })
+    }
+
+    pub fn object_text(&self, object: ObjectRef) -> Option<String> {
+        match object {
+            ObjectRef::Text(index) => Some(self.texts.get(index)?.text.clone()),
+            ObjectRef::Stencil(index) => Some(self.stencils.get(index)?.label.text.clone()),
+            ObjectRef::Table(index) => Some(
+                self.tables
+                    .get(index)?
+                    .rows
+                    .iter()
+                    .map(|row| row.join(", "))
+                    .collect::<Vec<_>>()
+                    .join(". "),
+            ),
+            ObjectRef::Code(index) => Some(self.code_blocks.get(index)?.code.clone()),
+            ObjectRef::Connector(index) => self.connectors.get(index)?.label.clone(),
+            ObjectRef::Stroke(_)
+            | ObjectRef::Shape(_)
+            | ObjectRef::Image(_)
+            | ObjectRef::Fill(_) => None,
+        }
+    }
+
+    pub fn set_object_text(&mut self, object: ObjectRef, text: &str) {
+        let entry = match object {
+            ObjectRef::Text(index) => self.texts.get_mut(index),
+            ObjectRef::Stencil(index) => self.stencils.get_mut(index).map(|s| &mut s.label),
+            _ => None,
+        };
+        if let Some(entry) = entry {
+            entry.text = text.to_string();
+            entry.cursor = entry.text.len();
+        }
+    }
+
+    pub fn swap_image_data(&mut self, edit: &mut ImageEdit) {
+        if let Some(image) = self.images.get_mut(edit.index) {
+            std::mem::swap(&mut image.data, &mut edit.data);
+            image.texture = None;
+        }
+    }
+
+    pub fn swap_image_adjust(&mut self, adjustment: &mut ImageAdjustment) {
+        if let Some(image) = self.images.get_mut(adjustment.index) {
+            std::mem::swap(&mut image.adjust, &mut adjustment.adjust);
+            std::mem::swap(&mut image.first, &mut adjustment.first);
+            std::mem::swap(&mut image.last, &mut adjustment.last);
+        }
+    }
+
+    // Cuts ink out of the area, scrubs the text under it and covers it with a
+    // black box, or pixelates pictures there instead of covering them.
+    pub fn redact(&mut self, area: Rect, pixelate: bool) {
+        let size = self.size;
+        let mut pieces = Vec::new();
+        for stroke in &mut self.strokes {
+            pieces.extend(redact_stroke(stroke, &area, size));
+        }
+        for text in self
+            .texts
+            .iter_mut()
+            .chain(self.stencils.iter_mut().map(|stencil| &mut stencil.label))
+        {
+            redact_text(text, &area);
+        }
+        for table in &mut self.tables {
+            redact_table(table, &area);
+        }
+        for code_block in &mut self.code_blocks {
+            redact_code(code_block, &area);
+        }
+        for image in &mut self.images {
+            redact_image(image, &area, pixelate);
+        }
+
+        // Undo history keeps its own copies of objects, so the redaction is
+        // baked into them as well or undoing would bring the hidden content
+        // back. Undo takes live objects back, so only the first piece of a
+        // copied stroke is kept, which is all an erased stroke returns as.
+        for action in self.actions.iter_mut().chain(&mut self.redo_actions) {
+            let content = match action {
+                Action::Erase(Erased {
+                    content: Some(content),
+                    ..
+                }) => content.as_mut(),
+                Action::Retouch(edit) => {
+                    if let Some(image) = self.images.get(edit.index) {
+                        let mut previous = BoardImage {
+                            data: std::mem::take(&mut edit.data),
+                            texture: None,
+                            ..image.clone()
+                        };
+                        redact_image(&mut previous, &area, pixelate);
+                        edit.data = previous.data;
+                    }
+                    continue;
+                }
+                action => action,
+            };
+            match content {
+                Action::Stroke(stroke) => {
+                    redact_stroke(stroke, &area, size);
+                }
+                Action::Text(text) => redact_text(text, &area),
+                Action::Stencil(stencil) => redact_text(&mut stencil.label, &area),
+                Action::Table(table) => redact_table(table, &area),
+                Action::Code(code_block) => redact_code(code_block, &area),
+                Action::Image(image) => redact_image(image, &area, pixelate),
+                _ => {}
+            }
+        }
+        // Later pieces of a cut stroke join the board as strokes of their own.
+        for piece in pieces {
+            self.strokes.push(piece.clone());
+            self.actions.push(Action::Stroke(piece));
+        }
+
+        if !pixelate {
+            self.redactions.push(area);
+        }
+    }
+
+    pub fn object_style(&self, object: ObjectRef) -> Option<ObjectStyle> {
+        let colored = |color: [u8; 4]| ObjectStyle {
+            color: Some(color),
+            alpha: Some(color[3]),
+            ..Default::default()
+        };
+        let style = match object {
+            ObjectRef::Stroke(index) => {
+                let stroke = self.strokes.get(index)?;
+                let color = stroke
+                    .points
+                    .first()
+                    .map(|point| normalized_to_rgba(point.color));
+                ObjectStyle {
+                    width: Some(stroke.width),
+                    ..color.map(colored).unwrap_or_default()
+                }
+            }
+            ObjectRef::Shape(index) => {
+                let shape = self.shapes.get(index)?;
+                ObjectStyle {
+                    filled: Some(shape.filled),
+                    ..colored(normalized_to_rgba(shape.color))
+                }
+            }
+            ObjectRef::Text(index) => {
+                let text = self.texts.get(index)?;
+                ObjectStyle {
+                    font_size: Some(text.font_size),
+                    font: Some(text.font.clone()),
+                    ..colored(text.color)
+                }
+            }
+            ObjectRef::Table(index) => {
+                let table = self.tables.get(index)?;
+                ObjectStyle {
+                    font_size: Some(table.font_size),
+                    ..colored(table.color)
+                }
+            }
+            ObjectRef::Code(index) => ObjectStyle {
+                font_size: Some(self.code_blocks.get(index)?.font_size),
+                ..Default::default()
+            },
+            ObjectRef::Stencil(index) => {
+                let stencil = self.stencils.get(index)?;
+                ObjectStyle {
+                    font_size: Some(stencil.label.font_size),
+                    font: Some(stencil.label.font.clone()),
+                    ..colored(stencil.color)
+                }
+            }
+            ObjectRef::Fill(index) => colored(normalized_to_rgba(self.fills.get(index)?.color)),
+            ObjectRef::Connector(index) => {
+                let connector = self.connectors.get(index)?;
+                ObjectStyle {
+                    font_size: Some(connector.font_size),
+                    ..colored(connector.color)
+                }
+            }
+            ObjectRef::Image(_) => return None,
+        };
+        Some(style)
+    }
+
+    pub fn apply_style(&mut self, object: ObjectRef, style: &ObjectStyle) {
+        match object {
+            ObjectRef::Stroke(index) => {
+                if let Some(stroke) = self.strokes.get_mut(index) {
+                    for point in &mut stroke.points {
+                        point.color = style.restyle_normalized(point.color);
+                    }
+                    stroke.width = style.width.unwrap_or(stroke.width);
+                }
+            }
+            ObjectRef::Shape(index) => {
+                if let Some(shape) = self.shapes.get_mut(index) {
+                    shape.color = style.restyle_normalized(shape.color);
+                    shape.filled = style.filled.unwrap_or(shape.filled);
+                }
+            }
+            ObjectRef::Text(index) => {
+                if let Some(text) = self.texts.get_mut(index) {
+                    text.color = style.restyle_color(text.color);
+                    text.font_size = style.font_size.unwrap_or(text.font_size);
+                    if let Some(font) = &style.font {
+                        text.font = font.clone();
+                    }
+                }
+            }
+            ObjectRef::Table(index) => {
+                if let Some(table) = self.tables.get_mut(index) {
+                    table.color = style.restyle_color(table.color);
+                    table.font_size = style.font_size.unwrap_or(table.font_size);
+                }
+            }
+            ObjectRef::Code(index) => {
+                if let Some(code_block) = self.code_blocks.get_mut(index) {
+                    code_block.font_size = style.font_size.unwrap_or(code_block.font_size);
+                }
+            }
+            ObjectRef::Stencil(index) => {
+                if let Some(stencil) = self.stencils.get_mut(index) {
+                    stencil.color = style.restyle_color(stencil.color);
+                    stencil.label.color = stencil.color;
+                    stencil.label.font_size = style.font_size.unwrap_or(stencil.label.font_size);
+                    if let Some(font) = &style.font {
+                        stencil.label.font = font.clone();
+                    }
+                }
+            }
+            ObjectRef::Fill(index) => {
+                if let Some(fill) = self.fills.get_mut(index) {
+                    fill.color = style.restyle_normalized(fill.color);
+                }
+            }
+            ObjectRef::Connector(index) => {
+                if let Some(connector) = self.connectors.get_mut(index) {
+                    connector.color = style.restyle_color(connector.color);
+                    connector.font_size = style.font_size.unwrap_or(connector.font_size);
+                }
+            }
+            ObjectRef::Image(_) => {}
+        }
+    }
+
+    pub fn object_bounds(&self, object: ObjectRef) -> Option<Rect> {
+        match object {
+            ObjectRef::Stroke(index) => {
+                Some(points_bounds(self.size, &self.strokes.get(index)?.points))
+            }
+            ObjectRef::Shape(index) => Some(points_bounds(
+                self.size,
+                &self.shapes.get(index)?.to_vertices(),
+            )),
+            ObjectRef::Text(index) => Some(self.texts.get(index)?.bounds.clone()),
+            ObjectRef::Table(index) => Some(self.tables.get(index)?.bounds.clone()),
+            ObjectRef::Code(index) => Some(self.code_blocks.get(index)?.bounds.clone()),
+            ObjectRef::Stencil(index) => Some(self.stencils.get(index)?.bounds()),
+            ObjectRef::Image(index) => Some(self.images.get(index)?.bounds()),
+            ObjectRef::Fill(index) => Some(self.fills.get(index)?.bounds()),
+            ObjectRef::Connector(index) => {
+                let ([x1, y1], [x2, y2]) = self.connectors.get(index)?.endpoints(&self.stencils)?;
+                Some(Rect {
+                    x: x1.min(x2),
+                    y: y1.min(y2),
+                    width: (x2 - x1).abs(),
+                    height: (y2 - y1).abs(),
+                })
+            }
+        }
+    }
+
+    // The corners a resize handle can drag, in board pixels.
+    pub fn object_corners(&self, object: ObjectRef) -> Option<[[f32; 2]; 2]> {
+        match object {
+            ObjectRef::Shape(index) => {
+                let shape = self.shapes.get(index)?;
+                Some([shape.first, shape.last].map(|corner| ndc_to_screen(self.size, corner)))
+            }
+            ObjectRef::Image(index) => {
+                let image = self.images.get(index)?;
+                Some([image.first, image.last])
+            }
+            // Dragging a text's corner sets the width its lines wrap at.
+            ObjectRef::Text(index) => {
+                let bounds = &self.texts.get(index)?.bounds;
+                Some([
+                    [bounds.x, bounds.y],
+                    [bounds.x + bounds.width, bounds.y + bounds.height],
+                ])
+            }
+            _ => None,
+        }
+    }
+
+    pub fn set_object_corners(&mut self, object: ObjectRef, corners: [[f32; 2]; 2]) {
+        match object {
+            ObjectRef::Shape(index) => {
+                if let Some(shape) = self.shapes.get_mut(index) {
+                    [shape.first, shape.last] =
+                        corners.map(|[x, y]| screen_to_ndc(self.size, x, y));
+                }
+            }
+            ObjectRef::Image(index) => {
+                if let Some(image) = self.images.get_mut(index) {
+                    [image.first, image.last] = corners;
+                }
+            }
+            ObjectRef::Text(index) => {
+                if let Some(text) = self.texts.get_mut(index) {
+                    let [[x1, _], [x2, _]] = corners;
+                    text.position[0] = x1.min(x2);
+                    text.wrap_width = Some((x2 - x1).abs().max(text.font_size as f32));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    pub fn translate_object(&mut self, object: ObjectRef, [dx, dy]: [f32; 2]) {
+        let ndc = [
+            dx * 2.0 / self.size.width as f32,
+            -dy * 2.0 / self.size.height as f32,
+        ];
+        let shift = |position: &mut [f32; 2], by: [f32; 2]| {
+            position[0] += by[0];
+            position[1] += by[1];
+        };
+        match object {
+            ObjectRef::Stroke(index) => {
+                if let Some(stroke) = self.strokes.get_mut(index) {
+                    for point in &mut stroke.points {
+                        shift(&mut point.position, ndc);
+                    }
+                }
+            }
+            ObjectRef::Shape(index) => {
+                if let Some(shape) = self.shapes.get_mut(index) {
+                    shift(&mut shape.first, ndc);
+                    shift(&mut shape.last, ndc);
+                }
+            }
+            ObjectRef::Text(index) => {
+                if let Some(text) = self.texts.get_mut(index) {
+                    shift(&mut text.position, [dx, dy]);
+                }
+            }
+            ObjectRef::Table(index) => {
+                if let Some(table) = self.tables.get_mut(index) {
+                    shift(&mut table.position, [dx, dy]);
+                }
+            }
+            ObjectRef::Code(index) => {
+                if let Some(code_block) = self.code_blocks.get_mut(index) {
+                    shift(&mut code_block.position, [dx, dy]);
+                }
+            }
+            ObjectRef::Stencil(index) => {
+                if let Some(stencil) = self.stencils.get_mut(index) {
+                    shift(&mut stencil.position, [dx, dy]);
+                }
+            }
+            ObjectRef::Image(index) => {
+                if let Some(image) = self.images.get_mut(index) {
+                    shift(&mut image.first, [dx, dy]);
+                    shift(&mut image.last, [dx, dy]);
+                }
+            }
+            ObjectRef::Fill(index) => {
+                if let Some(fill) = self.fills.get_mut(index) {
+                    for rect in &mut fill.rects {
+                        rect.x += dx;
+                        rect.y += dy;
+                    }
+                }
+            }
+            // Connectors follow the stencils at their ends.
+            ObjectRef::Connector(_) => {}
+        }
+    }
+
+    pub fn history_bytes(&self) -> usize {
+        self.actions.iter().map(Action::memory_size).sum()
+    }
+
+    pub fn compact_history(&mut self) {
+        let mut bytes = self.history_bytes();
+        let mut compacted = 0;
+        while compacted < self.actions.len()
+            && (self.actions.len() - compacted > self.history_max_entries
+                || bytes > self.history_max_bytes)
+        {
+            bytes -= self.actions[compacted].memory_size();
+            compacted += 1;
+        }
+        self.actions.drain(..compacted);
+    }
+
+    pub fn undo(&mut self) {
+        let Some(mut action) = self.actions.pop() else {
+            return;
+        };
+        self.revert(&mut action);
+        self.redo_actions.push(action);
+    }
+
+    fn revert(&mut self, action: &mut Action) {
+        // Take the live object back so edits made after it was pushed survive a redo.
+        match action {
+            Action::Stroke(stroke) => {
+                if let Some(current) = self.strokes.pop() {
+                    *stroke = current;
+                }
+            }
+            Action::Text(text) => {
+                if let Some(current) = self.texts.pop() {
+                    *text = current;
+                }
+            }
+            Action::Shapes(rectangle) => {
+                if let Some(current) = self.shapes.pop() {
+                    *rectangle = current;
+                }
+            }
+            Action::Table(table) => {
+                if let Some(current) = self.tables.pop() {
+                    *table = current;
+                }
+            }
+            Action::Code(code_block) => {
+                if let Some(current) = self.code_blocks.pop() {
+                    *code_block = current;
+                }
+            }
+            Action::Stencil(stencil) => {
+                if let Some(current) = self.stencils.pop() {
+                    *stencil = current;
+                }
+            }
+            Action::Connector(connector) => {
+                if let Some(current) = self.connectors.pop() {
+                    *connector = current;
+                }
+            }
+            Action::Arrange(moves) => {
+                for stencil_move in moves {
+                    if let Some(stencil) = self.stencils.get_mut(stencil_move.index) {
+                        stencil.position = stencil_move.from;
+                    }
+                }
+            }
+            Action::Restyle(restyle) => self.apply_style(restyle.object, &restyle.from),
+            Action::Erase(erased) => {
+                if let Some(content) = erased.content.take() {
+                    self.insert_object(erased.object, *content);
+                }
+            }
+            Action::Move(object_move) => {
+                let [dx, dy] = object_move.delta;
+                for &object in &object_move.objects {
+                    self.translate_object(object, [-dx, -dy]);
+                }
+            }
+            Action::Resize(resize) => self.set_object_corners(resize.object, resize.from),
+            Action::Image(image) => {
+                if let Some(current) = self.images.pop() {
+                    *image = current;
+                }
+            }
+            Action::Layers(edit) => self.set_layers(edit.from.clone()),
+            Action::Label(edit) => self.label = edit.from.clone(),
+            Action::Retext(edit) => self.set_object_text(edit.object, &edit.from),
+            Action::Relayer(layer_move) => {
+                for (&object, &layer) in layer_move.objects.iter().zip(&layer_move.from) {
+                    self.set_object_layer(object, layer);
+                }
+            }
+            Action::Retouch(edit) => self.swap_image_data(edit),
+            Action::Adjust(adjustment) => self.swap_image_adjust(adjustment),
+            Action::Fill(fill) => {
+                if let Some(current) = self.fills.pop() {
+                    *fill = current;
+                }
+            }
+            // Redactions never enter the history.
+            Action::Redact(_) => {}
+            // Parts are taken back last first.
+            Action::Group(parts) => {
+                for part in parts.iter_mut().rev() {
+                    self.revert(part);
+                }
+            }
+        }
+    }
+
+    pub fn redo(&mut self) {
+        if let Some(action) = self.redo_actions.pop() {
+            self.apply_action(action);
+        }
+    }
+
+    pub fn remove_object(&mut self, object: ObjectRef) -> Option<Action> {
+        match object {
+            ObjectRef::Stroke(index) => {
+                (index < self.strokes.len()).then(|| Action::Stroke(self.strokes.remove(index)))
+            }
+            ObjectRef::Shape(index) => {
+                (index < self.shapes.len()).then(|| Action::Shapes(self.shapes.remove(index)))
+            }
+            ObjectRef::Text(index) => {
+                (index < self.texts.len()).then(|| Action::Text(self.texts.remove(index)))
+            }
+            ObjectRef::Table(index) => {
+                (index < self.tables.len()).then(|| Action::Table(self.tables.remove(index)))
+            }
+            ObjectRef::Code(index) => (index < self.code_blocks.len())
+                .then(|| Action::Code(self.code_blocks.remove(index))),
+            ObjectRef::Image(index) => {
+                (index < self.images.len()).then(|| Action::Image(self.images.remove(index)))
+            }
+            ObjectRef::Fill(index) => {
+                (index < self.fills.len()).then(|| Action::Fill(self.fills.remove(index)))
+            }
+            ObjectRef::Connector(index) => (index < self.connectors.len())
+                .then(|| Action::Connector(self.connectors.remove(index))),
+            ObjectRef::Stencil(index) => {
+                if index >= self.stencils.len() {
+                    return None;
+                }
+                let mut parts = vec![Action::Stencil(self.stencils.remove(index))];
+                // The stencil's own connectors go with it, highest first, and
+                // the rest keep pointing at the same stencils.
+                for connector_index in (0..self.connectors.len()).rev() {
+                    let connector = &mut self.connectors[connector_index];
+                    if connector.from == index || connector.to == index {
+                        parts.push(Action::Erase(Erased {
+                            object: ObjectRef::Connector(connector_index),
+                            content: Some(Box::new(Action::Connector(
+                                self.connectors.remove(connector_index),
+                            ))),
+                        }));
+                        continue;
+                    }
+                    for end in [&mut connector.from, &mut connector.to] {
+                        if *end > index {
+                            *end -= 1;
+                        }
+                    }
+                }
+                Some(match parts.len() {
+                    1 => parts.remove(0),
+                    _ => Action::Group(parts),
+                })
+            }
+        }
+    }
+
+    pub fn insert_object(&mut self, object: ObjectRef, content: Action) {
+        match (object, content) {
+            (ObjectRef::Stroke(index), Action::Stroke(stroke)) => {
+                self.strokes.insert(index.min(self.strokes.len()), stroke)
+            }
+            (ObjectRef::Shape(index), Action::Shapes(rectangle)) => {
+                self.shapes.insert(index.min(self.shapes.len()), rectangle)
+            }
+            (ObjectRef::Text(index), Action::Text(text)) => {
+                self.texts.insert(index.min(self.texts.len()), text)
+            }
+            (ObjectRef::Table(index), Action::Table(table)) => {
+                self.tables.insert(index.min(self.tables.len()), table)
+            }
+            (ObjectRef::Code(index), Action::Code(code_block)) => self
+                .code_blocks
+                .insert(index.min(self.code_blocks.len()), code_block),
+            (ObjectRef::Image(index), Action::Image(image)) => {
+                self.images.insert(index.min(self.images.len()), image)
+            }
+            (ObjectRef::Fill(index), Action::Fill(fill)) => {
+                self.fills.insert(index.min(self.fills.len()), fill)
+            }
+            (ObjectRef::Connector(index), Action::Connector(connector)) => self
+                .connectors
+                .insert(index.min(self.connectors.len()), connector),
+            (ObjectRef::Stencil(index), Action::Stencil(stencil)) => {
+                let index = index.min(self.stencils.len());
+                for connector in &mut self.connectors {
+                    for end in [&mut connector.from, &mut connector.to] {
+                        if *end >= index {
+                            *end += 1;
+                        }
+                    }
+                }
+                self.stencils.insert(index, stencil);
+            }
+            // A stencil erased along with its connectors, which go back in
+            // the reverse of the order they were taken out.
+            (object @ ObjectRef::Stencil(_), Action::Group(parts)) => {
+                let mut parts = parts.into_iter();
+                if let Some(stencil) = parts.next() {
+                    self.insert_object(object, stencil);
+                }
+                for part in parts.rev() {
+                    if let Action::Erase(Erased {
+                        object,
+                        content: Some(content),
+                    }) = part
+                    {
+                        self.insert_object(object, *content);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        stencil::StencilKind, Connector, ImageAdjust, Stencil, TextEdit, TextEntries, Vertex,
+    };
+
+    fn board() -> Board {
+        Board::new(PhysicalSize::new(800, 600))
+    }
+
+    fn stroke(points: &[[f32; 2]]) -> Stroke {
+        Stroke {
+            points: points
+                .iter()
+                .map(|&position| Vertex {
+                    position,
+                    color: [0.0, 0.0, 0.0, 1.0],
+                })
+                .collect(),
+            ..Stroke::default()
+        }
+    }
+
+    #[test]
+    fn undo_and_redo_replay_history() {
+        let mut board = board();
+        board.push_action(Action::Stroke(stroke(&[[0.0, 0.0], [0.5, 0.5]])));
+        board.push_action(Action::Stroke(stroke(&[[-0.5, 0.0], [0.0, 0.5]])));
+
+        board.undo();
+        assert_eq!(board.strokes.len(), 1);
+        assert_eq!(board.redo_actions.len(), 1);
+
+        board.redo();
+        assert_eq!(board.strokes.len(), 2);
+        assert!(board.redo_actions.is_empty());
+    }
+
+    #[test]
+    fn a_group_is_undone_in_one_step() {
+        let mut board = board();
+        board.push_action(Action::Stroke(stroke(&[[0.0, 0.0], [0.5, 0.5]])));
+        board.push_action(Action::Stroke(stroke(&[[-0.5, 0.0], [0.0, 0.5]])));
+        board.push_action(Action::Group(
+            (0..2)
+                .rev()
+                .map(|index| {
+                    Action::Erase(Erased {
+                        object: ObjectRef::Stroke(index),
+                        content: None,
+                    })
+                })
+                .collect(),
+        ));
+        assert!(board.strokes.is_empty());
+
+        board.undo();
+        assert_eq!(board.strokes.len(), 2);
+        assert_eq!(board.strokes[0].points[1].position, [0.5, 0.5]);
+
+        board.redo();
+        assert!(board.strokes.is_empty());
+    }
+
+    #[test]
+    fn a_pushed_group_keeps_layers_and_finds_its_objects() {
+        let mut board = board();
+        board.push_action(Action::Stroke(stroke(&[[0.0, 0.0], [0.5, 0.5]])));
+        let mut moved = stroke(&[[-0.5, 0.0], [0.0, 0.5]]);
+        moved.layer = 7;
+        let added = board.push_group(vec![Action::Stroke(moved.clone()), Action::Stroke(moved)]);
+        assert_eq!(added, [ObjectRef::Stroke(1), ObjectRef::Stroke(2)]);
+        assert_eq!(board.strokes[2].layer, 7);
+
+        board.undo();
+        assert_eq!(board.strokes.len(), 1);
+    }
+
+    #[test]
+    fn erasing_a_stencil_takes_its_connectors_along() {
+        let mut board = board();
+        for x in [100.0, 300.0, 500.0] {
+            let stencil = Stencil::new(StencilKind::Process, [x, 100.0], [0, 0, 0, 255], 16);
+            board.push_action(Action::Stencil(stencil));
+        }
+        let connector = |from, to| Connector {
+            from,
+            to,
+            color: [0, 0, 0, 255],
+            font_size: 16,
+            label: None,
+        };
+        board.push_action(Action::Connector(connector(0, 1)));
+        board.push_action(Action::Connector(connector(0, 2)));
+        board.push_action(Action::Connector(connector(1, 2)));
+
+        board.push_action(Action::Erase(Erased {
+            object: ObjectRef::Stencil(1),
+            content: None,
+        }));
+        let ends = |board: &Board| -> Vec<_> {
+            board
+                .connectors
+                .iter()
+                .map(|connector| (connector.from, connector.to))
+                .collect()
+        };
+        assert_eq!(board.stencils.len(), 2);
+        assert_eq!(ends(&board), [(0, 1)]);
+
+        board.undo();
+        assert_eq!(board.stencils.len(), 3);
+        assert_eq!(
+            board.stencils[1].position[0],
+            300.0 - board.stencils[1].size[0] / 2.0
+        );
+        assert_eq!(ends(&board), [(0, 1), (0, 2), (1, 2)]);
+
+        board.push_action(Action::Erase(Erased {
+            object: ObjectRef::Connector(1),
+            content: None,
+        }));
+        assert_eq!(ends(&board), [(0, 1), (1, 2)]);
+    }
+
+    #[test]
+    fn text_edits_are_undone_to_the_text_before() {
+        let mut board = board();
+        let mut text = TextEntries::null([0, 0, 0, 255], 16);
+        text.text = "Draft".to_string();
+        board.push_action(Action::Text(text));
+        board.texts[0].text = "Final".to_string();
+        board.push_action(Action::Retext(TextEdit {
+            object: ObjectRef::Text(0),
+            from: "Draft".to_string(),
+            to: "Final".to_string(),
+        }));
+
+        board.undo();
+        assert_eq!(board.texts.len(), 1);
+        assert_eq!(board.texts[0].text, "Draft");
+
+        board.redo();
+        assert_eq!(board.texts[0].text, "Final");
+    }
+
+    #[test]
+    fn pages_keep_their_own_objects_and_history() {
+        let mut board = board();
+        board.push_action(Action::Stroke(stroke(&[[0.0, 0.0], [0.5, 0.5]])));
+        board.new_page();
+        assert_eq!(board.current_page, 1);
+        assert!(board.strokes.is_empty());
+
+        board.show_page(0);
+        assert_eq!(board.strokes.len(), 1);
+        assert_eq!(board.actions.len(), 1);
+        assert_eq!(board.page_scenes().len(), 2);
+    }
+
+    #[test]
+    fn page_labels_are_kept_in_the_scene() {
+        let mut board = board();
+        board.edit_label(PageLabel {
+            name: "Lecture 3".to_string(),
+            tags: vec![pages::PageTag {
+                name: "Homework".to_string(),
+                color: pages::TAG_COLORS[0],
+            }],
+        });
+        board.new_page();
+        assert_eq!(board.label, PageLabel::default());
+
+        let labels = board.page_labels();
+        assert_eq!(labels[0].title(0), "Lecture 3");
+        assert_eq!(labels[1].title(1), "Page 2");
+        assert!(labels[0].matches(0, "homew"));
+
+        board.show_page(0);
+        assert!(board.label.has_tag("Homework"));
+        board.undo();
+        assert_eq!(board.label, PageLabel::default());
+    }
+
+    #[test]
+    fn redactions_do_not_cut_later_strokes_on_replay() {
+        let mut board = board();
+        board.push_action(Action::Stroke(stroke(&[[-0.5, 0.0], [0.5, 0.0]])));
+        let area = Rect {
+            x: 300.0,
+            y: 200.0,
+            width: 200.0,
+            height: 200.0,
+        };
+        board.redact(area, false);
+        let cut = board.strokes.len();
+
+        // Drawn inside the box after the redaction was made.
+        let inside = [[-0.1, 0.1], [0.1, -0.1]];
+        board.push_action(Action::Stroke(stroke(&inside)));
+        board.new_page();
+        board.show_page(0);
+
+        assert_eq!(board.redactions.len(), 1);
+        assert_eq!(board.strokes.len(), cut + 1);
+        let positions: Vec<_> = board.strokes[cut]
+            .points
+            .iter()
+            .map(|vertex| vertex.position)
+            .collect();
+        assert_eq!(positions, inside);
+    }
+
+    #[test]
+    fn picture_adjustments_swap_with_undo_and_redo() {
+        let mut board = board();
+        board.push_action(Action::Image(BoardImage {
+            first: [0.0, 0.0],
+            last: [40.0, 20.0],
+            data: Vec::new(),
+            layer: 0,
+            adjust: ImageAdjust::default(),
+            texture: None,
+        }));
+        let turned = ImageAdjust {
+            turns: 1,
+            grayscale: true,
+            ..ImageAdjust::default()
+        };
+        board.push_action(Action::Adjust(ImageAdjustment {
+            index: 0,
+            adjust: turned,
+            first: [10.0, -10.0],
+            last: [30.0, 30.0],
+        }));
+        assert_eq!(board.images[0].adjust, turned);
+        assert_eq!(board.images[0].last, [30.0, 30.0]);
+
+        board.undo();
+        assert_eq!(board.images[0].adjust, ImageAdjust::default());
+        assert_eq!(board.images[0].first, [0.0, 0.0]);
+        board.redo();
+        assert_eq!(board.images[0].adjust, turned);
+        assert_eq!(board.images[0].first, [10.0, -10.0]);
+    }
+}
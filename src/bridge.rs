@@ -0,0 +1,264 @@
+use crate::{commands::Command, crash};
+use serde::{Deserialize, Serialize};
+use std::{
+    net::UdpSocket,
+    sync::mpsc::{self, Receiver, Sender},
+    thread,
+    time::Duration,
+};
+
+pub const POLL_INTERVAL: Duration = Duration::from_millis(100);
+const MAX_PACKET: usize = 64 * 1024;
+
+// OSC messages from buttons, controllers and installation software, turned
+// into board actions by the routes in the settings file, for example
+//
+//     [bridge]
+//     osc_port = 9000
+//     [[bridge.routes]]
+//     address = "/board/next"
+//     action = { command = "NextPage" }
+//     [[bridge.routes]]
+//     address = "/board/note"
+//     action = { note = { x = 200.0, y = 160.0 } }
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BridgeSettings {
+    pub osc_port: Option<u16>,
+    pub routes: Vec<Route>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Route {
+    // A trailing `*` matches any address that starts with the rest.
+    pub address: String,
+    pub action: BridgeAction,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BridgeAction {
+    Command(Command),
+    // Shows the page numbered, from 1, by the message's first number.
+    ShowPage,
+    // A sticky note holding the message's first string, centered at x and y
+    // in board pixels.
+    Note { x: f32, y: f32 },
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Argument {
+    Int(i32),
+    Float(f32),
+    Text(String),
+}
+
+impl Argument {
+    pub fn number(&self) -> Option<f32> {
+        match self {
+            Argument::Int(value) => Some(*value as f32),
+            Argument::Float(value) => Some(*value),
+            Argument::Text(text) => text.trim().parse().ok(),
+        }
+    }
+
+    pub fn text(&self) -> Option<&str> {
+        match self {
+            Argument::Text(text) => Some(text),
+            _ => None,
+        }
+    }
+}
+
+pub struct Message {
+    pub address: String,
+    pub arguments: Vec<Argument>,
+}
+
+impl BridgeSettings {
+    pub fn route(&self, address: &str) -> Option<&BridgeAction> {
+        self.routes
+            .iter()
+            .find(|route| match route.address.strip_suffix('*') {
+                Some(prefix) => address.starts_with(prefix),
+                None => route.address == address,
+            })
+            .map(|route| &route.action)
+    }
+}
+
+// Listens for OSC packets over UDP on all interfaces, since the hardware sits
+// elsewhere on the classroom network.
+pub struct Bridge {
+    messages: Receiver<Message>,
+}
+
+impl Bridge {
+    pub fn start(port: u16) -> Result<Self, String> {
+        let socket = UdpSocket::bind(("0.0.0.0", port))
+            .map_err(|err| format!("Unable to listen for OSC on port {}: {}", port, err))?;
+        let (sender, messages) = mpsc::channel();
+        thread::spawn(move || {
+            let mut packet = vec![0; MAX_PACKET];
+            while let Ok(length) = socket.recv(&mut packet) {
+                if let Err(err) = read_packet(&packet[..length], &sender) {
+                    crash::log(format!("Ignoring OSC packet: {}", err));
+                }
+            }
+        });
+        Ok(Bridge { messages })
+    }
+
+    pub fn poll(&self) -> Vec<Message> {
+        self.messages.try_iter().collect()
+    }
+}
+
+// A message, or a bundle of messages and further bundles.
+fn read_packet(packet: &[u8], sender: &Sender<Message>) -> Result<(), String> {
+    let mut reader = Reader { data: packet };
+    if packet.starts_with(b"#bundle\0") {
+        reader.take(16)?;
+        while !reader.data.is_empty() {
+            let length = usize::try_from(reader.int()?)
+                .map_err(|_| "a bundle element has a negative length")?;
+            read_packet(reader.take(length)?, sender)?;
+        }
+        return Ok(());
+    }
+
+    let address = reader.string()?;
+    let tags = match reader.data.is_empty() {
+        true => String::new(),
+        false => reader.string()?,
+    };
+    let mut arguments = Vec::new();
+    for tag in tags.chars().skip_while(|&tag| tag == ',') {
+        arguments.push(match tag {
+            'i' => Argument::Int(reader.int()?),
+            'f' => Argument::Float(f32::from_bits(reader.int()? as u32)),
+            's' => Argument::Text(reader.string()?),
+            'T' => Argument::Int(1),
+            'F' | 'N' => Argument::Int(0),
+            _ => return Err(format!("unsupported argument type {}", tag)),
+        });
+    }
+    let _ = sender.send(Message { address, arguments });
+    Ok(())
+}
+
+struct Reader<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, length: usize) -> Result<&'a [u8], String> {
+        if length > self.data.len() {
+            return Err("the packet ends early".to_string());
+        }
+        let (taken, rest) = self.data.split_at(length);
+        self.data = rest;
+        Ok(taken)
+    }
+
+    fn int(&mut self) -> Result<i32, String> {
+        let bytes = self.take(4)?;
+        Ok(i32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    // Strings end in a nul and are padded to a multiple of four bytes.
+    fn string(&mut self) -> Result<String, String> {
+        let end = self
+            .data
+            .iter()
+            .position(|&byte| byte == 0)
+            .ok_or("a string is not terminated")?;
+        let text = String::from_utf8_lossy(&self.data[..end]).into_owned();
+        self.take((end + 4) & !3)?;
+        Ok(text)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Nul terminated and padded to four bytes, as OSC strings are sent.
+    fn string(text: &str) -> Vec<u8> {
+        let mut bytes = text.as_bytes().to_vec();
+        bytes.resize((text.len() + 4) & !3, 0);
+        bytes
+    }
+
+    fn message(address: &str, tags: &str, arguments: &[u8]) -> Vec<u8> {
+        [string(address), string(tags), arguments.to_vec()].concat()
+    }
+
+    fn bundle(elements: &[Vec<u8>]) -> Vec<u8> {
+        let mut packet = [string("#bundle"), vec![0; 8]].concat();
+        for element in elements {
+            packet.extend((element.len() as i32).to_be_bytes());
+            packet.extend(element);
+        }
+        packet
+    }
+
+    fn read(packet: &[u8]) -> (Result<(), String>, Vec<Message>) {
+        let (sender, messages) = mpsc::channel();
+        let result = read_packet(packet, &sender);
+        (result, messages.try_iter().collect())
+    }
+
+    #[test]
+    fn a_message_brings_its_arguments() {
+        let arguments = [
+            3i32.to_be_bytes().to_vec(),
+            1.5f32.to_bits().to_be_bytes().to_vec(),
+            string("note"),
+        ]
+        .concat();
+        let (result, messages) = read(&message("/board/page", ",ifsT", &arguments));
+        assert_eq!(result, Ok(()));
+        assert_eq!(messages.len(), 1);
+        assert_eq!(messages[0].address, "/board/page");
+        assert_eq!(
+            messages[0].arguments,
+            [
+                Argument::Int(3),
+                Argument::Float(1.5),
+                Argument::Text("note".to_string()),
+                Argument::Int(1),
+            ]
+        );
+    }
+
+    #[test]
+    fn nested_bundles_deliver_every_message_in_order() {
+        let inner = bundle(&[message("/b", ",", &[]), message("/c", ",", &[])]);
+        let (result, messages) = read(&bundle(&[message("/a", ",", &[]), inner]));
+        assert_eq!(result, Ok(()));
+        let addresses: Vec<_> = messages.iter().map(|m| m.address.as_str()).collect();
+        assert_eq!(addresses, ["/a", "/b", "/c"]);
+    }
+
+    #[test]
+    fn a_truncated_packet_is_refused() {
+        let packet = message("/board/page", ",i", &3i32.to_be_bytes());
+        let (result, messages) = read(&packet[..packet.len() - 2]);
+        assert!(result.is_err());
+        assert!(messages.is_empty());
+
+        let (result, _) = read(b"/board");
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn a_negative_bundle_length_is_refused() {
+        let mut packet = bundle(&[]);
+        packet.extend((-4i32).to_be_bytes());
+        packet.extend(message("/a", ",", &[]));
+        let (result, messages) = read(&packet);
+        assert!(result.is_err());
+        assert!(messages.is_empty());
+    }
+}
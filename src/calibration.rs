@@ -0,0 +1,48 @@
+use tao::dpi::{PhysicalPosition, PhysicalSize};
+
+pub const TARGETS: [[f64; 2]; 3] = [[0.5, 0.5], [0.2, 0.25], [0.8, 0.75]];
+pub const TARGET_RADIUS: f32 = 14.0;
+
+pub struct Calibration {
+    offsets: Vec<[f64; 2]>,
+}
+
+impl Calibration {
+    pub fn new() -> Self {
+        Calibration {
+            offsets: Vec::new(),
+        }
+    }
+
+    pub fn step(&self) -> usize {
+        self.offsets.len()
+    }
+
+    pub fn target(&self, size: PhysicalSize<u32>) -> Option<PhysicalPosition<f64>> {
+        let [x, y] = TARGETS.get(self.offsets.len())?;
+        Some(PhysicalPosition::new(
+            x * size.width as f64,
+            y * size.height as f64,
+        ))
+    }
+
+    // `reported` is where the digitizer placed the tip, before any offset.
+    pub fn record(&mut self, reported: PhysicalPosition<f64>, size: PhysicalSize<u32>) {
+        if let Some(target) = self.target(size) {
+            self.offsets
+                .push([target.x - reported.x, target.y - reported.y]);
+        }
+    }
+
+    pub fn offset(&self) -> Option<[f64; 2]> {
+        if self.offsets.len() < TARGETS.len() {
+            return None;
+        }
+        let count = self.offsets.len() as f64;
+        let (x, y) = self
+            .offsets
+            .iter()
+            .fold((0.0, 0.0), |(x, y), offset| (x + offset[0], y + offset[1]));
+        Some([x / count, y / count])
+    }
+}
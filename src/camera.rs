@@ -0,0 +1,88 @@
+use tao::dpi::PhysicalSize;
+
+pub const MIN_ZOOM: f32 = 0.1;
+pub const MAX_ZOOM: f32 = 10.0;
+pub const ZOOM_STEP: f32 = 1.1;
+pub const SCROLL_LINE: f32 = 40.0;
+
+#[repr(C)]
+#[derive(Copy, Clone, bytemuck::Pod, bytemuck::Zeroable, Debug)]
+pub struct CameraUniform {
+    translate: [f32; 2],
+    scale: f32,
+    _padding: f32,
+}
+
+// Maps board pixels to window pixels as `screen = board * zoom + pan`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Camera {
+    pub pan: [f32; 2],
+    pub zoom: f32,
+}
+
+impl Default for Camera {
+    fn default() -> Self {
+        Camera {
+            pan: [0.0, 0.0],
+            zoom: 1.0,
+        }
+    }
+}
+
+impl Camera {
+    // The same mapping expressed in clip space, where board geometry lives.
+    pub fn uniform(&self, size: PhysicalSize<u32>) -> CameraUniform {
+        CameraUniform {
+            translate: [
+                self.zoom - 1.0 + 2.0 * self.pan[0] / size.width as f32,
+                1.0 - self.zoom - 2.0 * self.pan[1] / size.height as f32,
+            ],
+            scale: self.zoom,
+            _padding: 0.0,
+        }
+    }
+
+    pub fn unproject(&self, size: PhysicalSize<u32>, clip: [f32; 2]) -> [f32; 2] {
+        let uniform = self.uniform(size);
+        [
+            (clip[0] - uniform.translate[0]) / uniform.scale,
+            (clip[1] - uniform.translate[1]) / uniform.scale,
+        ]
+    }
+
+    pub fn to_world(self, screen: [f32; 2]) -> [f32; 2] {
+        [
+            (screen[0] - self.pan[0]) / self.zoom,
+            (screen[1] - self.pan[1]) / self.zoom,
+        ]
+    }
+
+    pub fn to_screen(self, world: [f32; 2]) -> [f32; 2] {
+        [
+            world[0] * self.zoom + self.pan[0],
+            world[1] * self.zoom + self.pan[1],
+        ]
+    }
+
+    // A paper roll is as wide as the window at 100% and starts at the top of
+    // the board, so only scrolling down from there is left.
+    pub fn roll(&mut self) {
+        self.zoom = 1.0;
+        self.pan = [0.0, self.pan[1].min(0.0)];
+    }
+
+    pub fn scroll(&mut self, dx: f32, dy: f32) {
+        self.pan[0] += dx;
+        self.pan[1] += dy;
+    }
+
+    // Zooms by `factor` while keeping the board point under `screen` in place.
+    pub fn zoom_at(&mut self, screen: [f32; 2], factor: f32) {
+        let anchor = self.to_world(screen);
+        self.zoom = (self.zoom * factor).clamp(MIN_ZOOM, MAX_ZOOM);
+        self.pan = [
+            screen[0] - anchor[0] * self.zoom,
+            screen[1] - anchor[1] * self.zoom,
+        ];
+    }
+}
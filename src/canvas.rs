@@ -0,0 +1,564 @@
+#[cfg(feature = "remote-api")]
+use crate::remote;
+use crate::{
+    crash, images,
+    layers::{self, Layer},
+    normalized_to_rgba, object_index,
+    paste::{self, PasteFormat},
+    project,
+    settings::DeviceTool,
+    stats, summary, timestamp_millis, Action, BoardImage, Damage, Erased, ImageAdjust,
+    ImageAdjustment, ImageEdit, LayerEdit, LayerMove, ObjectRef, ObjectStyle, Restyle, TextEntries,
+    WindowState, AUTO_SCROLL_MARGIN, AUTO_SCROLL_SPEED, LECTURE_HEADING_MARGIN, PASTE_OFFSET,
+};
+use std::{
+    path::PathBuf,
+    time::{Duration, Instant},
+};
+
+impl WindowState<'_> {
+    pub(crate) fn move_selection_to_layer(&mut self, layer: u32) {
+        let objects: Vec<ObjectRef> = self
+            .selection
+            .iter()
+            .copied()
+            .filter(|&object| {
+                self.board
+                    .object_layer(object)
+                    .is_some_and(|from| from != layer)
+            })
+            .collect();
+        if objects.is_empty() {
+            return;
+        }
+        let from = objects
+            .iter()
+            .filter_map(|&object| self.board.object_layer(object))
+            .collect();
+        self.board.push_action(Action::Relayer(LayerMove {
+            objects,
+            from,
+            to: layer,
+        }));
+        let selection = std::mem::take(&mut self.selection);
+        self.selection = selection
+            .into_iter()
+            .filter(|&object| self.board.layer_rank(object).is_some())
+            .collect();
+    }
+
+    pub(crate) fn open_board(&mut self, path: PathBuf) {
+        match project::load(&path, self.board.size) {
+            Ok(pages) => {
+                self.replace_pages(pages);
+                self.project_path = Some(path);
+            }
+            Err(err) => crash::log(format!("Unable to open {}: {}", path.display(), err)),
+        }
+    }
+
+    pub(crate) fn lecture_page_interval(&self) -> Duration {
+        Duration::from_secs(self.settings.lecture_page_minutes * 60)
+    }
+
+    // Starts a page headed with the date and, during a lecture, how far into it
+    // the page begins.
+    pub(crate) fn lecture_page(&mut self) {
+        self.new_page();
+        let mut title = stats::date(stats::local_day(timestamp_millis()));
+        if let Some(started) = self.lecture_started {
+            let minutes = started.elapsed().as_secs() / 60;
+            title.push_str(&format!(
+                " · {:02}:{:02} into the lecture",
+                minutes / 60,
+                minutes % 60
+            ));
+        }
+        let mut heading = TextEntries::null(normalized_to_rgba(self.current_color), self.font_size);
+        heading.position = [
+            LECTURE_HEADING_MARGIN,
+            LECTURE_HEADING_MARGIN + self.font_size as f32,
+        ];
+        heading.text = title;
+        heading.pending = false;
+        heading.font = self.font_family.clone();
+        self.board.push_object(Action::Text(heading));
+        self.last_lecture_page = Some(Instant::now());
+    }
+
+    pub(crate) fn flipbook_interval(&self) -> Duration {
+        Duration::from_secs(1) / self.settings.flipbook_fps.max(1)
+    }
+
+    // Plays the pages as the frames of an animation from the one shown, or
+    // stops where it is.
+    pub(crate) fn toggle_flipbook(&mut self) {
+        self.flipbook = match self.flipbook {
+            None if self.board.pages.len() > 1 => {
+                self.commit_text();
+                Some(Instant::now())
+            }
+            _ => None,
+        };
+    }
+
+    // Shows the next frame, going round to the first after the last.
+    pub(crate) fn flipbook_frame(&mut self) {
+        let next = (self.board.current_page + 1) % self.board.pages.len();
+        self.show_page(next);
+        self.flipbook = Some(Instant::now());
+    }
+
+    // Adds a last page that shows every other page scaled down in a grid.
+    pub(crate) fn summary_page(&mut self) {
+        let sheet = summary::contact_sheet(&self.board.page_scenes(), self.size);
+        self.show_page(self.board.pages.len() - 1);
+        self.new_page();
+        for action in sheet {
+            self.board.push_object(action);
+        }
+    }
+
+    // Copies as board JSON, which paste recognizes and Paste special can read.
+    pub(crate) fn copy_objects(&mut self) {
+        let objects = match self.selection.is_empty() {
+            true => {
+                let position = self.board_cursor();
+                self.pick([position.x as f32, position.y as f32])
+                    .into_iter()
+                    .collect()
+            }
+            false => self.selection.clone(),
+        };
+        let mut actions: Vec<Action> = objects
+            .into_iter()
+            .filter_map(|object| self.board.object_action(object))
+            .collect();
+        if actions.is_empty() {
+            return;
+        }
+        paste::sanitize(&mut actions);
+        let json = serde_json::to_string(&actions).unwrap_or_default();
+        if let Err(err) = arboard::Clipboard::new().and_then(|mut c| c.set_text(json)) {
+            crash::log(format!("Unable to write clipboard: {:?}", err));
+        }
+    }
+
+    pub(crate) fn paste(&mut self) {
+        let mut clipboard = match arboard::Clipboard::new() {
+            Ok(clipboard) => clipboard,
+            Err(err) => {
+                crash::log(format!("Unable to read clipboard: {:?}", err));
+                return;
+            }
+        };
+        if let Ok(text) = clipboard.get_text() {
+            if paste::detect(&text) == PasteFormat::BoardJson {
+                if let Ok(actions) = paste::parse_board_json(&text) {
+                    self.paste_objects(actions, [PASTE_OFFSET, PASTE_OFFSET]);
+                    return;
+                }
+            }
+            if !text.trim().is_empty() {
+                let mut entry =
+                    TextEntries::null(normalized_to_rgba(self.current_color), self.font_size);
+                let position = self.board_cursor();
+                entry.position = [position.x as f32, position.y as f32];
+                entry.text = text;
+                entry.pending = false;
+                entry.font = self.font_family.clone();
+                self.board.push_object(Action::Text(entry));
+                return;
+            }
+        }
+        match clipboard.get_image() {
+            Ok(image) => {
+                match images::import_rgba(image.width, image.height, image.bytes.into_owned()) {
+                    Some((data, _)) => {
+                        let index = self.board.images.len();
+                        self.import_image(data);
+                        if self.board.images.len() > index {
+                            self.pasted_image = Some(index);
+                        }
+                    }
+                    None => crash::log("Unable to read the clipboard image".to_string()),
+                }
+            }
+            Err(err) => crash::log(format!("Unable to read clipboard: {:?}", err)),
+        }
+    }
+
+    // Pasted copies land `offset` away from the originals and become the
+    // selection. Connectors and redactions are left out, since they only make
+    // sense on the board they came from.
+    pub(crate) fn paste_objects(&mut self, actions: Vec<Action>, offset: [f32; 2]) {
+        self.commit_text();
+        let mut pasted = Vec::new();
+        for action in actions {
+            let Some(object) = self.board.added_object(&action) else {
+                continue;
+            };
+            self.board.push_object(action);
+            self.board.translate_object(object, offset);
+            pasted.push(object);
+        }
+        self.selection = pasted;
+    }
+
+    // Erases the selection here and adds it in place on another page, or on a
+    // new page after this one, which is then shown. Each page takes its part
+    // of the move back in one undo step. The objects keep their layers, which
+    // are brought along when the page has none by that id. Stencils and
+    // connectors stay behind because connectors only point within a page.
+    pub(crate) fn move_selection_to_page(&mut self, page: Option<usize>) {
+        self.commit_text();
+        let mut objects: Vec<ObjectRef> = self
+            .selection
+            .drain(..)
+            .filter(|object| !matches!(object, ObjectRef::Stencil(_) | ObjectRef::Connector(_)))
+            .collect();
+        if objects.is_empty() || page == Some(self.board.current_page) {
+            return;
+        }
+        objects.sort_by_key(|&object| object_index(object));
+        let actions: Vec<Action> = objects
+            .iter()
+            .filter_map(|&object| self.board.object_action(object))
+            .collect();
+        let used: Vec<u32> = objects
+            .iter()
+            .filter_map(|&object| self.board.object_layer(object))
+            .collect();
+        let layers: Vec<Layer> = self
+            .board
+            .layers
+            .iter()
+            .filter(|layer| used.contains(&layer.id))
+            .cloned()
+            .collect();
+        // Highest indices first, so the ones still to go keep their place.
+        let erased = objects
+            .iter()
+            .rev()
+            .map(|&object| {
+                Action::Erase(Erased {
+                    object,
+                    content: None,
+                })
+            })
+            .collect();
+        self.board.push_action(Action::Group(erased));
+        match page {
+            Some(index) => self.show_page(index),
+            None => self.new_page(),
+        }
+
+        let mut added = Vec::new();
+        let mut to = self.board.layers.clone();
+        for layer in layers {
+            if !to.iter().any(|existing| existing.id == layer.id) {
+                to.push(layer);
+            }
+        }
+        if to != self.board.layers {
+            added.push(Action::Layers(LayerEdit {
+                from: self.board.layers.clone(),
+                to,
+            }));
+        }
+        added.extend(actions);
+        self.selection = self.board.push_group(added);
+        self.set_tool(DeviceTool::Select);
+    }
+
+    pub(crate) fn key_out_background(&mut self, index: usize) {
+        let Some(image) = self.board.images.get(index) else {
+            return;
+        };
+        match images::key_out_white(&image.data, self.settings.background_threshold) {
+            Some(data) => self
+                .board
+                .push_action(Action::Retouch(ImageEdit { index, data })),
+            None => crash::log("Unable to remove the image background".to_string()),
+        }
+    }
+
+    // Swaps a photographed page for its straightened, cleaned up scan on a
+    // new layer under everything else, where it can be written over. It keeps
+    // the photo's place and width.
+    pub(crate) fn scan_image(&mut self, index: usize, corners: [[f32; 2]; 4]) {
+        let Some(image) = self.board.images.get(index) else {
+            return;
+        };
+        let Some((data, [width, height])) = images::scan(&image.data, corners) else {
+            crash::log("Unable to straighten the photo".to_string());
+            return;
+        };
+        let bounds = image.bounds();
+        let height = bounds.width * height as f32 / width as f32;
+        let mut layers = self.board.layers.clone();
+        let layer = layers::add_bottom_layer(&mut layers, "Scan");
+        self.board.edit_layers(layers);
+        self.selection.clear();
+        self.board.push_action(Action::Erase(Erased {
+            object: ObjectRef::Image(index),
+            content: None,
+        }));
+        self.board.push_action(Action::Image(BoardImage {
+            first: [bounds.x, bounds.y],
+            last: [bounds.x + bounds.width, bounds.y + height],
+            data,
+            layer,
+            adjust: ImageAdjust::default(),
+            texture: None,
+        }));
+    }
+
+    // The picture was adjusted live; it is put back and the change pushed, so
+    // one undo takes the whole adjustment back.
+    pub(crate) fn finish_adjusting(&mut self) {
+        let Some((index, adjust, [first, last])) = self.adjusting.take() else {
+            return;
+        };
+        let Some(image) = self.board.images.get_mut(index) else {
+            return;
+        };
+        if image.adjust == adjust && image.first == first && image.last == last {
+            return;
+        }
+        let adjustment = ImageAdjustment {
+            index,
+            adjust: std::mem::replace(&mut image.adjust, adjust),
+            first: std::mem::replace(&mut image.first, first),
+            last: std::mem::replace(&mut image.last, last),
+        };
+        self.board.push_action(Action::Adjust(adjustment));
+    }
+
+    #[cfg(feature = "remote-api")]
+    pub(crate) fn poll_remote(&mut self) {
+        let Some(server) = &self.remote else {
+            return;
+        };
+        for edit in server.poll() {
+            match edit {
+                remote::Edit::Text { text, position } => {
+                    self.commit_text();
+                    let mut entry =
+                        TextEntries::null(normalized_to_rgba(self.current_color), self.font_size);
+                    entry.position = position;
+                    entry.text = text;
+                    entry.pending = false;
+                    entry.font = self.font_family.clone();
+                    entry.layer = self.board.current_layer;
+                    self.board.push_object(Action::Text(entry));
+                }
+                remote::Edit::Image { data, center } => self.place_image(data, center),
+                remote::Edit::ClearPage => self.clear_page(),
+            }
+        }
+    }
+
+    // Erases everything on the page as one undo step.
+    #[cfg(feature = "remote-api")]
+    pub(crate) fn clear_page(&mut self) {
+        self.commit_text();
+        self.selection.clear();
+        let counts = [
+            (
+                self.board.strokes.len(),
+                ObjectRef::Stroke as fn(usize) -> ObjectRef,
+            ),
+            (self.board.shapes.len(), ObjectRef::Shape),
+            (self.board.texts.len(), ObjectRef::Text),
+            (self.board.tables.len(), ObjectRef::Table),
+            (self.board.code_blocks.len(), ObjectRef::Code),
+            (self.board.images.len(), ObjectRef::Image),
+            (self.board.fills.len(), ObjectRef::Fill),
+            // Connectors before the stencils that would take them along.
+            (self.board.connectors.len(), ObjectRef::Connector),
+            (self.board.stencils.len(), ObjectRef::Stencil),
+        ];
+        // Highest indices first, so the ones still to go keep their place.
+        let erased = counts
+            .into_iter()
+            .flat_map(|(count, object)| (0..count).rev().map(object))
+            .map(|object| {
+                Action::Erase(Erased {
+                    object,
+                    content: None,
+                })
+            })
+            .collect();
+        self.board.push_action(Action::Group(erased));
+    }
+
+    // Places a picture centered on the cursor, scaled down to fit half the window.
+    pub(crate) fn import_image(&mut self, data: Vec<u8>) {
+        let center = self.board_cursor();
+        self.place_image(data, [center.x as f32, center.y as f32]);
+    }
+
+    pub(crate) fn place_image(&mut self, data: Vec<u8>, center: [f32; 2]) {
+        let Some((data, [width, height])) = images::import(data) else {
+            crash::log("Unable to read the image".to_string());
+            return;
+        };
+        let zoom = self.board.camera.zoom;
+        let fit = (self.size.width as f32 / 2.0 / zoom / width as f32)
+            .min(self.size.height as f32 / 2.0 / zoom / height as f32)
+            .min(1.0);
+        let (width, height) = (width as f32 * fit, height as f32 * fit);
+        let (x, y) = (center[0] - width / 2.0, center[1] - height / 2.0);
+        self.board.push_action(Action::Image(BoardImage {
+            first: [x, y],
+            last: [x + width, y + height],
+            data,
+            layer: self.board.current_layer,
+            adjust: ImageAdjust::default(),
+            texture: None,
+        }));
+    }
+
+    // Restyles the selected objects, or the one under the cursor when nothing
+    // is selected.
+    pub(crate) fn restyle_selected(&mut self, style: impl Fn(&ObjectStyle) -> Option<ObjectStyle>) {
+        let objects = match self.selection.is_empty() {
+            true => {
+                let position = self.board_cursor();
+                self.pick([position.x as f32, position.y as f32])
+                    .into_iter()
+                    .collect()
+            }
+            false => self.selection.clone(),
+        };
+        for object in objects {
+            let Some(from) = self.board.object_style(object) else {
+                continue;
+            };
+            let Some(to) = style(&from) else {
+                continue;
+            };
+            self.board
+                .push_action(Action::Restyle(Restyle { object, from, to }));
+        }
+    }
+
+    // An edit in progress is finished first, so undo never pops the object
+    // being typed into or the pending text instead of the last committed one.
+    pub(crate) fn undo(&mut self) {
+        self.discard_empty_text();
+        self.commit_text();
+        self.selection.clear();
+        self.board.undo();
+    }
+
+    pub(crate) fn redo(&mut self) {
+        self.discard_empty_text();
+        self.commit_text();
+        self.selection.clear();
+        self.board.redo();
+    }
+
+    pub(crate) fn replace_pages(&mut self, pages: Vec<Vec<Action>>) {
+        self.commit_text();
+        self.board.replace_pages(pages);
+        self.onion_page = None;
+        self.split = None;
+        self.board_replaced();
+    }
+
+    pub(crate) fn recover_pages(&mut self, pages: Vec<Vec<Action>>) {
+        self.commit_text();
+        self.board.recover_pages(pages);
+        self.onion_page = None;
+        self.split = None;
+        self.board_replaced();
+    }
+
+    pub(crate) fn show_page(&mut self, index: usize) {
+        self.commit_text();
+        if self.board.show_page(index) {
+            self.board_replaced();
+        }
+    }
+
+    pub(crate) fn turn_page(&mut self, forward: bool) {
+        let index = match forward {
+            true => self.board.current_page + 1,
+            false => match self.board.current_page.checked_sub(1) {
+                Some(index) => index,
+                None => return,
+            },
+        };
+        let current = self.board.current_page;
+        self.show_page(index);
+        if self.board.current_page != current {
+            self.page_turn = Some((Instant::now(), forward));
+        }
+    }
+
+    // While the pen is down or a caret is shown near the bottom or a side of
+    // the window, the board slides away from that edge, faster the closer it
+    // gets, so writing can go on without stopping to pan.
+    pub(crate) fn auto_scroll(&mut self) {
+        let writing = self.mouse_pressed && !self.current_stroke.is_empty();
+        let position = match writing {
+            true => Some(self.last_cursor_position),
+            false => self.caret_position,
+        };
+        let last = self.auto_scrolled.take();
+        let Some(position) = position.filter(|_| self.settings.auto_scroll) else {
+            return;
+        };
+        let depth = |distance: f64| (1.0 - distance as f32 / AUTO_SCROLL_MARGIN).clamp(0.0, 1.0);
+        let width = self.size.width as f64;
+        let height = self.size.height as f64;
+        let dx = depth(position.x) - depth(width - position.x);
+        let dy = -depth(height - position.y);
+        if dx == 0.0 && dy == 0.0 {
+            return;
+        }
+        let now = Instant::now();
+        if let Some(last) = last {
+            // A long gap between frames is not made up in one jump.
+            let step = AUTO_SCROLL_SPEED * (now - last).as_secs_f32().min(0.1);
+            self.board.camera.scroll(dx * step, dy * step);
+        }
+        self.auto_scrolled = Some(now);
+    }
+
+    pub(crate) fn new_page(&mut self) {
+        self.commit_text();
+        self.board.new_page();
+        if let Some(page) = &mut self.onion_page {
+            if *page >= self.board.current_page {
+                *page += 1;
+            }
+        }
+        if let Some(split) = &mut self.split {
+            if split.page >= self.board.current_page {
+                split.page += 1;
+            }
+        }
+        self.board_replaced();
+        self.pages_created += 1;
+    }
+
+    // Nothing being edited or selected survives the objects being swapped out.
+    fn board_replaced(&mut self) {
+        self.editing_text_index = None;
+        self.editing_stencil_index = None;
+        self.edited_text = None;
+        self.start_typing = false;
+        self.selection.clear();
+        self.pasted_image = None;
+        self.onion_vertices = None;
+        self.adjusting = None;
+        self.scan = None;
+        if let Some(split) = &mut self.split {
+            split.drawing = None;
+        }
+        self.damage = Damage::Full;
+    }
+}
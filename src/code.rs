@@ -0,0 +1,54 @@
+use lazy_static::lazy_static;
+use syntect::{
+    easy::HighlightLines,
+    highlighting::{Theme, ThemeSet},
+    parsing::SyntaxSet,
+    util::LinesWithEndings,
+};
+
+pub const LANGUAGES: [&str; 9] = [
+    "Plain Text",
+    "Rust",
+    "Python",
+    "C",
+    "C++",
+    "Java",
+    "JavaScript",
+    "Go",
+    "Bourne Again Shell (bash)",
+];
+
+const TAB_WIDTH: usize = 4;
+
+lazy_static! {
+    static ref SYNTAX_SET: SyntaxSet = SyntaxSet::load_defaults_newlines();
+    static ref THEME: Theme = ThemeSet::load_defaults().themes["InspiredGitHub"].clone();
+}
+
+pub fn normalize_indentation(code: &str) -> String {
+    code.replace("\r\n", "\n")
+        .replace('\t', &" ".repeat(TAB_WIDTH))
+        .trim_end()
+        .to_string()
+}
+
+pub fn highlight(code: &str, language: &str) -> Vec<(String, [u8; 4])> {
+    let syntax = SYNTAX_SET
+        .find_syntax_by_name(language)
+        .unwrap_or_else(|| SYNTAX_SET.find_syntax_plain_text());
+    let mut highlighter = HighlightLines::new(syntax, &THEME);
+
+    let mut spans = Vec::new();
+    for line in LinesWithEndings::from(code) {
+        match highlighter.highlight_line(line, &SYNTAX_SET) {
+            Ok(ranges) => {
+                for (style, text) in ranges {
+                    let color = style.foreground;
+                    spans.push((text.to_string(), [color.r, color.g, color.b, color.a]));
+                }
+            }
+            Err(_) => spans.push((line.to_string(), [0, 0, 0, 255])),
+        }
+    }
+    spans
+}
@@ -0,0 +1,697 @@
+use serde::{Deserialize, Serialize};
+use tao::keyboard::Key;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Command {
+    Undo,
+    Redo,
+    EraseLastStroke,
+    ToggleEraser,
+    ToggleSelect,
+    ToggleHighlighter,
+    ToggleCalligraphy,
+    PaintBucket,
+    HoldRectangle,
+    HoldRedaction,
+    LaserPointer,
+    PreviousTool,
+    CalibratePen,
+    ResetView,
+    TogglePaperRoll,
+    PreviousPage,
+    NextPage,
+    NewPage,
+    SummaryPage,
+    FindPage,
+    Flipbook,
+    SaveBoard,
+    OpenBoard,
+    ExportPdf,
+    ExportSvg,
+    ExportHtml,
+    ExportGif,
+    CycleBlendMode,
+    ToggleDropShadow,
+    DecreaseBrush,
+    IncreaseBrush,
+    PasteSpecial,
+    Copy,
+    Paste,
+    CopyStyle,
+    PasteStyle,
+    ToggleFill,
+    SwapColor,
+    PaletteColor(usize),
+    ArrangeStencils,
+    Replay,
+    LectureTimer,
+    LecturePage,
+    About,
+    InputDevices,
+    Shortcuts,
+    Statistics,
+    ReadAloud,
+    Magnifier,
+    ToggleMarkdown,
+    CycleTextDecoration,
+    ToggleFixedTextSize,
+    CommitText,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Context {
+    Board,
+    Typing,
+}
+
+pub struct CommandInfo {
+    pub command: Command,
+    pub context: Context,
+    pub category: &'static str,
+    pub description: &'static str,
+    pub binding: &'static str,
+}
+
+const fn info(
+    command: Command,
+    context: Context,
+    category: &'static str,
+    description: &'static str,
+    binding: &'static str,
+) -> CommandInfo {
+    CommandInfo {
+        command,
+        context,
+        category,
+        description,
+        binding,
+    }
+}
+
+pub const COMMANDS: [CommandInfo; 61] = [
+    info(Command::Undo, Context::Board, "Edit", "Undo", "Ctrl+Z"),
+    info(
+        Command::Redo,
+        Context::Board,
+        "Edit",
+        "Redo",
+        "Ctrl+Shift+Z",
+    ),
+    info(Command::Redo, Context::Board, "Edit", "Redo", "Ctrl+Y"),
+    info(
+        Command::EraseLastStroke,
+        Context::Board,
+        "Edit",
+        "Erase the last pen stroke",
+        "Ctrl+Backspace",
+    ),
+    info(
+        Command::ToggleSelect,
+        Context::Board,
+        "Edit",
+        "Toggle select tool",
+        "V",
+    ),
+    info(
+        Command::ToggleHighlighter,
+        Context::Board,
+        "Style",
+        "Toggle highlighter",
+        "H",
+    ),
+    info(
+        Command::ToggleCalligraphy,
+        Context::Board,
+        "Style",
+        "Toggle calligraphy nib",
+        "C",
+    ),
+    info(
+        Command::PaintBucket,
+        Context::Board,
+        "Style",
+        "Paint bucket",
+        "B",
+    ),
+    info(
+        Command::HoldRectangle,
+        Context::Board,
+        "Edit",
+        "Hold to draw a rectangle",
+        "S",
+    ),
+    info(
+        Command::HoldRedaction,
+        Context::Board,
+        "Edit",
+        "Hold to draw a redaction",
+        "R",
+    ),
+    info(
+        Command::LaserPointer,
+        Context::Board,
+        "Teaching",
+        "Toggle laser pointer",
+        "L",
+    ),
+    info(
+        Command::PreviousTool,
+        Context::Board,
+        "Edit",
+        "Switch back to the previous tool",
+        "",
+    ),
+    info(
+        Command::ToggleEraser,
+        Context::Board,
+        "Edit",
+        "Toggle eraser",
+        "E",
+    ),
+    info(
+        Command::SaveBoard,
+        Context::Board,
+        "File",
+        "Save board",
+        "Ctrl+S",
+    ),
+    info(
+        Command::OpenBoard,
+        Context::Board,
+        "File",
+        "Open board",
+        "Ctrl+O",
+    ),
+    info(
+        Command::ExportPdf,
+        Context::Board,
+        "File",
+        "Export pages as PDF",
+        "Ctrl+Shift+E",
+    ),
+    info(
+        Command::ExportSvg,
+        Context::Board,
+        "File",
+        "Export page as SVG",
+        "Ctrl+Alt+E",
+    ),
+    info(
+        Command::ExportHtml,
+        Context::Board,
+        "File",
+        "Export pages as an HTML viewer",
+        "Ctrl+Alt+H",
+    ),
+    info(
+        Command::ExportGif,
+        Context::Board,
+        "File",
+        "Export pages as a flipbook GIF",
+        "",
+    ),
+    info(
+        Command::Copy,
+        Context::Board,
+        "Edit",
+        "Copy selection or object under cursor",
+        "Ctrl+C",
+    ),
+    info(
+        Command::Paste,
+        Context::Board,
+        "Edit",
+        "Paste objects, text or an image",
+        "Ctrl+V",
+    ),
+    info(
+        Command::PasteSpecial,
+        Context::Board,
+        "Edit",
+        "Paste special",
+        "Ctrl+Shift+V",
+    ),
+    info(
+        Command::CopyStyle,
+        Context::Board,
+        "Edit",
+        "Copy style of object under cursor",
+        "Ctrl+Alt+C",
+    ),
+    info(
+        Command::PasteStyle,
+        Context::Board,
+        "Edit",
+        "Paste style onto selection or object under cursor",
+        "Ctrl+Alt+V",
+    ),
+    info(
+        Command::SwapColor,
+        Context::Board,
+        "Colors",
+        "Swap to previous color",
+        "X",
+    ),
+    info(
+        Command::PaletteColor(0),
+        Context::Board,
+        "Colors",
+        "Red",
+        "1",
+    ),
+    info(
+        Command::PaletteColor(1),
+        Context::Board,
+        "Colors",
+        "Green",
+        "2",
+    ),
+    info(
+        Command::PaletteColor(2),
+        Context::Board,
+        "Colors",
+        "Blue",
+        "3",
+    ),
+    info(
+        Command::PaletteColor(3),
+        Context::Board,
+        "Colors",
+        "Yellow",
+        "4",
+    ),
+    info(
+        Command::PaletteColor(4),
+        Context::Board,
+        "Colors",
+        "Magenta",
+        "5",
+    ),
+    info(
+        Command::PaletteColor(5),
+        Context::Board,
+        "Colors",
+        "Cyan",
+        "6",
+    ),
+    info(
+        Command::PaletteColor(6),
+        Context::Board,
+        "Colors",
+        "Black",
+        "7",
+    ),
+    info(
+        Command::PaletteColor(7),
+        Context::Board,
+        "Colors",
+        "White",
+        "8",
+    ),
+    info(
+        Command::CycleBlendMode,
+        Context::Board,
+        "Style",
+        "Cycle stroke blend mode",
+        "Ctrl+B",
+    ),
+    info(
+        Command::ToggleDropShadow,
+        Context::Board,
+        "Style",
+        "Toggle drop shadow",
+        "Ctrl+D",
+    ),
+    info(
+        Command::ToggleFill,
+        Context::Board,
+        "Style",
+        "Toggle fill of selected or hovered rectangles",
+        "F",
+    ),
+    info(
+        Command::DecreaseBrush,
+        Context::Board,
+        "Style",
+        "Thinner brush (or Alt+scroll)",
+        "[",
+    ),
+    info(
+        Command::IncreaseBrush,
+        Context::Board,
+        "Style",
+        "Thicker brush (or Alt+scroll)",
+        "]",
+    ),
+    info(
+        Command::ArrangeStencils,
+        Context::Board,
+        "Diagrams",
+        "Auto arrange stencils",
+        "Ctrl+L",
+    ),
+    info(
+        Command::Replay,
+        Context::Board,
+        "Teaching",
+        "Replay strokes",
+        "Ctrl+R",
+    ),
+    info(
+        Command::LectureTimer,
+        Context::Board,
+        "Teaching",
+        "Toggle lecture timer",
+        "Ctrl+T",
+    ),
+    info(
+        Command::LecturePage,
+        Context::Board,
+        "Teaching",
+        "New dated lecture page",
+        "Ctrl+Shift+T",
+    ),
+    info(
+        Command::ResetView,
+        Context::Board,
+        "View",
+        "Reset pan and zoom",
+        "Ctrl+0",
+    ),
+    info(
+        Command::TogglePaperRoll,
+        Context::Board,
+        "View",
+        "Scroll like a paper roll",
+        "",
+    ),
+    info(
+        Command::PreviousPage,
+        Context::Board,
+        "Pages",
+        "Previous page",
+        "Ctrl+PageUp",
+    ),
+    info(
+        Command::NextPage,
+        Context::Board,
+        "Pages",
+        "Next page",
+        "Ctrl+PageDown",
+    ),
+    info(
+        Command::NewPage,
+        Context::Board,
+        "Pages",
+        "New page",
+        "Ctrl+Shift+N",
+    ),
+    info(
+        Command::SummaryPage,
+        Context::Board,
+        "Pages",
+        "Summary page of all pages",
+        "Ctrl+Alt+S",
+    ),
+    info(
+        Command::FindPage,
+        Context::Board,
+        "Pages",
+        "Find a page by name or tag",
+        "Ctrl+P",
+    ),
+    info(
+        Command::Flipbook,
+        Context::Board,
+        "Pages",
+        "Play or stop pages as a flipbook",
+        "Ctrl+Alt+F",
+    ),
+    info(
+        Command::CalibratePen,
+        Context::Board,
+        "Help",
+        "Calibrate pen offset",
+        "Ctrl+Alt+P",
+    ),
+    info(
+        Command::About,
+        Context::Board,
+        "Help",
+        "About and diagnostics",
+        "F1",
+    ),
+    info(
+        Command::InputDevices,
+        Context::Board,
+        "Help",
+        "Tools per input device",
+        "",
+    ),
+    info(
+        Command::ReadAloud,
+        Context::Board,
+        "Accessibility",
+        "Read text under cursor aloud (again to stop)",
+        "Ctrl+Shift+S",
+    ),
+    info(
+        Command::Magnifier,
+        Context::Board,
+        "Accessibility",
+        "Toggle zoom lens",
+        "Z",
+    ),
+    info(
+        Command::Statistics,
+        Context::Board,
+        "Help",
+        "Usage statistics",
+        "Ctrl+I",
+    ),
+    info(
+        Command::Shortcuts,
+        Context::Board,
+        "Help",
+        "Keyboard shortcuts",
+        "?",
+    ),
+    info(
+        Command::CommitText,
+        Context::Typing,
+        "Text",
+        "Finish text",
+        "Enter",
+    ),
+    info(
+        Command::ToggleMarkdown,
+        Context::Typing,
+        "Text",
+        "Toggle markdown",
+        "Ctrl+M",
+    ),
+    info(
+        Command::CycleTextDecoration,
+        Context::Typing,
+        "Text",
+        "Cycle halo and chip decoration",
+        "Ctrl+H",
+    ),
+    info(
+        Command::ToggleFixedTextSize,
+        Context::Typing,
+        "Text",
+        "Keep text the same size on screen when zooming",
+        "Ctrl+K",
+    ),
+];
+
+// A binding from the settings file that replaces a command's default. Empty
+// `keys` leave the command unbound.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct KeyBinding {
+    pub command: Command,
+    pub keys: String,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Binding {
+    pub ctrl: bool,
+    pub alt: bool,
+    pub shift: bool,
+    pub key: String,
+}
+
+impl Binding {
+    pub fn parse(text: &str) -> Option<Self> {
+        let mut binding = Binding {
+            ctrl: false,
+            alt: false,
+            shift: false,
+            key: String::new(),
+        };
+        for part in text.split('+').map(str::trim) {
+            match part.to_lowercase().as_str() {
+                "ctrl" | "control" => binding.ctrl = true,
+                "alt" => binding.alt = true,
+                "shift" => binding.shift = true,
+                "" => {}
+                _ => binding.key = normalize(part),
+            }
+        }
+        if binding.key.is_empty() && text.ends_with('+') {
+            binding.key = "+".to_string();
+        }
+        (!binding.key.is_empty()).then_some(binding)
+    }
+
+    pub fn label(&self) -> String {
+        let mut label = String::new();
+        if self.ctrl {
+            label.push_str("Ctrl+");
+        }
+        if self.alt {
+            label.push_str("Alt+");
+        }
+        if self.shift {
+            label.push_str("Shift+");
+        }
+        if self.key.chars().count() == 1 {
+            label.push_str(&self.key.to_uppercase());
+        } else {
+            label.push_str(&self.key);
+        }
+        label
+    }
+
+    fn matches(&self, key: &str, ctrl: bool, alt: bool, shift: bool) -> bool {
+        let letter = self.key.chars().all(char::is_alphabetic) || self.key.chars().count() > 1;
+        self.key == key && self.ctrl == ctrl && self.alt == alt && (!letter || self.shift == shift)
+    }
+}
+
+fn normalize(key: &str) -> String {
+    if key.chars().count() == 1 {
+        key.to_lowercase()
+    } else {
+        key.to_string()
+    }
+}
+
+pub fn key_name(key: &Key) -> Option<String> {
+    match key {
+        Key::Character(char) => Some(normalize(char)),
+        Key::Enter => Some("Enter".to_string()),
+        Key::Escape => Some("Escape".to_string()),
+        Key::Tab => Some("Tab".to_string()),
+        Key::Space => Some("Space".to_string()),
+        Key::Backspace => Some("Backspace".to_string()),
+        Key::PageUp => Some("PageUp".to_string()),
+        Key::PageDown => Some("PageDown".to_string()),
+        Key::F1 => Some("F1".to_string()),
+        Key::F2 => Some("F2".to_string()),
+        Key::F3 => Some("F3".to_string()),
+        Key::F4 => Some("F4".to_string()),
+        Key::F5 => Some("F5".to_string()),
+        Key::F6 => Some("F6".to_string()),
+        Key::F7 => Some("F7".to_string()),
+        Key::F8 => Some("F8".to_string()),
+        Key::F9 => Some("F9".to_string()),
+        Key::F10 => Some("F10".to_string()),
+        Key::F11 => Some("F11".to_string()),
+        Key::F12 => Some("F12".to_string()),
+        _ => None,
+    }
+}
+
+pub struct Keymap {
+    bindings: Vec<(Command, Context, Binding)>,
+}
+
+impl Keymap {
+    // Custom bindings come first so they win over any default they collide with.
+    pub fn new(custom: &[KeyBinding]) -> Self {
+        let context = |command: Command| {
+            COMMANDS
+                .iter()
+                .find(|info| info.command == command)
+                .map_or(Context::Board, |info| info.context)
+        };
+        let custom_bindings = custom.iter().filter_map(|binding| {
+            let keys = Binding::parse(&binding.keys)?;
+            Some((binding.command, context(binding.command), keys))
+        });
+        let defaults = COMMANDS
+            .iter()
+            .filter(|info| !custom.iter().any(|binding| binding.command == info.command))
+            .filter_map(|info| Some((info.command, info.context, Binding::parse(info.binding)?)));
+        Keymap {
+            bindings: custom_bindings.chain(defaults).collect(),
+        }
+    }
+
+    pub fn lookup(
+        &self,
+        context: Context,
+        key: &Key,
+        ctrl: bool,
+        alt: bool,
+        shift: bool,
+    ) -> Option<Command> {
+        let key = key_name(key)?;
+        self.bindings
+            .iter()
+            .find(|(_, bound_context, binding)| {
+                *bound_context == context && binding.matches(&key, ctrl, alt, shift)
+            })
+            .map(|(command, _, _)| *command)
+    }
+
+    pub fn binding(&self, command: Command) -> Option<&Binding> {
+        self.bindings
+            .iter()
+            .find(|(bound, _, _)| *bound == command)
+            .map(|(_, _, binding)| binding)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bindings_read_modifiers_in_any_case() {
+        assert_eq!(
+            Binding::parse("control + Shift+Z"),
+            Some(Binding {
+                ctrl: true,
+                alt: false,
+                shift: true,
+                key: "z".to_string(),
+            })
+        );
+        assert_eq!(Binding::parse("Ctrl+Alt+PageDown").unwrap().key, "PageDown");
+        assert_eq!(Binding::parse("Ctrl+Alt+f").unwrap().label(), "Ctrl+Alt+F");
+    }
+
+    #[test]
+    fn a_plus_key_and_empty_bindings() {
+        let binding = Binding::parse("Ctrl++").unwrap();
+        assert!(binding.ctrl);
+        assert_eq!(binding.key, "+");
+        assert_eq!(Binding::parse(""), None);
+        assert_eq!(Binding::parse("Ctrl+Shift"), None);
+    }
+
+    #[test]
+    fn every_default_binding_parses() {
+        for info in COMMANDS.iter().filter(|info| !info.binding.is_empty()) {
+            assert!(
+                Binding::parse(info.binding).is_some(),
+                "{:?} has an unreadable binding {}",
+                info.command,
+                info.binding
+            );
+        }
+    }
+}
@@ -0,0 +1,94 @@
+use lazy_static::lazy_static;
+use std::{backtrace::Backtrace, collections::VecDeque, fs, panic, path::PathBuf, sync::Mutex};
+
+const LOG_LINES: usize = 100;
+
+lazy_static! {
+    static ref LOG: Mutex<VecDeque<String>> = Mutex::new(VecDeque::with_capacity(LOG_LINES));
+    static ref ADAPTER: Mutex<Option<String>> = Mutex::new(None);
+}
+
+pub fn dir() -> Option<PathBuf> {
+    dirs::data_dir().map(|dir| dir.join("rust-whiteboard"))
+}
+
+pub fn recovery_path() -> Option<PathBuf> {
+    dir().map(|dir| dir.join("recovery.json"))
+}
+
+pub fn log(line: String) {
+    eprintln!("{}", line);
+    if let Ok(mut log) = LOG.lock() {
+        if log.len() == LOG_LINES {
+            log.pop_front();
+        }
+        log.push_back(line);
+    }
+}
+
+pub fn set_adapter(info: &wgpu::AdapterInfo) {
+    if let Ok(mut adapter) = ADAPTER.lock() {
+        *adapter = Some(format!(
+            "{} ({:?}, {:?}, driver {} {})",
+            info.name, info.backend, info.device_type, info.driver, info.driver_info
+        ));
+    }
+}
+
+pub fn install() {
+    let default_hook = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        default_hook(info);
+        write_report(&info.to_string());
+    }));
+}
+
+fn write_report(message: &str) {
+    let Some(dir) = dir() else {
+        return;
+    };
+    let _ = fs::create_dir_all(&dir);
+
+    let mut report = format!("{}\n\n", message);
+    if let Ok(adapter) = ADAPTER.try_lock() {
+        let adapter = adapter.as_deref().unwrap_or("unknown");
+        report.push_str(&format!("Adapter: {}\n\n", adapter));
+    }
+    report.push_str(&format!("Backtrace:\n{}\n", Backtrace::force_capture()));
+    if let Ok(log) = LOG.try_lock() {
+        report.push_str(&format!("\nLast {} log lines:\n", log.len()));
+        for line in log.iter() {
+            report.push_str(line);
+            report.push('\n');
+        }
+    }
+
+    let path = dir.join(format!("crash-{}.txt", crate::timestamp_millis()));
+    match fs::write(&path, report) {
+        Ok(()) => eprintln!("Crash report written to {}", path.display()),
+        Err(err) => eprintln!("Unable to write crash report {}: {}", path.display(), err),
+    }
+}
+
+pub fn dump_board(contents: &str) {
+    let Some(path) = recovery_path() else {
+        return;
+    };
+    if let Some(dir) = path.parent() {
+        let _ = fs::create_dir_all(dir);
+    }
+    match fs::write(&path, contents) {
+        Ok(()) => eprintln!("Board saved to {}", path.display()),
+        Err(err) => eprintln!("Unable to save board {}: {}", path.display(), err),
+    }
+}
+
+pub fn load_recovery() -> Option<String> {
+    fs::read_to_string(recovery_path()?).ok()
+}
+
+pub fn discard_recovery() {
+    if let Some(path) = recovery_path() {
+        let _ = fs::remove_file(path);
+    }
+}
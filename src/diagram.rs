@@ -0,0 +1,394 @@
+use crate::stencil::StencilKind;
+
+const LAYER_SPACING: f32 = 160.0;
+const NODE_SPACING: f32 = 200.0;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Direction {
+    Vertical,
+    Horizontal,
+}
+
+type Shape = (String, StencilKind);
+
+#[derive(Clone, Debug)]
+pub struct Node {
+    pub id: String,
+    pub label: String,
+    pub kind: StencilKind,
+}
+
+#[derive(Clone, Debug)]
+pub struct Edge {
+    pub from: usize,
+    pub to: usize,
+    pub label: Option<String>,
+}
+
+#[derive(Clone, Debug)]
+pub struct Graph {
+    pub nodes: Vec<Node>,
+    pub edges: Vec<Edge>,
+    direction: Direction,
+}
+
+impl Graph {
+    fn new() -> Self {
+        Graph {
+            nodes: Vec::new(),
+            edges: Vec::new(),
+            direction: Direction::Vertical,
+        }
+    }
+
+    fn node(&mut self, id: &str, shape: Option<Shape>) -> usize {
+        let index = match self.nodes.iter().position(|node| node.id == id) {
+            Some(index) => index,
+            None => {
+                self.nodes.push(Node {
+                    id: id.to_string(),
+                    label: id.to_string(),
+                    kind: StencilKind::Process,
+                });
+                self.nodes.len() - 1
+            }
+        };
+        if let Some((label, kind)) = shape {
+            self.nodes[index].label = label;
+            self.nodes[index].kind = kind;
+        }
+        index
+    }
+
+    pub fn layout(&self, origin: [f32; 2]) -> Vec<[f32; 2]> {
+        let edges: Vec<_> = self.edges.iter().map(|edge| (edge.from, edge.to)).collect();
+        layered(self.nodes.len(), &edges, self.direction, origin)
+    }
+}
+
+pub fn arrange(node_count: usize, edges: &[(usize, usize)], origin: [f32; 2]) -> Vec<[f32; 2]> {
+    layered(node_count, edges, Direction::Vertical, origin)
+}
+
+fn back_edges(node_count: usize, edges: &[(usize, usize)]) -> Vec<bool> {
+    fn visit(edges: &[(usize, usize)], node: usize, state: &mut [u8], back: &mut [bool]) {
+        state[node] = 1;
+        for (index, &(from, to)) in edges.iter().enumerate() {
+            if from != node {
+                continue;
+            }
+            match state[to] {
+                0 => visit(edges, to, state, back),
+                1 => back[index] = true,
+                _ => {}
+            }
+        }
+        state[node] = 2;
+    }
+
+    let mut state = vec![0; node_count];
+    let mut back = vec![false; edges.len()];
+    for node in 0..node_count {
+        if state[node] == 0 {
+            visit(edges, node, &mut state, &mut back);
+        }
+    }
+    back
+}
+
+fn layered(
+    node_count: usize,
+    edges: &[(usize, usize)],
+    direction: Direction,
+    origin: [f32; 2],
+) -> Vec<[f32; 2]> {
+    let back = back_edges(node_count, edges);
+    let mut layers = vec![0; node_count];
+    for _ in 0..node_count {
+        let mut changed = false;
+        for (&(from, to), _) in edges.iter().zip(&back).filter(|(_, back)| !**back) {
+            let layer = layers[from] + 1;
+            if layers[to] < layer {
+                layers[to] = layer;
+                changed = true;
+            }
+        }
+        if !changed {
+            break;
+        }
+    }
+
+    let depth = layers.iter().max().map_or(0, |depth| depth + 1);
+    let counts: Vec<usize> = (0..depth)
+        .map(|layer| layers.iter().filter(|l| **l == layer).count())
+        .collect();
+    let mut slots = vec![0; depth];
+
+    layers
+        .iter()
+        .map(|&layer| {
+            let slot = slots[layer];
+            slots[layer] += 1;
+            let across = (slot as f32 - (counts[layer] as f32 - 1.0) / 2.0) * NODE_SPACING;
+            let along = layer as f32 * LAYER_SPACING;
+            match direction {
+                Direction::Vertical => [origin[0] + across, origin[1] + along],
+                Direction::Horizontal => [origin[0] + along, origin[1] + across],
+            }
+        })
+        .collect()
+}
+
+pub fn looks_like_diagram(text: &str) -> bool {
+    let first = text
+        .lines()
+        .map(str::trim)
+        .find(|line| !line.is_empty())
+        .unwrap_or_default();
+    ["graph", "flowchart", "digraph", "strict"]
+        .iter()
+        .any(|keyword| first.starts_with(keyword))
+}
+
+pub fn parse(text: &str) -> Result<Graph, String> {
+    let graph = if text.contains('{') && text.trim_end().ends_with('}') {
+        parse_dot(text)?
+    } else {
+        parse_mermaid(text)?
+    };
+
+    if graph.nodes.is_empty() {
+        return Err("no nodes found".to_string());
+    }
+    Ok(graph)
+}
+
+fn parse_mermaid(text: &str) -> Result<Graph, String> {
+    let mut graph = Graph::new();
+    let mut lines = text.lines().map(str::trim).filter(|line| !line.is_empty());
+
+    let header = lines.next().unwrap_or_default();
+    let mut words = header.split_whitespace();
+    if !matches!(words.next(), Some("graph" | "flowchart")) {
+        return Err("expected a graph or flowchart header".to_string());
+    }
+    if matches!(words.next(), Some("LR" | "RL")) {
+        graph.direction = Direction::Horizontal;
+    }
+
+    const IGNORED: [&str; 8] = [
+        "%%",
+        "classDef",
+        "class ",
+        "style",
+        "linkStyle",
+        "click",
+        "subgraph",
+        "end",
+    ];
+    for statement in lines.flat_map(|line| line.split(';')) {
+        let statement = statement.trim();
+        if statement.is_empty() || IGNORED.iter().any(|prefix| statement.starts_with(prefix)) {
+            continue;
+        }
+        parse_mermaid_statement(&mut graph, statement)?;
+    }
+    Ok(graph)
+}
+
+fn parse_mermaid_statement(graph: &mut Graph, statement: &str) -> Result<(), String> {
+    let mut rest = statement;
+    let mut previous: Option<(usize, Option<String>)> = None;
+
+    loop {
+        let (id, shape, after) =
+            mermaid_node(rest).ok_or_else(|| format!("expected a node in \"{}\"", statement))?;
+        let index = graph.node(id, shape);
+        if let Some((from, label)) = previous.take() {
+            graph.edges.push(Edge {
+                from,
+                to: index,
+                label,
+            });
+        }
+
+        rest = after.trim_start();
+        if rest.is_empty() {
+            return Ok(());
+        }
+        let (label, after) =
+            mermaid_arrow(rest).ok_or_else(|| format!("expected an arrow in \"{}\"", statement))?;
+        previous = Some((index, label));
+        rest = after.trim_start();
+    }
+}
+
+fn mermaid_node(text: &str) -> Option<(&str, Option<Shape>, &str)> {
+    let end = text
+        .find(|c: char| !(c.is_alphanumeric() || c == '_'))
+        .unwrap_or(text.len());
+    if end == 0 {
+        return None;
+    }
+    let (id, rest) = text.split_at(end);
+
+    const SHAPES: [(&str, &str, StencilKind); 7] = [
+        ("((", "))", StencilKind::Terminator),
+        ("([", "])", StencilKind::Terminator),
+        ("[[", "]]", StencilKind::Process),
+        ("[", "]", StencilKind::Process),
+        ("(", ")", StencilKind::Terminator),
+        ("{", "}", StencilKind::Decision),
+        (">", "]", StencilKind::Note),
+    ];
+    for (open, close, kind) in SHAPES {
+        if let Some(inner) = rest.strip_prefix(open) {
+            let close_at = inner.find(close)?;
+            let label = unquote(inner[..close_at].trim()).to_string();
+            return Some((id, Some((label, kind)), &inner[close_at + close.len()..]));
+        }
+    }
+    Some((id, None, rest))
+}
+
+fn mermaid_arrow(text: &str) -> Option<(Option<String>, &str)> {
+    let end = text
+        .find(|c: char| !"-=.<>".contains(c))
+        .unwrap_or(text.len());
+    if end < 2 {
+        return None;
+    }
+    let (arrow, mut rest) = text.split_at(end);
+
+    let mut label = None;
+    if arrow == "--" || arrow == "==" {
+        let close = ["-->", "---", "==>", "-.->"]
+            .iter()
+            .filter_map(|close| rest.find(close).map(|at| (at, close.len())))
+            .min()?;
+        label = Some(rest[..close.0].trim().to_string());
+        rest = &rest[close.0 + close.1..];
+    }
+
+    let trimmed = rest.trim_start();
+    if let Some(inner) = trimmed.strip_prefix('|') {
+        let close = inner.find('|')?;
+        label = Some(unquote(inner[..close].trim()).to_string());
+        rest = &inner[close + 1..];
+    }
+    Some((label.filter(|label| !label.is_empty()), rest))
+}
+
+fn parse_dot(text: &str) -> Result<Graph, String> {
+    let mut graph = Graph::new();
+    let (Some(open), Some(close)) = (text.find('{'), text.rfind('}')) else {
+        return Err("expected a { ... } graph body".to_string());
+    };
+    if !text[..open].contains("graph") {
+        return Err("expected a graph or digraph header".to_string());
+    }
+
+    for statement in text[open + 1..close].split([';', '\n']) {
+        let statement = statement.trim();
+        if statement.is_empty() || statement.starts_with("//") || statement.starts_with('#') {
+            continue;
+        }
+
+        let (head, attributes) = match statement.find('[') {
+            Some(at) => (
+                statement[..at].trim(),
+                dot_attributes(statement[at + 1..].trim_end_matches(']')),
+            ),
+            None => (statement, Vec::new()),
+        };
+        let attribute = |key: &str| {
+            attributes
+                .iter()
+                .find(|(name, _)| name == key)
+                .map(|(_, value)| value.clone())
+        };
+
+        if let Some(("rankdir", value)) = head.split_once('=').map(|(k, v)| (k.trim(), v.trim())) {
+            if matches!(unquote(value), "LR" | "RL") {
+                graph.direction = Direction::Horizontal;
+            }
+            continue;
+        }
+        if matches!(head, "graph" | "node" | "edge") {
+            if let Some("LR" | "RL") = attribute("rankdir").as_deref() {
+                graph.direction = Direction::Horizontal;
+            }
+            continue;
+        }
+        if head.contains('=') || head.starts_with("subgraph") {
+            continue;
+        }
+
+        let ids: Vec<&str> = head
+            .split("->")
+            .flat_map(|part| part.split("--"))
+            .map(|id| unquote(id.trim()))
+            .collect();
+        if ids.len() == 1 {
+            let shape = attribute("label").map(|label| {
+                let kind = dot_kind(attribute("shape").as_deref());
+                (label, kind)
+            });
+            let shape = shape.or_else(|| {
+                attribute("shape").map(|shape| (ids[0].to_string(), dot_kind(Some(&shape))))
+            });
+            graph.node(ids[0], shape);
+        } else {
+            let indices: Vec<usize> = ids.iter().map(|id| graph.node(id, None)).collect();
+            for pair in indices.windows(2) {
+                graph.edges.push(Edge {
+                    from: pair[0],
+                    to: pair[1],
+                    label: attribute("label"),
+                });
+            }
+        }
+    }
+    Ok(graph)
+}
+
+fn dot_attributes(text: &str) -> Vec<(String, String)> {
+    let mut attributes = Vec::new();
+    let mut current = String::new();
+    let mut quoted = false;
+    for c in text.chars().chain(std::iter::once(',')) {
+        match c {
+            '"' => {
+                quoted = !quoted;
+                current.push(c);
+            }
+            ',' | ';' if !quoted => {
+                if let Some((key, value)) = current.split_once('=') {
+                    attributes.push((
+                        key.trim().to_string(),
+                        unquote(value.trim()).replace("\\n", "\n"),
+                    ));
+                }
+                current.clear();
+            }
+            _ => current.push(c),
+        }
+    }
+    attributes
+}
+
+fn dot_kind(shape: Option<&str>) -> StencilKind {
+    match shape {
+        Some("diamond") => StencilKind::Decision,
+        Some("ellipse" | "oval" | "circle" | "doublecircle") => StencilKind::Terminator,
+        Some("note") => StencilKind::Note,
+        Some("record" | "Mrecord") => StencilKind::ClassBox,
+        _ => StencilKind::Process,
+    }
+}
+
+fn unquote(text: &str) -> &str {
+    text.strip_prefix('"')
+        .and_then(|text| text.strip_suffix('"'))
+        .unwrap_or(text)
+}
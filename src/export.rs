@@ -0,0 +1,6 @@
+pub mod gif;
+pub mod html;
+pub mod paint;
+pub mod pdf;
+pub mod preview;
+pub mod svg;
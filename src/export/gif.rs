@@ -0,0 +1,342 @@
+use crate::{
+    export::paint::{self, Backdrop, Painter},
+    normalized_to_rgba, rgba_to_normalized, scene, Action, Rect,
+};
+use glyphon::{cosmic_text::ttf_parser, fontdb, Buffer, FontSystem};
+use image::{
+    codecs::gif::{GifEncoder, Repeat},
+    Delay, Frame, RgbaImage,
+};
+use std::{
+    fs::{self, File},
+    io::BufWriter,
+    path::{Path, PathBuf},
+};
+use tao::dpi::PhysicalSize;
+use tiny_skia::{
+    ColorU8, FillRule, FilterQuality, IntSize, LineCap, LineJoin, Paint, PathBuilder, Pixmap,
+    PixmapPaint, Stroke, Transform,
+};
+
+pub const EXTENSION: &str = "gif";
+// The longer side of a frame, in pixels, at most.
+const MAX_SIZE: f32 = 960.0;
+
+// Plays the pages in order as the frames of a looping animation, each shown
+// for 1 / `fps` of a second. Every frame covers the area all the pages draw
+// in, so objects keep their place from one frame to the next.
+pub fn export(
+    path: &Path,
+    pages: &[Vec<Action>],
+    size: PhysicalSize<u32>,
+    backdrop: &Backdrop,
+    fps: u32,
+    font_system: &mut FontSystem,
+) -> Result<PathBuf, String> {
+    let path = if path.extension().is_none() {
+        path.with_extension(EXTENSION)
+    } else {
+        path.to_path_buf()
+    };
+    let area = scene::bounds(
+        pages.iter().flat_map(|page| paint::visible(page, size).0),
+        size,
+    )
+    .unwrap_or(Rect {
+        x: 0.0,
+        y: 0.0,
+        width: size.width as f32,
+        height: size.height as f32,
+    });
+    let frame = paint::frame(&area);
+    let scale = (MAX_SIZE / frame.width.max(frame.height)).min(1.0);
+    let (width, height) = (
+        (frame.width * scale).ceil() as u32,
+        (frame.height * scale).ceil() as u32,
+    );
+    let delay = Delay::from_numer_denom_ms(1000, fps.max(1));
+    let mut frames = Vec::new();
+    for page in pages {
+        let mut raster = Raster {
+            pixmap: Pixmap::new(width, height).ok_or("The pages are empty")?,
+            transform: Transform::from_scale(scale, scale).pre_translate(-frame.x, -frame.y),
+        };
+        paint::paper(backdrop, &frame, &mut raster);
+        paint::paint(page, size, font_system, &mut raster);
+        let pixels = raster
+            .pixmap
+            .pixels()
+            .iter()
+            .flat_map(|pixel| {
+                let color = pixel.demultiply();
+                [color.red(), color.green(), color.blue(), color.alpha()]
+            })
+            .collect();
+        let image = RgbaImage::from_raw(width, height, pixels)
+            .ok_or("The frame does not match its size")?;
+        frames.push(Frame::from_parts(image, 0, 0, delay));
+    }
+
+    if let Some(parent) = path.parent() {
+        let _ = fs::create_dir_all(parent);
+    }
+    let file = File::create(&path).map_err(|err| err.to_string())?;
+    let mut encoder = GifEncoder::new(BufWriter::new(file));
+    encoder
+        .set_repeat(Repeat::Infinite)
+        .map_err(|err| err.to_string())?;
+    encoder
+        .encode_frames(frames)
+        .map_err(|err| err.to_string())?;
+    Ok(path)
+}
+
+// Draws a page into a picture, with `transform` taking board pixels to the
+// picture's.
+struct Raster {
+    pixmap: Pixmap,
+    transform: Transform,
+}
+
+fn solid(color: [f32; 4]) -> Paint<'static> {
+    let [red, green, blue, alpha] = normalized_to_rgba(color);
+    let mut paint = Paint::default();
+    paint.set_color_rgba8(red, green, blue, alpha);
+    paint.anti_alias = true;
+    paint
+}
+
+fn pen(width: f32) -> Stroke {
+    Stroke {
+        width,
+        line_cap: LineCap::Round,
+        line_join: LineJoin::Round,
+        ..Stroke::default()
+    }
+}
+
+impl Painter for Raster {
+    fn fill(&mut self, rects: &[Rect], color: [f32; 4]) {
+        let paint = solid(color);
+        for rect in rects {
+            if let Some(rect) = tiny_skia::Rect::from_xywh(rect.x, rect.y, rect.width, rect.height)
+            {
+                self.pixmap.fill_rect(rect, &paint, self.transform, None);
+            }
+        }
+    }
+
+    fn outline(&mut self, rect: &Rect, color: [f32; 4]) {
+        let Some(rect) = tiny_skia::Rect::from_xywh(rect.x, rect.y, rect.width, rect.height) else {
+            return;
+        };
+        let path = PathBuilder::from_rect(rect);
+        self.pixmap
+            .stroke_path(&path, &solid(color), &pen(1.0), self.transform, None);
+    }
+
+    fn image(&mut self, bounds: &Rect, data: &[u8]) {
+        let Ok(picture) = image::load_from_memory(data) else {
+            return;
+        };
+        let picture = picture.to_rgba8();
+        let (width, height) = picture.dimensions();
+        // tiny-skia keeps its pixels premultiplied.
+        let pixels = picture
+            .pixels()
+            .flat_map(|pixel| {
+                let [red, green, blue, alpha] = pixel.0;
+                let color = ColorU8::from_rgba(red, green, blue, alpha).premultiply();
+                [color.red(), color.green(), color.blue(), color.alpha()]
+            })
+            .collect();
+        let Some(picture) =
+            IntSize::from_wh(width, height).and_then(|size| Pixmap::from_vec(pixels, size))
+        else {
+            return;
+        };
+        let transform = self
+            .transform
+            .pre_translate(bounds.x, bounds.y)
+            .pre_scale(bounds.width / width as f32, bounds.height / height as f32);
+        let paint = PixmapPaint {
+            quality: FilterQuality::Bilinear,
+            ..PixmapPaint::default()
+        };
+        self.pixmap
+            .draw_pixmap(0, 0, picture.as_ref(), &paint, transform, None);
+    }
+
+    fn lines(&mut self, points: &[[f32; 2]], color: [u8; 4]) {
+        let mut builder = PathBuilder::new();
+        for pair in points.chunks_exact(2) {
+            let [[x1, y1], [x2, y2]] = [pair[0], pair[1]];
+            builder.move_to(x1, y1);
+            builder.line_to(x2, y2);
+        }
+        if let Some(path) = builder.finish() {
+            let paint = solid(rgba_to_normalized(color));
+            self.pixmap
+                .stroke_path(&path, &paint, &pen(1.0), self.transform, None);
+        }
+    }
+
+    fn stroke(&mut self, points: &[[f32; 2]], width: f32, color: [f32; 4]) {
+        let paint = solid(color);
+        // A lone point still leaves a round dot.
+        if let [[x, y]] = points {
+            if let Some(dot) = PathBuilder::from_circle(*x, *y, width / 2.0) {
+                self.pixmap
+                    .fill_path(&dot, &paint, FillRule::Winding, self.transform, None);
+            }
+            return;
+        }
+        let mut builder = PathBuilder::new();
+        for (index, &[x, y]) in points.iter().enumerate() {
+            match index {
+                0 => builder.move_to(x, y),
+                _ => builder.line_to(x, y),
+            }
+        }
+        if let Some(path) = builder.finish() {
+            self.pixmap
+                .stroke_path(&path, &paint, &pen(width), self.transform, None);
+        }
+    }
+
+    // Each glyph is filled from its outline in the font it was shaped with.
+    fn glyphs(
+        &mut self,
+        fonts: &fontdb::Database,
+        buffer: &Buffer,
+        [left, top]: [f32; 2],
+        color: [u8; 4],
+    ) {
+        for run in buffer.layout_runs() {
+            for glyph in run.glyphs {
+                let color = glyph
+                    .color_opt
+                    .map_or(color, |color| [color.r(), color.g(), color.b(), color.a()]);
+                let origin = [
+                    left + glyph.x + glyph.font_size * glyph.x_offset,
+                    top + run.line_y + glyph.y - glyph.font_size * glyph.y_offset,
+                ];
+                let path = fonts
+                    .with_face_data(glyph.font_id, |data, index| {
+                        let face = ttf_parser::Face::parse(data, index).ok()?;
+                        let mut outline = Outline {
+                            builder: PathBuilder::new(),
+                            origin,
+                            scale: glyph.font_size / face.units_per_em() as f32,
+                        };
+                        face.outline_glyph(ttf_parser::GlyphId(glyph.glyph_id), &mut outline)?;
+                        outline.builder.finish()
+                    })
+                    .flatten();
+                if let Some(path) = path {
+                    let paint = solid(rgba_to_normalized(color));
+                    self.pixmap
+                        .fill_path(&path, &paint, FillRule::Winding, self.transform, None);
+                }
+            }
+        }
+    }
+}
+
+// A glyph's outline moved from font units, y up, to board pixels at its
+// baseline origin.
+struct Outline {
+    builder: PathBuilder,
+    origin: [f32; 2],
+    scale: f32,
+}
+
+impl Outline {
+    fn point(&self, x: f32, y: f32) -> (f32, f32) {
+        (
+            self.origin[0] + x * self.scale,
+            self.origin[1] - y * self.scale,
+        )
+    }
+}
+
+impl ttf_parser::OutlineBuilder for Outline {
+    fn move_to(&mut self, x: f32, y: f32) {
+        let (x, y) = self.point(x, y);
+        self.builder.move_to(x, y);
+    }
+
+    fn line_to(&mut self, x: f32, y: f32) {
+        let (x, y) = self.point(x, y);
+        self.builder.line_to(x, y);
+    }
+
+    fn quad_to(&mut self, x1: f32, y1: f32, x: f32, y: f32) {
+        let (x1, y1) = self.point(x1, y1);
+        let (x, y) = self.point(x, y);
+        self.builder.quad_to(x1, y1, x, y);
+    }
+
+    fn curve_to(&mut self, x1: f32, y1: f32, x2: f32, y2: f32, x: f32, y: f32) {
+        let (x1, y1) = self.point(x1, y1);
+        let (x2, y2) = self.point(x2, y2);
+        let (x, y) = self.point(x, y);
+        self.builder.cubic_to(x1, y1, x2, y2, x, y);
+    }
+
+    fn close(&mut self) {
+        self.builder.close();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::background::Background;
+    use image::{codecs::gif::GifDecoder, AnimationDecoder};
+    use std::io::BufReader;
+
+    #[test]
+    fn each_page_becomes_a_frame_in_the_same_place() {
+        let boxes = [[10.0, 20.0], [200.0, 100.0]].map(|[x, y]| Rect {
+            x,
+            y,
+            width: 30.0,
+            height: 40.0,
+        });
+        let pages: Vec<Vec<Action>> = boxes
+            .iter()
+            .cloned()
+            .map(|area| vec![Action::Redact(area)])
+            .collect();
+        let backdrop = Backdrop {
+            background: Background::default(),
+            pattern: false,
+            border: false,
+        };
+        let path = std::env::temp_dir().join("whiteboard-flipbook-test.gif");
+        let path = export(
+            &path,
+            &pages,
+            PhysicalSize::new(800, 600),
+            &backdrop,
+            10,
+            &mut FontSystem::new(),
+        )
+        .unwrap();
+
+        let file = BufReader::new(File::open(&path).unwrap());
+        let frames = GifDecoder::new(file)
+            .unwrap()
+            .into_frames()
+            .collect_frames()
+            .unwrap();
+        let _ = fs::remove_file(&path);
+        assert_eq!(frames.len(), 2);
+        // The first box's middle, in a frame that starts a margin before it.
+        let (x, y) = (25.0 - (10.0 - paint::MARGIN), 40.0 - (20.0 - paint::MARGIN));
+        let shade = |frame: &Frame| frame.buffer().get_pixel(x as u32, y as u32).0[0];
+        assert!(shade(&frames[0]) < 64);
+        assert!(shade(&frames[1]) > 192);
+    }
+}
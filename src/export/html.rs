@@ -0,0 +1,138 @@
+use crate::{
+    export::{paint::Backdrop, svg},
+    Action,
+};
+use glyphon::FontSystem;
+use std::{
+    fmt::Write,
+    fs,
+    path::{Path, PathBuf},
+};
+use tao::dpi::PhysicalSize;
+
+pub const EXTENSION: &str = "html";
+const VIEWER: &str = include_str!("../assets/viewer.html");
+
+// A single HTML file with every page as inline SVG, drawn like the PDF export
+// and set on the board's color, and a small script to pan, zoom and turn pages,
+// so it opens in any browser without the app.
+pub fn export(
+    path: &Path,
+    pages: &[Vec<Action>],
+    size: PhysicalSize<u32>,
+    backdrop: &Backdrop,
+    font_system: &mut FontSystem,
+) -> Result<PathBuf, String> {
+    let path = if path.extension().is_none() {
+        path.with_extension(EXTENSION)
+    } else {
+        path.to_path_buf()
+    };
+
+    let html = document(pages, size, backdrop, font_system);
+
+    if let Some(parent) = path.parent() {
+        let _ = fs::create_dir_all(parent);
+    }
+    fs::write(&path, html).map_err(|err| err.to_string())?;
+    Ok(path)
+}
+
+fn document(
+    pages: &[Vec<Action>],
+    size: PhysicalSize<u32>,
+    backdrop: &Backdrop,
+    font_system: &mut FontSystem,
+) -> String {
+    let mut markup = String::new();
+    for page in pages {
+        let _ = write!(
+            markup,
+            "<div class=\"page\" hidden>\n{}</div>\n",
+            svg::document(page, size, font_system, Some(backdrop))
+        );
+    }
+    let [r, g, b, a] = backdrop.background.color;
+    VIEWER
+        .replace(
+            "{{background}}",
+            &format!("rgba({}, {}, {}, {:.3})", r, g, b, a as f32 / 255.0),
+        )
+        .replace("{{pages}}", markup.trim_end())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        background::{Background, Paper},
+        BoardImage, CodeBlock, ImageAdjust, Rect, Stencil, StencilKind, Table,
+    };
+    use std::io::Cursor;
+
+    #[test]
+    fn pages_hold_every_kind_of_object() {
+        let mut png = Vec::new();
+        image::RgbaImage::new(2, 2)
+            .write_to(&mut Cursor::new(&mut png), image::ImageFormat::Png)
+            .unwrap();
+        let page = vec![
+            Action::Image(BoardImage {
+                first: [0.0, 0.0],
+                last: [20.0, 20.0],
+                data: png,
+                layer: 0,
+                adjust: ImageAdjust::default(),
+                texture: None,
+            }),
+            Action::Table(Table {
+                position: [40.0, 0.0],
+                rows: vec![vec!["a".to_string(), "b".to_string()]],
+                color: [0, 0, 0, 255],
+                font_size: 16,
+                shadow: Default::default(),
+                layer: 0,
+                bounds: Rect::default(),
+            }),
+            Action::Code(CodeBlock::new([0.0, 80.0], "fn main() {}", "rs", 16)),
+            Action::Stencil(Stencil::new(
+                StencilKind::FLOWCHART[0],
+                [200.0, 200.0],
+                [0, 0, 0, 255],
+                16,
+            )),
+            Action::Redact(Rect {
+                x: 300.0,
+                y: 300.0,
+                width: 10.0,
+                height: 10.0,
+            }),
+        ];
+        let backdrop = Backdrop {
+            background: Background {
+                paper: Paper::Grid,
+                ..Background::default()
+            },
+            pattern: true,
+            border: true,
+        };
+        let html = document(
+            &[page, Vec::new()],
+            PhysicalSize::new(800, 600),
+            &backdrop,
+            &mut FontSystem::new(),
+        );
+
+        assert_eq!(html.matches("<div class=\"page\"").count(), 2);
+        assert!(html.contains("<image ") && html.contains("data:image/png;base64,"));
+        // Table grid, code border and stencil outline.
+        assert!(
+            html.matches(r#"stroke-width="1" stroke-linecap="round""#)
+                .count()
+                >= 3
+        );
+        assert!(html.contains(
+            r##"<rect x="300.00" y="300.00" width="10.00" height="10.00" fill="#000000""##
+        ));
+    }
+}
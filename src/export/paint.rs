@@ -0,0 +1,371 @@
+use crate::{
+    background::Background, buffer_bounds, code, code_buffer, contrasting_color, images,
+    ndc_to_screen, rgba_to_normalized, scene, text_buffer, text_layout, Action, Rect, Stencil,
+    TextDecoration, ARROW_ANGLE, ARROW_SIZE, CHIP_ALPHA, CHIP_PADDING, CODE_BLOCK_PADDING,
+    CODE_BORDER_COLOR, DEFAULT_FONT, HALO_OFFSETS, LINE_HEIGHT, TABLE_CELL_PADDING,
+};
+use glyphon::{fontdb, Buffer, FontSystem};
+use tao::dpi::PhysicalSize;
+
+// Blank space kept around the drawing on every exported page, in board pixels.
+pub const MARGIN: f32 = 24.0;
+// How far inside the page's edge its border is drawn.
+const BORDER_INSET: f32 = 8.0;
+const BORDER_ALPHA: f32 = 0.5;
+
+// What an export puts under a page's objects: the board's color, and its
+// paper pattern and a border around the page when asked for.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Backdrop {
+    pub background: Background,
+    pub pattern: bool,
+    pub border: bool,
+}
+
+// What an exporter draws a page with. Everything is in board pixels.
+pub trait Painter {
+    fn fill(&mut self, rects: &[Rect], color: [f32; 4]);
+    // A rectangle's edges one pixel wide.
+    fn outline(&mut self, rect: &Rect, color: [f32; 4]);
+    // `data` is the encoded picture file, stretched over `bounds`.
+    fn image(&mut self, bounds: &Rect, data: &[u8]);
+    // Hairlines between each pair of points, the way the board draws object
+    // outlines.
+    fn lines(&mut self, points: &[[f32; 2]], color: [u8; 4]);
+    fn stroke(&mut self, points: &[[f32; 2]], width: f32, color: [f32; 4]);
+    // Every glyph the buffer laid out, with `origin` at the buffer's top left.
+    fn glyphs(
+        &mut self,
+        fonts: &fontdb::Database,
+        buffer: &Buffer,
+        origin: [f32; 2],
+        color: [u8; 4],
+    );
+}
+
+// The objects on the page's visible layers and the area they cover, or the
+// window's area when there are none.
+pub fn visible(page: &[Action], size: PhysicalSize<u32>) -> (Vec<&Action>, Rect) {
+    let hidden = scene::hidden_layers(page);
+    let visible: Vec<&Action> = page
+        .iter()
+        .filter(|action| scene::is_visible(action, &hidden))
+        .collect();
+    let area = scene::bounds(visible.iter().copied(), size).unwrap_or(Rect {
+        x: 0.0,
+        y: 0.0,
+        width: size.width as f32,
+        height: size.height as f32,
+    });
+    (visible, area)
+}
+
+// The page around `area`, with the margin kept on every side.
+pub fn frame(area: &Rect) -> Rect {
+    Rect {
+        x: area.x - MARGIN,
+        y: area.y - MARGIN,
+        width: area.width + MARGIN * 2.0,
+        height: area.height + MARGIN * 2.0,
+    }
+}
+
+// The backdrop over the whole of `frame`.
+pub fn paper(backdrop: &Backdrop, frame: &Rect, painter: &mut impl Painter) {
+    let background = &backdrop.background;
+    painter.fill(std::slice::from_ref(frame), background.normalized());
+    if backdrop.pattern {
+        let corners = [
+            [frame.x, frame.y],
+            [frame.x + frame.width, frame.y + frame.height],
+        ];
+        // The pattern comes as two triangles per line or dot, with opposite
+        // corners of the quad first and third.
+        let rects: Vec<Rect> = background
+            .pattern(corners, 1.0)
+            .chunks_exact(6)
+            .map(|quad| {
+                let ([x1, y1], [x2, y2]) = (quad[0], quad[2]);
+                Rect {
+                    x: x1.min(x2),
+                    y: y1.min(y2),
+                    width: (x2 - x1).abs(),
+                    height: (y2 - y1).abs(),
+                }
+            })
+            .collect();
+        if !rects.is_empty() {
+            painter.fill(&rects, background.pattern_color());
+        }
+    }
+    if backdrop.border {
+        let [red, green, blue, _] = background.pattern_color();
+        painter.outline(
+            &Rect {
+                x: frame.x + BORDER_INSET,
+                y: frame.y + BORDER_INSET,
+                width: frame.width - BORDER_INSET * 2.0,
+                height: frame.height - BORDER_INSET * 2.0,
+            },
+            [red, green, blue, BORDER_ALPHA],
+        );
+    }
+}
+
+// Draws every object on the page's visible layers in the board's order: fills,
+// images, rectangles, tables, code, stencils, connectors, strokes, text and,
+// over everything, redactions. Text is shaped and wrapped as on the board.
+pub fn paint(
+    page: &[Action],
+    size: PhysicalSize<u32>,
+    font_system: &mut FontSystem,
+    painter: &mut impl Painter,
+) {
+    let hidden = scene::hidden_layers(page);
+    let (visible, _) = visible(page, size);
+    let (width, height) = (size.width as f32, size.height as f32);
+
+    for action in &visible {
+        if let Action::Fill(fill) = action {
+            painter.fill(&fill.rects, fill.color);
+        }
+    }
+    for action in &visible {
+        if let Action::Image(image) = action {
+            painter.image(
+                &image.bounds(),
+                &images::adjusted(&image.data, &image.adjust),
+            );
+        }
+    }
+    for action in &visible {
+        if let Action::Shapes(rectangle) = action {
+            let [[x1, y1], [x2, y2]] =
+                [rectangle.first, rectangle.last].map(|corner| ndc_to_screen(size, corner));
+            let rect = Rect {
+                x: x1.min(x2),
+                y: y1.min(y2),
+                width: (x2 - x1).abs(),
+                height: (y2 - y1).abs(),
+            };
+            match rectangle.filled {
+                true => painter.fill(&[rect], rectangle.color),
+                false => painter.outline(&rect, rectangle.color),
+            }
+        }
+    }
+    for action in &visible {
+        let Action::Table(table) = action else {
+            continue;
+        };
+        let [x, y] = table.position;
+        let row_height = table.font_size as f32 * LINE_HEIGHT + TABLE_CELL_PADDING * 2.0;
+        let columns = table.rows.iter().map(Vec::len).max().unwrap_or(0);
+        let mut column_widths = vec![0.0f32; columns];
+        let mut cells = Vec::new();
+        for (row, cells_in_row) in table.rows.iter().enumerate() {
+            for (column, cell) in cells_in_row.iter().enumerate() {
+                let cell_buffer = text_buffer(
+                    font_system,
+                    cell,
+                    DEFAULT_FONT,
+                    table.font_size as f32,
+                    LINE_HEIGHT,
+                    width,
+                    height,
+                );
+                let cell_width = cell_buffer
+                    .layout_runs()
+                    .map(|run| run.line_w)
+                    .fold(0.0, f32::max);
+                column_widths[column] = column_widths[column].max(cell_width);
+                cells.push((row, column, cell_buffer));
+            }
+        }
+        let mut column_offsets = vec![0.0f32];
+        for column_width in &column_widths {
+            let last = *column_offsets.last().unwrap();
+            column_offsets.push(last + column_width + TABLE_CELL_PADDING * 2.0);
+        }
+        let table_width = *column_offsets.last().unwrap();
+        let table_height = row_height * table.rows.len() as f32;
+        let mut grid = Vec::new();
+        for row in 0..=table.rows.len() {
+            let top = y + row as f32 * row_height;
+            grid.extend([[x, top], [x + table_width, top]]);
+        }
+        for offset in &column_offsets {
+            grid.extend([[x + offset, y], [x + offset, y + table_height]]);
+        }
+        painter.lines(&grid, table.color);
+        for (row, column, cell_buffer) in cells {
+            let left = x + column_offsets[column] + TABLE_CELL_PADDING;
+            let top = y + row as f32 * row_height + TABLE_CELL_PADDING;
+            painter.glyphs(font_system.db(), &cell_buffer, [left, top], table.color);
+        }
+    }
+    for action in &visible {
+        let Action::Code(code_block) = action else {
+            continue;
+        };
+        let highlighted = match code_block.highlighted.is_empty() {
+            true => code::highlight(&code_block.code, &code_block.language),
+            false => code_block.highlighted.clone(),
+        };
+        let font_size = code_block.font_size as f32;
+        let buffer = code_buffer(font_system, &highlighted, font_size, width, height);
+        let bounds = buffer_bounds(&buffer, code_block.position, font_size);
+        let (left, top) = (bounds.x - CODE_BLOCK_PADDING, bounds.y - CODE_BLOCK_PADDING);
+        let right = bounds.x + bounds.width + CODE_BLOCK_PADDING;
+        let bottom = bounds.y + bounds.height + CODE_BLOCK_PADDING;
+        painter.lines(
+            &[
+                [left, top],
+                [right, top],
+                [right, top],
+                [right, bottom],
+                [right, bottom],
+                [left, bottom],
+                [left, bottom],
+                [left, top],
+            ],
+            CODE_BORDER_COLOR,
+        );
+        painter.glyphs(
+            font_system.db(),
+            &buffer,
+            code_block.position,
+            [0, 0, 0, 255],
+        );
+    }
+    // Connectors count stencils across the whole page, hidden ones included.
+    let stencils: Vec<Stencil> = page
+        .iter()
+        .filter_map(|action| match action {
+            Action::Stencil(stencil) => Some(stencil.clone()),
+            _ => None,
+        })
+        .collect();
+    for action in &visible {
+        let Action::Stencil(stencil) = action else {
+            continue;
+        };
+        let bounds = stencil.bounds();
+        painter.lines(&stencil.kind.outline(&bounds), stencil.color);
+        let label = &stencil.label;
+        let font_size = label.font_size as f32;
+        let buffer = text_buffer(
+            font_system,
+            &label.text,
+            &label.font,
+            font_size,
+            LINE_HEIGHT,
+            width,
+            height,
+        );
+        let label_width = buffer
+            .layout_runs()
+            .map(|run| run.line_w)
+            .fold(0.0, f32::max);
+        let label_height = buffer.layout_runs().count().max(1) as f32 * font_size * LINE_HEIGHT;
+        let area = stencil.kind.label_area(&bounds);
+        let position = [
+            area.x + (area.width - label_width) / 2.0,
+            area.y + (area.height - label_height) / 2.0,
+        ];
+        painter.glyphs(font_system.db(), &buffer, position, label.color);
+    }
+    for action in page {
+        let Action::Connector(connector) = action else {
+            continue;
+        };
+        let from_layer = stencils.get(connector.from).map(|stencil| stencil.layer);
+        if from_layer.is_none_or(|layer| hidden.contains(&layer)) {
+            continue;
+        }
+        let Some((from, to)) = connector.endpoints(&stencils) else {
+            continue;
+        };
+        let angle = (to[1] - from[1]).atan2(to[0] - from[0]);
+        let mut points = vec![from, to];
+        for side in [-1.0, 1.0] {
+            let wing = angle + std::f32::consts::PI - side * ARROW_ANGLE;
+            points.push(to);
+            points.push([
+                to[0] + ARROW_SIZE * wing.cos(),
+                to[1] + ARROW_SIZE * wing.sin(),
+            ]);
+        }
+        painter.lines(&points, connector.color);
+        if let Some(label) = &connector.label {
+            let font_size = connector.font_size as f32;
+            let buffer = text_buffer(
+                font_system,
+                label,
+                DEFAULT_FONT,
+                font_size,
+                LINE_HEIGHT,
+                width,
+                height,
+            );
+            let label_width = buffer
+                .layout_runs()
+                .map(|run| run.line_w)
+                .fold(0.0, f32::max);
+            let position = [
+                (from[0] + to[0] - label_width) / 2.0,
+                (from[1] + to[1]) / 2.0 - font_size * LINE_HEIGHT,
+            ];
+            painter.glyphs(font_system.db(), &buffer, position, connector.color);
+        }
+    }
+    for action in &visible {
+        if let Action::Stroke(stroke) = action {
+            let Some(first) = stroke.points.first() else {
+                continue;
+            };
+            let points: Vec<[f32; 2]> = stroke
+                .points
+                .iter()
+                .map(|point| ndc_to_screen(size, point.position))
+                .collect();
+            painter.stroke(&points, stroke.width, first.color);
+        }
+    }
+    for action in &visible {
+        let Action::Text(text) = action else {
+            continue;
+        };
+        let (buffer, origin) = text_layout(font_system, text, size);
+        let contrast = contrasting_color(text.color);
+        match text.decoration {
+            TextDecoration::Halo => {
+                for (dx, dy) in HALO_OFFSETS {
+                    let [x, y] = origin;
+                    painter.glyphs(font_system.db(), &buffer, [x + dx, y + dy], contrast);
+                }
+            }
+            TextDecoration::Chip => {
+                let bounds = buffer_bounds(&buffer, origin, text.font_size as f32);
+                let padding = text.font_size as f32 * CHIP_PADDING;
+                let [r, g, b, _] = contrast;
+                painter.fill(
+                    &[Rect {
+                        x: bounds.x - padding,
+                        y: bounds.y - padding,
+                        width: bounds.width + padding * 2.0,
+                        height: bounds.height + padding * 2.0,
+                    }],
+                    rgba_to_normalized([r, g, b, CHIP_ALPHA]),
+                );
+            }
+            TextDecoration::None => {}
+        }
+        painter.glyphs(font_system.db(), &buffer, origin, text.color);
+    }
+    for action in &visible {
+        if let Action::Redact(area) = action {
+            painter.fill(std::slice::from_ref(area), [0.0, 0.0, 0.0, 1.0]);
+        }
+    }
+}
@@ -0,0 +1,508 @@
+use crate::{
+    export::paint::{self, Backdrop, Painter, MARGIN},
+    rgba_to_normalized, Action, Rect,
+};
+use flate2::{write::ZlibEncoder, Compression};
+use glyphon::{cosmic_text::ttf_parser, fontdb, Buffer, FontSystem};
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap},
+    fmt::Write as _,
+    fs,
+    io::Write as _,
+    path::{Path, PathBuf},
+};
+use tao::dpi::PhysicalSize;
+
+pub const EXTENSION: &str = "pdf";
+// Entries per block of a ToUnicode map, the most PDF readers must accept.
+const CMAP_BLOCK: usize = 100;
+
+// Writes one PDF page per board page, one board pixel to a point. Shapes,
+// strokes and outlines become vector paths, images are embedded as they are,
+// and text is shaped and wrapped as on the board and set in the fonts it was
+// drawn with, which are embedded whole so Persian and anything else the board
+// shows comes out the same and can still be copied. Each page sits on the
+// backdrop, which covers the margin too.
+pub fn export(
+    path: &Path,
+    pages: &[Vec<Action>],
+    size: PhysicalSize<u32>,
+    backdrop: &Backdrop,
+    font_system: &mut FontSystem,
+) -> Result<PathBuf, String> {
+    let path = if path.extension().is_none() {
+        path.with_extension(EXTENSION)
+    } else {
+        path.to_path_buf()
+    };
+
+    let mut fonts = Fonts::default();
+    let mut images = Vec::new();
+    let contents: Vec<PageContent> = pages
+        .iter()
+        .map(|page| page_content(page, size, backdrop, font_system, &mut fonts, &mut images))
+        .collect();
+
+    // Objects 1 and 2 are the catalog and the page tree; each page then takes
+    // two, itself and its content stream, each font five and each image two,
+    // itself and its transparency.
+    let page_ids: Vec<usize> = (0..pages.len()).map(|index| 3 + index * 2).collect();
+    let font_base = 3 + pages.len() * 2;
+    let image_base = font_base + fonts.faces.len() * 5;
+    let mut objects = vec![
+        b"<< /Type /Catalog /Pages 2 0 R >>".to_vec(),
+        format!(
+            "<< /Type /Pages /Kids [{}] /Count {} >>",
+            page_ids
+                .iter()
+                .map(|id| format!("{} 0 R", id))
+                .collect::<Vec<_>>()
+                .join(" "),
+            pages.len()
+        )
+        .into_bytes(),
+    ];
+    let font_resources: String = (0..fonts.faces.len())
+        .map(|index| format!("/F{} {} 0 R ", index, font_base + index * 5))
+        .collect();
+    for (page, id) in contents.iter().zip(&page_ids) {
+        let states: String = page
+            .alphas
+            .iter()
+            .map(|alpha| {
+                format!(
+                    "/A{} << /CA {} /ca {} >> ",
+                    alpha,
+                    *alpha as f32 / 100.0,
+                    *alpha as f32 / 100.0
+                )
+            })
+            .collect();
+        let xobjects: String = page
+            .images
+            .iter()
+            .map(|index| format!("/Im{} {} 0 R ", index, image_base + index * 2))
+            .collect();
+        objects.push(
+            format!(
+                "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 {:.2} {:.2}] /Resources << /Font << {}>> /ExtGState << {}>> /XObject << {}>> >> /Contents {} 0 R >>",
+                page.media_box[0],
+                page.media_box[1],
+                font_resources,
+                states,
+                xobjects,
+                id + 1
+            )
+            .into_bytes(),
+        );
+        objects.push(stream("", page.content.as_bytes()));
+    }
+    for (index, (face, glyphs)) in fonts.faces.iter().enumerate() {
+        objects.extend(font_objects(
+            font_system,
+            *face,
+            glyphs,
+            font_base + index * 5,
+        )?);
+    }
+    for (index, image) in images.iter().enumerate() {
+        let id = image_base + index * 2;
+        objects.push(stream(
+            &format!(
+                "/Type /XObject /Subtype /Image /Width {} /Height {} /ColorSpace /DeviceRGB /BitsPerComponent 8 /SMask {} 0 R /Filter /FlateDecode",
+                image.width,
+                image.height,
+                id + 1
+            ),
+            &deflate(&image.color),
+        ));
+        objects.push(stream(
+            &format!(
+                "/Type /XObject /Subtype /Image /Width {} /Height {} /ColorSpace /DeviceGray /BitsPerComponent 8 /Filter /FlateDecode",
+                image.width, image.height
+            ),
+            &deflate(&image.alpha),
+        ));
+    }
+
+    let mut file = b"%PDF-1.4\n".to_vec();
+    let mut offsets = Vec::new();
+    for (index, object) in objects.iter().enumerate() {
+        offsets.push(file.len());
+        file.extend(format!("{} 0 obj\n", index + 1).into_bytes());
+        file.extend(object);
+        file.extend(b"\nendobj\n");
+    }
+    let xref = file.len();
+    let mut trailer = format!("xref\n0 {}\n0000000000 65535 f \n", objects.len() + 1);
+    for offset in offsets {
+        let _ = writeln!(trailer, "{:010} 00000 n ", offset);
+    }
+    let _ = write!(
+        trailer,
+        "trailer\n<< /Size {} /Root 1 0 R >>\nstartxref\n{}\n%%EOF\n",
+        objects.len() + 1,
+        xref
+    );
+    file.extend(trailer.into_bytes());
+
+    if let Some(parent) = path.parent() {
+        let _ = fs::create_dir_all(parent);
+    }
+    fs::write(&path, file).map_err(|err| err.to_string())?;
+    Ok(path)
+}
+
+type Glyphs = BTreeMap<u16, (u32, String)>;
+
+// The fonts the text was set in, each with the glyphs used from it, their
+// advance in thousandths of the font size and the text they stand for.
+#[derive(Default)]
+struct Fonts {
+    faces: Vec<(fontdb::ID, Glyphs)>,
+    indices: HashMap<fontdb::ID, usize>,
+}
+
+impl Fonts {
+    fn index(&mut self, id: fontdb::ID) -> usize {
+        *self.indices.entry(id).or_insert_with(|| {
+            self.faces.push((id, BTreeMap::new()));
+            self.faces.len() - 1
+        })
+    }
+}
+
+struct Image {
+    width: u32,
+    height: u32,
+    color: Vec<u8>,
+    alpha: Vec<u8>,
+}
+
+struct PageContent {
+    content: String,
+    media_box: [f32; 2],
+    // Opacities in percent that the page's graphics states need.
+    alphas: BTreeSet<u8>,
+    images: Vec<usize>,
+}
+
+struct Canvas<'a> {
+    page: PageContent,
+    area: Rect,
+    fonts: &'a mut Fonts,
+    images: &'a mut Vec<Image>,
+}
+
+impl Canvas<'_> {
+    // PDF counts y up from the bottom of the page.
+    fn to_page(&self, [x, y]: [f32; 2]) -> [f32; 2] {
+        [
+            x - self.area.x + MARGIN,
+            self.page.media_box[1] - (y - self.area.y + MARGIN),
+        ]
+    }
+
+    fn paint(&mut self, color: [f32; 4], operator: &str) {
+        let alpha = (color[3].clamp(0.0, 1.0) * 100.0).round() as u8;
+        self.page.alphas.insert(alpha);
+        let _ = writeln!(
+            self.page.content,
+            "/A{} gs {:.3} {:.3} {:.3} {}",
+            alpha, color[0], color[1], color[2], operator
+        );
+    }
+
+    fn rect(&mut self, first: [f32; 2], last: [f32; 2]) {
+        let ([x1, y1], [x2, y2]) = (self.to_page(first), self.to_page(last));
+        let _ = writeln!(
+            self.page.content,
+            "{:.2} {:.2} {:.2} {:.2} re",
+            x1.min(x2),
+            y1.min(y2),
+            (x2 - x1).abs(),
+            (y2 - y1).abs()
+        );
+    }
+}
+
+impl Painter for Canvas<'_> {
+    fn fill(&mut self, rects: &[Rect], color: [f32; 4]) {
+        self.paint(color, "rg");
+        for area in rects {
+            self.rect(
+                [area.x, area.y],
+                [area.x + area.width, area.y + area.height],
+            );
+        }
+        self.page.content.push_str("f\n");
+    }
+
+    fn outline(&mut self, rect: &Rect, color: [f32; 4]) {
+        self.paint(color, "RG 1 w");
+        self.rect(
+            [rect.x, rect.y],
+            [rect.x + rect.width, rect.y + rect.height],
+        );
+        self.page.content.push_str("S\n");
+    }
+
+    fn image(&mut self, bounds: &Rect, data: &[u8]) {
+        let Ok(decoded) = image::load_from_memory(data) else {
+            return;
+        };
+        let decoded = decoded.to_rgba8();
+        let (color, alpha) = decoded
+            .pixels()
+            .map(|pixel| ([pixel[0], pixel[1], pixel[2]], pixel[3]))
+            .unzip::<_, _, Vec<_>, Vec<_>>();
+        self.page.images.push(self.images.len());
+        self.images.push(Image {
+            width: decoded.width(),
+            height: decoded.height(),
+            color: color.concat(),
+            alpha,
+        });
+        let [x, y] = self.to_page([bounds.x, bounds.y + bounds.height]);
+        let _ = writeln!(
+            self.page.content,
+            "q {:.2} 0 0 {:.2} {:.2} {:.2} cm /Im{} Do Q",
+            bounds.width,
+            bounds.height,
+            x,
+            y,
+            self.images.len() - 1
+        );
+    }
+
+    fn lines(&mut self, points: &[[f32; 2]], color: [u8; 4]) {
+        if points.len() < 2 {
+            return;
+        }
+        self.paint(rgba_to_normalized(color), "RG 1 w 1 J");
+        for pair in points.chunks_exact(2) {
+            let ([x1, y1], [x2, y2]) = (self.to_page(pair[0]), self.to_page(pair[1]));
+            let _ = writeln!(
+                self.page.content,
+                "{:.2} {:.2} m {:.2} {:.2} l",
+                x1, y1, x2, y2
+            );
+        }
+        self.page.content.push_str("S\n");
+    }
+
+    fn stroke(&mut self, points: &[[f32; 2]], width: f32, color: [f32; 4]) {
+        self.paint(color, "RG 1 J 1 j");
+        let _ = writeln!(self.page.content, "{:.2} w", width);
+        for (index, point) in points.iter().enumerate() {
+            let [x, y] = self.to_page(*point);
+            let operator = if index == 0 { "m" } else { "l" };
+            let _ = writeln!(self.page.content, "{:.2} {:.2} {}", x, y, operator);
+        }
+        // A lone point still leaves a round dot.
+        if let [point] = points {
+            let [x, y] = self.to_page(*point);
+            let _ = writeln!(self.page.content, "{:.2} {:.2} l", x, y);
+        }
+        self.page.content.push_str("S\n");
+    }
+
+    // Every glyph the buffer laid out, placed one by one so shaping, wrapping
+    // and right-to-left runs stay exactly as they were on the board.
+    fn glyphs(
+        &mut self,
+        _fonts: &fontdb::Database,
+        buffer: &Buffer,
+        [left, top]: [f32; 2],
+        color: [u8; 4],
+    ) {
+        let mut current = None;
+        self.page.content.push_str("BT\n");
+        for run in buffer.layout_runs() {
+            for glyph in run.glyphs {
+                let font = self.fonts.index(glyph.font_id);
+                let text = run.text.get(glyph.start..glyph.end).unwrap_or_default();
+                let advance = match glyph.font_size > 0.0 {
+                    true => (glyph.w / glyph.font_size * 1000.0).round() as u32,
+                    false => 0,
+                };
+                self.fonts.faces[font]
+                    .1
+                    .entry(glyph.glyph_id)
+                    .or_insert_with(|| (advance, text.to_string()));
+
+                let color = glyph
+                    .color_opt
+                    .map_or(color, |color| [color.r(), color.g(), color.b(), color.a()]);
+                if current != Some((font, glyph.font_size.to_bits(), color)) {
+                    current = Some((font, glyph.font_size.to_bits(), color));
+                    self.paint(rgba_to_normalized(color), "rg");
+                    let _ = writeln!(self.page.content, "/F{} {} Tf", font, glyph.font_size);
+                }
+                let [x, y] = self.to_page([
+                    left + glyph.x + glyph.font_size * glyph.x_offset,
+                    top + run.line_y + glyph.y - glyph.font_size * glyph.y_offset,
+                ]);
+                let _ = writeln!(
+                    self.page.content,
+                    "1 0 0 1 {:.2} {:.2} Tm <{:04X}> Tj",
+                    x, y, glyph.glyph_id
+                );
+            }
+        }
+        self.page.content.push_str("ET\n");
+    }
+}
+
+fn page_content(
+    page: &[Action],
+    size: PhysicalSize<u32>,
+    backdrop: &Backdrop,
+    font_system: &mut FontSystem,
+    fonts: &mut Fonts,
+    images: &mut Vec<Image>,
+) -> PageContent {
+    let (_, area) = paint::visible(page, size);
+    let frame = paint::frame(&area);
+    let mut canvas = Canvas {
+        page: PageContent {
+            content: String::new(),
+            media_box: [area.width + MARGIN * 2.0, area.height + MARGIN * 2.0],
+            alphas: BTreeSet::new(),
+            images: Vec::new(),
+        },
+        area,
+        fonts,
+        images,
+    };
+    paint::paper(backdrop, &frame, &mut canvas);
+    paint::paint(page, size, font_system, &mut canvas);
+    canvas.page
+}
+
+// A Type 0 font over the embedded TrueType file, addressed by glyph id, with
+// its descendant, descriptor, font file and a map back to the text so the
+// PDF can be searched and copied from.
+fn font_objects(
+    font_system: &mut FontSystem,
+    id: fontdb::ID,
+    glyphs: &Glyphs,
+    first_id: usize,
+) -> Result<Vec<Vec<u8>>, String> {
+    let font = font_system
+        .get_font(id)
+        .ok_or("A font used on the board is no longer available")?;
+    let (index, name) = font_system
+        .db()
+        .face(id)
+        .map(|face| (face.index, face.post_script_name.clone()))
+        .unwrap_or_default();
+    let data = match font.data().starts_with(b"ttcf") {
+        true => collection_face(font.data(), index)
+            .ok_or("A font collection used on the board could not be read")?,
+        false => font.data().to_vec(),
+    };
+    let face = ttf_parser::Face::parse(&data, 0).map_err(|err| err.to_string())?;
+    let scale = 1000.0 / face.units_per_em() as f32;
+    let bbox = face.global_bounding_box();
+    let mut name: String = name
+        .chars()
+        .filter(|character| character.is_ascii_alphanumeric() || *character == '-')
+        .collect();
+    if name.is_empty() {
+        name = format!("Font{}", first_id);
+    }
+
+    let widths: String = glyphs
+        .iter()
+        .map(|(glyph, (advance, _))| format!("{} [{}] ", glyph, advance))
+        .collect();
+    let mut cmap = String::from(
+        "/CIDInit /ProcSet findresource begin\n12 dict begin\nbegincmap\n/CIDSystemInfo << /Registry (Adobe) /Ordering (UCS) /Supplement 0 >> def\n/CMapName /Adobe-Identity-UCS def\n/CMapType 2 def\n1 begincodespacerange\n<0000> <FFFF>\nendcodespacerange\n",
+    );
+    let mapped: Vec<_> = glyphs
+        .iter()
+        .filter(|(_, (_, text))| !text.is_empty())
+        .collect();
+    for block in mapped.chunks(CMAP_BLOCK) {
+        let _ = writeln!(cmap, "{} beginbfchar", block.len());
+        for (glyph, (_, text)) in block {
+            let units: String = text
+                .encode_utf16()
+                .map(|unit| format!("{:04X}", unit))
+                .collect();
+            let _ = writeln!(cmap, "<{:04X}> <{}>", glyph, units);
+        }
+        cmap.push_str("endbfchar\n");
+    }
+    cmap.push_str("endcmap\nCMapName currentdict /CMap defineresource pop\nend\nend\n");
+
+    Ok(vec![
+        format!(
+            "<< /Type /Font /Subtype /Type0 /BaseFont /{} /Encoding /Identity-H /DescendantFonts [{} 0 R] /ToUnicode {} 0 R >>",
+            name,
+            first_id + 1,
+            first_id + 4
+        )
+        .into_bytes(),
+        format!(
+            "<< /Type /Font /Subtype /CIDFontType2 /BaseFont /{} /CIDSystemInfo << /Registry (Adobe) /Ordering (Identity) /Supplement 0 >> /FontDescriptor {} 0 R /CIDToGIDMap /Identity /W [{}] >>",
+            name,
+            first_id + 2,
+            widths
+        )
+        .into_bytes(),
+        format!(
+            "<< /Type /FontDescriptor /FontName /{} /Flags 4 /FontBBox [{:.0} {:.0} {:.0} {:.0}] /ItalicAngle {:.0} /Ascent {:.0} /Descent {:.0} /CapHeight {:.0} /StemV 80 /FontFile2 {} 0 R >>",
+            name,
+            bbox.x_min as f32 * scale,
+            bbox.y_min as f32 * scale,
+            bbox.x_max as f32 * scale,
+            bbox.y_max as f32 * scale,
+            face.italic_angle().unwrap_or(0.0),
+            face.ascender() as f32 * scale,
+            face.descender() as f32 * scale,
+            face.capital_height().unwrap_or(face.ascender()) as f32 * scale,
+            first_id + 3
+        )
+        .into_bytes(),
+        stream(&format!("/Length1 {} /Filter /FlateDecode", data.len()), &deflate(&data)),
+        stream("", cmap.as_bytes()),
+    ])
+}
+
+// PDF embeds single fonts, so a face from a collection is copied out with its
+// tables into a file of its own.
+fn collection_face(data: &[u8], index: u32) -> Option<Vec<u8>> {
+    let read = |at: usize, length: usize| data.get(at..at + length);
+    let number = |at: usize| read(at, 4).map(|bytes| u32::from_be_bytes(bytes.try_into().unwrap()));
+    let offset = number(12 + index as usize * 4)? as usize;
+    let tables = read(offset + 4, 2).map(|bytes| u16::from_be_bytes([bytes[0], bytes[1]]))?;
+    let header = 12 + tables as usize * 16;
+    let mut font = read(offset, 12)?.to_vec();
+    let mut body = Vec::new();
+    for table in 0..tables as usize {
+        let record = offset + 12 + table * 16;
+        let (start, length) = (number(record + 8)? as usize, number(record + 12)?);
+        font.extend(read(record, 8)?);
+        font.extend(((header + body.len()) as u32).to_be_bytes());
+        font.extend(length.to_be_bytes());
+        body.extend(read(start, length as usize)?);
+        body.resize(body.len().next_multiple_of(4), 0);
+    }
+    font.extend(body);
+    Some(font)
+}
+
+fn stream(dictionary: &str, data: &[u8]) -> Vec<u8> {
+    let mut object = format!("<< {} /Length {} >>\nstream\n", dictionary, data.len()).into_bytes();
+    object.extend(data);
+    object.extend(b"\nendstream");
+    object
+}
+
+fn deflate(data: &[u8]) -> Vec<u8> {
+    let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+    let _ = encoder.write_all(data);
+    encoder.finish().unwrap_or_default()
+}
@@ -0,0 +1,145 @@
+use crate::{
+    export::paint::{self, Backdrop, Painter},
+    normalized_to_rgba, Action, Rect,
+};
+use egui::{Color32, Pos2, Shape, Stroke, Vec2};
+use glyphon::{fontdb, Buffer, FontSystem};
+use tao::dpi::PhysicalSize;
+
+// The longer side of the thumbnail, in points.
+const SIZE: f32 = 240.0;
+const PICTURE_COLOR: Color32 = Color32::from_gray(200);
+// Glyphs are drawn as bars this much of the font size tall.
+const GLYPH_HEIGHT: f32 = 0.5;
+
+// A small drawing of a page as an export would lay it out, built once and
+// shown while the export dialog is open.
+pub struct Preview {
+    pub backdrop: Backdrop,
+    size: Vec2,
+    shapes: Vec<Shape>,
+}
+
+impl Preview {
+    pub fn new(
+        page: &[Action],
+        size: PhysicalSize<u32>,
+        backdrop: Backdrop,
+        font_system: &mut FontSystem,
+    ) -> Self {
+        let (_, area) = paint::visible(page, size);
+        let frame = paint::frame(&area);
+        let scale = SIZE / frame.width.max(frame.height);
+        let mut thumbnail = Thumbnail {
+            origin: [frame.x, frame.y],
+            scale,
+            shapes: Vec::new(),
+        };
+        paint::paper(&backdrop, &frame, &mut thumbnail);
+        paint::paint(page, size, font_system, &mut thumbnail);
+        Preview {
+            backdrop,
+            size: Vec2::new(frame.width * scale, frame.height * scale),
+            shapes: thumbnail.shapes,
+        }
+    }
+
+    pub fn show(&self, ui: &mut egui::Ui) {
+        let (rect, _) = ui.allocate_exact_size(self.size, egui::Sense::hover());
+        let painter = ui.painter_at(rect);
+        painter.extend(self.shapes.iter().cloned().map(|mut shape| {
+            shape.translate(rect.min.to_vec2());
+            shape
+        }));
+    }
+}
+
+struct Thumbnail {
+    origin: [f32; 2],
+    scale: f32,
+    shapes: Vec<Shape>,
+}
+
+impl Thumbnail {
+    fn point(&self, [x, y]: [f32; 2]) -> Pos2 {
+        Pos2::new(
+            (x - self.origin[0]) * self.scale,
+            (y - self.origin[1]) * self.scale,
+        )
+    }
+
+    fn rect(&self, rect: &Rect) -> egui::Rect {
+        egui::Rect::from_two_pos(
+            self.point([rect.x, rect.y]),
+            self.point([rect.x + rect.width, rect.y + rect.height]),
+        )
+    }
+}
+
+fn color([r, g, b, a]: [u8; 4]) -> Color32 {
+    Color32::from_rgba_unmultiplied(r, g, b, a)
+}
+
+impl Painter for Thumbnail {
+    fn fill(&mut self, rects: &[Rect], fill: [f32; 4]) {
+        let fill = color(normalized_to_rgba(fill));
+        for rect in rects {
+            self.shapes
+                .push(Shape::rect_filled(self.rect(rect), 0.0, fill));
+        }
+    }
+
+    fn outline(&mut self, rect: &Rect, stroke: [f32; 4]) {
+        let stroke = Stroke::new(1.0, color(normalized_to_rgba(stroke)));
+        self.shapes
+            .push(Shape::rect_stroke(self.rect(rect), 0.0, stroke));
+    }
+
+    // Pictures are only blocked in; decoding them is not worth it at this size.
+    fn image(&mut self, bounds: &Rect, _: &[u8]) {
+        self.shapes
+            .push(Shape::rect_filled(self.rect(bounds), 0.0, PICTURE_COLOR));
+    }
+
+    fn lines(&mut self, points: &[[f32; 2]], line: [u8; 4]) {
+        let stroke = Stroke::new(1.0, color(line));
+        for pair in points.chunks_exact(2) {
+            self.shapes.push(Shape::line_segment(
+                [self.point(pair[0]), self.point(pair[1])],
+                stroke,
+            ));
+        }
+    }
+
+    fn stroke(&mut self, points: &[[f32; 2]], width: f32, stroke: [f32; 4]) {
+        let points = points.iter().map(|point| self.point(*point)).collect();
+        let stroke = Stroke::new(
+            (width * self.scale).max(0.5),
+            color(normalized_to_rgba(stroke)),
+        );
+        self.shapes.push(Shape::line(points, stroke));
+    }
+
+    // Text is too small to read here, so each glyph becomes a bar that keeps
+    // the shape of the words.
+    fn glyphs(&mut self, _: &fontdb::Database, buffer: &Buffer, origin: [f32; 2], text: [u8; 4]) {
+        let fill = color(text);
+        for run in buffer.layout_runs() {
+            for glyph in run.glyphs {
+                if run.text[glyph.start..glyph.end].trim().is_empty() {
+                    continue;
+                }
+                let [x, y] = [origin[0] + glyph.x, origin[1] + run.line_y];
+                let height = glyph.font_size * GLYPH_HEIGHT;
+                let bar = Rect {
+                    x,
+                    y: y - height,
+                    width: glyph.w,
+                    height,
+                };
+                self.shapes
+                    .push(Shape::rect_filled(self.rect(&bar), 0.0, fill));
+            }
+        }
+    }
+}
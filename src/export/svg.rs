@@ -0,0 +1,269 @@
+use crate::{
+    export::paint::{self, Backdrop, Painter, MARGIN},
+    rgba_to_normalized, Action, Rect,
+};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use glyphon::{fontdb, Buffer, FontSystem, LayoutGlyph};
+use std::{
+    fmt::Write,
+    fs,
+    path::{Path, PathBuf},
+};
+use tao::dpi::PhysicalSize;
+
+pub const EXTENSION: &str = "svg";
+
+pub fn export(
+    path: &Path,
+    page: &[Action],
+    size: PhysicalSize<u32>,
+    backdrop: &Backdrop,
+    font_system: &mut FontSystem,
+) -> Result<PathBuf, String> {
+    let path = if path.extension().is_none() {
+        path.with_extension(EXTENSION)
+    } else {
+        path.to_path_buf()
+    };
+    if let Some(parent) = path.parent() {
+        let _ = fs::create_dir_all(parent);
+    }
+    fs::write(&path, document(page, size, font_system, Some(backdrop)))
+        .map_err(|err| err.to_string())?;
+    Ok(path)
+}
+
+// Every object on the page in board pixels, so the view box simply frames the
+// visible content. Pictures are embedded as data URLs and text is placed run
+// by run where the board laid it out. With a `backdrop` the page sits on the
+// board's color, otherwise it is transparent.
+pub fn document(
+    page: &[Action],
+    size: PhysicalSize<u32>,
+    font_system: &mut FontSystem,
+    backdrop: Option<&Backdrop>,
+) -> String {
+    let (_, area) = paint::visible(page, size);
+    let mut svg = Document(String::new());
+    let _ = writeln!(
+        svg.0,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{:.2}" height="{:.2}" viewBox="{:.2} {:.2} {:.2} {:.2}">"#,
+        area.width + MARGIN * 2.0,
+        area.height + MARGIN * 2.0,
+        area.x - MARGIN,
+        area.y - MARGIN,
+        area.width + MARGIN * 2.0,
+        area.height + MARGIN * 2.0
+    );
+    if let Some(backdrop) = backdrop {
+        paint::paper(backdrop, &paint::frame(&area), &mut svg);
+    }
+    paint::paint(page, size, font_system, &mut svg);
+    svg.0.push_str("</svg>\n");
+    svg.0
+}
+
+struct Document(String);
+
+impl Painter for Document {
+    // Many rectangles, like a bucket fill or a paper pattern, share one path.
+    fn fill(&mut self, rects: &[Rect], color: [f32; 4]) {
+        if let [rect] = rects {
+            let _ = writeln!(
+                self.0,
+                r#"<rect x="{:.2}" y="{:.2}" width="{:.2}" height="{:.2}" {}/>"#,
+                rect.x,
+                rect.y,
+                rect.width,
+                rect.height,
+                paint("fill", color)
+            );
+            return;
+        }
+        let mut data = String::new();
+        for rect in rects {
+            let _ = write!(
+                data,
+                "M{:.2} {:.2}h{:.2}v{:.2}h{:.2}Z",
+                rect.x, rect.y, rect.width, rect.height, -rect.width
+            );
+        }
+        let _ = writeln!(self.0, r#"<path d="{}" {}/>"#, data, paint("fill", color));
+    }
+
+    fn outline(&mut self, rect: &Rect, color: [f32; 4]) {
+        let _ = writeln!(
+            self.0,
+            r#"<rect x="{:.2}" y="{:.2}" width="{:.2}" height="{:.2}" fill="none" {}/>"#,
+            rect.x,
+            rect.y,
+            rect.width,
+            rect.height,
+            paint("stroke", color)
+        );
+    }
+
+    fn image(&mut self, bounds: &Rect, data: &[u8]) {
+        let Ok(format) = image::guess_format(data) else {
+            return;
+        };
+        let _ = writeln!(
+            self.0,
+            r#"<image x="{:.2}" y="{:.2}" width="{:.2}" height="{:.2}" preserveAspectRatio="none" href="data:{};base64,{}"/>"#,
+            bounds.x,
+            bounds.y,
+            bounds.width,
+            bounds.height,
+            format.to_mime_type(),
+            STANDARD.encode(data)
+        );
+    }
+
+    fn lines(&mut self, points: &[[f32; 2]], color: [u8; 4]) {
+        let mut data = String::new();
+        for pair in points.chunks_exact(2) {
+            let [[x1, y1], [x2, y2]] = [pair[0], pair[1]];
+            let _ = write!(data, "M{:.2} {:.2}L{:.2} {:.2}", x1, y1, x2, y2);
+        }
+        if data.is_empty() {
+            return;
+        }
+        let _ = writeln!(
+            self.0,
+            r#"<path d="{}" fill="none" {} stroke-width="1" stroke-linecap="round"/>"#,
+            data,
+            paint("stroke", rgba_to_normalized(color))
+        );
+    }
+
+    fn stroke(&mut self, points: &[[f32; 2]], width: f32, color: [f32; 4]) {
+        let mut data = String::new();
+        for (index, [x, y]) in points.iter().enumerate() {
+            let command = if index == 0 { 'M' } else { 'L' };
+            let _ = write!(data, "{}{:.2} {:.2} ", command, x, y);
+        }
+        // A lone point still leaves a round dot.
+        if points.len() == 1 {
+            data.push_str("l0 0");
+        }
+        let _ = writeln!(
+            self.0,
+            r#"<path d="{}" fill="none" {} stroke-width="{:.2}" stroke-linecap="round" stroke-linejoin="round"/>"#,
+            data.trim_end(),
+            paint("stroke", color),
+            width
+        );
+    }
+
+    // Glyphs that share a face, size and color are set as one piece of text
+    // from where the first of them starts, so shaping and joining are left to
+    // the viewer while lines still break and markdown styles apply as they do
+    // on the board. Right-to-left pieces are anchored at their right edge.
+    fn glyphs(
+        &mut self,
+        fonts: &fontdb::Database,
+        buffer: &Buffer,
+        [left, top]: [f32; 2],
+        color: [u8; 4],
+    ) {
+        for run in buffer.layout_runs() {
+            let style = |glyph: &LayoutGlyph| {
+                let color = glyph
+                    .color_opt
+                    .map_or(color, |color| [color.r(), color.g(), color.b(), color.a()]);
+                (glyph.font_id, glyph.font_size.to_bits(), color)
+            };
+            for piece in run.glyphs.chunk_by(|a, b| style(a) == style(b)) {
+                let first = &piece[0];
+                let (start, end) = piece.iter().fold((usize::MAX, 0), |(start, end), glyph| {
+                    (start.min(glyph.start), end.max(glyph.end))
+                });
+                let Some(text) = run.text.get(start..end) else {
+                    continue;
+                };
+                let x = match run.rtl {
+                    true => piece
+                        .iter()
+                        .map(|glyph| glyph.x + glyph.w)
+                        .fold(f32::MIN, f32::max),
+                    false => piece.iter().map(|glyph| glyph.x).fold(f32::MAX, f32::min),
+                };
+                let face = fonts.face(first.font_id);
+                let family = face
+                    .and_then(|face| face.families.first())
+                    .map_or("sans-serif", |(family, _)| family.as_str());
+                let weight = face.map_or(400, |face| face.weight.0);
+                let italic = face.is_some_and(|face| face.style != fontdb::Style::Normal);
+                let _ = writeln!(
+                    self.0,
+                    r#"<text x="{:.2}" y="{:.2}" font-family="{}" font-size="{}" font-weight="{}"{}{} {} xml:space="preserve">{}</text>"#,
+                    left + x,
+                    top + run.line_y + first.y,
+                    escape(family),
+                    first.font_size,
+                    weight,
+                    if italic {
+                        r#" font-style="italic""#
+                    } else {
+                        ""
+                    },
+                    if run.rtl { r#" direction="rtl""# } else { "" },
+                    paint("fill", rgba_to_normalized(style(first).2)),
+                    escape(text)
+                );
+            }
+        }
+    }
+}
+
+fn paint(attribute: &str, color: [f32; 4]) -> String {
+    let [r, g, b] = [color[0], color[1], color[2]]
+        .map(|channel| (channel.clamp(0.0, 1.0) * 255.0).round() as u8);
+    format!(
+        r##"{}="#{:02x}{:02x}{:02x}" {}-opacity="{:.3}""##,
+        attribute,
+        r,
+        g,
+        b,
+        attribute,
+        color[3].clamp(0.0, 1.0)
+    )
+}
+
+fn escape(text: &str) -> String {
+    let mut escaped = String::new();
+    for character in text.chars() {
+        match character {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            _ => escaped.push(character),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn redactions_are_drawn_over_the_page() {
+        let area = Rect {
+            x: 10.0,
+            y: 20.0,
+            width: 30.0,
+            height: 40.0,
+        };
+        let svg = document(
+            &[Action::Redact(area)],
+            PhysicalSize::new(800, 600),
+            &mut FontSystem::new(),
+            None,
+        );
+        assert!(svg.contains(
+            r##"<rect x="10.00" y="20.00" width="30.00" height="40.00" fill="#000000" fill-opacity="1.000"/>"##
+        ));
+    }
+}
@@ -0,0 +1,140 @@
+use std::collections::HashMap;
+
+// Window pixels per grid cell. Boundaries are rasterized into the grid at the
+// current view, so what counts as enclosed is what the user can see.
+const CELL: f32 = 2.0;
+
+pub struct Mask {
+    columns: usize,
+    rows: usize,
+    walls: Vec<bool>,
+}
+
+impl Mask {
+    pub fn new(width: f32, height: f32) -> Self {
+        let columns = (width / CELL).ceil() as usize;
+        let rows = (height / CELL).ceil() as usize;
+        Mask {
+            columns,
+            rows,
+            walls: vec![false; columns * rows],
+        }
+    }
+
+    // Walls off every cell within `radius` of the polyline through `points`.
+    pub fn add_line(&mut self, points: &[[f32; 2]], radius: f32) {
+        let radius = radius.max(CELL / 2.0);
+        if let [point] = points {
+            self.add_disc(*point, radius);
+        }
+        for pair in points.windows(2) {
+            let [from, to] = [pair[0], pair[1]];
+            let length = (to[0] - from[0]).hypot(to[1] - from[1]);
+            let steps = ((length / (CELL / 2.0)).ceil() as usize).max(1);
+            for step in 0..=steps {
+                let t = step as f32 / steps as f32;
+                self.add_disc(
+                    [
+                        from[0] + (to[0] - from[0]) * t,
+                        from[1] + (to[1] - from[1]) * t,
+                    ],
+                    radius,
+                );
+            }
+        }
+    }
+
+    fn add_disc(&mut self, center: [f32; 2], radius: f32) {
+        let cells = radius / CELL;
+        let reach = cells.ceil() as isize;
+        let (x, y) = ((center[0] / CELL) as isize, (center[1] / CELL) as isize);
+        for dy in -reach..=reach {
+            for dx in -reach..=reach {
+                if ((dx * dx + dy * dy) as f32).sqrt() > cells + 0.5 {
+                    continue;
+                }
+                let (cx, cy) = (x + dx, y + dy);
+                if cx >= 0 && cy >= 0 && (cx as usize) < self.columns && (cy as usize) < self.rows {
+                    self.walls[cy as usize * self.columns + cx as usize] = true;
+                }
+            }
+        }
+    }
+
+    // The open region around `start` as rectangles in window pixels, given as
+    // [left, top, right, bottom]. None when the click lands on a wall or the
+    // region leaks out to the edge of the window.
+    pub fn flood(&self, start: [f32; 2]) -> Option<Vec<[f32; 4]>> {
+        let (x, y) = ((start[0] / CELL) as usize, (start[1] / CELL) as usize);
+        if start[0] < 0.0 || start[1] < 0.0 || x >= self.columns || y >= self.rows {
+            return None;
+        }
+        let mut filled = vec![false; self.walls.len()];
+        let open = |filled: &[bool], x: usize, y: usize| {
+            let index = y * self.columns + x;
+            !self.walls[index] && !filled[index]
+        };
+        let mut spans = vec![Vec::new(); self.rows];
+        let mut pending = vec![(x, y)];
+        while let Some((x, y)) = pending.pop() {
+            if !open(&filled, x, y) {
+                continue;
+            }
+            let (mut left, mut right) = (x, x);
+            while left > 0 && open(&filled, left - 1, y) {
+                left -= 1;
+            }
+            while right + 1 < self.columns && open(&filled, right + 1, y) {
+                right += 1;
+            }
+            if left == 0 || right + 1 == self.columns || y == 0 || y + 1 == self.rows {
+                return None;
+            }
+            for cx in left..=right {
+                filled[y * self.columns + cx] = true;
+                for cy in [y - 1, y + 1] {
+                    if open(&filled, cx, cy) {
+                        pending.push((cx, cy));
+                    }
+                }
+            }
+            spans[y].push((left, right));
+        }
+
+        // Rows with the same span stack into one taller rectangle.
+        let mut rects: Vec<[usize; 4]> = Vec::new();
+        let mut above: HashMap<(usize, usize), usize> = HashMap::new();
+        for (y, row) in spans.iter().enumerate() {
+            let mut current = HashMap::new();
+            for &(left, right) in row {
+                let index = match above.get(&(left, right)) {
+                    Some(&index) => {
+                        rects[index][3] = y + 1;
+                        index
+                    }
+                    None => {
+                        rects.push([left, y, right + 1, y + 1]);
+                        rects.len() - 1
+                    }
+                };
+                current.insert((left, right), index);
+            }
+            above = current;
+        }
+        // Reaching half a cell into the walls hides the seam under the boundary.
+        let overlap = CELL / 2.0;
+        Some(
+            rects
+                .into_iter()
+                .map(|[left, top, right, bottom]| {
+                    [
+                        left as f32 * CELL - overlap,
+                        top as f32 * CELL - overlap,
+                        right as f32 * CELL + overlap,
+                        bottom as f32 * CELL + overlap,
+                    ]
+                })
+                .collect(),
+        )
+    }
+}
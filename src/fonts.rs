@@ -0,0 +1,80 @@
+use crate::crash;
+use glyphon::FontSystem;
+use std::{collections::HashSet, fs, path::PathBuf, time::Duration};
+
+pub const DEFAULT_FONT: &str = "Vazir";
+pub const FONT_SCAN_INTERVAL: Duration = Duration::from_secs(2);
+const EXTENSIONS: [&str; 4] = ["ttf", "otf", "ttc", "otc"];
+
+pub struct FontLibrary {
+    pub dir: Option<PathBuf>,
+    pub families: Vec<String>,
+    loaded: HashSet<PathBuf>,
+}
+
+impl FontLibrary {
+    pub fn new(dir: Option<PathBuf>, font_system: &mut FontSystem) -> Self {
+        let mut library = FontLibrary {
+            dir,
+            families: Vec::new(),
+            loaded: HashSet::new(),
+        };
+        library.scan(font_system);
+        library.refresh_families(font_system);
+        library
+    }
+
+    pub fn is_font(path: &std::path::Path) -> bool {
+        path.extension()
+            .and_then(|ext| ext.to_str())
+            .is_some_and(|ext| EXTENSIONS.contains(&ext.to_lowercase().as_str()))
+    }
+
+    pub fn scan(&mut self, font_system: &mut FontSystem) -> bool {
+        let Some(entries) = self.dir.as_ref().and_then(|dir| fs::read_dir(dir).ok()) else {
+            return false;
+        };
+
+        let mut changed = false;
+        for path in entries.filter_map(Result::ok).map(|entry| entry.path()) {
+            if !Self::is_font(&path) || self.loaded.contains(&path) {
+                continue;
+            }
+            match font_system.db_mut().load_font_file(&path) {
+                Ok(()) => changed = true,
+                Err(err) => crash::log(format!("Unable to load font {}: {}", path.display(), err)),
+            }
+            self.loaded.insert(path);
+        }
+        if changed {
+            self.refresh_families(font_system);
+        }
+        changed
+    }
+
+    pub fn install(&mut self, path: &std::path::Path, font_system: &mut FontSystem) {
+        let (Some(dir), Some(name)) = (&self.dir, path.file_name()) else {
+            return;
+        };
+        let _ = fs::create_dir_all(dir);
+        if let Err(err) = fs::copy(path, dir.join(name)) {
+            crash::log(format!(
+                "Unable to install font {}: {}",
+                path.display(),
+                err
+            ));
+        }
+        self.scan(font_system);
+    }
+
+    fn refresh_families(&mut self, font_system: &FontSystem) {
+        let mut families: Vec<String> = font_system
+            .db()
+            .faces()
+            .filter_map(|face| face.families.first().map(|(name, _)| name.clone()))
+            .collect();
+        families.sort();
+        families.dedup();
+        self.families = families;
+    }
+}
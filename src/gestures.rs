@@ -0,0 +1,289 @@
+use crate::commands::Command;
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, time::Instant};
+use tao::event::TouchPhase;
+
+// A scribble turns back on itself at least this often, covering a path several
+// times longer than the box around it, within a short time.
+const SCRIBBLE_TURNS: usize = 5;
+const SCRIBBLE_SEGMENT: f32 = 6.0;
+const SCRIBBLE_PATH_RATIO: f32 = 3.0;
+const SCRIBBLE_MAX_MILLIS: u64 = 1500;
+
+const FLICK_DISTANCE: f32 = 80.0;
+const FLICK_MAX_MILLIS: u128 = 600;
+
+// A finger landing this close to the left or right side of the window swipes
+// in from the edge.
+const EDGE_WIDTH: f32 = 24.0;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum FlickDirection {
+    Left,
+    Right,
+    Up,
+    Down,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Flick {
+    pub fingers: usize,
+    pub direction: FlickDirection,
+    pub command: Command,
+}
+
+pub fn default_flicks() -> Vec<Flick> {
+    vec![
+        Flick {
+            fingers: 3,
+            direction: FlickDirection::Left,
+            command: Command::Undo,
+        },
+        Flick {
+            fingers: 3,
+            direction: FlickDirection::Right,
+            command: Command::Redo,
+        },
+    ]
+}
+
+// `points` are in board pixels.
+pub fn is_scribble(points: &[[f32; 2]], timestamps: &[u64]) -> bool {
+    if let (Some(first), Some(last)) = (timestamps.first(), timestamps.last()) {
+        if last.saturating_sub(*first) > SCRIBBLE_MAX_MILLIS {
+            return false;
+        }
+    }
+
+    let mut turns = 0;
+    let mut length = 0.0;
+    let mut anchor = match points.first() {
+        Some(point) => *point,
+        None => return false,
+    };
+    let mut heading: Option<[f32; 2]> = None;
+    let (mut min, mut max) = (anchor, anchor);
+    for point in points {
+        min = [min[0].min(point[0]), min[1].min(point[1])];
+        max = [max[0].max(point[0]), max[1].max(point[1])];
+        let segment = [point[0] - anchor[0], point[1] - anchor[1]];
+        let distance = segment[0].hypot(segment[1]);
+        // Jitter below a few pixels says nothing about direction.
+        if distance < SCRIBBLE_SEGMENT {
+            continue;
+        }
+        if let Some(heading) = heading {
+            if heading[0] * segment[0] + heading[1] * segment[1] < 0.0 {
+                turns += 1;
+            }
+        }
+        heading = Some(segment);
+        length += distance;
+        anchor = *point;
+    }
+
+    let diagonal = (max[0] - min[0]).hypot(max[1] - min[1]);
+    turns >= SCRIBBLE_TURNS && length >= diagonal * SCRIBBLE_PATH_RATIO
+}
+
+pub enum TouchGesture {
+    // The touch is an ordinary pointer and should draw.
+    Pointer,
+    // Several fingers are down, so the touch belongs to a gesture.
+    Tracking,
+    Flick(usize, FlickDirection),
+}
+
+#[derive(Default)]
+pub struct FlickTracker {
+    touches: HashMap<u64, ([f32; 2], [f32; 2])>,
+    lifted: Vec<[f32; 2]>,
+    fingers: usize,
+    started: Option<Instant>,
+}
+
+impl FlickTracker {
+    pub fn touch(
+        &mut self,
+        id: u64,
+        phase: TouchPhase,
+        location: [f32; 2],
+        min_fingers: usize,
+    ) -> TouchGesture {
+        match phase {
+            TouchPhase::Started => {
+                self.touches.insert(id, (location, location));
+                if self.started.is_none() && self.touches.len() >= min_fingers {
+                    self.started = Some(Instant::now());
+                }
+                if self.started.is_some() {
+                    self.fingers = self.fingers.max(self.touches.len());
+                }
+            }
+            TouchPhase::Moved => {
+                if let Some((_, current)) = self.touches.get_mut(&id) {
+                    *current = location;
+                }
+            }
+            _ => {
+                if let Some((start, _)) = self.touches.remove(&id) {
+                    if self.started.is_some() {
+                        self.lifted
+                            .push([location[0] - start[0], location[1] - start[1]]);
+                    }
+                }
+                if self.touches.is_empty() {
+                    return self.finish();
+                }
+            }
+        }
+        match self.started {
+            Some(_) => TouchGesture::Tracking,
+            None => TouchGesture::Pointer,
+        }
+    }
+
+    fn finish(&mut self) -> TouchGesture {
+        let Some(started) = self.started.take() else {
+            return TouchGesture::Pointer;
+        };
+        let fingers = std::mem::take(&mut self.fingers);
+        let lifted = std::mem::take(&mut self.lifted);
+        if started.elapsed().as_millis() > FLICK_MAX_MILLIS || lifted.is_empty() {
+            return TouchGesture::Tracking;
+        }
+
+        let count = lifted.len() as f32;
+        let (dx, dy) = lifted.iter().fold((0.0, 0.0), |(x, y), delta| {
+            (x + delta[0] / count, y + delta[1] / count)
+        });
+        if dx.hypot(dy) < FLICK_DISTANCE {
+            return TouchGesture::Tracking;
+        }
+        let direction = match dx.abs() > dy.abs() {
+            true if dx < 0.0 => FlickDirection::Left,
+            true => FlickDirection::Right,
+            false if dy < 0.0 => FlickDirection::Up,
+            false => FlickDirection::Down,
+        };
+        TouchGesture::Flick(fingers, direction)
+    }
+}
+
+// One finger that lands at the left or right edge of the window and swipes
+// inward, like turning a page.
+#[derive(Default)]
+pub struct EdgeSwipe {
+    touch: Option<(u64, [f32; 2])>,
+}
+
+impl EdgeSwipe {
+    pub fn touch(
+        &mut self,
+        id: u64,
+        phase: TouchPhase,
+        location: [f32; 2],
+        width: f32,
+    ) -> TouchGesture {
+        match phase {
+            TouchPhase::Started => {
+                let at_edge = location[0] < EDGE_WIDTH || location[0] > width - EDGE_WIDTH;
+                if self.touch.is_some() || !at_edge {
+                    return TouchGesture::Pointer;
+                }
+                self.touch = Some((id, location));
+            }
+            TouchPhase::Moved => {
+                if self.touch.is_none_or(|(touch, _)| touch != id) {
+                    return TouchGesture::Pointer;
+                }
+            }
+            _ => {
+                let Some((_, start)) = self.touch.take_if(|(touch, _)| *touch == id) else {
+                    return TouchGesture::Pointer;
+                };
+                let (dx, dy) = (location[0] - start[0], location[1] - start[1]);
+                let inward = match start[0] < EDGE_WIDTH {
+                    true => dx > 0.0,
+                    false => dx < 0.0,
+                };
+                if inward && dx.abs() >= FLICK_DISTANCE && dx.abs() > dy.abs() {
+                    let direction = match dx < 0.0 {
+                        true => FlickDirection::Left,
+                        false => FlickDirection::Right,
+                    };
+                    return TouchGesture::Flick(1, direction);
+                }
+            }
+        }
+        TouchGesture::Tracking
+    }
+}
+
+// How a two-finger gesture moved since the last touch event, in screen pixels.
+pub struct PinchStep {
+    pub center: [f32; 2],
+    pub pan: [f32; 2],
+    pub zoom: f32,
+}
+
+// Once a second finger lands every touch belongs to the pinch until all of
+// them lift, so the finger left behind doesn't start drawing.
+#[derive(Default)]
+pub struct PinchTracker {
+    touches: HashMap<u64, [f32; 2]>,
+    pinching: bool,
+}
+
+impl PinchTracker {
+    pub fn touch(&mut self, id: u64, phase: TouchPhase, location: [f32; 2]) -> Option<PinchStep> {
+        let before = self.span();
+        match phase {
+            TouchPhase::Started => {
+                self.touches.insert(id, location);
+                self.pinching |= self.touches.len() == 2;
+            }
+            TouchPhase::Moved => {
+                if let Some(current) = self.touches.get_mut(&id) {
+                    *current = location;
+                }
+            }
+            _ => {
+                self.touches.remove(&id);
+            }
+        }
+        if !self.pinching {
+            return None;
+        }
+        if self.touches.is_empty() {
+            self.pinching = false;
+        }
+        let mut step = PinchStep {
+            center: location,
+            pan: [0.0, 0.0],
+            zoom: 1.0,
+        };
+        if let (TouchPhase::Moved, Some((from, spread)), Some((to, distance))) =
+            (phase, before, self.span())
+        {
+            step.center = to;
+            step.pan = [to[0] - from[0], to[1] - from[1]];
+            if spread > 1.0 {
+                step.zoom = distance / spread;
+            }
+        }
+        Some(step)
+    }
+
+    // The midpoint of exactly two fingers and the distance between them.
+    fn span(&self) -> Option<([f32; 2], f32)> {
+        let mut touches = self.touches.values();
+        match (touches.next(), touches.next(), touches.next()) {
+            (Some(a), Some(b), None) => Some((
+                [(a[0] + b[0]) / 2.0, (a[1] + b[1]) / 2.0],
+                (a[0] - b[0]).hypot(a[1] - b[1]),
+            )),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,407 @@
+use crate::{
+    camera::Camera, crash, fonts::FontLibrary, project, scene, screen_to_ndc, settings::Settings,
+    text_layout, Action, Brush, Stroke, Vertex,
+};
+use glyphon::{
+    Cache, Color, FontSystem, Resolution, SwashCache, TextArea, TextAtlas, TextBounds,
+    TextRenderer, Viewport,
+};
+use std::path::{Path, PathBuf};
+use tao::dpi::PhysicalSize;
+use wgpu::util::DeviceExt;
+
+const FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba8UnormSrgb;
+const DEFAULT_SIZE: PhysicalSize<u32> = PhysicalSize::new(1280, 720);
+
+// `--headless board.wb --out board.png [--size 1280x720] [--page 1]` renders a
+// saved board to a picture without opening a window. Other flags go on to the
+// settings, so `--backend` and `--adapter` still pick the GPU.
+pub struct Job {
+    pub board: PathBuf,
+    pub out: PathBuf,
+    pub size: PhysicalSize<u32>,
+    pub page: usize,
+}
+
+impl Job {
+    pub fn from_args(
+        args: impl IntoIterator<Item = String>,
+        settings: &mut Settings,
+    ) -> Option<Result<Self, String>> {
+        let (mut board, mut out, mut size, mut page) = (None, None, DEFAULT_SIZE, 1);
+        let mut rest = Vec::new();
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            let (flag, value) = match arg.split_once('=') {
+                Some((flag, value)) => (flag.to_string(), Some(value.to_string())),
+                None => (arg.clone(), None),
+            };
+            let mut value = || value.clone().or_else(|| args.next()).unwrap_or_default();
+            match flag.as_str() {
+                "--headless" => board = Some(PathBuf::from(value())),
+                "--out" => out = Some(PathBuf::from(value())),
+                "--size" => {
+                    let value = value();
+                    let parsed = value.split_once('x').and_then(|(width, height)| {
+                        Some((width.parse().ok()?, height.parse().ok()?))
+                    });
+                    match parsed {
+                        Some((width, height)) if width > 0 && height > 0 => {
+                            size = PhysicalSize::new(width, height)
+                        }
+                        _ => {
+                            return Some(Err(format!(
+                                "Invalid size {}, expected WIDTHxHEIGHT",
+                                value
+                            )))
+                        }
+                    }
+                }
+                "--page" => match value().parse() {
+                    Ok(number) if number > 0 => page = number,
+                    _ => return Some(Err("Pages are numbered from 1".to_string())),
+                },
+                _ => rest.push(arg),
+            }
+        }
+        let board = board?;
+        settings.apply_args(rest);
+        let Some(out) = out else {
+            return Some(Err("--headless needs --out <file.png>".to_string()));
+        };
+        Some(Ok(Job {
+            board,
+            out,
+            size,
+            page,
+        }))
+    }
+}
+
+// Draws the page's bucket fills, rectangles, strokes and texts the way the
+// board shows them at the default camera, then reads the frame back. Images,
+// tables, code blocks and stencils are left out.
+pub async fn render(job: &Job, settings: &Settings) -> Result<PathBuf, String> {
+    let pages = project::load(&job.board, job.size)?;
+    let page = pages
+        .get(job.page - 1)
+        .ok_or_else(|| format!("The board has {} pages", pages.len()))?;
+    let hidden = scene::hidden_layers(page);
+    let visible: Vec<&Action> = page
+        .iter()
+        .filter(|action| scene::is_visible(action, &hidden))
+        .collect();
+    let size = job.size;
+
+    let instance = wgpu::Instance::new(wgpu::InstanceDescriptor {
+        backends: settings.backends(),
+        ..Default::default()
+    });
+    let adapter = instance
+        .request_adapter(&wgpu::RequestAdapterOptions::default())
+        .await
+        .ok_or("No graphics adapter found")?;
+    crash::set_adapter(&adapter.get_info());
+    let (device, queue) = adapter
+        .request_device(&wgpu::DeviceDescriptor::default(), None)
+        .await
+        .map_err(|err| err.to_string())?;
+
+    let texture = device.create_texture(&wgpu::TextureDescriptor {
+        label: Some("Headless Target"),
+        size: wgpu::Extent3d {
+            width: size.width,
+            height: size.height,
+            depth_or_array_layers: 1,
+        },
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format: FORMAT,
+        usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+        view_formats: &[],
+    });
+    let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+
+    let camera_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        label: Some("Camera Uniform Buffer"),
+        contents: bytemuck::bytes_of(&Camera::default().uniform(size)),
+        usage: wgpu::BufferUsages::UNIFORM,
+    });
+    let camera_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+        label: Some("Camera Bind Group Layout"),
+        entries: &[wgpu::BindGroupLayoutEntry {
+            binding: 0,
+            visibility: wgpu::ShaderStages::VERTEX,
+            ty: wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Uniform,
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        }],
+    });
+    let camera_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: Some("Camera Bind Group"),
+        layout: &camera_layout,
+        entries: &[wgpu::BindGroupEntry {
+            binding: 0,
+            resource: camera_buffer.as_entire_binding(),
+        }],
+    });
+    let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+        label: Some("Pipeline Layout"),
+        bind_group_layouts: &[&camera_layout],
+        push_constant_ranges: &[],
+    });
+    let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: Some("rect shader"),
+        source: wgpu::ShaderSource::Wgsl(include_str!("shaders/shape.wgsl").into()),
+    });
+    let pipeline = |entry_point: &str, topology: wgpu::PrimitiveTopology| {
+        device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("headless pipeline"),
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: Some(entry_point),
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+                buffers: &[wgpu::VertexBufferLayout {
+                    array_stride: size_of::<Vertex>() as wgpu::BufferAddress,
+                    step_mode: wgpu::VertexStepMode::Vertex,
+                    attributes: &wgpu::vertex_attr_array![0 => Float32x2, 1 => Float32x4],
+                }],
+            },
+            primitive: wgpu::PrimitiveState {
+                topology,
+                ..Default::default()
+            },
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: Some("fs_main"),
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+                targets: &[Some(wgpu::ColorTargetState {
+                    format: FORMAT,
+                    blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            multiview: None,
+            cache: None,
+        })
+    };
+    let fill_pipeline = pipeline("triangle_vs", wgpu::PrimitiveTopology::TriangleList);
+    let line_pipeline = pipeline("rectangle_vs", wgpu::PrimitiveTopology::LineList);
+
+    // Fills go first as they do on the board, then shapes and strokes.
+    let (mut fills, mut lines, mut strokes) = (Vec::new(), Vec::new(), Vec::new());
+    let quad = |[x1, y1]: [f32; 2], [x2, y2]: [f32; 2], color: [f32; 4]| {
+        [(x1, y1), (x2, y1), (x1, y2), (x1, y2), (x2, y1), (x2, y2)].map(|(x, y)| Vertex {
+            position: [x, y],
+            color,
+        })
+    };
+    let background = settings.background;
+    let pattern = background.pattern([[0.0, 0.0], [size.width as f32, size.height as f32]], 1.0);
+    fills.extend(pattern.into_iter().map(|[x, y]| Vertex {
+        position: screen_to_ndc(size, x, y),
+        color: background.pattern_color(),
+    }));
+    for action in &visible {
+        match action {
+            Action::Fill(fill) => {
+                for rect in &fill.rects {
+                    let first = screen_to_ndc(size, rect.x, rect.y);
+                    let last = screen_to_ndc(size, rect.x + rect.width, rect.y + rect.height);
+                    fills.extend(quad(first, last, fill.color));
+                }
+            }
+            Action::Shapes(rectangle) if rectangle.filled => {
+                fills.extend(quad(rectangle.first, rectangle.last, rectangle.color))
+            }
+            Action::Shapes(rectangle) => lines.extend(rectangle.to_vertices()),
+            Action::Stroke(stroke) => {
+                let taper = match stroke.brush {
+                    Brush::Pen => settings.tapers.pen,
+                    Brush::Highlighter => settings.tapers.highlighter,
+                    Brush::Calligraphy => settings.tapers.calligraphy,
+                };
+                let nib =
+                    (stroke.brush == Brush::Calligraphy).then(|| settings.nib_angle.to_radians());
+                Stroke::ribbon_vertices(
+                    &stroke.points,
+                    stroke.width,
+                    [taper; 2],
+                    nib,
+                    size,
+                    &mut strokes,
+                );
+            }
+            _ => {}
+        }
+    }
+
+    let mut font_system = FontSystem::new();
+    font_system
+        .db_mut()
+        .load_font_data(include_bytes!("assets/vazir.ttf").to_vec());
+    let _ = FontLibrary::new(settings.fonts_dir(), &mut font_system);
+    let mut swash_cache = SwashCache::new();
+    let cache = Cache::new(&device);
+    let mut viewport = Viewport::new(&device, &cache);
+    viewport.update(
+        &queue,
+        Resolution {
+            width: size.width,
+            height: size.height,
+        },
+    );
+    let mut atlas = TextAtlas::new(&device, &queue, &cache, FORMAT);
+    let mut text_renderer =
+        TextRenderer::new(&mut atlas, &device, wgpu::MultisampleState::default(), None);
+    let mut buffers = Vec::new();
+    for action in &visible {
+        let Action::Text(text) = action else {
+            continue;
+        };
+        let (buffer, origin) = text_layout(&mut font_system, text, size);
+        buffers.push((buffer, origin, text.color));
+    }
+    let text_areas = buffers.iter().map(|(buffer, [left, top], color)| TextArea {
+        buffer,
+        left: *left,
+        top: *top,
+        scale: 1.0,
+        bounds: TextBounds {
+            left: 0,
+            top: 0,
+            right: size.width as i32,
+            bottom: size.height as i32,
+        },
+        default_color: Color::rgba(color[0], color[1], color[2], color[3]),
+        custom_glyphs: &[],
+    });
+    text_renderer
+        .prepare(
+            &device,
+            &queue,
+            &mut font_system,
+            &mut atlas,
+            &viewport,
+            text_areas,
+            &mut swash_cache,
+        )
+        .map_err(|err| err.to_string())?;
+
+    let buffer = |vertices: &[Vertex]| {
+        device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Vertex Buffer"),
+            contents: bytemuck::cast_slice(vertices),
+            usage: wgpu::BufferUsages::VERTEX,
+        })
+    };
+    let draws = [
+        (&fill_pipeline, &fills),
+        (&line_pipeline, &lines),
+        (&fill_pipeline, &strokes),
+    ]
+    .map(|(pipeline, vertices)| (pipeline, buffer(vertices), vertices.len() as u32));
+
+    let [red, green, blue, alpha] = background.normalized().map(f64::from);
+    let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+        label: Some("Headless Encoder"),
+    });
+    {
+        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Headless Pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: &view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(wgpu::Color {
+                        r: red,
+                        g: green,
+                        b: blue,
+                        a: alpha,
+                    }),
+                    store: wgpu::StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: None,
+            timestamp_writes: None,
+            occlusion_query_set: None,
+        });
+        render_pass.set_bind_group(0, &camera_bind_group, &[]);
+        for (pipeline, vertex_buffer, count) in &draws {
+            if *count == 0 {
+                continue;
+            }
+            render_pass.set_pipeline(pipeline);
+            render_pass.set_vertex_buffer(0, vertex_buffer.slice(..));
+            render_pass.draw(0..*count, 0..1);
+        }
+        text_renderer
+            .render(&atlas, &viewport, &mut render_pass)
+            .map_err(|err| err.to_string())?;
+    }
+
+    // Rows are copied out padded to the alignment wgpu needs.
+    let row = size.width * 4;
+    let padded_row =
+        row.div_ceil(wgpu::COPY_BYTES_PER_ROW_ALIGNMENT) * wgpu::COPY_BYTES_PER_ROW_ALIGNMENT;
+    let readback = device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("Headless Readback"),
+        size: (padded_row * size.height) as wgpu::BufferAddress,
+        usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+        mapped_at_creation: false,
+    });
+    encoder.copy_texture_to_buffer(
+        texture.as_image_copy(),
+        wgpu::ImageCopyBuffer {
+            buffer: &readback,
+            layout: wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: Some(padded_row),
+                rows_per_image: Some(size.height),
+            },
+        },
+        texture.size(),
+    );
+    queue.submit(std::iter::once(encoder.finish()));
+
+    let slice = readback.slice(..);
+    let (sender, receiver) = std::sync::mpsc::channel();
+    slice.map_async(wgpu::MapMode::Read, move |result| {
+        let _ = sender.send(result);
+    });
+    device.poll(wgpu::Maintain::Wait);
+    receiver
+        .recv()
+        .map_err(|err| err.to_string())?
+        .map_err(|err| err.to_string())?;
+    let pixels: Vec<u8> = slice
+        .get_mapped_range()
+        .chunks(padded_row as usize)
+        .flat_map(|line| &line[..row as usize])
+        .copied()
+        .collect();
+
+    save_png(&job.out, pixels, size)
+}
+
+fn save_png(path: &Path, pixels: Vec<u8>, size: PhysicalSize<u32>) -> Result<PathBuf, String> {
+    let path = if path.extension().is_none() {
+        path.with_extension("png")
+    } else {
+        path.to_path_buf()
+    };
+    let image = image::RgbaImage::from_raw(size.width, size.height, pixels)
+        .ok_or("The frame does not match its size")?;
+    if let Some(parent) = path.parent() {
+        let _ = std::fs::create_dir_all(parent);
+    }
+    image.save(&path).map_err(|err| err.to_string())?;
+    Ok(path)
+}
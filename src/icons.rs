@@ -0,0 +1,144 @@
+use crate::crash;
+use std::{fs, path::PathBuf};
+
+pub const ICON_SIZE: f32 = 48.0;
+
+const BUILTIN: [(&str, &str, &str); 21] = [
+    (
+        "Arrows",
+        "Arrow right",
+        r#"<path d="M4 12h16M14 6l6 6-6 6"/>"#,
+    ),
+    (
+        "Arrows",
+        "Arrow left",
+        r#"<path d="M20 12H4M10 6l-6 6 6 6"/>"#,
+    ),
+    ("Arrows", "Arrow up", r#"<path d="M12 20V4M6 10l6-6 6 6"/>"#),
+    (
+        "Arrows",
+        "Arrow down",
+        r#"<path d="M12 4v16M6 14l6 6 6-6"/>"#,
+    ),
+    (
+        "Arrows",
+        "Double arrow",
+        r#"<path d="M3 12h18M7 8l-4 4 4 4M17 8l4 4-4 4"/>"#,
+    ),
+    (
+        "People",
+        "Person",
+        r#"<circle cx="12" cy="7" r="4"/><path d="M4 21c0-4 4-7 8-7s8 3 8 7"/>"#,
+    ),
+    (
+        "People",
+        "Group",
+        r#"<circle cx="8" cy="8" r="3"/><circle cx="16" cy="8" r="3"/><path d="M2 20c0-3 3-6 6-6s6 3 6 6M10 20c0-3 3-6 6-6s6 3 6 6"/>"#,
+    ),
+    (
+        "Devices",
+        "Laptop",
+        r#"<rect x="4" y="5" width="16" height="11"/><path d="M2 19h20"/>"#,
+    ),
+    (
+        "Devices",
+        "Phone",
+        r#"<rect x="7" y="2" width="10" height="20"/><path d="M11 18h2"/>"#,
+    ),
+    (
+        "Devices",
+        "Monitor",
+        r#"<rect x="3" y="4" width="18" height="12"/><path d="M12 16v4M8 20h8"/>"#,
+    ),
+    (
+        "Devices",
+        "Server",
+        r#"<rect x="4" y="3" width="16" height="7"/><rect x="4" y="14" width="16" height="7"/><path d="M7 6.5h2M7 17.5h2"/>"#,
+    ),
+    ("Math", "Plus", r#"<path d="M12 5v14M5 12h14"/>"#),
+    ("Math", "Minus", r#"<path d="M5 12h14"/>"#),
+    ("Math", "Times", r#"<path d="M6 6l12 12M18 6L6 18"/>"#),
+    (
+        "Math",
+        "Divide",
+        r#"<path d="M5 12h14"/><circle cx="12" cy="6" r="1"/><circle cx="12" cy="18" r="1"/>"#,
+    ),
+    ("Math", "Equals", r#"<path d="M5 9h14M5 15h14"/>"#),
+    ("Math", "Square root", r#"<path d="M3 13h3l3 7 5-16h7"/>"#),
+    (
+        "Math",
+        "Integral",
+        r#"<path d="M15 3c-3 0-3 3-3 6v6c0 3 0 6-3 6"/>"#,
+    ),
+    (
+        "Math",
+        "Infinity",
+        r#"<path d="M12 12c-2-3-4-4-6-4a4 4 0 0 0 0 8c2 0 4-1 6-4s4-4 6-4a4 4 0 0 1 0 8c-2 0-4-1-6-4z"/>"#,
+    ),
+    ("Math", "Sum", r#"<path d="M18 4H6l6 8-6 8h12"/>"#),
+    (
+        "Math",
+        "Pi",
+        r#"<path d="M4 7h16M9 7v13M15 7v10c0 2 1 3 3 3"/>"#,
+    ),
+];
+
+pub struct Icon {
+    pub category: String,
+    pub name: String,
+    pub svg: String,
+}
+
+impl Icon {
+    pub fn matches(&self, query: &str) -> bool {
+        let query = query.trim().to_lowercase();
+        self.name.to_lowercase().contains(&query) || self.category.to_lowercase().contains(&query)
+    }
+}
+
+pub fn library(folders: &[PathBuf]) -> Vec<Icon> {
+    let mut icons: Vec<Icon> = BUILTIN
+        .iter()
+        .map(|(category, name, body)| Icon {
+            category: category.to_string(),
+            name: name.to_string(),
+            svg: format!(
+                r#"<svg xmlns="http://www.w3.org/2000/svg" width="{size}" height="{size}" viewBox="0 0 24 24" fill="none" stroke="currentColor" stroke-width="2">{}</svg>"#,
+                body,
+                size = ICON_SIZE
+            ),
+        })
+        .collect();
+
+    for folder in folders {
+        let entries = match fs::read_dir(folder) {
+            Ok(entries) => entries,
+            Err(err) => {
+                crash::log(format!("Unable to scan {}: {}", folder.display(), err));
+                continue;
+            }
+        };
+        let category = folder.file_name().map_or_else(
+            || folder.display().to_string(),
+            |name| name.to_string_lossy().to_string(),
+        );
+        let mut found: Vec<Icon> = entries
+            .filter_map(Result::ok)
+            .map(|entry| entry.path())
+            .filter(|path| {
+                path.extension()
+                    .is_some_and(|ext| ext.eq_ignore_ascii_case("svg"))
+            })
+            .filter_map(|path| {
+                Some(Icon {
+                    category: category.clone(),
+                    name: path.file_stem()?.to_string_lossy().to_string(),
+                    svg: fs::read_to_string(&path).ok()?,
+                })
+            })
+            .collect();
+        found.sort_by(|a, b| a.name.cmp(&b.name));
+        icons.extend(found);
+    }
+    icons
+}
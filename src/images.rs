@@ -0,0 +1,500 @@
+use crate::{ImageAdjust, Rect};
+use egui_wgpu::wgpu::{self, util::DeviceExt};
+use image::{
+    imageops::{self, FilterType},
+    DynamicImage, ImageFormat, Rgba, RgbaImage,
+};
+use std::{borrow::Cow, io::Cursor, path::Path};
+
+pub const EXTENSIONS: [&str; 3] = ["png", "jpg", "jpeg"];
+// Larger pictures are scaled down on import so boards stay small enough to save.
+const MAX_IMAGE_SIDE: u32 = 4096;
+// Pixelated areas use blocks of about a fortieth of the picture's longer side.
+const PIXELATE_BLOCKS: u32 = 40;
+const PIXELATE_MIN_BLOCK: u32 = 8;
+// The weights the image shader uses for grayscale.
+const LUMA: [f32; 3] = [0.299, 0.587, 0.114];
+// Scans find the paper's color as the brightest value in blocks this many
+// pixels wide, then darker than INK of it turns black and lighter than PAPER
+// turns white.
+const SCAN_BLOCK: u32 = 32;
+const SCAN_INK: f32 = 0.25;
+const SCAN_PAPER: f32 = 0.85;
+
+#[repr(C)]
+#[derive(Copy, Clone, bytemuck::Pod, bytemuck::Zeroable, Debug)]
+pub struct ImageVertex {
+    position: [f32; 2],
+    uv: [f32; 2],
+    // Brightness, contrast and grayscale from the picture's adjustment.
+    effect: [f32; 4],
+}
+
+pub struct ImageTexture {
+    pub bind_group: wgpu::BindGroup,
+}
+
+impl std::fmt::Debug for ImageTexture {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("ImageTexture")
+    }
+}
+
+pub struct ImageRenderer {
+    pub pipeline: wgpu::RenderPipeline,
+    layout: wgpu::BindGroupLayout,
+    sampler: wgpu::Sampler,
+    format: wgpu::TextureFormat,
+}
+
+impl ImageRenderer {
+    pub fn new(
+        device: &wgpu::Device,
+        camera_layout: &wgpu::BindGroupLayout,
+        target: wgpu::TextureFormat,
+    ) -> Self {
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("image shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("shaders/image.wgsl").into()),
+        });
+
+        let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Image Bind Group Layout"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+            ],
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Image Pipeline Layout"),
+            bind_group_layouts: &[camera_layout, &layout],
+            push_constant_ranges: &[],
+        });
+
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("image pipeline"),
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: Some("image_vs"),
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+                buffers: &[wgpu::VertexBufferLayout {
+                    array_stride: std::mem::size_of::<ImageVertex>() as wgpu::BufferAddress,
+                    step_mode: wgpu::VertexStepMode::Vertex,
+                    attributes: &wgpu::vertex_attr_array![
+                        0 => Float32x2,
+                        1 => Float32x2,
+                        2 => Float32x4
+                    ],
+                }],
+            },
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::TriangleList,
+                strip_index_format: None,
+                ..Default::default()
+            },
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: Some("image_fs"),
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+                targets: &[Some(wgpu::ColorTargetState {
+                    format: target,
+                    blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            multiview: None,
+            cache: None,
+        });
+
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("Image Sampler"),
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+
+        // Board colors go to the target unconverted, so pictures are sampled
+        // the same way to keep their colors.
+        let format = match target.is_srgb() {
+            true => wgpu::TextureFormat::Rgba8UnormSrgb,
+            false => wgpu::TextureFormat::Rgba8Unorm,
+        };
+
+        ImageRenderer {
+            pipeline,
+            layout,
+            sampler,
+            format,
+        }
+    }
+
+    pub fn upload(
+        &self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        data: &[u8],
+    ) -> Option<ImageTexture> {
+        let image = decode(data)?;
+        let texture = device.create_texture_with_data(
+            queue,
+            &wgpu::TextureDescriptor {
+                label: Some("Board Image"),
+                size: wgpu::Extent3d {
+                    width: image.width(),
+                    height: image.height(),
+                    depth_or_array_layers: 1,
+                },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format: self.format,
+                usage: wgpu::TextureUsages::TEXTURE_BINDING,
+                view_formats: &[],
+            },
+            wgpu::util::TextureDataOrder::LayerMajor,
+            image.as_raw(),
+        );
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Image Bind Group"),
+            layout: &self.layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(&self.sampler),
+                },
+            ],
+        });
+        Some(ImageTexture { bind_group })
+    }
+}
+
+pub fn is_image(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| {
+            EXTENSIONS
+                .iter()
+                .any(|known| ext.eq_ignore_ascii_case(known))
+        })
+}
+
+fn decode(data: &[u8]) -> Option<RgbaImage> {
+    Some(image::load_from_memory(data).ok()?.to_rgba8())
+}
+
+// The picture as egui shows it, for panels floating over the board.
+pub fn color_image(data: &[u8]) -> Option<egui::ColorImage> {
+    let image = decode(data)?;
+    let size = [image.width() as usize, image.height() as usize];
+    Some(egui::ColorImage::from_rgba_unmultiplied(
+        size,
+        image.as_raw(),
+    ))
+}
+
+fn encode(image: RgbaImage) -> Option<Vec<u8>> {
+    let mut data = Vec::new();
+    DynamicImage::ImageRgba8(image)
+        .write_to(&mut Cursor::new(&mut data), ImageFormat::Png)
+        .ok()?;
+    Some(data)
+}
+
+// Checks an imported file and returns the data to keep on the board with its
+// size in pixels.
+pub fn import(data: Vec<u8>) -> Option<(Vec<u8>, [u32; 2])> {
+    let image = image::load_from_memory(&data).ok()?;
+    if image.width().max(image.height()) <= MAX_IMAGE_SIDE {
+        return Some((data, [image.width(), image.height()]));
+    }
+    let image = image
+        .resize(MAX_IMAGE_SIDE, MAX_IMAGE_SIDE, FilterType::Triangle)
+        .to_rgba8();
+    let size = [image.width(), image.height()];
+    Some((encode(image)?, size))
+}
+
+pub fn import_rgba(width: usize, height: usize, pixels: Vec<u8>) -> Option<(Vec<u8>, [u32; 2])> {
+    let image = RgbaImage::from_raw(width as u32, height as u32, pixels)?;
+    import(encode(image)?)
+}
+
+// Paints the part of the picture inside `region`, given as fractions of its
+// width and height, solid black.
+pub fn black_out(data: &[u8], region: [[f32; 2]; 2]) -> Option<Vec<u8>> {
+    let mut image = decode(data)?;
+    let (width, height) = (image.width() as f32, image.height() as f32);
+    let column = |fraction: f32| (fraction.clamp(0.0, 1.0) * width).round() as u32;
+    let row = |fraction: f32| (fraction.clamp(0.0, 1.0) * height).round() as u32;
+    for y in row(region[0][1])..row(region[1][1]) {
+        for x in column(region[0][0])..column(region[1][0]) {
+            image.put_pixel(x, y, Rgba([0, 0, 0, 255]));
+        }
+    }
+    encode(image)
+}
+
+// Replaces the part of the picture inside `region` with blocks of its average
+// color, coarse enough that faces and writing can't be made out.
+pub fn pixelate(data: &[u8], region: [[f32; 2]; 2]) -> Option<Vec<u8>> {
+    let mut image = decode(data)?;
+    let (width, height) = (image.width() as f32, image.height() as f32);
+    let column = |fraction: f32| (fraction.clamp(0.0, 1.0) * width).round() as u32;
+    let row = |fraction: f32| (fraction.clamp(0.0, 1.0) * height).round() as u32;
+    let (left, right) = (column(region[0][0]), column(region[1][0]));
+    let (top, bottom) = (row(region[0][1]), row(region[1][1]));
+    let block = (image.width().max(image.height()) / PIXELATE_BLOCKS).max(PIXELATE_MIN_BLOCK);
+    for block_top in (top..bottom).step_by(block as usize) {
+        for block_left in (left..right).step_by(block as usize) {
+            let columns = block_left..(block_left + block).min(right);
+            let rows = block_top..(block_top + block).min(bottom);
+            let mut sum = [0u32; 4];
+            for y in rows.clone() {
+                for x in columns.clone() {
+                    for (total, channel) in sum.iter_mut().zip(image.get_pixel(x, y).0) {
+                        *total += channel as u32;
+                    }
+                }
+            }
+            let count = (columns.len() * rows.len()).max(1) as u32;
+            let average = Rgba(sum.map(|total| (total / count) as u8));
+            for y in rows {
+                for x in columns.clone() {
+                    image.put_pixel(x, y, average);
+                }
+            }
+        }
+    }
+    encode(image)
+}
+
+// Clears the alpha of pixels within `threshold` of white in every channel, so
+// a pasted screenshot shows the board through its background.
+pub fn key_out_white(data: &[u8], threshold: u8) -> Option<Vec<u8>> {
+    let mut image = decode(data)?;
+    let cutoff = 255 - threshold;
+    for pixel in image.pixels_mut() {
+        let Rgba([red, green, blue, _]) = *pixel;
+        if red.min(green).min(blue) >= cutoff {
+            pixel[3] = 0;
+        }
+    }
+    encode(image)
+}
+
+// Two triangles spanning `first` to `last` in clip space, showing the cropped
+// and turned picture with `first` at its top left corner. The rest of the
+// adjustment rides along for the fragment shader.
+pub fn quad(first: [f32; 2], last: [f32; 2], adjust: &ImageAdjust) -> [ImageVertex; 6] {
+    let [[left, top], [right, bottom]] = adjust.crop;
+    let effect = [
+        adjust.brightness,
+        adjust.contrast,
+        adjust.grayscale as u8 as f32,
+        0.0,
+    ];
+    let corner = |u: f32, v: f32| {
+        // The picture turns under a fixed quad, so each corner samples the
+        // one a quarter turn back.
+        let [s, t] = match adjust.turns % 4 {
+            0 => [u, v],
+            1 => [v, 1.0 - u],
+            2 => [1.0 - u, 1.0 - v],
+            _ => [1.0 - v, u],
+        };
+        ImageVertex {
+            position: [
+                first[0] + (last[0] - first[0]) * u,
+                first[1] + (last[1] - first[1]) * v,
+            ],
+            uv: [left + (right - left) * s, top + (bottom - top) * t],
+            effect,
+        }
+    };
+    [
+        corner(0.0, 0.0),
+        corner(1.0, 0.0),
+        corner(0.0, 1.0),
+        corner(0.0, 1.0),
+        corner(1.0, 0.0),
+        corner(1.0, 1.0),
+    ]
+}
+
+// The picture with its adjustment baked in, for exports that only take the
+// pixels. Unadjusted pictures are passed through as they are.
+pub fn adjusted<'a>(data: &'a [u8], adjust: &ImageAdjust) -> Cow<'a, [u8]> {
+    if *adjust == ImageAdjust::default() {
+        return Cow::Borrowed(data);
+    }
+    let Some(image) = decode(data) else {
+        return Cow::Borrowed(data);
+    };
+    let (width, height) = (image.width() as f32, image.height() as f32);
+    let [[left, top], [right, bottom]] = adjust.crop;
+    let x = (left.clamp(0.0, 1.0) * width) as u32;
+    let y = (top.clamp(0.0, 1.0) * height) as u32;
+    let crop_width = ((right - left).clamp(0.0, 1.0) * width).max(1.0) as u32;
+    let crop_height = ((bottom - top).clamp(0.0, 1.0) * height).max(1.0) as u32;
+    let mut image = DynamicImage::ImageRgba8(image).crop_imm(x, y, crop_width, crop_height);
+    image = match adjust.turns % 4 {
+        1 => image.rotate90(),
+        2 => image.rotate180(),
+        3 => image.rotate270(),
+        _ => image,
+    };
+    let mut image = image.to_rgba8();
+    for pixel in image.pixels_mut() {
+        let Rgba([red, green, blue, alpha]) = *pixel;
+        let mut channels = [red, green, blue].map(|channel| channel as f32 / 255.0);
+        if adjust.grayscale {
+            let [r, g, b] = channels;
+            channels = [r * LUMA[0] + g * LUMA[1] + b * LUMA[2]; 3];
+        }
+        let [r, g, b] = channels.map(|channel| {
+            let channel = (channel - 0.5) * adjust.contrast + 0.5 + adjust.brightness;
+            (channel.clamp(0.0, 1.0) * 255.0).round() as u8
+        });
+        *pixel = Rgba([r, g, b, alpha]);
+    }
+    encode(image).map_or(Cow::Borrowed(data), Cow::Owned)
+}
+
+// Where a picture shown in `bounds` with `from` applied goes once `to` is,
+// keeping its scale and its center.
+pub fn adjusted_bounds(bounds: &Rect, from: &ImageAdjust, to: &ImageAdjust) -> Rect {
+    let upright = |[width, height]: [f32; 2], turns: u8| match turns % 2 {
+        0 => [width, height],
+        _ => [height, width],
+    };
+    let extent = |adjust: &ImageAdjust| {
+        let [[left, top], [right, bottom]] = adjust.crop;
+        [
+            (right - left).max(f32::EPSILON),
+            (bottom - top).max(f32::EPSILON),
+        ]
+    };
+    let [width, height] = upright([bounds.width, bounds.height], from.turns);
+    let [from_width, from_height] = extent(from);
+    let [to_width, to_height] = extent(to);
+    let [width, height] = upright(
+        [
+            width / from_width * to_width,
+            height / from_height * to_height,
+        ],
+        to.turns,
+    );
+    Rect {
+        x: bounds.x + (bounds.width - width) / 2.0,
+        y: bounds.y + (bounds.height - height) / 2.0,
+        width,
+        height,
+    }
+}
+
+// Straightens the page photographed between `corners`, given as fractions of
+// the photo from its top left corner clockwise, and evens out its lighting.
+// Returns the encoded page with its size in pixels.
+pub fn scan(data: &[u8], corners: [[f32; 2]; 4]) -> Option<(Vec<u8>, [u32; 2])> {
+    let photo = decode(data)?;
+    let (width, height) = (photo.width() as f32, photo.height() as f32);
+    let pixels = corners.map(|[x, y]| [x * width, y * height]);
+    let distance = |[x1, y1]: [f32; 2], [x2, y2]: [f32; 2]| (x2 - x1).hypot(y2 - y1);
+    let width = distance(pixels[0], pixels[1]).max(distance(pixels[3], pixels[2]));
+    let height = distance(pixels[0], pixels[3]).max(distance(pixels[1], pixels[2]));
+    let fit = (MAX_IMAGE_SIDE as f32 / width.max(height)).min(1.0);
+    let width = (width * fit).round().max(1.0) as u32;
+    let height = (height * fit).round().max(1.0) as u32;
+
+    let map = square_to_quad(corners)?;
+    let mut page = RgbaImage::new(width, height);
+    for (x, y, pixel) in page.enumerate_pixels_mut() {
+        let [u, v] = map([
+            (x as f32 + 0.5) / width as f32,
+            (y as f32 + 0.5) / height as f32,
+        ]);
+        *pixel = imageops::sample_bilinear(&photo, u, v).unwrap_or(Rgba([255; 4]));
+    }
+    clean_up(&mut page);
+    Some((encode(page)?, [width, height]))
+}
+
+// The projective map taking the unit square onto the quadrilateral `corners`,
+// listed from the origin clockwise, after Heckbert's closed form.
+fn square_to_quad(corners: [[f32; 2]; 4]) -> Option<impl Fn([f32; 2]) -> [f32; 2]> {
+    let [[x0, y0], [x1, y1], [x2, y2], [x3, y3]] = corners;
+    let (sx, sy) = (x0 - x1 + x2 - x3, y0 - y1 + y2 - y3);
+    let (dx1, dx2, dy1, dy2) = (x1 - x2, x3 - x2, y1 - y2, y3 - y2);
+    let den = dx1 * dy2 - dx2 * dy1;
+    if den.abs() < f32::EPSILON {
+        return None;
+    }
+    let g = (sx * dy2 - dx2 * sy) / den;
+    let h = (dx1 * sy - sx * dy1) / den;
+    let (a, b, c) = (x1 - x0 + g * x1, x3 - x0 + h * x3, x0);
+    let (d, e, f) = (y1 - y0 + g * y1, y3 - y0 + h * y3, y0);
+    Some(move |[u, v]: [f32; 2]| {
+        let w = g * u + h * v + 1.0;
+        [(a * u + b * v + c) / w, (d * u + e * v + f) / w]
+    })
+}
+
+// Divides each channel by the paper's color around it, so uneven light and
+// a tinted board turn white while the ink keeps its color.
+fn clean_up(page: &mut RgbaImage) {
+    let (width, height) = page.dimensions();
+    let mut paper = RgbaImage::new(width.div_ceil(SCAN_BLOCK), height.div_ceil(SCAN_BLOCK));
+    for (x, y, pixel) in page.enumerate_pixels() {
+        let brightest = paper.get_pixel_mut(x / SCAN_BLOCK, y / SCAN_BLOCK);
+        for channel in 0..3 {
+            brightest[channel] = brightest[channel].max(pixel[channel]);
+        }
+    }
+    let paper = imageops::resize(&paper, width, height, FilterType::Triangle);
+    for (pixel, paper) in page.pixels_mut().zip(paper.pixels()) {
+        for channel in 0..3 {
+            let level = pixel[channel] as f32 / paper[channel].max(1) as f32;
+            let level = ((level - SCAN_INK) / (SCAN_PAPER - SCAN_INK)).clamp(0.0, 1.0);
+            pixel[channel] = (level * 255.0).round() as u8;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn the_square_maps_onto_the_corners_in_order() {
+        let corners = [[0.1, 0.2], [0.9, 0.1], [0.8, 0.95], [0.2, 0.7]];
+        let map = square_to_quad(corners).unwrap();
+        let square = [[0.0, 0.0], [1.0, 0.0], [1.0, 1.0], [0.0, 1.0]];
+        for (point, corner) in square.into_iter().zip(corners) {
+            let [x, y] = map(point);
+            assert!((x - corner[0]).abs() < 1e-5 && (y - corner[1]).abs() < 1e-5);
+        }
+    }
+}
@@ -0,0 +1,1262 @@
+use crate::{
+    bridge::{Argument, BridgeAction},
+    calibration::Calibration,
+    camera::{Camera, SCROLL_LINE, ZOOM_STEP},
+    commands::{Command, Context as CommandContext},
+    convert_to_buffer, crash, egui_key,
+    fonts::FontLibrary,
+    gestures::{self, FlickDirection, TouchGesture},
+    images,
+    magnifier::Lens,
+    ndc_to_screen, normalized_to_rgba,
+    paste::PasteSpecial,
+    project::{self, ProjectDialog, ProjectMode},
+    replay::Replay,
+    screen_to_ndc,
+    settings::DeviceTool,
+    smoothing, speech,
+    stats::Statistics,
+    stencil::StencilKind,
+    timestamp_millis, Action, Brush, Erased, ObjectRef, ObjectStyle, PointerStroke, Shadow,
+    Stencil, Stroke, StrokeLod, TextEntries, Vertex, WindowState, BRUSH_WIDTH_STEP,
+    CALLIGRAPHY_SCALE, DOUBLE_CLICK_DISTANCE, DOUBLE_CLICK_THRESHOLD, HIGHLIGHTER_SCALE,
+    MAX_BRUSH_WIDTH, MIN_BRUSH_WIDTH, PALETTE, PALM_DELAY_MILLIS, PEN_ONLY_GRACE,
+    SNAP_HOLD_TOLERANCE,
+};
+use egui::{Event as EventEgui, Key as KeyEgui};
+use std::{
+    collections::VecDeque,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+use tao::{
+    dpi::PhysicalPosition,
+    event::{ElementState, Force, MouseButton, MouseScrollDelta, Touch, TouchPhase, WindowEvent},
+    keyboard::Key,
+    window::Window,
+};
+
+impl WindowState<'_> {
+    pub(crate) fn input(&mut self, window: Arc<Window>, event: &WindowEvent) -> bool {
+        match event {
+            WindowEvent::Focused(focused) => {
+                self.raw_input
+                    .events
+                    .push(egui::Event::WindowFocused(*focused));
+                true
+            }
+            WindowEvent::ModifiersChanged(modifiers_state) => {
+                self.raw_input.modifiers = egui::Modifiers {
+                    alt: modifiers_state.alt_key(),
+                    ctrl: modifiers_state.control_key(),
+                    shift: modifiers_state.shift_key(),
+                    mac_cmd: cfg!(target_os = "macos") && modifiers_state.super_key(),
+                    command: if cfg!(target_os = "macos") {
+                        modifiers_state.super_key()
+                    } else {
+                        modifiers_state.control_key()
+                    },
+                };
+                true
+            }
+            WindowEvent::MouseWheel { delta, .. } if self.egui_context.is_pointer_over_area() => {
+                let (x, y) = match delta {
+                    MouseScrollDelta::LineDelta(x, y) => (*x * SCROLL_LINE, *y * SCROLL_LINE),
+                    MouseScrollDelta::PixelDelta(position) => {
+                        (position.x as f32, position.y as f32)
+                    }
+                    _ => (0.0, 0.0),
+                };
+                self.raw_input.events.push(egui::Event::MouseWheel {
+                    unit: egui::MouseWheelUnit::Point,
+                    delta: egui::vec2(x, y),
+                    modifiers: self.raw_input.modifiers,
+                });
+                window.request_redraw();
+                true
+            }
+            WindowEvent::MouseWheel { delta, .. } => {
+                let (x, y) = match delta {
+                    MouseScrollDelta::LineDelta(x, y) => (*x * SCROLL_LINE, *y * SCROLL_LINE),
+                    MouseScrollDelta::PixelDelta(position) => {
+                        (position.x as f32, position.y as f32)
+                    }
+                    _ => (0.0, 0.0),
+                };
+                if y == 0.0 && x == 0.0 {
+                    return true;
+                }
+                let position = self.last_cursor_position;
+                if self.pressed_keys.contains(&Key::Control) {
+                    let factor = if y > 0.0 { ZOOM_STEP } else { 1.0 / ZOOM_STEP };
+                    self.board
+                        .camera
+                        .zoom_at([position.x as f32, position.y as f32], factor);
+                } else if self.pressed_keys.contains(&Key::Alt) {
+                    // Ctrl+scroll already zooms, so the brush is sized on
+                    // Alt+scroll, which nothing else claims while drawing.
+                    self.adjust_brush(y.signum() * BRUSH_WIDTH_STEP);
+                } else if self.pressed_keys.contains(&Key::Shift) {
+                    self.board.camera.scroll(y, x);
+                } else {
+                    self.board.camera.scroll(x, y);
+                }
+                window.request_redraw();
+                true
+            }
+            WindowEvent::DroppedFile(path) if FontLibrary::is_font(path) => {
+                self.fonts.install(path, &mut self.font_system);
+                window.request_redraw();
+                true
+            }
+            WindowEvent::DroppedFile(path) if project::is_project(path) => {
+                self.open_board(path.clone());
+                window.request_redraw();
+                true
+            }
+            WindowEvent::DroppedFile(path) if images::is_image(path) => {
+                match std::fs::read(path) {
+                    Ok(data) => self.import_image(data),
+                    Err(err) => crash::log(format!("Unable to open {}: {}", path.display(), err)),
+                }
+                window.request_redraw();
+                true
+            }
+            WindowEvent::DroppedFile(path) => {
+                match std::fs::read_to_string(path) {
+                    Ok(text) => {
+                        let position = self.board_cursor();
+                        self.paste_special = Some(PasteSpecial::new(
+                            text,
+                            [position.x as f32, position.y as f32],
+                        ));
+                        window.request_redraw();
+                    }
+                    Err(err) => crash::log(format!("Unable to open {}: {}", path.display(), err)),
+                }
+                true
+            }
+            WindowEvent::Touch(touch) => {
+                self.track_pen(touch);
+                let location = [touch.location.x as f32, touch.location.y as f32];
+                if self.pan_only(touch) {
+                    let pinch = self.pinch.touch(touch.id, touch.phase, location);
+                    let last = self.pan_touches.insert(touch.id, touch.location);
+                    if !matches!(touch.phase, TouchPhase::Started | TouchPhase::Moved) {
+                        self.pan_touches.remove(&touch.id);
+                    }
+                    match (pinch, last) {
+                        (Some(step), _) => {
+                            self.board.camera.scroll(step.pan[0], step.pan[1]);
+                            self.board.camera.zoom_at(step.center, step.zoom);
+                        }
+                        (None, Some(last)) => self.board.camera.scroll(
+                            (touch.location.x - last.x) as f32,
+                            (touch.location.y - last.y) as f32,
+                        ),
+                        (None, None) => {}
+                    }
+                    window.request_redraw();
+                    return true;
+                }
+                if self.reject_palm(touch) {
+                    return true;
+                }
+                if self.settings.edge_swipes && self.track_edge_swipe(touch) {
+                    window.request_redraw();
+                    return true;
+                }
+                // A pen drawing beside panning fingers must not join their pinch.
+                let pinch = match self.settings.pen_only && is_pen(touch) {
+                    true => None,
+                    false => self.pinch.touch(touch.id, touch.phase, location),
+                };
+                if self.track_flick(touch) {
+                    window.request_redraw();
+                    return true;
+                }
+                if let Some(step) = pinch {
+                    self.cancel_touches();
+                    self.board.camera.scroll(step.pan[0], step.pan[1]);
+                    self.board.camera.zoom_at(step.center, step.zoom);
+                    window.request_redraw();
+                    return true;
+                }
+                if let Some(trail) = &mut self.laser {
+                    trail.push_back((touch.location, Instant::now()));
+                    window.request_redraw();
+                    return true;
+                }
+                let [dx, dy] = self.settings.pen_offset;
+                let position = self.board_position(PhysicalPosition::new(
+                    touch.location.x + dx,
+                    touch.location.y + dy,
+                ));
+                let point = Vertex {
+                    position: screen_to_ndc(self.size, position.x as f32, position.y as f32),
+                    color: self.stroke_color(),
+                };
+                let over_ui = self.egui_context.is_pointer_over_area();
+                let tool = self.settings.device_tools.touch;
+                if touch.phase == TouchPhase::Started && !over_ui {
+                    self.set_tool(tool);
+                }
+                let dragging = tool == DeviceTool::Pan || self.selecting;
+                let board = [position.x as f32, position.y as f32];
+                match touch.phase {
+                    TouchPhase::Started if over_ui => {}
+                    // Panning and selecting follow a single finger.
+                    TouchPhase::Started if dragging => {
+                        if self.touch_drag.is_none() {
+                            self.touch_drag = Some((touch.id, touch.location));
+                            if tool != DeviceTool::Pan {
+                                self.commit_text();
+                                self.start_selection_drag(board);
+                            }
+                        }
+                    }
+                    _ if self.touch_drag.is_some_and(|(id, _)| id == touch.id) => {
+                        let (_, last) = self.touch_drag.take().unwrap();
+                        if tool == DeviceTool::Pan {
+                            self.board.camera.scroll(
+                                (touch.location.x - last.x) as f32,
+                                (touch.location.y - last.y) as f32,
+                            );
+                        } else {
+                            self.drag_selection(board);
+                        }
+                        if touch.phase == TouchPhase::Moved {
+                            self.touch_drag = Some((touch.id, touch.location));
+                        } else if tool != DeviceTool::Pan {
+                            self.finish_selection_drag();
+                        }
+                    }
+                    TouchPhase::Started | TouchPhase::Moved if self.erasing => {
+                        self.eraser_pressure =
+                            touch.force.map_or(1.0, |force| force.normalized() as f32);
+                        self.erase_around([position.x as f32, position.y as f32]);
+                    }
+                    TouchPhase::Started => {
+                        self.touch_strokes.insert(
+                            touch.id,
+                            PointerStroke {
+                                points: vec![point],
+                                timestamps: vec![timestamp_millis()],
+                                blend: self.blend_mode,
+                                brush: self.brush,
+                                width: self.stroke_width(),
+                            },
+                        );
+                    }
+                    TouchPhase::Moved => {
+                        if let Some(stroke) = self.touch_strokes.get_mut(&touch.id) {
+                            stroke.points.push(Vertex {
+                                color: stroke.points[0].color,
+                                ..point
+                            });
+                            stroke.timestamps.push(timestamp_millis());
+                        }
+                    }
+                    TouchPhase::Ended => {
+                        if let Some(mut stroke) = self.touch_strokes.remove(&touch.id) {
+                            if self.scribble_erase(&stroke.points, &stroke.timestamps) {
+                                window.request_redraw();
+                                return true;
+                            }
+                            if self.settings.smooth_strokes {
+                                (stroke.points, stroke.timestamps) = smoothing::smooth(
+                                    &stroke.points,
+                                    &stroke.timestamps,
+                                    self.size,
+                                );
+                            }
+                            self.board.push_action(Action::Stroke(Stroke {
+                                points: stroke.points,
+                                blend: stroke.blend,
+                                brush: stroke.brush,
+                                layer: self.board.current_layer,
+                                timestamps: stroke.timestamps,
+                                width: stroke.width,
+                                lod: StrokeLod::default(),
+                            }));
+                        }
+                    }
+                    _ => {
+                        self.touch_strokes.remove(&touch.id);
+                    }
+                }
+                window.request_redraw();
+                true
+            }
+            WindowEvent::CursorLeft { .. } => {
+                self.cursor_inside = false;
+                self.raw_input.events.push(egui::Event::PointerGone);
+                window.request_redraw();
+                true
+            }
+            WindowEvent::CursorMoved {
+                device_id: _,
+                position,
+                ..
+            } => {
+                // Digitizers that are out of alignment are corrected everywhere,
+                // so panels hit-test at the pen tip too.
+                let [dx, dy] = self.settings.pen_offset;
+                let position = &PhysicalPosition::new(position.x + dx, position.y + dy);
+                self.last_cursor_position = *position;
+                self.cursor_inside = true;
+                if self.crosshair_shown {
+                    window.request_redraw();
+                }
+                if let Some(trail) = &mut self.laser {
+                    trail.push_back((*position, Instant::now()));
+                    window.request_redraw();
+                }
+
+                self.raw_input
+                    .events
+                    .push(egui::Event::PointerMoved(egui::pos2(
+                        position.x as f32,
+                        position.y as f32,
+                    )));
+
+                if let Some(last) = self.panning.replace(*position) {
+                    self.board
+                        .camera
+                        .scroll((position.x - last.x) as f32, (position.y - last.y) as f32);
+                    window.request_redraw();
+                } else if self.mouse_pressed && self.selecting {
+                    let position = self.board_cursor();
+                    self.drag_selection([position.x as f32, position.y as f32]);
+                    window.request_redraw();
+                } else if self.mouse_pressed && self.erasing {
+                    let position = self.board_cursor();
+                    self.erase_around([position.x as f32, position.y as f32]);
+                    window.request_redraw();
+                } else if self.mouse_pressed {
+                    let position = self.board_cursor();
+                    let [x, y] = screen_to_ndc(self.size, position.x as f32, position.y as f32);
+                    if self.create_rect {
+                        if self.shape_positions.is_empty() {
+                            self.shape_positions.push(Vertex {
+                                position: [x, y],
+                                color: self.current_color,
+                            });
+                        } else {
+                            if self.shape_positions.len() > 1 {
+                                self.shape_positions.pop();
+                            }
+                            self.shape_positions.push(Vertex {
+                                position: [x, y],
+                                color: self.current_color,
+                            });
+                        }
+                    } else {
+                        // Once straightened, the line's end follows the pen.
+                        if self.stroke_snapped {
+                            self.current_stroke.truncate(1);
+                            self.current_timestamps.truncate(1);
+                        } else {
+                            self.track_stroke_hold();
+                        }
+                        self.current_stroke.push(Vertex {
+                            position: [x, y],
+                            color: self.stroke_color(),
+                        });
+                        self.current_timestamps.push(timestamp_millis());
+                    }
+
+                    window.request_redraw();
+                }
+                true
+            }
+            WindowEvent::MouseInput {
+                device_id: _,
+                state,
+                button,
+                ..
+            } => {
+                let pressed = *state == tao::event::ElementState::Pressed;
+
+                // Tablet drivers report the stylus barrel button as an extra
+                // mouse button. When a double tap command is bound, a press
+                // waits for a second one before running its own command.
+                if *button == MouseButton::Other(self.settings.stylus_button_id) {
+                    if pressed {
+                        self.finish_stylus_tap();
+                        let command = match self.settings.stylus_double_tap {
+                            Some(command) if self.last_stylus_tap.take().is_some() => Some(command),
+                            Some(_) => {
+                                self.last_stylus_tap = Some(Instant::now());
+                                None
+                            }
+                            None => self.settings.stylus_button,
+                        };
+                        if let Some(command) = command {
+                            self.run_command(command);
+                            window.request_redraw();
+                        }
+                    }
+                    return true;
+                }
+
+                let button_egui = match button {
+                    MouseButton::Left => egui::PointerButton::Primary,
+                    MouseButton::Right => egui::PointerButton::Secondary,
+                    MouseButton::Middle => egui::PointerButton::Middle,
+                    _ => return false,
+                };
+
+                self.raw_input.events.push(egui::Event::PointerButton {
+                    pos: egui::pos2(
+                        self.last_cursor_position.x as f32,
+                        self.last_cursor_position.y as f32,
+                    ),
+                    button: button_egui,
+                    pressed,
+                    modifiers: self.raw_input.modifiers,
+                });
+
+                if *button == MouseButton::Right && *state == ElementState::Pressed {
+                    let now = Instant::now();
+                    let position = self.last_cursor_position;
+
+                    let mut double_click_detected = false;
+
+                    if let Some(last_click_time) = self.last_click_time {
+                        if now.duration_since(last_click_time) <= DOUBLE_CLICK_THRESHOLD {
+                            if let Some(last_click_position) = self.last_click_position {
+                                let dx = position.x - last_click_position.x;
+                                let dy = position.y - last_click_position.y;
+                                let distance_squared = dx * dx + dy * dy;
+                                if distance_squared <= DOUBLE_CLICK_DISTANCE * DOUBLE_CLICK_DISTANCE
+                                {
+                                    double_click_detected = true;
+                                }
+                            }
+                        }
+                    }
+
+                    let mut hit = None;
+                    if double_click_detected {
+                        self.discard_empty_text();
+                        let position = self.board_cursor();
+                        hit = self.pick([position.x as f32, position.y as f32]);
+                    }
+
+                    self.last_click_time = Some(now);
+                    self.last_click_position = Some(position);
+
+                    if let Some(ObjectRef::Text(index)) = hit {
+                        self.commit_text();
+                        self.editing_text_index = Some(index);
+                        self.edited_text = Some(self.board.texts[index].text.clone());
+                        self.start_typing = true;
+                        self.board.texts[index].pending = true;
+                        self.board.texts[index].cursor = self.board.texts[index].text.len();
+                        window.request_redraw();
+                    } else if let Some(ObjectRef::Stencil(index)) = hit {
+                        self.commit_text();
+                        self.editing_stencil_index = Some(index);
+                        self.edited_text = Some(self.board.stencils[index].label.text.clone());
+                        self.start_typing = true;
+                        let label = &mut self.board.stencils[index].label;
+                        label.pending = true;
+                        label.cursor = label.text.len();
+                        window.request_redraw();
+                    } else if self.start_typing {
+                        self.commit_text();
+                    } else {
+                        self.start_typing = true;
+                        self.board.redo_actions.clear();
+                        self.board.texts.push(TextEntries::null(
+                            normalized_to_rgba(self.current_color),
+                            self.font_size,
+                        ));
+                        let position = self.board_cursor();
+                        let x = position.x as f32;
+                        let y = position.y as f32;
+                        if let Some(text) = self.board.texts.last_mut() {
+                            text.position = [x, y];
+                            text.font = self.font_family.clone();
+                            text.layer = self.board.current_layer;
+                        }
+                    }
+                }
+                if *button == MouseButton::Middle {
+                    self.panning = (pressed && !self.egui_context.is_pointer_over_area())
+                        .then_some(self.last_cursor_position);
+                }
+                if *button == MouseButton::Left {
+                    if *state == ElementState::Pressed {
+                        if self.egui_context.is_pointer_over_area() {
+                            return true;
+                        }
+                        if let Some(calibration) = &mut self.calibration {
+                            let [dx, dy] = self.settings.pen_offset;
+                            let position = self.last_cursor_position;
+                            calibration.record(
+                                PhysicalPosition::new(position.x - dx, position.y - dy),
+                                self.size,
+                            );
+                            if let Some(offset) = calibration.offset() {
+                                self.settings.pen_offset = offset;
+                                self.settings.save();
+                                self.calibration = None;
+                            }
+                            window.request_redraw();
+                            return true;
+                        }
+                        // The laser only points, so pressing never draws.
+                        if self.laser.is_some() {
+                            return true;
+                        }
+                        if self.bucket {
+                            self.commit_text();
+                            let position = self.last_cursor_position;
+                            self.bucket_fill([position.x as f32, position.y as f32]);
+                            window.request_redraw();
+                            return true;
+                        }
+                        let tool = self.settings.device_tools.mouse;
+                        self.set_tool(tool);
+                        if tool == DeviceTool::Pan {
+                            self.panning = Some(self.last_cursor_position);
+                            return true;
+                        }
+                        self.mouse_pressed = true;
+                        self.stroke_hold = None;
+                        self.stroke_snapped = false;
+                        self.current_stroke = Vec::new();
+                        self.current_timestamps = Vec::new();
+
+                        if self.selecting {
+                            self.commit_text();
+                            let position = self.board_cursor();
+                            self.start_selection_drag([position.x as f32, position.y as f32]);
+                            window.request_redraw();
+                            return true;
+                        }
+                        if self.erasing {
+                            self.commit_text();
+                            self.eraser_pressure = 1.0;
+                            let position = self.board_cursor();
+                            self.erase_around([position.x as f32, position.y as f32]);
+                            window.request_redraw();
+                            return true;
+                        }
+
+                        match self.held_shape {
+                            Some(Command::HoldRedaction) => {
+                                self.create_rect = true;
+                                self.redacting = true;
+                            }
+                            Some(_) => self.create_rect = true,
+                            None => {}
+                        }
+                    } else {
+                        if self.settings.device_tools.mouse == DeviceTool::Pan {
+                            self.panning = None;
+                        }
+                        self.mouse_pressed = false;
+                        self.stroke_hold = None;
+                        self.finish_selection_drag();
+                        let mut points = std::mem::take(&mut self.current_stroke);
+                        let mut timestamps = std::mem::take(&mut self.current_timestamps);
+                        if !points.is_empty() && !self.scribble_erase(&points, &timestamps) {
+                            if self.settings.smooth_strokes {
+                                (points, timestamps) =
+                                    smoothing::smooth(&points, &timestamps, self.size);
+                            }
+                            let stroke = Stroke {
+                                points,
+                                blend: self.blend_mode,
+                                brush: self.brush,
+                                layer: self.board.current_layer,
+                                timestamps,
+                                width: self.stroke_width(),
+                                lod: StrokeLod::default(),
+                            };
+                            self.board.push_action(Action::Stroke(stroke));
+                        }
+                        self.create_rect = false;
+
+                        self.finish_rectangle();
+
+                        window.request_redraw();
+                    }
+                }
+                true
+            }
+            WindowEvent::ReceivedImeText(composed) => {
+                self.raw_input
+                    .events
+                    .push(EventEgui::Text(composed.clone()));
+                if self.start_typing || self.editing_text_index.is_some() {
+                    if let Some(text) = self.active_text_mut() {
+                        text.insert(composed);
+                    }
+                    window.request_redraw();
+                }
+                true
+            }
+            WindowEvent::KeyboardInput { event, .. } => {
+                if let Some(key) = egui_key(event.logical_key.clone()) {
+                    self.raw_input.events.push(EventEgui::Key {
+                        key,
+                        physical_key: KeyEgui::from_name(&event.physical_key.to_string()),
+                        pressed: true,
+                        repeat: false,
+                        modifiers: self.raw_input.modifiers,
+                    });
+                }
+                let typed = event
+                    .text
+                    .filter(|typed| !typed.chars().any(char::is_control));
+                if let (Some(typed), ElementState::Pressed) = (typed, event.state) {
+                    if !self.raw_input.modifiers.command {
+                        self.raw_input
+                            .events
+                            .push(EventEgui::Text(typed.to_string()));
+                    }
+                }
+                match event.state {
+                    ElementState::Pressed => {
+                        self.pressed_keys.insert(event.logical_key.clone());
+
+                        let control = self.pressed_keys.contains(&Key::Control);
+                        let alt = self.pressed_keys.contains(&Key::Alt);
+                        let shift = self.pressed_keys.contains(&Key::Shift);
+                        if self.start_typing || self.editing_text_index.is_some() {
+                            if let Some(command) = self.keymap.lookup(
+                                CommandContext::Typing,
+                                &event.logical_key,
+                                control,
+                                alt,
+                                shift,
+                            ) {
+                                self.run_command(command);
+                            } else if let Some(typed) = typed.filter(|_| !control) {
+                                // `text` rather than the logical key, so dead keys
+                                // arrive composed with the key that follows them.
+                                if let Some(text) = self.active_text_mut() {
+                                    text.insert(typed);
+                                }
+                            }
+                            match event.logical_key {
+                                Key::GoBack => self.commit_text(),
+                                Key::Enter if shift => {
+                                    if let Some(text) = self.active_text_mut() {
+                                        text.insert("\n");
+                                    }
+                                }
+                                Key::Backspace => {
+                                    if let Some(text) = self.active_text_mut() {
+                                        text.backspace();
+                                    }
+                                }
+                                Key::Delete => {
+                                    if let Some(text) = self.active_text_mut() {
+                                        text.delete();
+                                    }
+                                }
+                                Key::ArrowLeft | Key::ArrowRight | Key::Home | Key::End => {
+                                    if let Some(text) = self.active_text_mut() {
+                                        match event.logical_key {
+                                            Key::ArrowLeft => text.move_left(),
+                                            Key::ArrowRight => text.move_right(),
+                                            Key::Home => text.move_home(),
+                                            _ => text.move_end(),
+                                        }
+                                    }
+                                    self.cursor_visible = true;
+                                }
+                                _ => {}
+                            }
+                            window.request_redraw();
+                        } else if self.egui_context.wants_keyboard_input() {
+                            return true;
+                        } else if let Some(command) = self.keymap.lookup(
+                            CommandContext::Board,
+                            &event.logical_key,
+                            control,
+                            alt,
+                            shift,
+                        ) {
+                            self.run_command(command);
+                            window.request_redraw();
+                            return true;
+                        }
+                    }
+                    ElementState::Released => {
+                        self.pressed_keys.remove(&event.logical_key);
+                        self.held_shape = None;
+                        self.create_rect = false;
+
+                        self.finish_rectangle();
+                    }
+                    _ => (),
+                }
+                true
+            }
+            WindowEvent::Resized(physical_size) => {
+                self.size = *physical_size;
+                self.resize(*physical_size);
+                self.raw_input.screen_rect = Some(egui::Rect {
+                    min: egui::pos2(0.0, 0.0),
+                    max: egui::pos2(physical_size.width as f32, physical_size.height as f32),
+                });
+                true
+            }
+            _ => false,
+        }
+    }
+
+    pub(crate) fn poll_bridge(&mut self) {
+        let Some(bridge) = &self.bridge else {
+            return;
+        };
+        for message in bridge.poll() {
+            let Some(action) = self.settings.bridge.route(&message.address).cloned() else {
+                continue;
+            };
+            match action {
+                BridgeAction::Command(command) => self.run_command(command),
+                BridgeAction::ShowPage => {
+                    let number = message.arguments.iter().find_map(Argument::number);
+                    if let Some(number) = number.filter(|number| *number >= 1.0) {
+                        self.show_page(number as usize - 1);
+                    }
+                }
+                BridgeAction::Note { x, y } => {
+                    self.commit_text();
+                    let mut note = Stencil::new(
+                        StencilKind::Note,
+                        [x, y],
+                        normalized_to_rgba(self.current_color),
+                        self.font_size,
+                    );
+                    if let Some(text) = message.arguments.iter().find_map(Argument::text) {
+                        note.label.text = text.to_string();
+                    }
+                    self.board.push_object(Action::Stencil(note));
+                }
+            }
+        }
+    }
+
+    pub(crate) fn run_command(&mut self, command: Command) {
+        match command {
+            Command::Undo => self.undo(),
+            Command::Redo => self.redo(),
+            // Unlike undo this skips over whatever else happened since, and
+            // can itself be undone.
+            Command::EraseLastStroke => {
+                if let Some(index) = self.board.strokes.len().checked_sub(1) {
+                    self.selection.clear();
+                    self.board.push_action(Action::Erase(Erased {
+                        object: ObjectRef::Stroke(index),
+                        content: None,
+                    }));
+                }
+            }
+            Command::ToggleEraser => self.set_tool(match self.erasing {
+                true => DeviceTool::Pen,
+                false => DeviceTool::Eraser,
+            }),
+            Command::ToggleSelect => self.set_tool(match self.selecting {
+                true => DeviceTool::Pen,
+                false => DeviceTool::Select,
+            }),
+            Command::PreviousTool => self.set_tool(self.previous_tool),
+            Command::ToggleHighlighter => self.toggle_brush(Brush::Highlighter),
+            Command::ToggleCalligraphy => self.toggle_brush(Brush::Calligraphy),
+            // Only armed while the key is down; the next press draws the shape.
+            Command::HoldRectangle | Command::HoldRedaction => self.held_shape = Some(command),
+            Command::PaintBucket => {
+                self.bucket = !self.bucket;
+                if self.bucket {
+                    self.set_tool(DeviceTool::Pen);
+                }
+            }
+            Command::LaserPointer => {
+                self.laser = match self.laser {
+                    Some(_) => None,
+                    None => Some(VecDeque::new()),
+                }
+            }
+            Command::ResetView => self.board.camera = Camera::default(),
+            Command::TogglePaperRoll => {
+                let mut background = self.settings.background;
+                background.roll = !background.roll;
+                self.set_background(background);
+            }
+            Command::PreviousPage => self.turn_page(false),
+            Command::NextPage => self.turn_page(true),
+            Command::NewPage => self.new_page(),
+            Command::SummaryPage => self.summary_page(),
+            Command::FindPage => self.page_search = Some(String::new()),
+            Command::Flipbook => self.toggle_flipbook(),
+            Command::CalibratePen => self.calibration = Some(Calibration::new()),
+            Command::SaveBoard => {
+                self.project_dialog = Some(ProjectDialog::new(
+                    ProjectMode::Save,
+                    self.project_path.as_deref(),
+                ))
+            }
+            Command::OpenBoard => {
+                self.project_dialog = Some(ProjectDialog::new(
+                    ProjectMode::Open,
+                    self.project_path.as_deref(),
+                ))
+            }
+            Command::ExportPdf => {
+                self.project_dialog = Some(ProjectDialog::new(
+                    ProjectMode::ExportPdf,
+                    self.project_path.as_deref(),
+                ))
+            }
+            Command::ExportSvg => {
+                self.project_dialog = Some(ProjectDialog::new(
+                    ProjectMode::ExportSvg,
+                    self.project_path.as_deref(),
+                ))
+            }
+            Command::ExportHtml => {
+                self.project_dialog = Some(ProjectDialog::new(
+                    ProjectMode::ExportHtml,
+                    self.project_path.as_deref(),
+                ))
+            }
+            Command::ExportGif => {
+                self.project_dialog = Some(ProjectDialog::new(
+                    ProjectMode::ExportGif,
+                    self.project_path.as_deref(),
+                ))
+            }
+            Command::CycleBlendMode => self.blend_mode = self.blend_mode.next(),
+            Command::ToggleDropShadow => self.drop_shadow = !self.drop_shadow,
+            Command::PasteSpecial => {
+                match arboard::Clipboard::new().and_then(|mut c| c.get_text()) {
+                    Ok(text) => {
+                        let position = self.board_cursor();
+                        self.paste_special = Some(PasteSpecial::new(
+                            text,
+                            [position.x as f32, position.y as f32],
+                        ));
+                    }
+                    Err(err) => crash::log(format!("Unable to read clipboard: {:?}", err)),
+                }
+            }
+            Command::Copy => self.copy_objects(),
+            Command::Paste => self.paste(),
+            Command::SwapColor => self.set_color(self.previous_color),
+            Command::PaletteColor(slot) => {
+                if let Some(&color) = PALETTE.get(slot) {
+                    self.set_color(convert_to_buffer(color));
+                }
+            }
+            Command::ArrangeStencils => self.arrange_stencils(),
+            Command::Replay => {
+                self.replay = match self.replay {
+                    Some(_) => None,
+                    None => Some(Replay::new(
+                        self.selection
+                            .iter()
+                            .filter_map(|object| match object {
+                                ObjectRef::Stroke(index) => Some(*index),
+                                _ => None,
+                            })
+                            .collect(),
+                    )),
+                };
+            }
+            Command::LectureTimer => {
+                self.lecture_started = match self.lecture_started {
+                    Some(_) => None,
+                    None => Some(Instant::now()),
+                };
+                self.last_lecture_page = self.lecture_started;
+            }
+            Command::LecturePage => self.lecture_page(),
+            Command::About => self.show_about = !self.show_about,
+            Command::InputDevices => self.show_devices = !self.show_devices,
+            Command::Shortcuts => self.show_shortcuts = !self.show_shortcuts,
+            Command::DecreaseBrush => self.adjust_brush(-BRUSH_WIDTH_STEP),
+            Command::IncreaseBrush => self.adjust_brush(BRUSH_WIDTH_STEP),
+            Command::CopyStyle => {
+                let position = self.board_cursor();
+                if let Some(object) = self.pick([position.x as f32, position.y as f32]) {
+                    self.copied_style = self.board.object_style(object);
+                }
+            }
+            Command::PasteStyle => {
+                if let Some(copied) = self.copied_style.clone() {
+                    self.restyle_selected(|from| {
+                        Some(ObjectStyle {
+                            color: copied.color.or(from.color),
+                            alpha: from.alpha.and(copied.alpha).or(from.alpha),
+                            width: from.width.and(copied.width).or(from.width),
+                            filled: from.filled.and(copied.filled).or(from.filled),
+                            font_size: from.font_size.and(copied.font_size).or(from.font_size),
+                            font: from
+                                .font
+                                .as_ref()
+                                .and(copied.font.clone())
+                                .or(from.font.clone()),
+                        })
+                    });
+                }
+            }
+            Command::ToggleFill => self.restyle_selected(|from| {
+                let filled = !from.filled?;
+                Some(ObjectStyle {
+                    filled: Some(filled),
+                    ..from.clone()
+                })
+            }),
+            Command::ReadAloud => {
+                if let Some(mut speech) = self.speech.take() {
+                    if speech.try_wait().ok().flatten().is_none() {
+                        let _ = speech.kill();
+                        return;
+                    }
+                }
+                let position = self.board_cursor();
+                let text = match self.active_text_mut() {
+                    Some(text) => Some(text.text.clone()),
+                    None => self
+                        .pick([position.x as f32, position.y as f32])
+                        .and_then(|object| self.board.object_text(object)),
+                };
+                if let Some(text) = text.filter(|text| !text.trim().is_empty()) {
+                    self.speech = speech::speak(&text);
+                }
+            }
+            Command::Magnifier => {
+                if let Some(lens) = self.magnifier.take() {
+                    if let Some(id) = lens.texture {
+                        self.egui_renderer.free_texture(&id);
+                    }
+                    self.lens_texture = None;
+                } else if self.canvas.is_none() {
+                    crash::log("The zoom lens needs partial redraw support".to_string());
+                } else {
+                    self.magnifier = Some(Lens::new());
+                }
+                self.window.request_redraw();
+            }
+            Command::Statistics => {
+                self.statistics = match self.statistics {
+                    Some(_) => None,
+                    None => Some(Statistics::compute(
+                        &self.board.scene_actions(),
+                        self.pages_created,
+                    )),
+                };
+            }
+            Command::ToggleMarkdown => {
+                if let Some(text) = self.active_text_mut() {
+                    text.markdown = !text.markdown;
+                }
+            }
+            Command::CycleTextDecoration => {
+                if let Some(text) = self.active_text_mut() {
+                    text.decoration = text.decoration.next();
+                }
+            }
+            Command::ToggleFixedTextSize => {
+                if let Some(text) = self.active_text_mut() {
+                    text.fixed_size = !text.fixed_size;
+                }
+            }
+            Command::CommitText => self.commit_text(),
+        }
+    }
+
+    pub(crate) fn board_cursor(&self) -> PhysicalPosition<f64> {
+        self.board_position(self.last_cursor_position)
+    }
+
+    pub(crate) fn board_position(&self, screen: PhysicalPosition<f64>) -> PhysicalPosition<f64> {
+        let [x, y] = self
+            .board
+            .camera
+            .to_world([screen.x as f32, screen.y as f32]);
+        PhysicalPosition::new(x as f64, y as f64)
+    }
+
+    pub(crate) fn adjust_brush(&mut self, step: f32) {
+        self.brush_width = (self.brush_width + step).clamp(MIN_BRUSH_WIDTH, MAX_BRUSH_WIDTH);
+        self.brush_indicator = Some(Instant::now());
+    }
+
+    pub(crate) fn set_color(&mut self, color: [f32; 4]) {
+        if color != self.current_color {
+            self.previous_color = self.current_color;
+            self.current_color = color;
+        }
+    }
+
+    pub(crate) fn shadow_style(&self) -> Shadow {
+        if self.drop_shadow {
+            Shadow::DEFAULT
+        } else {
+            Shadow::default()
+        }
+    }
+
+    pub(crate) fn tool(&self) -> DeviceTool {
+        match (self.erasing, self.selecting) {
+            (true, _) => DeviceTool::Eraser,
+            (_, true) => DeviceTool::Select,
+            _ => DeviceTool::Pen,
+        }
+    }
+
+    pub(crate) fn set_tool(&mut self, tool: DeviceTool) {
+        let (erasing, selecting) = match tool {
+            DeviceTool::Toolbar | DeviceTool::Pan => return,
+            DeviceTool::Pen => (false, false),
+            DeviceTool::Eraser => (true, false),
+            DeviceTool::Select => (false, true),
+        };
+        if self.tool() != tool {
+            self.previous_tool = self.tool();
+        }
+        if self.selecting != selecting {
+            self.selection.clear();
+        }
+        // The bucket is a pen-side mode; other tools take over from it.
+        self.bucket &= tool == DeviceTool::Pen;
+        self.erasing = erasing;
+        self.selecting = selecting;
+    }
+
+    pub(crate) fn stroke_color(&self) -> [f32; 4] {
+        self.brush.ink(self.current_color)
+    }
+
+    pub(crate) fn stroke_width(&self) -> f32 {
+        match self.brush {
+            Brush::Pen => self.brush_width,
+            Brush::Highlighter => self.brush_width * HIGHLIGHTER_SCALE,
+            Brush::Calligraphy => self.brush_width * CALLIGRAPHY_SCALE,
+        }
+    }
+
+    pub(crate) fn toggle_brush(&mut self, brush: Brush) {
+        self.brush = if self.brush == brush {
+            Brush::Pen
+        } else {
+            brush
+        };
+        self.bucket = false;
+        self.set_tool(DeviceTool::Pen);
+    }
+
+    pub(crate) fn nib(&self, brush: Brush) -> Option<f32> {
+        (brush == Brush::Calligraphy).then(|| self.settings.nib_angle.to_radians())
+    }
+
+    pub(crate) fn taper(&self, brush: Brush) -> f32 {
+        match brush {
+            Brush::Pen => self.settings.tapers.pen,
+            Brush::Highlighter => self.settings.tapers.highlighter,
+            Brush::Calligraphy => self.settings.tapers.calligraphy,
+        }
+    }
+
+    pub(crate) fn track_stroke_hold(&mut self) {
+        let position = self.last_cursor_position;
+        let moved = self.stroke_hold.is_none_or(|(anchor, _)| {
+            (position.x - anchor.x).hypot(position.y - anchor.y) > SNAP_HOLD_TOLERANCE
+        });
+        if moved {
+            self.stroke_hold = Some((position, Instant::now()));
+        }
+    }
+
+    pub(crate) fn snap_hold(&self) -> Option<Duration> {
+        let hold = Duration::from_millis(self.settings.snap_hold_millis);
+        (self.mouse_pressed && !self.stroke_snapped && !hold.is_zero()).then_some(hold)
+    }
+
+    // Replaces the stroke being drawn with a straight line once the pen has
+    // rested at its end for long enough.
+    pub(crate) fn snap_held_stroke(&mut self) {
+        let (Some(hold), Some((_, since))) = (self.snap_hold(), self.stroke_hold) else {
+            return;
+        };
+        if since.elapsed() < hold || self.current_stroke.len() < 3 {
+            return;
+        }
+        let (Some(&first), Some(&last)) = (self.current_stroke.first(), self.current_stroke.last())
+        else {
+            return;
+        };
+        self.current_stroke = vec![first, last];
+        if let (Some(&start), Some(&end)) = (
+            self.current_timestamps.first(),
+            self.current_timestamps.last(),
+        ) {
+            self.current_timestamps = vec![start, end];
+        }
+        self.stroke_snapped = true;
+    }
+
+    // Runs the single press command of a barrel button press once no second
+    // press came in time to make it a double tap.
+    pub(crate) fn finish_stylus_tap(&mut self) {
+        let Some(tap) = self.last_stylus_tap else {
+            return;
+        };
+        if tap.elapsed() <= DOUBLE_CLICK_THRESHOLD {
+            return;
+        }
+        self.last_stylus_tap = None;
+        if let Some(command) = self.settings.stylus_button {
+            self.run_command(command);
+        }
+    }
+
+    // A zig-zag drawn over content erases what it crosses instead of staying
+    // on the board. Over empty board it is kept as an ordinary stroke.
+    pub(crate) fn scribble_erase(&mut self, points: &[Vertex], timestamps: &[u64]) -> bool {
+        if !self.settings.scribble_erase || self.create_rect || self.brush != Brush::Pen {
+            return false;
+        }
+        let path: Vec<[f32; 2]> = points
+            .iter()
+            .map(|point| ndc_to_screen(self.size, point.position))
+            .collect();
+        if !gestures::is_scribble(&path, timestamps) {
+            return false;
+        }
+        let mut erased = false;
+        for position in path {
+            erased |= self.erase_at(position);
+        }
+        erased
+    }
+
+    pub(crate) fn track_flick(&mut self, touch: &Touch) -> bool {
+        let min_fingers = self
+            .settings
+            .flicks
+            .iter()
+            .map(|flick| flick.fingers)
+            .min()
+            .unwrap_or(usize::MAX)
+            .max(2);
+        let location = [touch.location.x as f32, touch.location.y as f32];
+        match self
+            .flicks
+            .touch(touch.id, touch.phase, location, min_fingers)
+        {
+            TouchGesture::Pointer => return false,
+            TouchGesture::Tracking => {}
+            TouchGesture::Flick(fingers, direction) => {
+                let command = self
+                    .settings
+                    .flicks
+                    .iter()
+                    .find(|flick| flick.fingers == fingers && flick.direction == direction)
+                    .map(|flick| flick.command);
+                if let Some(command) = command {
+                    self.run_command(command);
+                }
+            }
+        }
+        self.cancel_touches();
+        true
+    }
+
+    // Swiping in from the right edge goes forward, like turning a page.
+    fn track_edge_swipe(&mut self, touch: &Touch) -> bool {
+        let location = [touch.location.x as f32, touch.location.y as f32];
+        let width = self.size.width as f32;
+        match self
+            .edge_swipe
+            .touch(touch.id, touch.phase, location, width)
+        {
+            TouchGesture::Pointer => return false,
+            TouchGesture::Tracking => {}
+            TouchGesture::Flick(_, FlickDirection::Left) => self.turn_page(true),
+            TouchGesture::Flick(..) => self.turn_page(false),
+        }
+        true
+    }
+
+    // Fingers that already started drawing or dragging become part of the
+    // gesture.
+    fn cancel_touches(&mut self) {
+        self.touch_strokes.clear();
+        if self.touch_drag.take().is_some() && self.selecting {
+            self.finish_selection_drag();
+        }
+    }
+
+    // Notes when the pen touches, moves and lifts, so pen only mode knows
+    // whether it was just in use.
+    fn track_pen(&mut self, touch: &Touch) {
+        if !is_pen(touch) {
+            return;
+        }
+        match touch.phase {
+            TouchPhase::Started => self.pen_touch = Some(touch.id),
+            TouchPhase::Moved => {}
+            _ => self.pen_touch = None,
+        }
+        self.pen_seen = Some(Instant::now());
+    }
+
+    // With pen only on, a finger landing while the pen is down or was just
+    // lifted pans and zooms until it lifts, and never draws. That includes a
+    // palm resting beside the pen.
+    fn pan_only(&self, touch: &Touch) -> bool {
+        if touch.phase != TouchPhase::Started {
+            return self.pan_touches.contains_key(&touch.id);
+        }
+        let pen_recent = self.pen_touch.is_some()
+            || self
+                .pen_seen
+                .is_some_and(|seen| seen.elapsed() < PEN_ONLY_GRACE);
+        self.settings.pen_only && !is_pen(touch) && pen_recent
+    }
+
+    // A touch landing while the pen or another finger is well into a stroke
+    // is a resting palm, and is ignored until it lifts.
+    fn reject_palm(&mut self, touch: &Touch) -> bool {
+        if touch.phase != TouchPhase::Started {
+            let palm = self.palm_touches.contains(&touch.id);
+            if palm && touch.phase != TouchPhase::Moved {
+                self.palm_touches.remove(&touch.id);
+            }
+            return palm;
+        }
+        let now = timestamp_millis();
+        let drawing = self.mouse_pressed && !self.current_stroke.is_empty()
+            || self.touch_strokes.values().any(|stroke| {
+                stroke
+                    .timestamps
+                    .first()
+                    .is_some_and(|&started| now.saturating_sub(started) > PALM_DELAY_MILLIS)
+            });
+        if drawing {
+            self.palm_touches.insert(touch.id);
+        }
+        drawing
+    }
+}
+
+// Only pens report an altitude.
+fn is_pen(touch: &Touch) -> bool {
+    matches!(
+        touch.force,
+        Some(Force::Calibrated {
+            altitude_angle: Some(_),
+            ..
+        })
+    )
+}
@@ -0,0 +1,41 @@
+use serde::{Deserialize, Serialize};
+
+// Objects name their layer by id, so layers can be reordered or removed
+// without touching what is drawn on them. A board keeps its layers bottom
+// first.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Layer {
+    pub id: u32,
+    pub name: String,
+    pub visible: bool,
+}
+
+impl Layer {
+    fn new(id: u32, index: usize) -> Self {
+        Layer {
+            id,
+            name: format!("Layer {}", index + 1),
+            visible: true,
+        }
+    }
+}
+
+pub fn default_layers() -> Vec<Layer> {
+    vec![Layer::new(0, 0)]
+}
+
+// Adds a layer on top of the others and returns its id.
+pub fn add_layer(layers: &mut Vec<Layer>) -> u32 {
+    let id = layers.iter().map(|layer| layer.id + 1).max().unwrap_or(0);
+    layers.push(Layer::new(id, layers.len()));
+    id
+}
+
+// Adds a layer under the others and returns its id.
+pub fn add_bottom_layer(layers: &mut Vec<Layer>, name: &str) -> u32 {
+    let id = layers.iter().map(|layer| layer.id + 1).max().unwrap_or(0);
+    let mut layer = Layer::new(id, 0);
+    layer.name = name.to_string();
+    layers.insert(0, layer);
+    id
+}
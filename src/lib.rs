@@ -0,0 +1,2160 @@
+#![allow(dead_code)]
+
+mod animation;
+mod background;
+pub mod board;
+mod bridge;
+mod calibration;
+pub mod camera;
+mod canvas;
+mod code;
+mod commands;
+mod crash;
+mod diagram;
+mod export;
+mod fill;
+mod fonts;
+mod gestures;
+mod headless;
+mod icons;
+mod images;
+mod input;
+pub mod layers;
+mod magnifier;
+mod markdown;
+mod ocr;
+mod onion;
+pub mod pages;
+mod panels;
+mod paste;
+mod picking;
+mod project;
+mod reference;
+#[cfg(feature = "remote-api")]
+mod remote;
+mod render;
+mod replay;
+mod scan;
+mod scene;
+mod settings;
+mod shapes;
+pub mod simulate;
+pub mod smoothing;
+mod speech;
+mod split;
+mod stats;
+pub mod stencil;
+mod summary;
+mod svg;
+mod text;
+mod tiles;
+mod ui;
+
+use animation::{
+    Animation, AnimationClock, AUTO_SCROLL_FRAME, CARET_BLINK_INTERVAL, LASER_FRAME,
+    PAGE_TURN_FRAME,
+};
+use board::Board;
+use bridge::Bridge;
+use calibration::Calibration;
+use camera::Camera;
+use commands::{Command, Keymap};
+use egui::{Color32, Context, ImageSource, Key as KeyEgui, RawInput};
+use egui_wgpu::Renderer;
+use fonts::{FontLibrary, DEFAULT_FONT, FONT_SCAN_INTERVAL};
+use gestures::{EdgeSwipe, FlickTracker, PinchTracker};
+use glyphon::{
+    Attrs, Buffer, Color, Family, FontSystem, Metrics, Resolution, Shaping, Style, SwashCache,
+    Weight,
+};
+use icons::Icon;
+use images::{ImageRenderer, ImageTexture, ImageVertex};
+use layers::Layer;
+use magnifier::Lens;
+use ocr::Recognition;
+use pages::PageLabel;
+use paste::PasteSpecial;
+use picking::Picker;
+use project::ProjectDialog;
+use reference::Reference;
+use replay::{Replay, REPLAY_FRAME};
+use scan::Scan;
+use serde::{Deserialize, Serialize};
+use settings::{DeviceTool, Settings};
+use split::Split;
+use stats::Statistics;
+use std::{
+    borrow::Cow,
+    collections::{HashMap, HashSet, VecDeque},
+    panic::{self, AssertUnwindSafe},
+    path::PathBuf,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+use stencil::StencilKind;
+use tao::{
+    dpi::{PhysicalPosition, PhysicalSize},
+    event::{Event, WindowEvent},
+    event_loop::{ControlFlow, EventLoop},
+    keyboard::Key,
+    window::{Window, WindowId},
+};
+use wgpu::{self, SurfaceConfiguration, TextureUsages};
+
+pub fn run() {
+    crash::install();
+    let mut settings = Settings::load();
+    if let Some(job) = headless::Job::from_args(std::env::args().skip(1), &mut settings) {
+        match job.and_then(|job| pollster::block_on(headless::render(&job, &settings))) {
+            Ok(path) => println!("{}", path.display()),
+            Err(err) => {
+                crash::log(format!("Headless rendering failed: {}", err));
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
+    let event_loop = EventLoop::new();
+
+    let window = Window::new(&event_loop).unwrap_or_else(|err| {
+        panic!("Error occurred: {:?}", err);
+    });
+
+    window.set_title("وایت برد");
+    let window = Arc::new(window);
+
+    let mut app = Application {
+        window_state: Some(pollster::block_on(WindowState::new(window, settings))),
+    };
+
+    event_loop.run(move |event, _, control_flow| {
+        let result = panic::catch_unwind(AssertUnwindSafe(|| {
+            let Some(state) = &mut app.window_state else {
+                return;
+            };
+            match event {
+                Event::MainEventsCleared => {
+                    let deadline = app.about_to_wait();
+                    if *control_flow != ControlFlow::Exit {
+                        *control_flow = match deadline {
+                            Some(deadline) => ControlFlow::WaitUntil(deadline),
+                            None => ControlFlow::Wait,
+                        };
+                    }
+                }
+                Event::WindowEvent {
+                    window_id, event, ..
+                } => match event {
+                    WindowEvent::CloseRequested => *control_flow = ControlFlow::Exit,
+                    _ => {
+                        app.window_event(window_id, event);
+                    }
+                },
+                Event::Resumed => {
+                    state
+                        .surface
+                        .configure(&state.device, &state.surface_config);
+
+                    state.egui_renderer =
+                        Renderer::new(&state.device, state.surface_config.format, None, 1, true);
+
+                    state.window.request_redraw();
+                }
+                Event::RedrawRequested(_window_id) => {
+                    state.viewport.update(
+                        &state.queue,
+                        Resolution {
+                            width: state.size.width,
+                            height: state.size.height,
+                        },
+                    );
+                    let _ = state.update();
+                    match state.render() {
+                        Ok(_) => {}
+                        Err(egui_wgpu::wgpu::SurfaceError::Lost) => state.resize(state.size),
+                        Err(egui_wgpu::wgpu::SurfaceError::OutOfMemory) => {
+                            *control_flow = ControlFlow::Exit
+                        }
+                        Err(e) => crash::log(format!("{:?}", e)),
+                    }
+                }
+                Event::LoopDestroyed => *control_flow = ControlFlow::Exit,
+                _ => (),
+            }
+        }));
+        if result.is_err() {
+            if let Some(state) = &app.window_state {
+                if let Ok(json) = project::to_json(state.board.page_scenes(), state.board.size) {
+                    crash::dump_board(&json);
+                }
+            }
+            std::process::exit(1);
+        }
+    });
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, bytemuck::Pod, bytemuck::Zeroable, Debug, Serialize, Deserialize)]
+pub struct Vertex {
+    pub position: [f32; 2],
+    pub color: [f32; 4],
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Rect {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+}
+
+impl Rect {
+    fn contains(&self, [x, y]: [f32; 2]) -> bool {
+        x >= self.x && x <= self.x + self.width && y >= self.y && y <= self.y + self.height
+    }
+
+    fn intersects(&self, other: &Rect) -> bool {
+        self.x <= other.x + other.width
+            && other.x <= self.x + self.width
+            && self.y <= other.y + other.height
+            && other.y <= self.y + self.height
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Damage {
+    None,
+    Region {
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    },
+    Full,
+}
+
+impl Damage {
+    fn region(size: PhysicalSize<u32>, bounds: &Rect) -> Self {
+        let left = (bounds.x - DAMAGE_PADDING).floor().max(0.0) as u32;
+        let top = (bounds.y - DAMAGE_PADDING).floor().max(0.0) as u32;
+        let right = ((bounds.x + bounds.width + DAMAGE_PADDING).ceil() as u32).min(size.width);
+        let bottom = ((bounds.y + bounds.height + DAMAGE_PADDING).ceil() as u32).min(size.height);
+
+        if right <= left || bottom <= top {
+            return Damage::None;
+        }
+        Damage::Region {
+            x: left,
+            y: top,
+            width: right - left,
+            height: bottom - top,
+        }
+    }
+
+    fn union(self, other: Damage) -> Self {
+        match (self, other) {
+            (Damage::Full, _) | (_, Damage::Full) => Damage::Full,
+            (Damage::None, damage) | (damage, Damage::None) => damage,
+            (
+                Damage::Region {
+                    x,
+                    y,
+                    width,
+                    height,
+                },
+                Damage::Region {
+                    x: other_x,
+                    y: other_y,
+                    width: other_width,
+                    height: other_height,
+                },
+            ) => {
+                let left = x.min(other_x);
+                let top = y.min(other_y);
+                let right = (x + width).max(other_x + other_width);
+                let bottom = (y + height).max(other_y + other_height);
+                Damage::Region {
+                    x: left,
+                    y: top,
+                    width: right - left,
+                    height: bottom - top,
+                }
+            }
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, bytemuck::Pod, bytemuck::Zeroable, Debug)]
+struct ShadowVertex {
+    position: [f32; 2],
+    local: [f32; 2],
+    half_size: [f32; 2],
+    blur: f32,
+    color: [f32; 4],
+}
+
+#[repr(C)]
+#[derive(
+    Copy, Clone, bytemuck::Pod, bytemuck::Zeroable, Debug, Default, Serialize, Deserialize,
+)]
+pub struct Shadow {
+    pub offset: [f32; 2],
+    pub blur: f32,
+    pub opacity: f32,
+}
+
+impl Shadow {
+    const DEFAULT: Shadow = Shadow {
+        offset: [6.0, 6.0],
+        blur: 8.0,
+        opacity: 0.35,
+    };
+
+    fn is_visible(&self) -> bool {
+        self.opacity > 0.0
+    }
+
+    fn quad(&self, size: PhysicalSize<u32>, bounds: &Rect) -> [ShadowVertex; 6] {
+        let half_size = [bounds.width / 2.0, bounds.height / 2.0];
+        let center = [
+            bounds.x + half_size[0] + self.offset[0],
+            bounds.y + half_size[1] + self.offset[1],
+        ];
+        let extent = [
+            half_size[0] + self.blur * 2.0,
+            half_size[1] + self.blur * 2.0,
+        ];
+
+        [
+            (-1.0, -1.0),
+            (1.0, -1.0),
+            (1.0, 1.0),
+            (-1.0, -1.0),
+            (1.0, 1.0),
+            (-1.0, 1.0),
+        ]
+        .map(|(dx, dy)| {
+            let local = [dx * extent[0], dy * extent[1]];
+            ShadowVertex {
+                position: screen_to_ndc(size, center[0] + local[0], center[1] + local[1]),
+                local,
+                half_size,
+                blur: self.blur,
+                color: [0.0, 0.0, 0.0, self.opacity],
+            }
+        })
+    }
+}
+
+#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
+pub struct Rectangle {
+    pub first: [f32; 2],
+    pub last: [f32; 2],
+    pub color: [f32; 4],
+    #[serde(default)]
+    pub shadow: Shadow,
+    #[serde(default)]
+    pub filled: bool,
+    #[serde(default)]
+    pub layer: u32,
+}
+
+impl Rectangle {
+    fn to_vertices(self) -> Vec<Vertex> {
+        let (x1, y1) = (self.first[0], self.first[1]);
+        let (x2, y2) = (self.last[0], self.last[1]);
+
+        vec![
+            Vertex {
+                position: [x1, y2],
+                color: self.color,
+            },
+            Vertex {
+                position: [x2, y2],
+                color: self.color,
+            },
+            Vertex {
+                position: [x2, y2],
+                color: self.color,
+            },
+            Vertex {
+                position: [x2, y1],
+                color: self.color,
+            },
+            Vertex {
+                position: [x2, y1],
+                color: self.color,
+            },
+            Vertex {
+                position: [x1, y1],
+                color: self.color,
+            },
+            Vertex {
+                position: [x1, y1],
+                color: self.color,
+            },
+            Vertex {
+                position: [x1, y2],
+                color: self.color,
+            },
+        ]
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TextDecoration {
+    #[default]
+    None,
+    Halo,
+    Chip,
+}
+
+impl TextDecoration {
+    fn next(self) -> Self {
+        match self {
+            TextDecoration::None => TextDecoration::Halo,
+            TextDecoration::Halo => TextDecoration::Chip,
+            TextDecoration::Chip => TextDecoration::None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TextEntries {
+    pub position: [f32; 2],
+    pub color: [u8; 4],
+    pub text: String,
+    pub pending: bool,
+    pub bounds: Rect,
+    pub font_size: i32,
+    #[serde(default)]
+    pub markdown: bool,
+    #[serde(default)]
+    pub decoration: TextDecoration,
+    #[serde(default = "default_font")]
+    pub font: String,
+    #[serde(default)]
+    pub layer: u32,
+    #[serde(default)]
+    pub wrap_width: Option<f32>,
+    // Keeps the text the same size on screen at any zoom, for labels that
+    // point at things rather than being part of the drawing.
+    #[serde(default)]
+    pub fixed_size: bool,
+    #[serde(skip)]
+    pub(crate) cursor: usize,
+}
+
+impl TextEntries {
+    pub fn null(color: [u8; 4], font_size: i32) -> Self {
+        TextEntries {
+            font_size,
+            position: [0.0, 0.0],
+            color,
+            text: String::new(),
+            pending: true,
+            bounds: Rect {
+                x: 0.0,
+                y: 0.0,
+                width: 0.0,
+                height: 0.0,
+            },
+            markdown: false,
+            decoration: TextDecoration::None,
+            font: default_font(),
+            layer: 0,
+            wrap_width: None,
+            fixed_size: false,
+            cursor: 0,
+        }
+    }
+
+    // How much the text's size and wrap width are scaled on the board so it
+    // comes out at `font_size` on screen when fixed.
+    pub fn board_scale(&self, zoom: f32) -> f32 {
+        match self.fixed_size {
+            true => 1.0 / zoom,
+            false => 1.0,
+        }
+    }
+
+    // The cursor is not saved and the text can change under it, so every edit
+    // first pulls it back inside the text and onto a character boundary.
+    fn clamp_cursor(&mut self) {
+        self.cursor = self.cursor.min(self.text.len());
+        while !self.text.is_char_boundary(self.cursor) {
+            self.cursor -= 1;
+        }
+    }
+
+    fn insert(&mut self, text: &str) {
+        self.clamp_cursor();
+        self.text.insert_str(self.cursor, text);
+        self.cursor += text.len();
+    }
+
+    fn backspace(&mut self) {
+        self.clamp_cursor();
+        if let Some((index, _)) = self.text[..self.cursor].char_indices().next_back() {
+            self.text.remove(index);
+            self.cursor = index;
+        }
+    }
+
+    fn delete(&mut self) {
+        self.clamp_cursor();
+        if self.cursor < self.text.len() {
+            self.text.remove(self.cursor);
+        }
+    }
+
+    fn move_left(&mut self) {
+        self.clamp_cursor();
+        if let Some((index, _)) = self.text[..self.cursor].char_indices().next_back() {
+            self.cursor = index;
+        }
+    }
+
+    fn move_right(&mut self) {
+        self.clamp_cursor();
+        if let Some(char) = self.text[self.cursor..].chars().next() {
+            self.cursor += char.len_utf8();
+        }
+    }
+
+    fn move_home(&mut self) {
+        self.clamp_cursor();
+        self.cursor = self.text[..self.cursor]
+            .rfind('\n')
+            .map_or(0, |index| index + 1);
+    }
+
+    fn move_end(&mut self) {
+        self.clamp_cursor();
+        self.cursor = self.text[self.cursor..]
+            .find('\n')
+            .map_or(self.text.len(), |index| self.cursor + index);
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Table {
+    pub position: [f32; 2],
+    pub rows: Vec<Vec<String>>,
+    pub color: [u8; 4],
+    pub font_size: i32,
+    #[serde(default)]
+    pub shadow: Shadow,
+    #[serde(default)]
+    pub layer: u32,
+    #[serde(skip)]
+    pub bounds: Rect,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CodeBlock {
+    pub position: [f32; 2],
+    pub code: String,
+    pub language: String,
+    pub font_size: i32,
+    #[serde(default)]
+    pub shadow: Shadow,
+    #[serde(default)]
+    pub layer: u32,
+    #[serde(skip)]
+    pub(crate) highlighted: Vec<(String, [u8; 4])>,
+    #[serde(skip)]
+    pub bounds: Rect,
+}
+
+impl CodeBlock {
+    fn new(position: [f32; 2], code: &str, language: &str, font_size: i32) -> Self {
+        let code = code::normalize_indentation(code);
+        CodeBlock {
+            position,
+            highlighted: code::highlight(&code, language),
+            code,
+            language: language.to_string(),
+            font_size,
+            shadow: Shadow::default(),
+            layer: 0,
+            bounds: Rect::default(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Stencil {
+    pub kind: StencilKind,
+    pub position: [f32; 2],
+    pub size: [f32; 2],
+    pub color: [u8; 4],
+    pub label: TextEntries,
+    #[serde(default)]
+    pub layer: u32,
+}
+
+impl Stencil {
+    fn new(kind: StencilKind, center: [f32; 2], color: [u8; 4], font_size: i32) -> Self {
+        let size = kind.size();
+        let mut label = TextEntries::null(color, font_size);
+        label.text = kind.name().to_string();
+        label.pending = false;
+        Stencil {
+            kind,
+            position: [center[0] - size[0] / 2.0, center[1] - size[1] / 2.0],
+            size,
+            color,
+            label,
+            layer: 0,
+        }
+    }
+
+    fn bounds(&self) -> Rect {
+        Rect {
+            x: self.position[0],
+            y: self.position[1],
+            width: self.size[0],
+            height: self.size[1],
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Connector {
+    pub from: usize,
+    pub to: usize,
+    pub color: [u8; 4],
+    pub font_size: i32,
+    #[serde(default)]
+    pub label: Option<String>,
+}
+
+impl Connector {
+    fn endpoints(&self, stencils: &[Stencil]) -> Option<([f32; 2], [f32; 2])> {
+        let from = stencils.get(self.from)?.bounds();
+        let to = stencils.get(self.to)?.bounds();
+        if self.from == self.to {
+            return None;
+        }
+        let from_center = [from.x + from.width / 2.0, from.y + from.height / 2.0];
+        let to_center = [to.x + to.width / 2.0, to.y + to.height / 2.0];
+        Some((
+            clip_to_bounds(from_center, to_center, &from),
+            clip_to_bounds(to_center, from_center, &to),
+        ))
+    }
+}
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct StencilMove {
+    pub index: usize,
+    pub from: [f32; 2],
+    pub to: [f32; 2],
+}
+
+// The color's channels and its opacity are restyled separately, so `alpha`
+// wins over the color's own alpha when both are set.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct ObjectStyle {
+    pub color: Option<[u8; 4]>,
+    #[serde(default)]
+    pub alpha: Option<u8>,
+    pub width: Option<f32>,
+    pub filled: Option<bool>,
+    pub font_size: Option<i32>,
+    #[serde(default)]
+    pub font: Option<String>,
+}
+
+impl ObjectStyle {
+    fn restyle_color(&self, current: [u8; 4]) -> [u8; 4] {
+        let [red, green, blue, alpha] = self.color.unwrap_or(current);
+        [red, green, blue, self.alpha.unwrap_or(alpha)]
+    }
+
+    fn restyle_normalized(&self, current: [f32; 4]) -> [f32; 4] {
+        rgba_to_normalized(self.restyle_color(normalized_to_rgba(current)))
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Restyle {
+    pub object: ObjectRef,
+    pub from: ObjectStyle,
+    pub to: ObjectStyle,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BlendMode {
+    #[default]
+    Normal,
+    Multiply,
+    Screen,
+}
+
+impl BlendMode {
+    const ALL: [BlendMode; 3] = [BlendMode::Normal, BlendMode::Multiply, BlendMode::Screen];
+
+    fn next(self) -> Self {
+        match self {
+            BlendMode::Normal => BlendMode::Multiply,
+            BlendMode::Multiply => BlendMode::Screen,
+            BlendMode::Screen => BlendMode::Normal,
+        }
+    }
+
+    fn blend_state(self) -> egui_wgpu::wgpu::BlendState {
+        use egui_wgpu::wgpu::{BlendComponent, BlendFactor, BlendOperation, BlendState};
+
+        let color = match self {
+            BlendMode::Normal => return BlendState::ALPHA_BLENDING,
+            BlendMode::Multiply => BlendComponent {
+                src_factor: BlendFactor::Dst,
+                dst_factor: BlendFactor::OneMinusSrcAlpha,
+                operation: BlendOperation::Add,
+            },
+            BlendMode::Screen => BlendComponent {
+                src_factor: BlendFactor::One,
+                dst_factor: BlendFactor::OneMinusSrc,
+                operation: BlendOperation::Add,
+            },
+        };
+        BlendState {
+            color,
+            alpha: BlendState::ALPHA_BLENDING.alpha,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Brush {
+    #[default]
+    Pen,
+    // Wide and translucent, drawn beneath pen strokes.
+    Highlighter,
+    // A flat nib held at a fixed angle, thick across it and thin along it.
+    Calligraphy,
+}
+
+impl Brush {
+    // The ink this brush lays down in `color`.
+    fn ink(self, color: [f32; 4]) -> [f32; 4] {
+        let mut ink = color;
+        if self == Brush::Highlighter {
+            ink[3] *= HIGHLIGHTER_ALPHA;
+        }
+        ink
+    }
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Stroke {
+    pub points: Vec<Vertex>,
+    #[serde(default)]
+    pub blend: BlendMode,
+    #[serde(default)]
+    pub brush: Brush,
+    #[serde(default)]
+    pub layer: u32,
+    #[serde(default)]
+    pub timestamps: Vec<u64>,
+    #[serde(default = "hairline_width")]
+    pub width: f32,
+    #[serde(skip)]
+    pub lod: StrokeLod,
+}
+
+// Which points of a stroke are kept for each of LOD_TOLERANCES, worked out
+// once the stroke is finished. Edits that change the number of points leave
+// it stale, and the stroke is drawn in full.
+#[derive(Clone, Debug, Default)]
+pub struct StrokeLod {
+    len: usize,
+    levels: Vec<Vec<u32>>,
+}
+
+impl StrokeLod {
+    pub fn new(points: &[Vertex], size: PhysicalSize<u32>) -> Self {
+        let positions: Vec<[f32; 2]> = points
+            .iter()
+            .map(|point| ndc_to_screen(size, point.position))
+            .collect();
+        StrokeLod {
+            len: points.len(),
+            levels: LOD_TOLERANCES
+                .iter()
+                .map(|&tolerance| smoothing::simplify(&positions, tolerance))
+                .collect(),
+        }
+    }
+}
+
+impl Stroke {
+    // The points to draw at `zoom`: the coarsest level of detail that strays
+    // no more than LOD_SCREEN_TOLERANCE on screen, or all of them.
+    pub fn lod_points(&self, zoom: f32) -> Cow<'_, [Vertex]> {
+        let level = LOD_TOLERANCES
+            .iter()
+            .rposition(|tolerance| tolerance * zoom <= LOD_SCREEN_TOLERANCE)
+            .filter(|_| self.lod.len == self.points.len())
+            .and_then(|level| self.lod.levels.get(level));
+        match level {
+            Some(indices) => indices
+                .iter()
+                .map(|&index| self.points[index as usize])
+                .collect(),
+            None => Cow::Borrowed(&self.points),
+        }
+    }
+
+    fn line_vertices(points: &[Vertex], vertices: &mut Vec<Vertex>) {
+        for pair in points.windows(2) {
+            vertices.push(pair[0]);
+            vertices.push(pair[1]);
+        }
+    }
+
+    // `taper` holds the lengths over which the width ramps up from nothing at
+    // the start and end of the stroke. With a `nib` angle the ribbon's edges
+    // stay parallel to the nib instead of following the stroke.
+    pub fn ribbon_vertices(
+        points: &[Vertex],
+        width: f32,
+        taper: [f32; 2],
+        nib: Option<f32>,
+        size: PhysicalSize<u32>,
+        vertices: &mut Vec<Vertex>,
+    ) {
+        let (half_width, half_height) = (size.width as f32 / 2.0, size.height as f32 / 2.0);
+        let segment = |pair: &[Vertex]| {
+            let [start, end] = [pair[0].position, pair[1].position];
+            let dx = (end[0] - start[0]) * half_width;
+            let dy = (end[1] - start[1]) * half_height;
+            (dx, dy, (dx * dx + dy * dy).sqrt())
+        };
+        let total: f32 = points.windows(2).map(|pair| segment(pair).2).sum();
+        let ramp = |length: f32, distance: f32| {
+            if length > 0.0 {
+                (distance / length).clamp(0.0, 1.0)
+            } else {
+                1.0
+            }
+        };
+        let radius_at = |distance: f32| {
+            width / 2.0 * ramp(taper[0], distance).min(ramp(taper[1], total - distance))
+        };
+        let mut distance = 0.0;
+        for pair in points.windows(2) {
+            let (dx, dy, length) = segment(pair);
+            let (ux, uy) = if length > 0.0 {
+                (dx / length, dy / length)
+            } else {
+                (1.0, 0.0)
+            };
+            // Square caps overlap neighbouring segments and hide gaps at joints.
+            // Nib edges already meet at the joints, so they get no caps.
+            let (cap, [sx, sy]) = match nib {
+                Some(angle) => (
+                    0.0,
+                    [angle.cos() - uy * NIB_THIN, angle.sin() + ux * NIB_THIN],
+                ),
+                None => (1.0, [-uy, ux]),
+            };
+            let corner = |vertex: Vertex, radius: f32, along: f32, side: f32| Vertex {
+                position: [
+                    vertex.position[0] + (ux * along * cap + sx * side) * radius / half_width,
+                    vertex.position[1] + (uy * along * cap + sy * side) * radius / half_height,
+                ],
+                color: vertex.color,
+            };
+            let (from, to) = (radius_at(distance), radius_at(distance + length));
+            distance += length;
+            let a = corner(pair[0], from, -1.0, 1.0);
+            let b = corner(pair[0], from, -1.0, -1.0);
+            let c = corner(pair[1], to, 1.0, 1.0);
+            let d = corner(pair[1], to, 1.0, -1.0);
+            vertices.extend([a, b, c, c, b, d]);
+        }
+    }
+}
+
+enum SelectDrag {
+    Move {
+        last: [f32; 2],
+        total: [f32; 2],
+    },
+    Resize {
+        object: ObjectRef,
+        corner: usize,
+        from: [[f32; 2]; 2],
+    },
+    Band {
+        start: [f32; 2],
+        end: [f32; 2],
+    },
+}
+
+// An in-progress stroke from one touch point, keeping the tool it started with.
+struct PointerStroke {
+    points: Vec<Vertex>,
+    timestamps: Vec<u64>,
+    blend: BlendMode,
+    brush: Brush,
+    width: f32,
+}
+
+fn default_font() -> String {
+    DEFAULT_FONT.to_string()
+}
+
+fn hairline_width() -> f32 {
+    MIN_BRUSH_WIDTH
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ObjectRef {
+    Stroke(usize),
+    Shape(usize),
+    Text(usize),
+    Table(usize),
+    Code(usize),
+    Stencil(usize),
+    Image(usize),
+    Fill(usize),
+    Connector(usize),
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ObjectMove {
+    pub objects: Vec<ObjectRef>,
+    pub delta: [f32; 2],
+}
+
+// Corners are in board pixels.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct ObjectResize {
+    pub object: ObjectRef,
+    pub from: [[f32; 2]; 2],
+    pub to: [[f32; 2]; 2],
+}
+
+// Typing into a text or stencil label that was already on the board.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct TextEdit {
+    pub object: ObjectRef,
+    pub from: String,
+    pub to: String,
+}
+
+// A picture placed on the board between two corners in board pixels. `data`
+// holds the encoded file; the texture is created again after loading.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct BoardImage {
+    pub first: [f32; 2],
+    pub last: [f32; 2],
+    pub data: Vec<u8>,
+    #[serde(default)]
+    pub layer: u32,
+    #[serde(default)]
+    pub adjust: ImageAdjust,
+    #[serde(skip)]
+    pub(crate) texture: Option<Arc<ImageTexture>>,
+}
+
+// How a picture is shown, kept apart from its pixels so any change can be
+// taken back. The image shader applies it on the board and exports bake it in.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ImageAdjust {
+    // The part of the picture shown, as fractions of its width and height.
+    pub crop: [[f32; 2]; 2],
+    // Clockwise quarter turns.
+    pub turns: u8,
+    // Added to every channel, from -1 to 1.
+    pub brightness: f32,
+    // Scales each channel's distance from middle gray.
+    pub contrast: f32,
+    pub grayscale: bool,
+}
+
+impl Default for ImageAdjust {
+    fn default() -> Self {
+        ImageAdjust {
+            crop: [[0.0, 0.0], [1.0, 1.0]],
+            turns: 0,
+            brightness: 0.0,
+            contrast: 1.0,
+            grayscale: false,
+        }
+    }
+}
+
+impl BoardImage {
+    fn bounds(&self) -> Rect {
+        Rect {
+            x: self.first[0].min(self.last[0]),
+            y: self.first[1].min(self.last[1]),
+            width: (self.last[0] - self.first[0]).abs(),
+            height: (self.last[1] - self.first[1]).abs(),
+        }
+    }
+}
+
+// A region flooded by the paint bucket, kept as the rectangles in board pixels
+// that cover it. It is drawn beneath the strokes on its layer.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Fill {
+    pub rects: Vec<Rect>,
+    pub color: [f32; 4],
+    #[serde(default)]
+    pub layer: u32,
+}
+
+impl Fill {
+    fn bounds(&self) -> Rect {
+        let (mut left, mut top) = (f32::MAX, f32::MAX);
+        let (mut right, mut bottom) = (f32::MIN, f32::MIN);
+        for rect in &self.rects {
+            left = left.min(rect.x);
+            top = top.min(rect.y);
+            right = right.max(rect.x + rect.width);
+            bottom = bottom.max(rect.y + rect.height);
+        }
+        Rect {
+            x: left,
+            y: top,
+            width: (right - left).max(0.0),
+            height: (bottom - top).max(0.0),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct LayerEdit {
+    pub from: Vec<Layer>,
+    pub to: Vec<Layer>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct LabelEdit {
+    pub from: PageLabel,
+    pub to: PageLabel,
+}
+
+// Picture data swapped in for the image at `index`; applying or undoing the
+// edit swaps it back, so `data` always holds the version not on the board.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ImageEdit {
+    pub index: usize,
+    pub data: Vec<u8>,
+}
+
+// Adjustments and corners swapped in for the image at `index`, the same way
+// as `ImageEdit`.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ImageAdjustment {
+    pub index: usize,
+    pub adjust: ImageAdjust,
+    pub first: [f32; 2],
+    pub last: [f32; 2],
+}
+
+// `from` holds each object's layer before the move, in `objects` order.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct LayerMove {
+    pub objects: Vec<ObjectRef>,
+    pub from: Vec<u32>,
+    pub to: u32,
+}
+
+// Index ranges into one frame's vertex lists that belong to a single layer, so
+// the render pass can draw layers bottom to top.
+struct LayerDraw {
+    images: std::ops::Range<usize>,
+    shadows: std::ops::Range<u32>,
+    fills: std::ops::Range<u32>,
+    shapes: std::ops::Range<u32>,
+    strokes: std::ops::Range<usize>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Erased {
+    pub object: ObjectRef,
+    pub content: Option<Box<Action>>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum Action {
+    Stroke(Stroke),
+    Text(TextEntries),
+    Shapes(Rectangle),
+    Table(Table),
+    Code(CodeBlock),
+    Stencil(Stencil),
+    Connector(Connector),
+    Arrange(Vec<StencilMove>),
+    Restyle(Restyle),
+    Redact(Rect),
+    Erase(Erased),
+    Move(ObjectMove),
+    Resize(ObjectResize),
+    Retext(TextEdit),
+    Image(BoardImage),
+    Layers(LayerEdit),
+    Label(LabelEdit),
+    Relayer(LayerMove),
+    Retouch(ImageEdit),
+    Adjust(ImageAdjustment),
+    Fill(Fill),
+    // Several edits made at once, undone and redone as one step.
+    Group(Vec<Action>),
+}
+
+impl Action {
+    fn memory_size(&self) -> usize {
+        let heap = match self {
+            Action::Stroke(stroke) => {
+                stroke.points.capacity() * std::mem::size_of::<Vertex>()
+                    + stroke.timestamps.capacity() * std::mem::size_of::<u64>()
+            }
+            Action::Text(text) => text.text.capacity(),
+            Action::Image(image) => image.data.capacity(),
+            Action::Retouch(edit) => edit.data.capacity(),
+            Action::Fill(fill) => fill.rects.capacity() * std::mem::size_of::<Rect>(),
+            Action::Shapes(_) => 0,
+            Action::Stencil(stencil) => stencil.label.text.capacity(),
+            Action::Connector(connector) => connector.label.as_ref().map_or(0, String::capacity),
+            Action::Arrange(moves) => moves.capacity() * std::mem::size_of::<StencilMove>(),
+            Action::Restyle(_) | Action::Redact(_) | Action::Resize(_) | Action::Adjust(_) => 0,
+            Action::Layers(edit) => {
+                (edit.from.capacity() + edit.to.capacity()) * std::mem::size_of::<Layer>()
+            }
+            Action::Label(edit) => edit.from.name.capacity() + edit.to.name.capacity(),
+            Action::Retext(edit) => edit.from.capacity() + edit.to.capacity(),
+            Action::Group(parts) => parts.iter().map(Action::memory_size).sum(),
+            Action::Move(object_move) => {
+                object_move.objects.capacity() * std::mem::size_of::<ObjectRef>()
+            }
+            Action::Relayer(layer_move) => {
+                layer_move.objects.capacity() * std::mem::size_of::<ObjectRef>()
+                    + layer_move.from.capacity() * std::mem::size_of::<u32>()
+            }
+            Action::Erase(erased) => erased
+                .content
+                .as_ref()
+                .map_or(0, |content| content.memory_size()),
+            Action::Table(table) => table
+                .rows
+                .iter()
+                .map(|row| {
+                    row.capacity() * std::mem::size_of::<String>()
+                        + row.iter().map(String::capacity).sum::<usize>()
+                })
+                .sum(),
+            Action::Code(code_block) => {
+                code_block.code.capacity()
+                    + code_block.language.capacity()
+                    + code_block
+                        .highlighted
+                        .iter()
+                        .map(|(text, _)| std::mem::size_of::<(String, [u8; 4])>() + text.capacity())
+                        .sum::<usize>()
+            }
+        };
+        std::mem::size_of::<Action>() + heap
+    }
+
+    fn set_layer(&mut self, layer: u32) {
+        match self {
+            Action::Stroke(stroke) => stroke.layer = layer,
+            Action::Text(text) => text.layer = layer,
+            Action::Shapes(rectangle) => rectangle.layer = layer,
+            Action::Table(table) => table.layer = layer,
+            Action::Code(code_block) => code_block.layer = layer,
+            Action::Stencil(stencil) => stencil.layer = layer,
+            Action::Image(image) => image.layer = layer,
+            Action::Fill(fill) => fill.layer = layer,
+            _ => {}
+        }
+    }
+}
+
+struct WindowState<'a> {
+    device: egui_wgpu::wgpu::Device,
+    pressed_keys: HashSet<Key<'a>>,
+    queue: egui_wgpu::wgpu::Queue,
+    show_about: bool,
+    reference: Option<Reference>,
+    // The picture being adjusted, with its adjustment and corners from before.
+    adjusting: Option<(usize, ImageAdjust, [[f32; 2]; 2])>,
+    scan: Option<Scan>,
+    show_devices: bool,
+    recovery: Option<Vec<Vec<Action>>>,
+    show_shortcuts: bool,
+    // The text typed into the page finder while it is open.
+    page_search: Option<String>,
+    // The tag the page list is narrowed to.
+    page_filter: Option<String>,
+    new_page_tag: String,
+    statistics: Option<Statistics>,
+    // Counts new, summary and lecture pages since the board opened.
+    pages_created: usize,
+    keymap: Keymap,
+    settings: Settings,
+    panels_dirty: bool,
+    adapter_info: wgpu::AdapterInfo,
+    adapters: Vec<wgpu::AdapterInfo>,
+    font_size: i32,
+    surface: egui_wgpu::wgpu::Surface<'static>,
+    surface_config: SurfaceConfiguration,
+    board: Board,
+    last_cursor_position: PhysicalPosition<f64>,
+    scale_factor: f64,
+    egui_renderer: Renderer,
+    raw_input: RawInput,
+    egui_context: Context,
+    size: PhysicalSize<u32>,
+
+    font_system: FontSystem,
+    fonts: FontLibrary,
+    font_family: String,
+    new_fonts_dir: String,
+    swash_cache: SwashCache,
+    viewport: glyphon::Viewport,
+    atlas: glyphon::TextAtlas,
+    text_renderer: glyphon::TextRenderer,
+    window: Arc<Window>,
+
+    mouse_pressed: bool,
+    replay: Option<Replay>,
+    lecture_started: Option<Instant>,
+    // When the last dated page of the lecture was started.
+    last_lecture_page: Option<Instant>,
+    // When the flipbook last turned a page, while it plays.
+    flipbook: Option<Instant>,
+    brush_width: f32,
+    brush: Brush,
+    brush_indicator: Option<Instant>,
+    laser: Option<VecDeque<(PhysicalPosition<f64>, Instant)>>,
+    copied_style: Option<ObjectStyle>,
+    speech: Option<std::process::Child>,
+    magnifier: Option<Lens>,
+    lens_texture: Option<egui_wgpu::wgpu::Texture>,
+    current_stroke: Vec<Vertex>,
+    current_timestamps: Vec<u64>,
+    stroke_hold: Option<(PhysicalPosition<f64>, Instant)>,
+    stroke_snapped: bool,
+    touch_strokes: HashMap<u64, PointerStroke>,
+    touch_drag: Option<(u64, PhysicalPosition<f64>)>,
+    flicks: FlickTracker,
+    edge_swipe: EdgeSwipe,
+    // When the last page turn started and whether it went forward.
+    page_turn: Option<(Instant, bool)>,
+    // Where the text caret was last drawn on screen, and when the board last
+    // slid away from a window edge.
+    caret_position: Option<PhysicalPosition<f64>>,
+    auto_scrolled: Option<Instant>,
+    pinch: PinchTracker,
+    // Touches taken for a palm resting beside the drawing finger.
+    palm_touches: HashSet<u64>,
+    // The touch reported as a pen, while it is down, and when a pen last
+    // touched, moved or lifted.
+    pen_touch: Option<u64>,
+    pen_seen: Option<Instant>,
+    // Fingers that only pan and zoom in pen only mode, and where each was last.
+    pan_touches: HashMap<u64, PhysicalPosition<f64>>,
+    // Another page drawn faded under the current one, and its triangles once
+    // they are built.
+    onion_page: Option<usize>,
+    onion_vertices: Option<Vec<Vertex>>,
+    split: Option<Split>,
+    blend_mode: BlendMode,
+    current_color: [f32; 4],
+    previous_color: [f32; 4],
+
+    stroke_pipelines: Vec<egui_wgpu::wgpu::RenderPipeline>,
+    rectangle_shader: Option<egui_wgpu::wgpu::RenderPipeline>,
+    fill_pipeline: egui_wgpu::wgpu::RenderPipeline,
+    shadow_pipeline: egui_wgpu::wgpu::RenderPipeline,
+    picker: Picker,
+    gpu_picking: bool,
+    shadow_vertices: Vec<ShadowVertex>,
+    drop_shadow: bool,
+    stroke_buffers: Vec<(BlendMode, egui_wgpu::wgpu::Buffer, u32)>,
+    layer_draws: Vec<LayerDraw>,
+    shape_vertices: Vec<Vertex>,
+    image_renderer: ImageRenderer,
+    image_vertices: Vec<ImageVertex>,
+    image_textures: Vec<Arc<ImageTexture>>,
+    canvas: Option<egui_wgpu::wgpu::Texture>,
+    camera_buffer: egui_wgpu::wgpu::Buffer,
+    camera_bind_group: egui_wgpu::wgpu::BindGroup,
+    split_camera_buffer: egui_wgpu::wgpu::Buffer,
+    split_camera_bind_group: egui_wgpu::wgpu::BindGroup,
+    panning: Option<PhysicalPosition<f64>>,
+    damage: Damage,
+    drawn_scene: Vec<u8>,
+    drawn_stroke_len: usize,
+    start_typing: bool,
+    shape_positions: Vec<Vertex>,
+    dragging_stencil: Option<StencilKind>,
+    icons: Vec<Icon>,
+    icon_query: String,
+    new_asset_folder: String,
+    dragging_icon: Option<usize>,
+    object_lines: Vec<Vertex>,
+    object_fills: Vec<Vertex>,
+    paste_special: Option<PasteSpecial>,
+    project_dialog: Option<ProjectDialog>,
+    project_path: Option<PathBuf>,
+    create_rect: bool,
+    redacting: bool,
+    held_shape: Option<Command>,
+    bucket: bool,
+    erasing: bool,
+    // The last stylus force, 1 for devices that report none.
+    eraser_pressure: f32,
+    previous_tool: DeviceTool,
+    selecting: bool,
+    selection: Vec<ObjectRef>,
+    // A screenshot just pasted from the clipboard, offered background removal.
+    pasted_image: Option<usize>,
+    ocr: Option<Recognition>,
+    bridge: Option<Bridge>,
+    #[cfg(feature = "remote-api")]
+    remote: Option<remote::Server>,
+    select_drag: Option<SelectDrag>,
+    redaction_vertices: Vec<Vertex>,
+    cursor_visible: bool,
+    cursor_inside: bool,
+    calibration: Option<Calibration>,
+    crosshair_shown: bool,
+    animations: AnimationClock,
+    last_click_time: Option<Instant>,
+    // A barrel button press whose command waits to see if a double tap follows.
+    last_stylus_tap: Option<Instant>,
+    last_click_position: Option<PhysicalPosition<f64>>,
+    editing_text_index: Option<usize>,
+    editing_stencil_index: Option<usize>,
+    // What the text or label being edited said before, to undo the edit to.
+    edited_text: Option<String>,
+
+    color: ImageSource<'static>,
+    rect: ImageSource<'static>,
+    prev: ImageSource<'static>,
+    font: ImageSource<'static>,
+}
+
+struct Application<'a> {
+    window_state: Option<WindowState<'a>>,
+}
+
+const PALETTE: [Color32; 8] = [
+    Color32::from_rgb(255, 0, 0),     // Red
+    Color32::from_rgb(0, 255, 0),     // Green
+    Color32::from_rgb(0, 0, 255),     // Blue
+    Color32::from_rgb(255, 255, 0),   // Yellow
+    Color32::from_rgb(255, 0, 255),   // Magenta
+    Color32::from_rgb(0, 255, 255),   // Cyan
+    Color32::from_rgb(0, 0, 0),       // Black
+    Color32::from_rgb(255, 255, 255), // White
+];
+const DOUBLE_CLICK_THRESHOLD: Duration = Duration::from_millis(500);
+const DOUBLE_CLICK_DISTANCE: f64 = 5.0;
+const TABLE_CELL_PADDING: f32 = 6.0;
+const LINE_HEIGHT: f32 = 1.3;
+// Plain text used to be laid out with a line height of 0.1, which centred the
+// first line that much higher. Lifting it by the difference keeps older boards
+// in place now that lines are spaced for wrapping.
+const TEXT_LIFT: f32 = (LINE_HEIGHT - 0.1) / 2.0;
+const CODE_BLOCK_PADDING: f32 = 8.0;
+const GLYPH_ASCENT: f32 = 0.9;
+const GLYPH_DESCENT: f32 = 0.3;
+const CHIP_PADDING: f32 = 0.3;
+const CHIP_ALPHA: u8 = 220;
+const DAMAGE_PADDING: f32 = 4.0;
+const DEFAULT_BRUSH_WIDTH: f32 = 2.0;
+const MIN_BRUSH_WIDTH: f32 = 1.0;
+const MAX_BRUSH_WIDTH: f32 = 48.0;
+const BRUSH_WIDTH_STEP: f32 = 1.0;
+const BRUSH_INDICATOR_DURATION: Duration = Duration::from_secs(1);
+const CROSSHAIR_GAP: f32 = 3.0;
+const HIGHLIGHTER_SCALE: f32 = 6.0;
+const HIGHLIGHTER_ALPHA: f32 = 0.35;
+const CALLIGRAPHY_SCALE: f32 = 3.0;
+// How much of the full width a nib keeps when moving along its own edge.
+const NIB_THIN: f32 = 0.12;
+const SNAP_HOLD_TOLERANCE: f64 = 4.0;
+// A finger drawing for longer than this keeps its stroke when another touch
+// lands; earlier, the two fingers start a pinch instead.
+const PALM_DELAY_MILLIS: u64 = 250;
+// How long after the pen lifts pen only mode still keeps fingers from drawing.
+const PEN_ONLY_GRACE: Duration = Duration::from_millis(1000);
+const LASER_FADE: Duration = Duration::from_secs(1);
+const LASER_WIDTH: f32 = 4.0;
+const LASER_COLOR: Color32 = Color32::from_rgb(255, 40, 40);
+const PASTE_OFFSET: f32 = 12.0;
+const OCR_MARGIN: f32 = 16.0;
+const LECTURE_HEADING_MARGIN: f32 = 32.0;
+const SELECTION_HANDLE: f32 = 8.0;
+const SELECTION_COLOR: Color32 = Color32::from_rgb(30, 120, 230);
+const CROSSHAIR_ARM: f32 = 10.0;
+const ARROW_SIZE: f32 = 10.0;
+const NARROW_TOOLBAR_WIDTH: f32 = 700.0;
+const EDGE_ARROW_SIZE: f32 = 32.0;
+// The least of a picture's width or height a crop keeps.
+const MIN_CROP: f32 = 0.05;
+// Board pixels a stroke may stray from its points at each level of detail,
+// and how far that may show on screen before a finer level is drawn.
+const LOD_TOLERANCES: [f32; 3] = [1.0, 4.0, 16.0];
+const LOD_SCREEN_TOLERANCE: f32 = 0.75;
+// How opaque the page being turned away starts out.
+const PAGE_TURN_ALPHA: f32 = 0.6;
+// How close to a window edge, in screen pixels, writing starts sliding the
+// board, and how fast it slides with the pen right at the edge.
+const AUTO_SCROLL_MARGIN: f32 = 64.0;
+const AUTO_SCROLL_SPEED: f32 = 480.0;
+const TOOLBAR_SPACING: f32 = 12.0;
+const ARROW_ANGLE: f32 = 0.45;
+const ROUNDED_CORNER_SEGMENTS: usize = 6;
+const HALO_OFFSETS: [(f32, f32); 8] = [
+    (-1.5, 0.0),
+    (1.5, 0.0),
+    (0.0, -1.5),
+    (0.0, 1.5),
+    (-1.0, -1.0),
+    (1.0, -1.0),
+    (-1.0, 1.0),
+    (1.0, 1.0),
+];
+const CODE_BORDER_COLOR: [u8; 4] = [200, 200, 200, 255];
+
+impl Application<'_> {
+    fn about_to_wait(&mut self) -> Option<Instant> {
+        let state = self.window_state.as_mut()?;
+
+        if !state.raw_input.events.is_empty() {
+            state.window.request_redraw();
+        }
+
+        if !state.start_typing {
+            state.animations.cancel(Animation::CaretBlink);
+        } else if !state.animations.is_scheduled(Animation::CaretBlink) {
+            state
+                .animations
+                .schedule(Animation::CaretBlink, CARET_BLINK_INTERVAL);
+        }
+
+        if state.replay.is_some() {
+            state.animations.schedule(Animation::Replay, REPLAY_FRAME);
+        }
+        if let (Some(hold), Some((_, since))) = (state.snap_hold(), state.stroke_hold) {
+            state
+                .animations
+                .schedule(Animation::StrokeHold, hold.saturating_sub(since.elapsed()));
+        }
+        if let Some(tap) = state.last_stylus_tap {
+            state.animations.schedule(
+                Animation::StylusTap,
+                DOUBLE_CLICK_THRESHOLD.saturating_sub(tap.elapsed()),
+            );
+        }
+        if state.bridge.is_some() {
+            state
+                .animations
+                .schedule(Animation::Bridge, bridge::POLL_INTERVAL);
+        }
+        #[cfg(feature = "remote-api")]
+        if state.remote.is_some() {
+            state
+                .animations
+                .schedule(Animation::Remote, remote::POLL_INTERVAL);
+        }
+        if state.ocr.is_some() {
+            state
+                .animations
+                .schedule(Animation::Ocr, ocr::POLL_INTERVAL);
+        }
+        if state.laser.as_ref().is_some_and(|trail| !trail.is_empty()) {
+            state.animations.schedule(Animation::Laser, LASER_FRAME);
+        }
+        if state.page_turn.is_some() {
+            state
+                .animations
+                .schedule(Animation::PageTurn, PAGE_TURN_FRAME);
+        }
+        if state.auto_scrolled.is_some() {
+            state
+                .animations
+                .schedule(Animation::AutoScroll, AUTO_SCROLL_FRAME);
+        }
+        if state.fonts.dir.is_some() && !state.animations.is_scheduled(Animation::FontScan) {
+            state
+                .animations
+                .schedule(Animation::FontScan, FONT_SCAN_INTERVAL);
+        }
+        if let Some(shown) = state.brush_indicator {
+            state.animations.schedule(
+                Animation::BrushIndicator,
+                BRUSH_INDICATOR_DURATION.saturating_sub(shown.elapsed()),
+            );
+        }
+        match state.lecture_started {
+            Some(started) => {
+                let elapsed = started.elapsed().subsec_nanos();
+                let until_next_second = Duration::from_nanos(1_000_000_000 - elapsed as u64);
+                state
+                    .animations
+                    .schedule(Animation::SessionTimer, until_next_second);
+            }
+            None => state.animations.cancel(Animation::SessionTimer),
+        }
+        let interval = state.lecture_page_interval();
+        match state.last_lecture_page {
+            Some(last) if state.lecture_started.is_some() && !interval.is_zero() => {
+                state.animations.schedule(
+                    Animation::LecturePage,
+                    interval.saturating_sub(last.elapsed()),
+                );
+            }
+            _ => state.animations.cancel(Animation::LecturePage),
+        }
+        match state.flipbook {
+            Some(shown) => state.animations.schedule(
+                Animation::Flipbook,
+                state.flipbook_interval().saturating_sub(shown.elapsed()),
+            ),
+            None => state.animations.cancel(Animation::Flipbook),
+        }
+
+        for animation in state.animations.take_due(Instant::now()) {
+            match animation {
+                Animation::CaretBlink => {
+                    state.cursor_visible = !state.cursor_visible;
+                    state
+                        .animations
+                        .schedule(Animation::CaretBlink, CARET_BLINK_INTERVAL);
+                }
+                Animation::FontScan => {
+                    state
+                        .animations
+                        .schedule(Animation::FontScan, FONT_SCAN_INTERVAL);
+                    if !state.fonts.scan(&mut state.font_system) {
+                        continue;
+                    }
+                }
+                Animation::Interface
+                | Animation::Replay
+                | Animation::SessionTimer
+                | Animation::BrushIndicator
+                | Animation::Laser
+                | Animation::PageTurn
+                | Animation::AutoScroll => {}
+                Animation::StrokeHold => state.snap_held_stroke(),
+                Animation::StylusTap => state.finish_stylus_tap(),
+                Animation::LecturePage => {
+                    let interval = state.lecture_page_interval();
+                    if state
+                        .last_lecture_page
+                        .is_some_and(|last| last.elapsed() >= interval)
+                    {
+                        state.lecture_page();
+                    }
+                }
+                Animation::Flipbook => {
+                    if state
+                        .flipbook
+                        .is_some_and(|shown| shown.elapsed() >= state.flipbook_interval())
+                    {
+                        state.flipbook_frame();
+                    }
+                }
+                Animation::Ocr => state.poll_ocr(),
+                Animation::Bridge => state.poll_bridge(),
+                #[cfg(feature = "remote-api")]
+                Animation::Remote => state.poll_remote(),
+            }
+            state.window.request_redraw();
+        }
+
+        state.animations.next_deadline()
+    }
+
+    fn window_event(&mut self, _window_id: WindowId, event: WindowEvent) {
+        let Some(state) = &mut self.window_state else {
+            return;
+        };
+
+        let window = &state.window;
+        state.input(window.clone(), &event);
+    }
+}
+
+fn convert_to_buffer(color: Color32) -> [f32; 4] {
+    rgba_to_normalized(color.to_array())
+}
+
+fn rgba_to_normalized(rgba: [u8; 4]) -> [f32; 4] {
+    [
+        rgba[0] as f32 / 255.0,
+        rgba[1] as f32 / 255.0,
+        rgba[2] as f32 / 255.0,
+        rgba[3] as f32 / 255.0,
+    ]
+}
+
+fn screen_to_ndc(size: PhysicalSize<u32>, x: f32, y: f32) -> [f32; 2] {
+    [
+        x / size.width as f32 * 2.0 - 1.0,
+        -(y / size.height as f32 * 2.0 - 1.0),
+    ]
+}
+
+fn select_adapter(adapters: Vec<wgpu::Adapter>, selector: &str) -> Option<wgpu::Adapter> {
+    let selected = match selector.parse::<usize>() {
+        Ok(index) => adapters.into_iter().nth(index),
+        Err(_) => {
+            let selector = selector.to_lowercase();
+            adapters
+                .into_iter()
+                .find(|adapter| adapter.get_info().name.to_lowercase().contains(&selector))
+        }
+    };
+
+    if selected.is_none() {
+        crash::log(format!(
+            "No adapter matches {}, using the default adapter",
+            selector
+        ));
+    }
+    selected
+}
+
+fn create_canvas(
+    device: &egui_wgpu::wgpu::Device,
+    config: &SurfaceConfiguration,
+    usage: TextureUsages,
+) -> egui_wgpu::wgpu::Texture {
+    device.create_texture(&egui_wgpu::wgpu::TextureDescriptor {
+        label: Some("Canvas Texture"),
+        size: egui_wgpu::wgpu::Extent3d {
+            width: config.width,
+            height: config.height,
+            depth_or_array_layers: 1,
+        },
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: egui_wgpu::wgpu::TextureDimension::D2,
+        format: config.format,
+        usage: TextureUsages::RENDER_ATTACHMENT | TextureUsages::COPY_SRC | usage,
+        view_formats: &[],
+    })
+}
+
+fn points_bounds(size: PhysicalSize<u32>, vertices: &[Vertex]) -> Rect {
+    let (mut left, mut top) = (f32::MAX, f32::MAX);
+    let (mut right, mut bottom) = (f32::MIN, f32::MIN);
+    for vertex in vertices {
+        let [x, y] = ndc_to_screen(size, vertex.position);
+        left = left.min(x);
+        top = top.min(y);
+        right = right.max(x);
+        bottom = bottom.max(y);
+    }
+
+    Rect {
+        x: left,
+        y: top,
+        width: right - left,
+        height: bottom - top,
+    }
+}
+
+fn ndc_to_screen(size: PhysicalSize<u32>, position: [f32; 2]) -> [f32; 2] {
+    [
+        (position[0] + 1.0) / 2.0 * size.width as f32,
+        (1.0 - position[1]) / 2.0 * size.height as f32,
+    ]
+}
+
+// Keeps shaping left-to-right; cursor offsets on the first line skip it.
+const TEXT_PREFIX: &str = "\u{200E}\u{200C}";
+
+fn text_buffer(
+    font_system: &mut FontSystem,
+    text: &str,
+    family: &str,
+    font_size: f32,
+    line_height: f32,
+    width: f32,
+    height: f32,
+) -> Buffer {
+    let mut buffer = Buffer::new(
+        font_system,
+        Metrics::new(font_size, font_size * line_height),
+    );
+    buffer.set_size(font_system, Some(width), Some(height));
+
+    let text = format!("{}{}", TEXT_PREFIX, text);
+    buffer.set_text(
+        font_system,
+        &text,
+        Attrs::new().family(Family::Name(family)),
+        Shaping::Advanced,
+    );
+    buffer.shape_until_scroll(font_system, false);
+    buffer
+}
+
+fn markdown_buffer(
+    font_system: &mut FontSystem,
+    source: &str,
+    family: &str,
+    font_size: f32,
+    width: f32,
+    height: f32,
+) -> Buffer {
+    let mut buffer = Buffer::new(
+        font_system,
+        Metrics::new(font_size, font_size * LINE_HEIGHT),
+    );
+    buffer.set_size(font_system, Some(width), Some(height));
+
+    let default_attrs = Attrs::new().family(Family::Name(family));
+    let spans = markdown::spans(source);
+    let rich_text =
+        std::iter::once(("\u{200E}\u{200C}", default_attrs)).chain(spans.iter().map(|span| {
+            let mut attrs = default_attrs;
+            if span.bold {
+                attrs = attrs.weight(Weight::BOLD);
+            }
+            if span.italic {
+                attrs = attrs.style(Style::Italic);
+            }
+            if span.code {
+                attrs = attrs.family(Family::Monospace);
+            }
+            if span.scale != 1.0 {
+                let size = font_size * span.scale;
+                attrs = attrs.metrics(Metrics::new(size, size * LINE_HEIGHT));
+            }
+            (span.text.as_str(), attrs)
+        }));
+
+    buffer.set_rich_text(font_system, rich_text, default_attrs, Shaping::Advanced);
+    buffer.shape_until_scroll(font_system, false);
+    buffer
+}
+
+// A text laid out the way the board shows it, with where the buffer's top left
+// corner goes.
+pub fn text_layout(
+    font_system: &mut FontSystem,
+    text: &TextEntries,
+    size: PhysicalSize<u32>,
+) -> (Buffer, [f32; 2]) {
+    let font_size = text.font_size as f32;
+    let wrap_width = text.wrap_width.unwrap_or(size.width as f32);
+    if text.markdown {
+        let buffer = markdown_buffer(
+            font_system,
+            &text.text,
+            &text.font,
+            font_size,
+            wrap_width,
+            size.height as f32,
+        );
+        return (buffer, text.position);
+    }
+    let buffer = text_buffer(
+        font_system,
+        &text.text,
+        &text.font,
+        font_size,
+        LINE_HEIGHT,
+        wrap_width,
+        size.height as f32,
+    );
+    let [x, y] = text.position;
+    (buffer, [x, y - font_size * TEXT_LIFT])
+}
+
+fn code_buffer(
+    font_system: &mut FontSystem,
+    highlighted: &[(String, [u8; 4])],
+    font_size: f32,
+    width: f32,
+    height: f32,
+) -> Buffer {
+    let mut buffer = Buffer::new(
+        font_system,
+        Metrics::new(font_size, font_size * LINE_HEIGHT),
+    );
+    buffer.set_size(font_system, Some(width), Some(height));
+
+    let default_attrs = Attrs::new().family(Family::Monospace);
+    let rich_text = highlighted.iter().map(|(text, color)| {
+        (
+            text.as_str(),
+            default_attrs.color(Color::rgba(color[0], color[1], color[2], color[3])),
+        )
+    });
+
+    buffer.set_rich_text(font_system, rich_text, default_attrs, Shaping::Advanced);
+    buffer.shape_until_scroll(font_system, false);
+    buffer
+}
+
+// A vertical line at the text cursor, placed from the shaped glyphs so it
+// lands between characters rather than after the last one.
+fn caret_vertices(
+    buffer: &Buffer,
+    text: &TextEntries,
+    position: [f32; 2],
+    size: PhysicalSize<u32>,
+) -> [Vertex; 2] {
+    let cursor = text.cursor.min(text.text.len());
+    let before = &text.text[..cursor];
+    let line = before.matches('\n').count();
+    let mut column = before
+        .rfind('\n')
+        .map_or(cursor, |index| cursor - index - 1);
+    if line == 0 {
+        column += TEXT_PREFIX.len();
+    }
+
+    let font_size = buffer.metrics().font_size;
+    let (mut x, mut top, mut bottom) = (0.0, 0.0, font_size);
+    for run in buffer.layout_runs().filter(|run| run.line_i == line) {
+        top = run.line_y - font_size * GLYPH_ASCENT;
+        bottom = run.line_y + font_size * GLYPH_DESCENT;
+        for glyph in run.glyphs {
+            if glyph.start >= column {
+                x = glyph.x;
+                break;
+            }
+            x = glyph.x + glyph.w;
+        }
+        if run.glyphs.iter().any(|glyph| glyph.end > column) {
+            break;
+        }
+    }
+
+    let color = rgba_to_normalized(text.color);
+    let x = position[0] + x;
+    [
+        Vertex {
+            position: screen_to_ndc(size, x, position[1] + top),
+            color,
+        },
+        Vertex {
+            position: screen_to_ndc(size, x, position[1] + bottom),
+            color,
+        },
+    ]
+}
+
+fn object_index(object: ObjectRef) -> usize {
+    match object {
+        ObjectRef::Stroke(index)
+        | ObjectRef::Shape(index)
+        | ObjectRef::Text(index)
+        | ObjectRef::Table(index)
+        | ObjectRef::Code(index)
+        | ObjectRef::Stencil(index)
+        | ObjectRef::Image(index)
+        | ObjectRef::Fill(index)
+        | ObjectRef::Connector(index) => index,
+    }
+}
+
+// Puts the input method's candidate window just below the caret.
+fn ime_position(
+    camera: Camera,
+    size: PhysicalSize<u32>,
+    caret: &[Vertex; 2],
+) -> PhysicalPosition<f64> {
+    let [x, y] = camera.to_screen(ndc_to_screen(size, caret[1].position));
+    PhysicalPosition::new(x as f64, y as f64)
+}
+
+fn buffer_bounds(buffer: &Buffer, position: [f32; 2], font_size: f32) -> Rect {
+    let mut width = 0.0f32;
+    let (mut top, mut bottom) = (f32::MAX, f32::MIN);
+    for run in buffer.layout_runs() {
+        let size = run
+            .glyphs
+            .iter()
+            .map(|glyph| glyph.font_size)
+            .fold(font_size, f32::max);
+        width = width.max(run.line_w);
+        top = top.min(run.line_y - size * GLYPH_ASCENT);
+        bottom = bottom.max(run.line_y + size * GLYPH_DESCENT);
+    }
+    if top > bottom {
+        (top, bottom) = (0.0, font_size);
+    }
+
+    Rect {
+        x: position[0],
+        y: position[1] + top,
+        width,
+        height: bottom - top,
+    }
+}
+
+// Cuts the points inside `area` out of the stroke so no segment is drawn
+// across it. The stroke keeps its first run of points outside the area and the
+// later runs come back as strokes of their own.
+fn redact_stroke(stroke: &mut Stroke, area: &Rect, size: PhysicalSize<u32>) -> Vec<Stroke> {
+    let timed = stroke.timestamps.len() == stroke.points.len();
+    let mut runs: Vec<(Vec<Vertex>, Vec<u64>)> = Vec::new();
+    let (mut cut, mut inside) = (false, true);
+    for (index, point) in stroke.points.iter().enumerate() {
+        if area.contains(ndc_to_screen(size, point.position)) {
+            (cut, inside) = (true, true);
+            continue;
+        }
+        if inside {
+            runs.push((Vec::new(), Vec::new()));
+            inside = false;
+        }
+        let (points, timestamps) = runs.last_mut().unwrap();
+        points.push(*point);
+        if timed {
+            timestamps.push(stroke.timestamps[index]);
+        }
+    }
+    if !cut {
+        return Vec::new();
+    }
+
+    let (blend, brush, layer, width) = (stroke.blend, stroke.brush, stroke.layer, stroke.width);
+    let mut pieces = runs.into_iter().map(|(points, timestamps)| Stroke {
+        points,
+        timestamps,
+        blend,
+        brush,
+        layer,
+        width,
+        lod: StrokeLod::default(),
+    });
+    match pieces.next() {
+        Some(first) => *stroke = first,
+        None => {
+            stroke.points.clear();
+            stroke.timestamps.clear();
+        }
+    }
+    pieces.collect()
+}
+
+fn redact_text(text: &mut TextEntries, area: &Rect) {
+    if text.bounds.intersects(area) {
+        text.text = scrub(&text.text);
+    }
+}
+
+fn redact_table(table: &mut Table, area: &Rect) {
+    if table.bounds.intersects(area) {
+        for cell in table.rows.iter_mut().flatten() {
+            *cell = scrub(cell);
+        }
+    }
+}
+
+fn redact_image(image: &mut BoardImage, area: &Rect, pixelate: bool) {
+    let bounds = image.bounds();
+    if !bounds.intersects(area) {
+        return;
+    }
+    let fraction = |value: f32, axis: usize| {
+        (value - image.first[axis]) / (image.last[axis] - image.first[axis])
+    };
+    let (u0, u1) = (fraction(area.x, 0), fraction(area.x + area.width, 0));
+    let (v0, v1) = (fraction(area.y, 1), fraction(area.y + area.height, 1));
+    let region = [[u0.min(u1), v0.min(v1)], [u0.max(u1), v0.max(v1)]];
+    let redacted = match pixelate {
+        true => images::pixelate(&image.data, region),
+        false => images::black_out(&image.data, region),
+    };
+    match redacted {
+        Some(data) => {
+            image.data = data;
+            image.texture = None;
+        }
+        None => crash::log("Unable to redact an image".to_string()),
+    }
+}
+
+fn redact_code(code_block: &mut CodeBlock, area: &Rect) {
+    if code_block.bounds.intersects(area) {
+        code_block.code = scrub(&code_block.code);
+        code_block.highlighted = code::highlight(&code_block.code, &code_block.language);
+    }
+}
+
+fn scrub(text: &str) -> String {
+    text.chars()
+        .map(|c| if c.is_whitespace() { c } else { '█' })
+        .collect()
+}
+
+fn timestamp_millis() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_millis() as u64)
+}
+
+fn clip_to_bounds(center: [f32; 2], toward: [f32; 2], bounds: &Rect) -> [f32; 2] {
+    let (dx, dy) = (toward[0] - center[0], toward[1] - center[1]);
+    let scale_x = if dx == 0.0 {
+        f32::MAX
+    } else {
+        bounds.width / 2.0 / dx.abs()
+    };
+    let scale_y = if dy == 0.0 {
+        f32::MAX
+    } else {
+        bounds.height / 2.0 / dy.abs()
+    };
+    let scale = scale_x.min(scale_y).min(1.0);
+    [center[0] + dx * scale, center[1] + dy * scale]
+}
+
+fn rounded_rect_outline(
+    left: f32,
+    top: f32,
+    right: f32,
+    bottom: f32,
+    radius: f32,
+) -> Vec<[f32; 2]> {
+    let radius = radius.min((right - left) / 2.0).min((bottom - top) / 2.0);
+    let corners = [
+        (right - radius, top + radius, -0.5),
+        (right - radius, bottom - radius, 0.0),
+        (left + radius, bottom - radius, 0.5),
+        (left + radius, top + radius, 1.0),
+    ];
+
+    let mut outline = Vec::new();
+    for (cx, cy, start) in corners {
+        for step in 0..=ROUNDED_CORNER_SEGMENTS {
+            let angle =
+                std::f32::consts::PI * (start + 0.5 * step as f32 / ROUNDED_CORNER_SEGMENTS as f32);
+            outline.push([cx + radius * angle.cos(), cy + radius * angle.sin()]);
+        }
+    }
+    outline
+}
+
+fn rounded_rect_vertices(
+    left: f32,
+    top: f32,
+    right: f32,
+    bottom: f32,
+    radius: f32,
+) -> Vec<[f32; 2]> {
+    let outline = rounded_rect_outline(left, top, right, bottom, radius);
+    let center = [(left + right) / 2.0, (top + bottom) / 2.0];
+    let mut vertices = Vec::new();
+    for i in 0..outline.len() {
+        vertices.push(center);
+        vertices.push(outline[i]);
+        vertices.push(outline[(i + 1) % outline.len()]);
+    }
+    vertices
+}
+
+fn contrasting_color(color: [u8; 4]) -> [u8; 4] {
+    let luminance = 0.299 * color[0] as f32 + 0.587 * color[1] as f32 + 0.114 * color[2] as f32;
+    if luminance > 140.0 {
+        [0, 0, 0, 255]
+    } else {
+        [255, 255, 255, 255]
+    }
+}
+
+fn normalized_to_rgba(normalized: [f32; 4]) -> [u8; 4] {
+    let red = (normalized[0] * 255.0) as u8;
+    let green = (normalized[1] * 255.0) as u8;
+    let blue = (normalized[2] * 255.0) as u8;
+    let alpha = (normalized[3] * 255.0) as u8;
+    [red, green, blue, alpha]
+}
+
+fn egui_key(key: Key) -> Option<KeyEgui> {
+    match key {
+        Key::Character(char) => KeyEgui::from_name(char),
+        Key::Enter => Some(KeyEgui::Enter),
+        Key::Space => Some(KeyEgui::Space),
+        Key::Backspace => Some(KeyEgui::Backspace),
+        Key::Tab => Some(KeyEgui::Tab),
+        _ => None,
+    }
+}
+
+fn is_persian(char: char) -> bool {
+    ('\u{0600}'..='\u{06FF}').contains(&char) || ('\u{0750}'..='\u{077F}').contains(&char)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn zoomed_out_strokes_draw_fewer_points() {
+        let size = PhysicalSize::new(800, 600);
+        let points: Vec<Vertex> = (0..=400)
+            .map(|step| {
+                let x = step as f32 / 400.0 * 1.6 - 0.8;
+                Vertex {
+                    position: [x, (x * 8.0).sin() * 0.2],
+                    color: [0.0, 0.0, 0.0, 1.0],
+                }
+            })
+            .collect();
+        let stroke = Stroke {
+            lod: StrokeLod::new(&points, size),
+            points,
+            ..Stroke::default()
+        };
+
+        assert_eq!(stroke.lod_points(1.0).len(), stroke.points.len());
+        let far = stroke.lod_points(0.1);
+        assert!(far.len() < stroke.points.len() / 4);
+        let ends = |points: &[Vertex]| [points[0].position, points[points.len() - 1].position];
+        assert_eq!(ends(&far), ends(&stroke.points));
+    }
+
+    #[test]
+    fn text_editing_clamps_a_stale_cursor() {
+        let mut text = TextEntries::null([0, 0, 0, 255], 16);
+        text.text = "سلام".to_string();
+        text.cursor = 3;
+        text.backspace();
+        assert_eq!(text.text, "لام");
+        assert_eq!(text.cursor, 0);
+
+        text.cursor = 100;
+        text.move_left();
+        assert_eq!(text.cursor, "لا".len());
+        text.cursor = 100;
+        text.move_home();
+        assert_eq!(text.cursor, 0);
+        text.cursor = 100;
+        text.backspace();
+        assert_eq!(text.text, "لا");
+    }
+
+    #[test]
+    fn highlighter_is_translucent_in_palette_colors() {
+        for color in PALETTE {
+            let ink = Brush::Highlighter.ink(convert_to_buffer(color));
+            assert!((ink[3] - HIGHLIGHTER_ALPHA).abs() < f32::EPSILON);
+            assert!(ink.iter().all(|channel| (0.0..=1.0).contains(channel)));
+        }
+    }
+}
@@ -0,0 +1,46 @@
+use egui::{
+    epaint::{Mesh, Vertex},
+    Color32, Pos2, TextureId, Vec2,
+};
+
+pub const LENS_RADIUS: f32 = 110.0;
+pub const ZOOM_LEVELS: [f32; 3] = [2.0, 3.0, 4.0];
+const LENS_SEGMENTS: u32 = 64;
+
+pub struct Lens {
+    pub zoom: f32,
+    pub texture: Option<TextureId>,
+}
+
+impl Lens {
+    pub fn new() -> Self {
+        Lens {
+            zoom: ZOOM_LEVELS[0],
+            texture: None,
+        }
+    }
+}
+
+// A triangle fan around `center` whose texture coordinates cover a circle
+// `zoom` times smaller than the lens, so the board under it appears magnified.
+pub fn lens_mesh(texture: TextureId, center: Pos2, zoom: f32, screen: Vec2) -> Mesh {
+    let uv = |position: Pos2| Pos2::new(position.x / screen.x, position.y / screen.y);
+    let mut mesh = Mesh::with_texture(texture);
+    mesh.vertices.push(Vertex {
+        pos: center,
+        uv: uv(center),
+        color: Color32::WHITE,
+    });
+    for segment in 0..=LENS_SEGMENTS {
+        let direction = Vec2::angled(segment as f32 / LENS_SEGMENTS as f32 * std::f32::consts::TAU);
+        mesh.vertices.push(Vertex {
+            pos: center + direction * LENS_RADIUS,
+            uv: uv(center + direction * LENS_RADIUS / zoom),
+            color: Color32::WHITE,
+        });
+    }
+    for segment in 1..=LENS_SEGMENTS {
+        mesh.add_triangle(0, segment, segment + 1);
+    }
+    mesh
+}
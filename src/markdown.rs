@@ -0,0 +1,154 @@
+use pulldown_cmark::{Event, HeadingLevel, Parser, Tag, TagEnd};
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Span {
+    pub text: String,
+    pub bold: bool,
+    pub italic: bool,
+    pub code: bool,
+    pub scale: f32,
+}
+
+impl Span {
+    fn plain(text: &str, scale: f32) -> Self {
+        Span {
+            text: text.to_string(),
+            bold: false,
+            italic: false,
+            code: false,
+            scale,
+        }
+    }
+}
+
+fn heading_scale(level: HeadingLevel) -> f32 {
+    match level {
+        HeadingLevel::H1 => 2.0,
+        HeadingLevel::H2 => 1.6,
+        HeadingLevel::H3 => 1.3,
+        _ => 1.1,
+    }
+}
+
+fn list_prefix(list_numbers: &mut [Option<u64>]) -> String {
+    let depth = list_numbers.len().saturating_sub(1);
+    let mut prefix = "    ".repeat(depth);
+    match list_numbers.last_mut() {
+        Some(Some(number)) => {
+            prefix.push_str(&format!("{}. ", number));
+            *number += 1;
+        }
+        _ => prefix.push_str("• "),
+    }
+    prefix
+}
+
+pub fn spans(source: &str) -> Vec<Span> {
+    let mut spans: Vec<Span> = Vec::new();
+    let mut bold = 0;
+    let mut italic = 0;
+    let mut scale = 1.0;
+    let mut list_numbers: Vec<Option<u64>> = Vec::new();
+
+    for event in Parser::new(source) {
+        match event {
+            Event::Start(Tag::Heading { level, .. }) => scale = heading_scale(level),
+            Event::Start(Tag::Strong) => bold += 1,
+            Event::End(TagEnd::Strong) => bold -= 1,
+            Event::Start(Tag::Emphasis) => italic += 1,
+            Event::End(TagEnd::Emphasis) => italic -= 1,
+            Event::Start(Tag::List(start)) => {
+                if !list_numbers.is_empty() {
+                    spans.push(Span::plain("\n", 1.0));
+                }
+                list_numbers.push(start);
+            }
+            Event::End(TagEnd::List(_)) => {
+                list_numbers.pop();
+            }
+            Event::Start(Tag::Item) => {
+                spans.push(Span::plain(&list_prefix(&mut list_numbers), scale));
+            }
+            Event::Text(text) => spans.push(Span {
+                text: text.to_string(),
+                bold: bold > 0,
+                italic: italic > 0,
+                code: false,
+                scale,
+            }),
+            Event::Code(text) => spans.push(Span {
+                text: text.to_string(),
+                bold: bold > 0,
+                italic: italic > 0,
+                code: true,
+                scale,
+            }),
+            Event::SoftBreak => spans.push(Span::plain(" ", scale)),
+            Event::HardBreak => spans.push(Span::plain("\n", scale)),
+            Event::End(TagEnd::Heading(_)) => {
+                spans.push(Span::plain("\n", scale));
+                scale = 1.0;
+            }
+            Event::End(TagEnd::Paragraph | TagEnd::Item | TagEnd::CodeBlock)
+                if !spans.last().is_some_and(|span| span.text.ends_with('\n')) =>
+            {
+                spans.push(Span::plain("\n", scale));
+            }
+            _ => {}
+        }
+    }
+
+    while let Some(last) = spans.last_mut() {
+        let trimmed = last.text.trim_end_matches('\n').len();
+        if trimmed > 0 {
+            last.text.truncate(trimmed);
+            break;
+        }
+        spans.pop();
+    }
+    spans
+}
+
+pub fn blocks(source: &str) -> Vec<(String, f32)> {
+    let mut blocks = Vec::new();
+    let mut text = String::new();
+    let mut scale = 1.0;
+    let mut list_numbers: Vec<Option<u64>> = Vec::new();
+
+    for event in Parser::new(source) {
+        match event {
+            Event::Start(Tag::Heading { level, .. }) => scale = heading_scale(level),
+            Event::Start(Tag::List(start)) => list_numbers.push(start),
+            Event::End(TagEnd::List(_)) => {
+                list_numbers.pop();
+            }
+            Event::Start(Tag::Item) => {
+                flush_block(&mut blocks, &mut text, &mut scale);
+                text.push_str(&list_prefix(&mut list_numbers));
+            }
+            Event::Text(content) | Event::Code(content) => text.push_str(&content),
+            Event::SoftBreak => text.push(' '),
+            Event::HardBreak => text.push('\n'),
+            Event::End(TagEnd::Item) => flush_block(&mut blocks, &mut text, &mut scale),
+            Event::End(TagEnd::Heading(_) | TagEnd::Paragraph | TagEnd::CodeBlock)
+                if list_numbers.is_empty() =>
+            {
+                flush_block(&mut blocks, &mut text, &mut scale)
+            }
+            _ => {}
+        }
+    }
+
+    flush_block(&mut blocks, &mut text, &mut scale);
+    blocks
+}
+
+fn flush_block(blocks: &mut Vec<(String, f32)>, text: &mut String, scale: &mut f32) {
+    for line in text.lines() {
+        if !line.trim().is_empty() {
+            blocks.push((line.trim_end().to_string(), *scale));
+        }
+    }
+    text.clear();
+    *scale = 1.0;
+}
@@ -0,0 +1,71 @@
+use std::{
+    collections::hash_map::DefaultHasher,
+    hash::{Hash, Hasher},
+    io::{Read, Write},
+    process::{Child, Command, Stdio},
+    time::Duration,
+};
+
+pub const POLL_INTERVAL: Duration = Duration::from_millis(200);
+
+// Text recognition runs the `tesseract` program in the background, the same
+// way reading aloud hands text to the system's speech synthesizer.
+pub struct Recognition {
+    child: Child,
+    pub image: usize,
+    // The picture's data as it was sent, to tell whether the image at `image`
+    // is still the one read.
+    fingerprint: u64,
+}
+
+impl Recognition {
+    pub fn start(data: &[u8], image: usize) -> Result<Self, String> {
+        let mut child = Command::new("tesseract")
+            .args(["stdin", "stdout"])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .map_err(|err| format!("Unable to run tesseract: {}", err))?;
+        if let Some(mut stdin) = child.stdin.take() {
+            if let Err(err) = stdin.write_all(data) {
+                let _ = child.kill();
+                return Err(format!("Unable to send the image to tesseract: {}", err));
+            }
+        }
+        Ok(Recognition {
+            child,
+            image,
+            fingerprint: fingerprint(data),
+        })
+    }
+
+    // Whether `data` is the picture that was read.
+    pub fn reads(&self, data: &[u8]) -> bool {
+        fingerprint(data) == self.fingerprint
+    }
+
+    // None while tesseract is still reading the picture.
+    pub fn poll(&mut self) -> Option<Result<String, String>> {
+        let status = match self.child.try_wait() {
+            Ok(status) => status?,
+            Err(err) => return Some(Err(err.to_string())),
+        };
+        if !status.success() {
+            return Some(Err(format!("tesseract exited with {}", status)));
+        }
+        let mut text = String::new();
+        if let Some(mut stdout) = self.child.stdout.take() {
+            if let Err(err) = stdout.read_to_string(&mut text) {
+                return Some(Err(err.to_string()));
+            }
+        }
+        Some(Ok(text.trim().to_string()))
+    }
+}
+
+fn fingerprint(data: &[u8]) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    data.hash(&mut hasher);
+    hasher.finish()
+}
@@ -0,0 +1,124 @@
+use crate::{
+    export::paint::{self, Painter},
+    rgba_to_normalized, screen_to_ndc, Action, Rect, Stroke, Vertex,
+};
+use glyphon::{fontdb, Buffer, FontSystem};
+use tao::dpi::PhysicalSize;
+
+// How strongly the ghost page shows through under the current one.
+pub const GHOST_ALPHA: f32 = 0.5;
+
+// Triangles for the fill pipeline that draw another page, faded by `alpha`.
+// Text is left out, since glyphon draws text over the whole board, and
+// pictures are only outlined.
+pub fn vertices(
+    page: &[Action],
+    size: PhysicalSize<u32>,
+    alpha: f32,
+    font_system: &mut FontSystem,
+) -> Vec<Vertex> {
+    let mut ghost = Ghost {
+        size,
+        alpha,
+        vertices: Vec::new(),
+    };
+    paint::paint(page, size, font_system, &mut ghost);
+    ghost.vertices
+}
+
+struct Ghost {
+    size: PhysicalSize<u32>,
+    alpha: f32,
+    vertices: Vec<Vertex>,
+}
+
+impl Ghost {
+    fn quad(&mut self, [x1, y1]: [f32; 2], [x2, y2]: [f32; 2], color: [f32; 4]) {
+        let [r, g, b, a] = color;
+        let color = [r, g, b, a * self.alpha];
+        let size = self.size;
+        self.vertices.extend(
+            [(x1, y1), (x2, y1), (x1, y2), (x1, y2), (x2, y1), (x2, y2)].map(|(x, y)| Vertex {
+                position: screen_to_ndc(size, x, y),
+                color,
+            }),
+        );
+    }
+
+    // A segment one board pixel wide.
+    fn segment(&mut self, from: [f32; 2], to: [f32; 2], color: [f32; 4]) {
+        let points = [from, to].map(|[x, y]| Vertex {
+            position: screen_to_ndc(self.size, x, y),
+            color,
+        });
+        self.stroke_vertices(&points, 1.0, color);
+    }
+
+    fn stroke_vertices(&mut self, points: &[Vertex], width: f32, color: [f32; 4]) {
+        let [r, g, b, a] = color;
+        let points: Vec<Vertex> = points
+            .iter()
+            .map(|point| Vertex {
+                position: point.position,
+                color: [r, g, b, a * self.alpha],
+            })
+            .collect();
+        Stroke::ribbon_vertices(
+            &points,
+            width,
+            [0.0; 2],
+            None,
+            self.size,
+            &mut self.vertices,
+        );
+    }
+}
+
+impl Painter for Ghost {
+    fn fill(&mut self, rects: &[Rect], color: [f32; 4]) {
+        for rect in rects {
+            self.quad(
+                [rect.x, rect.y],
+                [rect.x + rect.width, rect.y + rect.height],
+                color,
+            );
+        }
+    }
+
+    fn outline(&mut self, rect: &Rect, color: [f32; 4]) {
+        let (left, top) = (rect.x, rect.y);
+        let (right, bottom) = (rect.x + rect.width, rect.y + rect.height);
+        for (from, to) in [
+            ([left, top], [right, top]),
+            ([right, top], [right, bottom]),
+            ([right, bottom], [left, bottom]),
+            ([left, bottom], [left, top]),
+        ] {
+            self.segment(from, to, color);
+        }
+    }
+
+    fn image(&mut self, bounds: &Rect, _: &[u8]) {
+        self.outline(bounds, [0.5, 0.5, 0.5, 1.0]);
+    }
+
+    fn lines(&mut self, points: &[[f32; 2]], color: [u8; 4]) {
+        let color = rgba_to_normalized(color);
+        for pair in points.chunks_exact(2) {
+            self.segment(pair[0], pair[1], color);
+        }
+    }
+
+    fn stroke(&mut self, points: &[[f32; 2]], width: f32, color: [f32; 4]) {
+        let points: Vec<Vertex> = points
+            .iter()
+            .map(|&[x, y]| Vertex {
+                position: screen_to_ndc(self.size, x, y),
+                color,
+            })
+            .collect();
+        self.stroke_vertices(&points, width, color);
+    }
+
+    fn glyphs(&mut self, _: &fontdb::Database, _: &Buffer, _: [f32; 2], _: [u8; 4]) {}
+}
@@ -0,0 +1,92 @@
+use crate::{camera::Camera, Action};
+use serde::{Deserialize, Serialize};
+
+// Swatches offered for a new tag.
+pub const TAG_COLORS: [[u8; 4]; 6] = [
+    [220, 60, 60, 255],
+    [235, 150, 40, 255],
+    [210, 190, 40, 255],
+    [60, 170, 80, 255],
+    [60, 120, 220, 255],
+    [150, 80, 200, 255],
+];
+
+// A page that is not on screen. Its objects are kept as the scene actions that
+// rebuild it, next to the undo history it had when it was left.
+#[derive(Default)]
+pub struct Page {
+    pub scene: Vec<Action>,
+    pub actions: Vec<Action>,
+    pub redo_actions: Vec<Action>,
+    pub camera: Camera,
+}
+
+impl Page {
+    pub fn from_scene(scene: Vec<Action>) -> Self {
+        Page {
+            scene,
+            ..Default::default()
+        }
+    }
+}
+
+// A page's name and colored tags, like "Lecture 3" or "Homework". They are
+// kept in the page's scene, so they are saved with it.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct PageLabel {
+    pub name: String,
+    pub tags: Vec<PageTag>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct PageTag {
+    pub name: String,
+    pub color: [u8; 4],
+}
+
+impl PageLabel {
+    // Pages without a name go by their number.
+    pub fn title(&self, index: usize) -> String {
+        match self.name.trim().is_empty() {
+            true => format!("Page {}", index + 1),
+            false => self.name.clone(),
+        }
+    }
+
+    pub fn has_tag(&self, tag: &str) -> bool {
+        self.tags.iter().any(|page_tag| page_tag.name == tag)
+    }
+
+    // Whether the title or a tag contains the query, ignoring case.
+    pub fn matches(&self, index: usize, query: &str) -> bool {
+        let query = query.trim().to_lowercase();
+        self.title(index).to_lowercase().contains(&query)
+            || self
+                .tags
+                .iter()
+                .any(|tag| tag.name.to_lowercase().contains(&query))
+    }
+}
+
+// The label a page's scene leaves it with.
+pub fn label(scene: &[Action]) -> PageLabel {
+    scene
+        .iter()
+        .rev()
+        .find_map(|action| match action {
+            Action::Label(edit) => Some(edit.to.clone()),
+            _ => None,
+        })
+        .unwrap_or_default()
+}
+
+// Every tag used on the board once, in the order the pages first use them.
+pub fn tags(labels: &[PageLabel]) -> Vec<PageTag> {
+    let mut tags: Vec<PageTag> = Vec::new();
+    for tag in labels.iter().flat_map(|label| &label.tags) {
+        if !tags.iter().any(|known| known.name == tag.name) {
+            tags.push(tag.clone());
+        }
+    }
+    tags
+}
@@ -0,0 +1,62 @@
+use egui::{collapsing_header::CollapsingState, Context, Id, Ui};
+use serde::{Deserialize, Serialize};
+
+const DEFAULT_POSITION: [f32; 2] = [16.0, 64.0];
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PanelLayout {
+    pub open: bool,
+    pub minimized: bool,
+    pub pinned: bool,
+    pub position: Option<[f32; 2]>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PanelLayouts {
+    pub colors: PanelLayout,
+    pub fonts: PanelLayout,
+    pub stencils: PanelLayout,
+    pub icons: PanelLayout,
+    pub layers: PanelLayout,
+}
+
+impl PanelLayout {
+    pub fn show(
+        &mut self,
+        ctx: &Context,
+        id: &str,
+        title: &str,
+        add_contents: impl FnOnce(&mut Ui),
+    ) {
+        if !self.open {
+            return;
+        }
+
+        let id = Id::new(id);
+        let [x, y] = self.position.unwrap_or(DEFAULT_POSITION);
+        let pinned = &mut self.pinned;
+        let response = egui::Window::new(title)
+            .id(id)
+            .collapsible(true)
+            .default_open(!self.minimized)
+            .resizable(false)
+            .movable(!*pinned)
+            .order(egui::Order::Foreground)
+            .default_pos([x, y])
+            .open(&mut self.open)
+            .show(ctx, |ui| {
+                ui.checkbox(pinned, "Pin");
+                add_contents(ui);
+            });
+
+        if let Some(response) = response {
+            let position = response.response.rect.min;
+            self.position = Some([position.x, position.y]);
+        }
+        if let Some(collapsing) = CollapsingState::load(ctx, id.with("collapsing")) {
+            self.minimized = !collapsing.is_open();
+        }
+    }
+}
@@ -0,0 +1,288 @@
+use crate::{
+    code, diagram, markdown, rgba_to_normalized, screen_to_ndc, svg, Action, CodeBlock, Connector,
+    Rectangle, Stencil, Stroke, Table, TextEntries, Vertex,
+};
+use tao::dpi::PhysicalSize;
+
+const BLOCK_SPACING: f32 = 1.6;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum PasteFormat {
+    Markdown,
+    Csv,
+    BoardJson,
+    Code,
+    Diagram,
+    Svg,
+}
+
+impl PasteFormat {
+    pub const ALL: [PasteFormat; 6] = [
+        PasteFormat::Markdown,
+        PasteFormat::Csv,
+        PasteFormat::BoardJson,
+        PasteFormat::Code,
+        PasteFormat::Diagram,
+        PasteFormat::Svg,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            PasteFormat::Markdown => "Markdown",
+            PasteFormat::Csv => "CSV table",
+            PasteFormat::BoardJson => "Board JSON",
+            PasteFormat::Code => "Code",
+            PasteFormat::Diagram => "Mermaid/DOT diagram",
+            PasteFormat::Svg => "SVG drawing",
+        }
+    }
+}
+
+pub struct PasteSpecial {
+    pub text: String,
+    pub format: PasteFormat,
+    pub language: String,
+    pub position: [f32; 2],
+}
+
+impl PasteSpecial {
+    pub fn new(text: String, position: [f32; 2]) -> Self {
+        let format = detect(&text);
+        PasteSpecial {
+            text,
+            format,
+            language: code::LANGUAGES[0].to_string(),
+            position,
+        }
+    }
+
+    pub fn summary(&self) -> String {
+        match self.format {
+            PasteFormat::Markdown => {
+                format!("{} text blocks", markdown::blocks(&self.text).len())
+            }
+            PasteFormat::Csv => match parse_csv(&self.text) {
+                Ok(rows) => format!(
+                    "{} rows x {} columns",
+                    rows.len(),
+                    rows.iter().map(Vec::len).max().unwrap_or(0)
+                ),
+                Err(err) => format!("Invalid CSV: {}", err),
+            },
+            PasteFormat::BoardJson => match parse_board_json(&self.text) {
+                Ok(actions) => format!("{} objects", actions.len()),
+                Err(err) => format!("Invalid board JSON: {}", err),
+            },
+            PasteFormat::Code => {
+                format!("{} lines of {}", self.text.lines().count(), self.language)
+            }
+            PasteFormat::Diagram => match diagram::parse(&self.text) {
+                Ok(graph) => format!("{} nodes, {} edges", graph.nodes.len(), graph.edges.len()),
+                Err(err) => format!("Invalid diagram: {}", err),
+            },
+            PasteFormat::Svg => match svg::parse(&self.text, [0, 0, 0, 255]) {
+                Ok(drawing) if drawing.unsupported > 0 => format!(
+                    "{} shapes, {} images skipped",
+                    drawing.elements.len(),
+                    drawing.unsupported
+                ),
+                Ok(drawing) => format!("{} shapes", drawing.elements.len()),
+                Err(err) => format!("Invalid SVG: {}", err),
+            },
+        }
+    }
+
+    pub fn to_actions(
+        &self,
+        color: [u8; 4],
+        font_size: i32,
+        size: PhysicalSize<u32>,
+    ) -> Result<Vec<Action>, String> {
+        match self.format {
+            PasteFormat::Markdown => {
+                let mut actions = Vec::new();
+                let [x, mut y] = self.position;
+                for (text, scale) in markdown::blocks(&self.text) {
+                    let size = (font_size as f32 * scale).round() as i32;
+
+                    let mut entry = TextEntries::null(color, size);
+                    entry.text = text;
+                    entry.pending = false;
+                    entry.position = [x, y];
+                    actions.push(Action::Text(entry));
+
+                    y += size as f32 * BLOCK_SPACING;
+                }
+                Ok(actions)
+            }
+            PasteFormat::Csv => {
+                let rows = parse_csv(&self.text).map_err(|err| err.to_string())?;
+                Ok(vec![Action::Table(Table {
+                    position: self.position,
+                    rows,
+                    color,
+                    font_size,
+                    shadow: Default::default(),
+                    layer: Default::default(),
+                    bounds: Default::default(),
+                })])
+            }
+            PasteFormat::BoardJson => parse_board_json(&self.text).map_err(|err| err.to_string()),
+            PasteFormat::Code => Ok(vec![Action::Code(CodeBlock::new(
+                self.position,
+                &self.text,
+                &self.language,
+                font_size,
+            ))]),
+            PasteFormat::Diagram => {
+                let graph = diagram::parse(&self.text)?;
+                let centers = graph.layout(self.position);
+                let stencils = graph.nodes.iter().zip(centers).map(|(node, center)| {
+                    let mut stencil = Stencil::new(node.kind, center, color, font_size);
+                    stencil.label.text = node.label.clone();
+                    Action::Stencil(stencil)
+                });
+                let connectors = graph.edges.iter().map(|edge| {
+                    Action::Connector(Connector {
+                        from: edge.from,
+                        to: edge.to,
+                        color,
+                        font_size,
+                        label: edge.label.clone(),
+                    })
+                });
+                Ok(stencils.chain(connectors).collect())
+            }
+            PasteFormat::Svg => {
+                let drawing = svg::parse(&self.text, color)?;
+                let [x, y] = self.position;
+                let ndc = |point: [f32; 2]| screen_to_ndc(size, point[0] + x, point[1] + y);
+                let actions = drawing.elements.into_iter().map(|element| match element {
+                    svg::Element::Polyline {
+                        points,
+                        color,
+                        width,
+                    } => Action::Stroke(Stroke {
+                        points: points
+                            .into_iter()
+                            .map(|point| Vertex {
+                                position: ndc(point),
+                                color: rgba_to_normalized(color),
+                            })
+                            .collect(),
+                        blend: Default::default(),
+                        brush: Default::default(),
+                        layer: Default::default(),
+                        timestamps: Vec::new(),
+                        width,
+                        lod: Default::default(),
+                    }),
+                    svg::Element::Rect {
+                        min,
+                        max,
+                        color,
+                        filled,
+                    } => Action::Shapes(Rectangle {
+                        first: ndc(min),
+                        last: ndc(max),
+                        color: rgba_to_normalized(color),
+                        shadow: Default::default(),
+                        filled,
+                        layer: Default::default(),
+                    }),
+                    svg::Element::Text {
+                        position,
+                        text,
+                        color,
+                        font_size,
+                    } => {
+                        let mut entry = TextEntries::null(color, font_size.round() as i32);
+                        entry.text = text;
+                        entry.pending = false;
+                        entry.position = [position[0] + x, position[1] + y];
+                        Action::Text(entry)
+                    }
+                });
+                Ok(actions.collect())
+            }
+        }
+    }
+}
+
+pub fn detect(text: &str) -> PasteFormat {
+    if svg::looks_like_svg(text) {
+        PasteFormat::Svg
+    } else if parse_board_json(text).is_ok() {
+        PasteFormat::BoardJson
+    } else if diagram::looks_like_diagram(text) {
+        PasteFormat::Diagram
+    } else if looks_like_csv(text) {
+        PasteFormat::Csv
+    } else {
+        PasteFormat::Markdown
+    }
+}
+
+pub fn parse_board_json(text: &str) -> Result<Vec<Action>, serde_json::Error> {
+    let mut actions = match serde_json::from_str::<Vec<Action>>(text) {
+        Ok(actions) => actions,
+        Err(_) => vec![serde_json::from_str::<Action>(text)?],
+    };
+    sanitize(&mut actions);
+    Ok(actions)
+}
+
+// Keeps only the actions that add content, ready to be replayed onto a board.
+pub fn sanitize(actions: &mut Vec<Action>) {
+    actions.retain(|action| {
+        !matches!(
+            action,
+            Action::Arrange(_)
+                | Action::Restyle(_)
+                | Action::Erase(_)
+                | Action::Move(_)
+                | Action::Group(_)
+                | Action::Resize(_)
+                | Action::Retext(_)
+        )
+    });
+    for action in actions.iter_mut() {
+        match action {
+            Action::Text(text) => text.pending = false,
+            Action::Stencil(stencil) => stencil.label.pending = false,
+            _ => {}
+        }
+    }
+}
+
+fn delimiter(text: &str) -> u8 {
+    if text.contains('\t') {
+        b'\t'
+    } else {
+        b','
+    }
+}
+
+fn looks_like_csv(text: &str) -> bool {
+    let delimiter = delimiter(text) as char;
+    let mut lines = text.lines().filter(|line| !line.trim().is_empty());
+    let Some(first) = lines.next() else {
+        return false;
+    };
+
+    let columns = first.matches(delimiter).count();
+    columns > 0 && lines.all(|line| line.matches(delimiter).count() == columns)
+}
+
+pub fn parse_csv(text: &str) -> Result<Vec<Vec<String>>, csv::Error> {
+    let mut reader = csv::ReaderBuilder::new()
+        .has_headers(false)
+        .flexible(true)
+        .delimiter(delimiter(text))
+        .from_reader(text.as_bytes());
+
+    reader
+        .records()
+        .map(|record| Ok(record?.iter().map(|cell| cell.trim().to_string()).collect()))
+        .collect()
+}
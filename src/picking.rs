@@ -0,0 +1,266 @@
+use egui_wgpu::wgpu::{self, util::DeviceExt};
+
+pub const PICK_RADIUS: u32 = 4;
+const PICK_SIZE: u32 = PICK_RADIUS * 2 + 1;
+const BYTES_PER_ROW: u32 = wgpu::COPY_BYTES_PER_ROW_ALIGNMENT;
+
+#[repr(C)]
+#[derive(Copy, Clone, bytemuck::Pod, bytemuck::Zeroable, Debug)]
+pub struct PickVertex {
+    pub position: [f32; 2],
+    pub id: u32,
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, bytemuck::Pod, bytemuck::Zeroable, Debug)]
+struct PickUniform {
+    offset: [f32; 2],
+    scale: [f32; 2],
+}
+
+pub struct Picker {
+    line_pipeline: wgpu::RenderPipeline,
+    triangle_pipeline: wgpu::RenderPipeline,
+    uniform_buffer: wgpu::Buffer,
+    bind_group: wgpu::BindGroup,
+    texture: wgpu::Texture,
+    readback: wgpu::Buffer,
+}
+
+impl Picker {
+    pub fn new(device: &wgpu::Device) -> Self {
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("picking shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("shaders/picking.wgsl").into()),
+        });
+
+        let uniform_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Picking Uniform Buffer"),
+            contents: bytemuck::bytes_of(&PickUniform {
+                offset: [0.0, 0.0],
+                scale: [1.0, 1.0],
+            }),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Picking Bind Group Layout"),
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::VERTEX,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            }],
+        });
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Picking Bind Group"),
+            layout: &bind_group_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: uniform_buffer.as_entire_binding(),
+            }],
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Picking Pipeline Layout"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        let pipeline = |topology| {
+            device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                label: Some("picking pipeline"),
+                layout: Some(&pipeline_layout),
+                vertex: wgpu::VertexState {
+                    module: &shader,
+                    entry_point: Some("pick_vs"),
+                    compilation_options: wgpu::PipelineCompilationOptions::default(),
+                    buffers: &[wgpu::VertexBufferLayout {
+                        array_stride: size_of::<PickVertex>() as wgpu::BufferAddress,
+                        step_mode: wgpu::VertexStepMode::Vertex,
+                        attributes: &wgpu::vertex_attr_array![
+                            0 => Float32x2,
+                            1 => Uint32
+                        ],
+                    }],
+                },
+                primitive: wgpu::PrimitiveState {
+                    topology,
+                    ..Default::default()
+                },
+                depth_stencil: None,
+                multisample: wgpu::MultisampleState::default(),
+                fragment: Some(wgpu::FragmentState {
+                    module: &shader,
+                    entry_point: Some("pick_fs"),
+                    compilation_options: wgpu::PipelineCompilationOptions::default(),
+                    targets: &[Some(wgpu::ColorTargetState {
+                        format: wgpu::TextureFormat::R32Uint,
+                        blend: None,
+                        write_mask: wgpu::ColorWrites::ALL,
+                    })],
+                }),
+                multiview: None,
+                cache: None,
+            })
+        };
+
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Picking Texture"),
+            size: wgpu::Extent3d {
+                width: PICK_SIZE,
+                height: PICK_SIZE,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::R32Uint,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+            view_formats: &[],
+        });
+
+        let readback = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Picking Readback Buffer"),
+            size: (BYTES_PER_ROW * PICK_SIZE) as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        Picker {
+            line_pipeline: pipeline(wgpu::PrimitiveTopology::LineList),
+            triangle_pipeline: pipeline(wgpu::PrimitiveTopology::TriangleList),
+            uniform_buffer,
+            bind_group,
+            texture,
+            readback,
+        }
+    }
+
+    // Renders the candidates into a small region around the cursor and returns the id
+    // closest to its center.
+    pub fn pick(
+        &self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        window_size: [f32; 2],
+        cursor: [f32; 2],
+        triangles: &[PickVertex],
+        lines: &[PickVertex],
+    ) -> Option<u32> {
+        if triangles.is_empty() && lines.is_empty() {
+            return None;
+        }
+
+        let uniform = PickUniform {
+            offset: [
+                cursor[0] / window_size[0] * 2.0 - 1.0,
+                -(cursor[1] / window_size[1] * 2.0 - 1.0),
+            ],
+            scale: [
+                window_size[0] / PICK_SIZE as f32,
+                window_size[1] / PICK_SIZE as f32,
+            ],
+        };
+        queue.write_buffer(&self.uniform_buffer, 0, bytemuck::bytes_of(&uniform));
+
+        let triangle_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Picking Triangle Buffer"),
+            contents: bytemuck::cast_slice(triangles),
+            usage: wgpu::BufferUsages::VERTEX,
+        });
+        let line_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Picking Line Buffer"),
+            contents: bytemuck::cast_slice(lines),
+            usage: wgpu::BufferUsages::VERTEX,
+        });
+
+        let view = self
+            .texture
+            .create_view(&wgpu::TextureViewDescriptor::default());
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Picking Encoder"),
+        });
+
+        {
+            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Picking Render Pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: &view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+                        store: wgpu::StoreOp::Store,
+                    },
+                })],
+                depth_stencil_attachment: None,
+                timestamp_writes: None,
+                occlusion_query_set: None,
+            });
+
+            render_pass.set_bind_group(0, &self.bind_group, &[]);
+            if !triangles.is_empty() {
+                render_pass.set_pipeline(&self.triangle_pipeline);
+                render_pass.set_vertex_buffer(0, triangle_buffer.slice(..));
+                render_pass.draw(0..triangles.len() as u32, 0..1);
+            }
+            if !lines.is_empty() {
+                render_pass.set_pipeline(&self.line_pipeline);
+                render_pass.set_vertex_buffer(0, line_buffer.slice(..));
+                render_pass.draw(0..lines.len() as u32, 0..1);
+            }
+        }
+
+        encoder.copy_texture_to_buffer(
+            wgpu::ImageCopyTexture {
+                texture: &self.texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d::ZERO,
+                aspect: wgpu::TextureAspect::All,
+            },
+            wgpu::ImageCopyBuffer {
+                buffer: &self.readback,
+                layout: wgpu::ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: Some(BYTES_PER_ROW),
+                    rows_per_image: Some(PICK_SIZE),
+                },
+            },
+            wgpu::Extent3d {
+                width: PICK_SIZE,
+                height: PICK_SIZE,
+                depth_or_array_layers: 1,
+            },
+        );
+        queue.submit(std::iter::once(encoder.finish()));
+
+        let slice = self.readback.slice(..);
+        slice.map_async(wgpu::MapMode::Read, |_| {});
+        device.poll(wgpu::Maintain::Wait);
+
+        let mut closest = None;
+        {
+            let data = slice.get_mapped_range();
+            let mut best_distance = u32::MAX;
+            for y in 0..PICK_SIZE {
+                let row = (y * BYTES_PER_ROW) as usize;
+                for x in 0..PICK_SIZE {
+                    let offset = row + x as usize * 4;
+                    let id = u32::from_ne_bytes(data[offset..offset + 4].try_into().unwrap());
+                    let distance = x.abs_diff(PICK_RADIUS).pow(2) + y.abs_diff(PICK_RADIUS).pow(2);
+                    if id != 0 && distance < best_distance {
+                        best_distance = distance;
+                        closest = Some(id);
+                    }
+                }
+            }
+        }
+        self.readback.unmap();
+
+        closest
+    }
+}
@@ -0,0 +1,240 @@
+use crate::{
+    export::{gif, html, pdf, preview::Preview, svg},
+    ndc_to_screen, paste, screen_to_ndc, Action,
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+use tao::dpi::PhysicalSize;
+
+pub const EXTENSION: &str = "wb";
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ProjectMode {
+    Save,
+    Open,
+    ExportPdf,
+    ExportSvg,
+    ExportHtml,
+    ExportGif,
+}
+
+#[derive(Serialize, Deserialize)]
+struct ProjectFile {
+    // The board size strokes and rectangles were saved at, since they are kept
+    // in NDC of it. Older files leave it out and open unchanged.
+    #[serde(default)]
+    size: Option<[u32; 2]>,
+    pages: Vec<Vec<Action>>,
+}
+
+pub struct ProjectDialog {
+    pub mode: ProjectMode,
+    pub path: String,
+    pub error: Option<String>,
+    // The page as the export will look, for the export modes.
+    pub preview: Option<Preview>,
+}
+
+impl ProjectDialog {
+    pub fn new(mode: ProjectMode, current: Option<&Path>) -> Self {
+        let path = current
+            .map(Path::to_path_buf)
+            .or_else(default_path)
+            .map(|path| match mode {
+                ProjectMode::ExportPdf => path.with_extension(pdf::EXTENSION),
+                ProjectMode::ExportSvg => path.with_extension(svg::EXTENSION),
+                ProjectMode::ExportHtml => path.with_extension(html::EXTENSION),
+                ProjectMode::ExportGif => path.with_extension(gif::EXTENSION),
+                _ => path,
+            })
+            .map(|path| path.display().to_string())
+            .unwrap_or_default();
+        ProjectDialog {
+            mode,
+            path,
+            error: None,
+            preview: None,
+        }
+    }
+
+    pub fn is_export(&self) -> bool {
+        !matches!(self.mode, ProjectMode::Save | ProjectMode::Open)
+    }
+
+    pub fn title(&self) -> &'static str {
+        match self.mode {
+            ProjectMode::Save => "Save board",
+            ProjectMode::Open => "Open board",
+            ProjectMode::ExportPdf => "Export PDF",
+            ProjectMode::ExportSvg => "Export page as SVG",
+            ProjectMode::ExportHtml => "Export HTML viewer",
+            ProjectMode::ExportGif => "Export flipbook GIF",
+        }
+    }
+}
+
+pub fn is_project(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case(EXTENSION))
+}
+
+fn default_path() -> Option<PathBuf> {
+    dirs::document_dir()
+        .or_else(dirs::home_dir)
+        .map(|dir| dir.join(format!("board.{}", EXTENSION)))
+}
+
+pub fn save(
+    path: &Path,
+    pages: Vec<Vec<Action>>,
+    size: PhysicalSize<u32>,
+) -> Result<PathBuf, String> {
+    let path = if path.extension().is_none() {
+        path.with_extension(EXTENSION)
+    } else {
+        path.to_path_buf()
+    };
+    let json = to_json(pages, size)?;
+    if let Some(parent) = path.parent() {
+        let _ = fs::create_dir_all(parent);
+    }
+    fs::write(&path, json).map_err(|err| err.to_string())?;
+    Ok(path)
+}
+
+// Pages come back laid out for a board of `size`.
+pub fn load(path: &Path, size: PhysicalSize<u32>) -> Result<Vec<Vec<Action>>, String> {
+    parse(
+        &fs::read_to_string(path).map_err(|err| err.to_string())?,
+        size,
+    )
+}
+
+pub fn to_json(pages: Vec<Vec<Action>>, size: PhysicalSize<u32>) -> Result<String, String> {
+    let size = Some([size.width, size.height]);
+    serde_json::to_string_pretty(&ProjectFile { size, pages }).map_err(|err| err.to_string())
+}
+
+// Older files hold a single page as a bare list of actions.
+pub fn parse(text: &str, size: PhysicalSize<u32>) -> Result<Vec<Vec<Action>>, String> {
+    match serde_json::from_str::<ProjectFile>(text) {
+        Ok(mut project) => {
+            let saved = project
+                .size
+                .map(|[width, height]| PhysicalSize::new(width, height));
+            for page in &mut project.pages {
+                paste::sanitize(page);
+                if let Some(saved) = saved {
+                    rescale(page, saved, size);
+                }
+            }
+            if project.pages.is_empty() {
+                project.pages.push(Vec::new());
+            }
+            Ok(project.pages)
+        }
+        Err(_) => paste::parse_board_json(text)
+            .map(|actions| vec![actions])
+            .map_err(|err| err.to_string()),
+    }
+}
+
+// Moves strokes and rectangles from NDC of one board size to another, so they
+// keep their board pixels next to the texts and pictures around them.
+fn rescale(page: &mut [Action], from: PhysicalSize<u32>, to: PhysicalSize<u32>) {
+    if from == to || [from.width, from.height, to.width, to.height].contains(&0) {
+        return;
+    }
+    let convert = |position: &mut [f32; 2]| {
+        let [x, y] = ndc_to_screen(from, *position);
+        *position = screen_to_ndc(to, x, y);
+    };
+    for action in page {
+        match action {
+            Action::Stroke(stroke) => {
+                for point in &mut stroke.points {
+                    convert(&mut point.position);
+                }
+            }
+            Action::Shapes(rectangle) => {
+                convert(&mut rectangle.first);
+                convert(&mut rectangle.last);
+            }
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{BoardImage, ImageAdjust, ObjectMove, ObjectRef, Stroke, Vertex};
+
+    #[test]
+    fn strokes_stay_beside_pictures_in_another_window_size() {
+        let saved = PhysicalSize::new(800, 600);
+        let corner = [100.0, 150.0];
+        let stroke = Stroke {
+            points: vec![Vertex {
+                position: screen_to_ndc(saved, corner[0], corner[1]),
+                color: [0.0, 0.0, 0.0, 1.0],
+            }],
+            ..Stroke::default()
+        };
+        let image = BoardImage {
+            first: corner,
+            last: [200.0, 250.0],
+            data: Vec::new(),
+            layer: 0,
+            adjust: ImageAdjust::default(),
+            texture: None,
+        };
+        let json = to_json(
+            vec![vec![Action::Stroke(stroke), Action::Image(image)]],
+            saved,
+        )
+        .unwrap();
+
+        for size in [saved, PhysicalSize::new(1600, 900)] {
+            let pages = parse(&json, size).unwrap();
+            let [Action::Stroke(stroke), Action::Image(image)] = &pages[0][..] else {
+                panic!("The page changed on the way through the file");
+            };
+            let point = ndc_to_screen(size, stroke.points[0].position).map(f32::round);
+            assert_eq!(point, image.first);
+        }
+    }
+
+    #[test]
+    fn copied_board_json_opens_as_one_page() {
+        let size = PhysicalSize::new(800, 600);
+        let stroke = Action::Stroke(Stroke::default());
+        let list = serde_json::to_string(&vec![stroke.clone(), stroke.clone()]).unwrap();
+        let pages = parse(&list, size).unwrap();
+        assert_eq!(pages.len(), 1);
+        assert_eq!(pages[0].len(), 2);
+
+        let single = serde_json::to_string(&stroke).unwrap();
+        assert_eq!(parse(&single, size).unwrap()[0].len(), 1);
+
+        // Only what adds content is kept from a copied history.
+        let moved = Action::Move(ObjectMove {
+            objects: vec![ObjectRef::Stroke(0)],
+            delta: [1.0, 1.0],
+        });
+        let history = serde_json::to_string(&vec![stroke, moved]).unwrap();
+        assert_eq!(parse(&history, size).unwrap()[0].len(), 1);
+    }
+
+    #[test]
+    fn empty_and_unreadable_files() {
+        let size = PhysicalSize::new(800, 600);
+        let pages = parse(r#"{"pages": []}"#, size).unwrap();
+        assert_eq!(pages.len(), 1);
+        assert!(pages[0].is_empty());
+        assert!(parse("not a board", size).is_err());
+    }
+}
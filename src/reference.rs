@@ -0,0 +1,44 @@
+use crate::images;
+use egui::{Color32, TextureHandle, TextureOptions};
+
+const DEFAULT_WIDTH: f32 = 280.0;
+// Below this the picture could not be made out at all.
+const MIN_OPACITY: f32 = 0.1;
+
+// A picture from the board pinned in a floating panel that stays put while
+// the board pans and zooms, handy for copying a diagram by hand.
+pub struct Reference {
+    texture: TextureHandle,
+    opacity: f32,
+}
+
+impl Reference {
+    pub fn new(context: &egui::Context, data: &[u8]) -> Option<Self> {
+        let image = images::color_image(data)?;
+        Some(Reference {
+            texture: context.load_texture("Reference", image, TextureOptions::LINEAR),
+            opacity: 1.0,
+        })
+    }
+
+    // Returns false once the panel has been closed.
+    pub fn show(&mut self, context: &egui::Context) -> bool {
+        let mut open = true;
+        let frame = egui::Frame::window(&context.style()).multiply_with_opacity(self.opacity);
+        egui::Window::new("Reference")
+            .order(egui::Order::Foreground)
+            .default_width(DEFAULT_WIDTH)
+            .frame(frame)
+            .open(&mut open)
+            .show(context, |ui| {
+                ui.add(egui::Slider::new(&mut self.opacity, MIN_OPACITY..=1.0).text("Opacity"));
+                let size = self.texture.size_vec2();
+                let scale = ui.available_width() / size.x;
+                ui.add(
+                    egui::Image::new((self.texture.id(), size * scale))
+                        .tint(Color32::WHITE.gamma_multiply(self.opacity)),
+                );
+            });
+        open
+    }
+}
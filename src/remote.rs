@@ -0,0 +1,149 @@
+use serde::Deserialize;
+use std::{
+    io::{BufRead, BufReader, Write},
+    net::{TcpListener, TcpStream},
+    sync::mpsc::{self, Receiver, Sender},
+    thread,
+    time::Duration,
+};
+
+pub const POLL_INTERVAL: Duration = Duration::from_millis(200);
+const MAX_BODY: usize = 1024 * 1024;
+// How long a client may take to send its request before it is dropped.
+const READ_TIMEOUT: Duration = Duration::from_secs(5);
+
+// The JSON body of a POST, for example
+// `{"command": "add_text", "text": "Hello", "x": 100, "y": 80}`.
+#[derive(Deserialize)]
+#[serde(tag = "command", rename_all = "snake_case")]
+enum Request {
+    AddText { text: String, x: f32, y: f32 },
+    AddImage { url: String, x: f32, y: f32 },
+    ClearPage,
+}
+
+// What the board is asked to do, with images already downloaded. Positions
+// are in board pixels.
+pub enum Edit {
+    Text { text: String, position: [f32; 2] },
+    Image { data: Vec<u8>, center: [f32; 2] },
+    ClearPage,
+}
+
+// Listens on localhost only; every request is answered on its own thread, so
+// a slow client or image download holds up nobody else, and its edit is
+// handed to the board through a channel.
+pub struct Server {
+    edits: Receiver<Edit>,
+}
+
+impl Server {
+    pub fn start(port: u16) -> Result<Self, String> {
+        let listener = TcpListener::bind(("127.0.0.1", port))
+            .map_err(|err| format!("Unable to listen on port {}: {}", port, err))?;
+        let (sender, edits) = mpsc::channel();
+        thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                let sender = sender.clone();
+                thread::spawn(move || handle(stream, &sender));
+            }
+        });
+        Ok(Server { edits })
+    }
+
+    pub fn poll(&self) -> Vec<Edit> {
+        self.edits.try_iter().collect()
+    }
+}
+
+fn handle(stream: TcpStream, sender: &Sender<Edit>) {
+    let _ = stream.set_read_timeout(Some(READ_TIMEOUT));
+    let mut reader = BufReader::new(&stream);
+    let (status, message) = match read_edit(&mut reader) {
+        Ok(edit) => match sender.send(edit) {
+            Ok(()) => ("200 OK", "ok".to_string()),
+            Err(_) => ("503 Service Unavailable", "The board is closed".to_string()),
+        },
+        Err((status, message)) => (status, message),
+    };
+    let body = serde_json::json!({ "status": message }).to_string();
+    let _ = write!(
+        &stream,
+        "HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        body.len(),
+        body
+    );
+}
+
+fn read_edit(reader: &mut impl BufRead) -> Result<Edit, (&'static str, String)> {
+    let bad_request = |message: String| ("400 Bad Request", message);
+    let mut line = String::new();
+    reader
+        .read_line(&mut line)
+        .map_err(|err| bad_request(err.to_string()))?;
+    if !line.starts_with("POST ") {
+        return Err((
+            "405 Method Not Allowed",
+            "Send commands with POST".to_string(),
+        ));
+    }
+
+    let mut length = 0;
+    let mut json = false;
+    loop {
+        line.clear();
+        reader
+            .read_line(&mut line)
+            .map_err(|err| bad_request(err.to_string()))?;
+        let header = line.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            if name.eq_ignore_ascii_case("content-length") {
+                length = value.trim().parse().unwrap_or(0);
+            } else if name.eq_ignore_ascii_case("content-type") {
+                // A browser page can only send JSON after asking first, so
+                // other sites cannot post to the board behind its back.
+                let media = value.split(';').next().unwrap_or_default();
+                json = media.trim().eq_ignore_ascii_case("application/json");
+            }
+        }
+    }
+    if !json {
+        return Err((
+            "415 Unsupported Media Type",
+            "Send commands as application/json".to_string(),
+        ));
+    }
+    if length > MAX_BODY {
+        return Err(("413 Payload Too Large", "The body is too large".to_string()));
+    }
+    let mut body = vec![0; length];
+    reader
+        .read_exact(&mut body)
+        .map_err(|err| bad_request(err.to_string()))?;
+
+    match serde_json::from_slice(&body).map_err(|err| bad_request(err.to_string()))? {
+        Request::AddText { text, x, y } => Ok(Edit::Text {
+            text,
+            position: [x, y],
+        }),
+        Request::AddImage { url, x, y } => {
+            let response = ehttp::fetch_blocking(&ehttp::Request::get(&url))
+                .map_err(|err| ("502 Bad Gateway", err))?;
+            if !response.ok {
+                return Err((
+                    "502 Bad Gateway",
+                    format!("{} answered {}", url, response.status),
+                ));
+            }
+            Ok(Edit::Image {
+                data: response.bytes,
+                center: [x, y],
+            })
+        }
+        Request::ClearPage => Ok(Edit::ClearPage),
+    }
+}
@@ -0,0 +1,1714 @@
+#[cfg(feature = "remote-api")]
+use crate::remote;
+use crate::{
+    animation::{Animation, AnimationClock},
+    board::Board,
+    bridge::Bridge,
+    buffer_bounds,
+    camera::Camera,
+    caret_vertices, code, code_buffer,
+    commands::Keymap,
+    contrasting_color, crash, create_canvas,
+    fonts::{FontLibrary, DEFAULT_FONT},
+    gestures::{EdgeSwipe, FlickTracker, PinchTracker},
+    icons,
+    images::{self, ImageRenderer},
+    ime_position, markdown_buffer, ndc_to_screen, onion,
+    picking::Picker,
+    points_bounds, project, rgba_to_normalized, rounded_rect_vertices, screen_to_ndc,
+    select_adapter,
+    settings::{DeviceTool, Settings},
+    split::{self, Drawing},
+    text_buffer,
+    tiles::TileRange,
+    BlendMode, Brush, Damage, LayerDraw, Rect, Rectangle, ShadowVertex, Stroke, TextDecoration,
+    Vertex, WindowState, ARROW_ANGLE, ARROW_SIZE, CHIP_ALPHA, CHIP_PADDING, CODE_BLOCK_PADDING,
+    CODE_BORDER_COLOR, DEFAULT_BRUSH_WIDTH, HALO_OFFSETS, LINE_HEIGHT, TABLE_CELL_PADDING,
+    TEXT_LIFT,
+};
+use egui::{include_image, RawInput};
+use egui_wgpu::{Renderer, ScreenDescriptor};
+use glyphon::{
+    Cache, Color, FontSystem, SwashCache, TextArea, TextAtlas, TextBounds, TextRenderer, Viewport,
+};
+use std::{
+    borrow::{BorrowMut, Cow},
+    collections::{HashMap, HashSet},
+    sync::Arc,
+    time::Duration,
+};
+use tao::{
+    dpi::{PhysicalPosition, PhysicalSize},
+    window::Window,
+};
+use wgpu::{
+    self, util::DeviceExt, vertex_attr_array, CompositeAlphaMode, DeviceDescriptor, FragmentState,
+    Instance, InstanceDescriptor, MultisampleState, PipelineCompilationOptions, PresentMode,
+    PrimitiveState, RequestAdapterOptions, ShaderModuleDescriptor, StoreOp, SurfaceConfiguration,
+    TextureFormat, TextureUsages, VertexBufferLayout,
+};
+
+impl WindowState<'_> {
+    pub(crate) async fn new(window: Arc<Window>, settings: Settings) -> Self {
+        let physical_size = window.inner_size();
+        let scale_factor = window.scale_factor();
+
+        let mut launch = settings.clone();
+        launch.apply_args(std::env::args().skip(1));
+
+        #[cfg(not(feature = "remote-api"))]
+        if launch.api_port.is_some() {
+            crash::log("Built without the remote-api feature, the API port is ignored".to_string());
+        }
+
+        let backends = launch.backends();
+        let instance = Instance::new(InstanceDescriptor {
+            backends,
+            ..Default::default()
+        });
+
+        let surface = instance
+            .create_surface(window.clone())
+            .expect("Create surface");
+
+        let adapters: Vec<_> = instance
+            .enumerate_adapters(backends)
+            .into_iter()
+            .filter(|adapter| adapter.is_surface_supported(&surface))
+            .collect();
+        let adapter_infos = adapters.iter().map(|adapter| adapter.get_info()).collect();
+
+        let selected = launch
+            .adapter
+            .as_deref()
+            .and_then(|selector| select_adapter(adapters, selector));
+        let adapter = match selected {
+            Some(adapter) => adapter,
+            None => instance
+                .request_adapter(&RequestAdapterOptions {
+                    compatible_surface: Some(&surface),
+                    ..Default::default()
+                })
+                .await
+                .unwrap(),
+        };
+        let adapter_info = adapter.get_info();
+        crash::set_adapter(&adapter_info);
+        let (device, queue) = adapter
+            .request_device(&DeviceDescriptor::default(), None)
+            .await
+            .unwrap();
+
+        let swapchain_format = TextureFormat::Bgra8UnormSrgb;
+        let partial_redraw = surface
+            .get_capabilities(&adapter)
+            .usages
+            .contains(TextureUsages::COPY_DST);
+        let surface_config = SurfaceConfiguration {
+            usage: if partial_redraw {
+                TextureUsages::RENDER_ATTACHMENT | TextureUsages::COPY_DST
+            } else {
+                TextureUsages::RENDER_ATTACHMENT
+            },
+            format: swapchain_format,
+            width: physical_size.width,
+            height: (physical_size.height as f32 * 0.8) as u32,
+            present_mode: PresentMode::Fifo,
+            alpha_mode: CompositeAlphaMode::Opaque,
+            view_formats: vec![],
+            desired_maximum_frame_latency: 2,
+        };
+        let egui_ctx = egui::Context::default();
+        let egui_renderer = Renderer::new(&device, surface_config.format, None, 1, true);
+        let raw_input = RawInput::default();
+        egui_extras::install_image_loaders(&egui_ctx);
+        surface.configure(&device, &surface_config);
+
+        let mut font_system = FontSystem::new();
+        font_system
+            .db_mut()
+            .load_font_data(include_bytes!("assets/vazir.ttf").to_vec());
+        let fonts = FontLibrary::new(settings.fonts_dir(), &mut font_system);
+        let swash_cache = SwashCache::new();
+        let cache = Cache::new(&device);
+        let viewport = Viewport::new(&device, &cache);
+        let mut atlas = TextAtlas::new(&device, &queue, &cache, swapchain_format);
+        let text_renderer =
+            TextRenderer::new(&mut atlas, &device, wgpu::MultisampleState::default(), None);
+
+        let shader = device.create_shader_module(egui_wgpu::wgpu::ShaderModuleDescriptor {
+            label: Some("Shader"),
+            source: egui_wgpu::wgpu::ShaderSource::Wgsl(include_str!("shader.wgsl").into()),
+        });
+
+        let camera_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Camera Uniform Buffer"),
+            contents: bytemuck::bytes_of(&Camera::default().uniform(physical_size)),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+        let camera_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Camera Bind Group Layout"),
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::VERTEX,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            }],
+        });
+        let camera_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Camera Bind Group"),
+            layout: &camera_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: camera_buffer.as_entire_binding(),
+            }],
+        });
+        // The right half of a split view has a camera of its own.
+        let split_camera_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Split Camera Uniform Buffer"),
+            contents: bytemuck::bytes_of(&Camera::default().uniform(physical_size)),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+        let split_camera_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Split Camera Bind Group"),
+            layout: &camera_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: split_camera_buffer.as_entire_binding(),
+            }],
+        });
+
+        let pipeline_layout =
+            device.create_pipeline_layout(&egui_wgpu::wgpu::PipelineLayoutDescriptor {
+                label: Some("Pipeline Layout"),
+                bind_group_layouts: &[&camera_layout],
+                push_constant_ranges: &[],
+            });
+
+        let shader_shape = device.create_shader_module(ShaderModuleDescriptor {
+            label: Some("rect shader"),
+            source: egui_wgpu::wgpu::ShaderSource::Wgsl(include_str!("shaders/shape.wgsl").into()),
+        });
+        let rectangle_shader =
+            device.create_render_pipeline(&egui_wgpu::wgpu::RenderPipelineDescriptor {
+                label: Some("rect pipline"),
+                layout: Some(&pipeline_layout),
+                vertex: egui_wgpu::wgpu::VertexState {
+                    module: &shader_shape,
+                    entry_point: Some("rectangle_vs"),
+                    compilation_options: PipelineCompilationOptions::default(),
+                    buffers: &[VertexBufferLayout {
+                        array_stride: size_of::<Vertex>() as egui_wgpu::wgpu::BufferAddress,
+                        step_mode: egui_wgpu::wgpu::VertexStepMode::Vertex,
+                        attributes: &[
+                            egui_wgpu::wgpu::VertexAttribute {
+                                format: egui_wgpu::wgpu::VertexFormat::Float32x2,
+                                offset: 0,
+                                shader_location: 0,
+                            },
+                            egui_wgpu::wgpu::VertexAttribute {
+                                format: egui_wgpu::wgpu::VertexFormat::Float32x4,
+                                offset: std::mem::size_of::<[f32; 2]>()
+                                    as egui_wgpu::wgpu::BufferAddress,
+                                shader_location: 1,
+                            },
+                        ],
+                    }],
+                },
+                primitive: PrimitiveState {
+                    topology: egui_wgpu::wgpu::PrimitiveTopology::LineList,
+                    strip_index_format: None,
+                    ..Default::default()
+                },
+                depth_stencil: None,
+                multisample: MultisampleState::default(),
+                fragment: Some(FragmentState {
+                    module: &shader_shape,
+                    entry_point: Some("fs_main"),
+                    compilation_options: PipelineCompilationOptions::default(),
+                    targets: &[Some(egui_wgpu::wgpu::ColorTargetState {
+                        format: surface_config.format,
+                        blend: Some(egui_wgpu::wgpu::BlendState::ALPHA_BLENDING),
+                        write_mask: egui_wgpu::wgpu::ColorWrites::ALL,
+                    })],
+                }),
+                multiview: None,
+                cache: None,
+            });
+
+        let fill_pipeline =
+            device.create_render_pipeline(&egui_wgpu::wgpu::RenderPipelineDescriptor {
+                label: Some("fill pipeline"),
+                layout: Some(&pipeline_layout),
+                vertex: egui_wgpu::wgpu::VertexState {
+                    module: &shader_shape,
+                    entry_point: Some("triangle_vs"),
+                    compilation_options: PipelineCompilationOptions::default(),
+                    buffers: &[VertexBufferLayout {
+                        array_stride: size_of::<Vertex>() as egui_wgpu::wgpu::BufferAddress,
+                        step_mode: egui_wgpu::wgpu::VertexStepMode::Vertex,
+                        attributes: &vertex_attr_array![
+                            0 => Float32x2,
+                            1 => Float32x4
+                        ],
+                    }],
+                },
+                primitive: PrimitiveState {
+                    topology: egui_wgpu::wgpu::PrimitiveTopology::TriangleList,
+                    strip_index_format: None,
+                    ..Default::default()
+                },
+                depth_stencil: None,
+                multisample: MultisampleState::default(),
+                fragment: Some(FragmentState {
+                    module: &shader_shape,
+                    entry_point: Some("fs_main"),
+                    compilation_options: PipelineCompilationOptions::default(),
+                    targets: &[Some(egui_wgpu::wgpu::ColorTargetState {
+                        format: surface_config.format,
+                        blend: Some(egui_wgpu::wgpu::BlendState::ALPHA_BLENDING),
+                        write_mask: egui_wgpu::wgpu::ColorWrites::ALL,
+                    })],
+                }),
+                multiview: None,
+                cache: None,
+            });
+
+        let shader_shadow = device.create_shader_module(ShaderModuleDescriptor {
+            label: Some("shadow shader"),
+            source: egui_wgpu::wgpu::ShaderSource::Wgsl(include_str!("shaders/shadow.wgsl").into()),
+        });
+        let shadow_pipeline =
+            device.create_render_pipeline(&egui_wgpu::wgpu::RenderPipelineDescriptor {
+                label: Some("shadow pipeline"),
+                layout: Some(&pipeline_layout),
+                vertex: egui_wgpu::wgpu::VertexState {
+                    module: &shader_shadow,
+                    entry_point: Some("shadow_vs"),
+                    compilation_options: PipelineCompilationOptions::default(),
+                    buffers: &[VertexBufferLayout {
+                        array_stride: size_of::<ShadowVertex>() as egui_wgpu::wgpu::BufferAddress,
+                        step_mode: egui_wgpu::wgpu::VertexStepMode::Vertex,
+                        attributes: &vertex_attr_array![
+                            0 => Float32x2,
+                            1 => Float32x2,
+                            2 => Float32x2,
+                            3 => Float32,
+                            4 => Float32x4
+                        ],
+                    }],
+                },
+                primitive: PrimitiveState {
+                    topology: egui_wgpu::wgpu::PrimitiveTopology::TriangleList,
+                    strip_index_format: None,
+                    ..Default::default()
+                },
+                depth_stencil: None,
+                multisample: MultisampleState::default(),
+                fragment: Some(FragmentState {
+                    module: &shader_shadow,
+                    entry_point: Some("shadow_fs"),
+                    compilation_options: PipelineCompilationOptions::default(),
+                    targets: &[Some(egui_wgpu::wgpu::ColorTargetState {
+                        format: surface_config.format,
+                        blend: Some(egui_wgpu::wgpu::BlendState::ALPHA_BLENDING),
+                        write_mask: egui_wgpu::wgpu::ColorWrites::ALL,
+                    })],
+                }),
+                multiview: None,
+                cache: None,
+            });
+
+        let stroke_pipelines = BlendMode::ALL
+            .map(|blend_mode| {
+                device.create_render_pipeline(&egui_wgpu::wgpu::RenderPipelineDescriptor {
+                    label: Some("Render Pipeline"),
+                    layout: Some(&pipeline_layout),
+                    vertex: egui_wgpu::wgpu::VertexState {
+                        module: &shader,
+                        entry_point: Some("vs_main"),
+                        buffers: &[egui_wgpu::wgpu::VertexBufferLayout {
+                            array_stride: std::mem::size_of::<Vertex>()
+                                as egui_wgpu::wgpu::BufferAddress,
+                            step_mode: egui_wgpu::wgpu::VertexStepMode::Vertex,
+                            attributes: &vertex_attr_array![
+                                0 => Float32x2,
+                                1 => Float32x4
+                            ],
+                        }],
+                        compilation_options: PipelineCompilationOptions::default(),
+                    },
+                    fragment: Some(egui_wgpu::wgpu::FragmentState {
+                        module: &shader,
+                        entry_point: Some("fs_main"),
+                        targets: &[Some(egui_wgpu::wgpu::ColorTargetState {
+                            format: surface_config.format,
+                            blend: Some(blend_mode.blend_state()),
+                            write_mask: egui_wgpu::wgpu::ColorWrites::ALL,
+                        })],
+                        compilation_options: PipelineCompilationOptions::default(),
+                    }),
+                    primitive: egui_wgpu::wgpu::PrimitiveState {
+                        topology: egui_wgpu::wgpu::PrimitiveTopology::TriangleList,
+                        strip_index_format: None,
+                        ..Default::default()
+                    },
+                    depth_stencil: None,
+                    multisample: egui_wgpu::wgpu::MultisampleState::default(),
+                    multiview: None,
+                    cache: None,
+                })
+            })
+            .into();
+
+        let picker = Picker::new(&device);
+        let image_renderer = ImageRenderer::new(&device, &camera_layout, surface_config.format);
+        let canvas =
+            partial_redraw.then(|| create_canvas(&device, &surface_config, TextureUsages::empty()));
+        let mut board = Board::new(physical_size);
+        board.history_max_entries = settings.history_max_entries;
+        board.history_max_bytes = settings.history_max_bytes;
+        let mut render_self = Self {
+            device,
+            board,
+            dragging_stencil: None,
+            icons: icons::library(&settings.asset_folders),
+            icon_query: String::new(),
+            new_asset_folder: String::new(),
+            dragging_icon: None,
+            object_lines: Vec::new(),
+            object_fills: Vec::new(),
+            paste_special: None,
+            project_dialog: None,
+            project_path: None,
+            last_cursor_position: PhysicalPosition::new(0.0, 0.0),
+            queue,
+            scale_factor,
+            surface,
+            pressed_keys: HashSet::new(),
+            surface_config,
+            font_system,
+            fonts,
+            font_family: DEFAULT_FONT.to_string(),
+            new_fonts_dir: String::new(),
+            font_size: 16,
+            swash_cache,
+            viewport,
+            atlas,
+            text_renderer,
+            create_rect: false,
+            redacting: false,
+            held_shape: None,
+            bucket: false,
+            erasing: false,
+            eraser_pressure: 1.0,
+            previous_tool: DeviceTool::Pen,
+            selecting: false,
+            selection: Vec::new(),
+            pasted_image: None,
+            ocr: None,
+            bridge: settings
+                .bridge
+                .osc_port
+                .and_then(|port| Bridge::start(port).map_err(crash::log).ok()),
+            #[cfg(feature = "remote-api")]
+            remote: launch
+                .api_port
+                .and_then(|port| remote::Server::start(port).map_err(crash::log).ok()),
+            select_drag: None,
+            redaction_vertices: Vec::new(),
+            window,
+            size: physical_size,
+            mouse_pressed: false,
+            stroke_pipelines,
+            stroke_buffers: Vec::new(),
+            layer_draws: Vec::new(),
+            shape_vertices: Vec::new(),
+            image_renderer,
+            image_vertices: Vec::new(),
+            image_textures: Vec::new(),
+            canvas,
+            camera_buffer,
+            camera_bind_group,
+            split_camera_buffer,
+            split_camera_bind_group,
+            panning: None,
+            damage: Damage::Full,
+            drawn_scene: Vec::new(),
+            drawn_stroke_len: 0,
+            replay: None,
+            lecture_started: None,
+            last_lecture_page: None,
+            flipbook: None,
+            brush_width: DEFAULT_BRUSH_WIDTH,
+            brush: Brush::Pen,
+            brush_indicator: None,
+            laser: None,
+            copied_style: None,
+            speech: None,
+            magnifier: None,
+            lens_texture: None,
+            current_stroke: Vec::new(),
+            current_timestamps: Vec::new(),
+            stroke_hold: None,
+            stroke_snapped: false,
+            touch_strokes: HashMap::new(),
+            touch_drag: None,
+            flicks: FlickTracker::default(),
+            edge_swipe: EdgeSwipe::default(),
+            page_turn: None,
+            caret_position: None,
+            auto_scrolled: None,
+            pinch: PinchTracker::default(),
+            palm_touches: HashSet::new(),
+            pen_touch: None,
+            pen_seen: None,
+            pan_touches: HashMap::new(),
+            onion_page: None,
+            onion_vertices: None,
+            split: None,
+            blend_mode: BlendMode::Normal,
+            current_color: rgba_to_normalized(contrasting_color(settings.background.color)),
+            previous_color: [0.0, 0.0, 0.0, 1.0],
+            start_typing: false,
+            cursor_visible: false,
+            cursor_inside: true,
+            calibration: None,
+            crosshair_shown: false,
+            animations: AnimationClock::default(),
+            last_click_time: None,
+            last_stylus_tap: None,
+            last_click_position: None,
+            editing_text_index: None,
+            editing_stencil_index: None,
+            edited_text: None,
+            rectangle_shader: Some(rectangle_shader),
+            fill_pipeline,
+            shadow_pipeline,
+            picker,
+            gpu_picking: true,
+            shadow_vertices: Vec::new(),
+            drop_shadow: false,
+            shape_positions: Vec::new(),
+            egui_renderer,
+            show_about: false,
+            reference: None,
+            adjusting: None,
+            scan: None,
+            show_devices: false,
+            recovery: crash::load_recovery().and_then(|contents| {
+                project::parse(&contents, physical_size)
+                    .map_err(|err| crash::log(format!("Invalid recovery file: {}", err)))
+                    .ok()
+            }),
+            show_shortcuts: false,
+            page_search: None,
+            page_filter: None,
+            new_page_tag: String::new(),
+            statistics: None,
+            pages_created: 0,
+            keymap: Keymap::new(&settings.key_bindings),
+            settings,
+            panels_dirty: false,
+            adapter_info,
+            adapters: adapter_infos,
+
+            color: include_image!("assets/color.png"),
+            font: include_image!("assets/font.png"),
+            rect: include_image!("assets/rect.png"),
+            prev: include_image!("assets/prev.png"),
+            raw_input,
+            egui_context: egui_ctx,
+        };
+
+        let _ = Self::render(&mut render_self);
+        render_self
+    }
+
+    pub(crate) fn resize(&mut self, new_size: PhysicalSize<u32>) {
+        if new_size.width > 0 && new_size.height > 0 {
+            self.size = new_size;
+            self.board.size = new_size;
+            self.surface_config.width = self.size.width;
+            self.surface_config.height = self.size.height;
+            self.surface.configure(&self.device, &self.surface_config);
+            if self.canvas.is_some() {
+                self.canvas = Some(create_canvas(
+                    &self.device,
+                    &self.surface_config,
+                    TextureUsages::empty(),
+                ));
+            }
+            self.damage = Damage::Full;
+            self.onion_vertices = None;
+            if let Some(split) = &mut self.split {
+                split.drawing = None;
+            }
+
+            let _ = self.render();
+        }
+    }
+
+    pub(crate) fn update(&mut self) -> Result<(), egui_wgpu::wgpu::SurfaceError> {
+        let mut text_areas: Vec<TextArea> = Vec::new();
+        self.auto_scroll();
+        self.caret_position = None;
+        if self.settings.background.roll {
+            self.board.camera.roll();
+        }
+
+        let physical_width = (self.size.width as f64 * self.scale_factor) as f32;
+        let physical_height = (self.size.height as f64 * self.scale_factor) as f32;
+
+        let visible = self.replay.as_mut().map(|replay| {
+            replay.advance();
+            replay.visible_points(&self.board.strokes)
+        });
+        let mut scene = Vec::new();
+        let mut buffers = Vec::new();
+        self.stroke_buffers.clear();
+        self.object_fills.clear();
+        self.shadow_vertices.clear();
+        self.shape_vertices.clear();
+        self.image_vertices.clear();
+        self.image_textures.clear();
+        self.layer_draws.clear();
+        let shown: Vec<u32> = self
+            .board
+            .layers
+            .iter()
+            .filter(|layer| layer.visible)
+            .map(|layer| layer.id)
+            .collect();
+        let view = TileRange::in_view(self.board.camera, self.size);
+        let in_view = |bounds: &Rect| view.overlaps(&TileRange::covering(bounds));
+        for layer in shown {
+            let images = self.image_textures.len();
+            let shadows = self.shadow_vertices.len() as u32;
+            let fills = self.object_fills.len() as u32;
+            let shapes = self.shape_vertices.len() as u32;
+            let strokes = self.stroke_buffers.len();
+            let live = layer == self.board.current_layer;
+
+            let mut highlights = BlendMode::ALL.map(|_| Vec::new());
+            let mut batches = BlendMode::ALL.map(|_| Vec::new());
+            for (index, stroke) in self.board.strokes.iter().enumerate() {
+                if stroke.layer != layer || !in_view(&points_bounds(self.size, &stroke.points)) {
+                    continue;
+                }
+                let points = match &visible {
+                    Some((visible, _)) => Cow::Borrowed(&stroke.points[..visible[index]]),
+                    None => stroke.lod_points(self.board.camera.zoom),
+                };
+                let batch = match stroke.brush {
+                    Brush::Pen | Brush::Calligraphy => &mut batches[stroke.blend as usize],
+                    Brush::Highlighter => &mut highlights[stroke.blend as usize],
+                };
+                let (taper, nib) = (self.taper(stroke.brush), self.nib(stroke.brush));
+                Stroke::ribbon_vertices(&points, stroke.width, [taper; 2], nib, self.size, batch);
+            }
+            for vertices in highlights.iter().chain(&batches) {
+                scene.extend_from_slice(bytemuck::cast_slice(vertices));
+            }
+            if live {
+                let width = self.stroke_width();
+                let batch = match self.brush {
+                    Brush::Pen | Brush::Calligraphy => &mut batches[self.blend_mode as usize],
+                    Brush::Highlighter => &mut highlights[self.blend_mode as usize],
+                };
+                // Strokes still being drawn only taper at the start, so the partial
+                // redraw never has to revisit earlier segments.
+                let (taper, nib) = ([self.taper(self.brush), 0.0], self.nib(self.brush));
+                Stroke::ribbon_vertices(&self.current_stroke, width, taper, nib, self.size, batch);
+                // Touch strokes are not covered by the mouse stroke's partial redraw.
+                for stroke in self.touch_strokes.values() {
+                    let batch = match stroke.brush {
+                        Brush::Pen | Brush::Calligraphy => &mut batches[stroke.blend as usize],
+                        Brush::Highlighter => &mut highlights[stroke.blend as usize],
+                    };
+                    let start = batch.len();
+                    let (taper, nib) = ([self.taper(stroke.brush), 0.0], self.nib(stroke.brush));
+                    Stroke::ribbon_vertices(
+                        &stroke.points,
+                        stroke.width,
+                        taper,
+                        nib,
+                        self.size,
+                        batch,
+                    );
+                    scene.extend_from_slice(bytemuck::cast_slice(&batch[start..]));
+                }
+            }
+
+            let blend_modes = BlendMode::ALL.into_iter().chain(BlendMode::ALL);
+            for (blend_mode, vertices) in blend_modes.zip(highlights.into_iter().chain(batches)) {
+                if vertices.is_empty() {
+                    continue;
+                }
+                let vertex_buffer =
+                    self.device
+                        .create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                            label: Some("Vertex Buffer"),
+                            contents: bytemuck::cast_slice(&vertices),
+                            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+                        });
+                self.stroke_buffers
+                    .push((blend_mode, vertex_buffer, vertices.len() as u32));
+            }
+
+            for fill in self.board.fills.iter().filter(|fill| fill.layer == layer) {
+                for rect in &fill.rects {
+                    let (left, top) = (rect.x, rect.y);
+                    let (right, bottom) = (rect.x + rect.width, rect.y + rect.height);
+                    self.object_fills.extend(
+                        [
+                            (left, top),
+                            (right, top),
+                            (left, bottom),
+                            (left, bottom),
+                            (right, top),
+                            (right, bottom),
+                        ]
+                        .map(|(x, y)| Vertex {
+                            position: screen_to_ndc(self.size, x, y),
+                            color: fill.color,
+                        }),
+                    );
+                }
+            }
+
+            let mut text_bounds = Vec::new();
+            let mut carets = Vec::new();
+            for text_entry in self.board.texts.iter().filter(|text| text.layer == layer) {
+                let scale = text_entry.board_scale(self.board.camera.zoom);
+                let font_size = text_entry.font_size as f32 * scale;
+                let wrap_width = text_entry.wrap_width.unwrap_or(physical_width) * scale;
+                let (text_buffer, origin) = if text_entry.markdown && !text_entry.pending {
+                    let buffer = markdown_buffer(
+                        &mut self.font_system,
+                        &text_entry.text,
+                        &text_entry.font,
+                        font_size,
+                        wrap_width,
+                        physical_height,
+                    );
+                    (buffer, text_entry.position)
+                } else {
+                    let buffer = text_buffer(
+                        &mut self.font_system,
+                        &text_entry.text,
+                        &text_entry.font,
+                        font_size,
+                        LINE_HEIGHT,
+                        wrap_width,
+                        physical_height,
+                    );
+                    let [x, y] = text_entry.position;
+                    (buffer, [x, y - font_size * TEXT_LIFT])
+                };
+                let bounds = buffer_bounds(&text_buffer, origin, font_size);
+                if text_entry.pending {
+                    let caret = caret_vertices(&text_buffer, text_entry, origin, self.size);
+                    let position = ime_position(self.board.camera, self.size, &caret);
+                    self.window.set_ime_position(position);
+                    self.caret_position = Some(position);
+                    if self.cursor_visible {
+                        carets.extend(caret);
+                    }
+                }
+
+                let contrast = contrasting_color(text_entry.color);
+                let halo = match text_entry.decoration {
+                    TextDecoration::Halo => Some(contrast),
+                    TextDecoration::Chip => {
+                        let padding = font_size * CHIP_PADDING;
+                        let chip_color = [contrast[0], contrast[1], contrast[2], CHIP_ALPHA];
+                        self.object_fills.extend(
+                            rounded_rect_vertices(
+                                bounds.x - padding,
+                                bounds.y - padding,
+                                bounds.x + bounds.width + padding,
+                                bounds.y + bounds.height + padding,
+                                padding * 2.0,
+                            )
+                            .into_iter()
+                            .map(|position| Vertex {
+                                position: screen_to_ndc(self.size, position[0], position[1]),
+                                color: rgba_to_normalized(chip_color),
+                            }),
+                        );
+                        None
+                    }
+                    TextDecoration::None => None,
+                };
+
+                text_bounds.push(bounds);
+                buffers.push((text_buffer, origin, text_entry.color, halo));
+            }
+            let texts = self
+                .board
+                .texts
+                .iter_mut()
+                .filter(|text| text.layer == layer);
+            for (text_entry, bounds) in texts.zip(text_bounds) {
+                text_entry.bounds = bounds;
+            }
+
+            self.object_lines.clear();
+            self.object_lines.append(&mut carets);
+            for shape in self
+                .board
+                .shapes
+                .iter()
+                .filter(|shape| shape.layer == layer)
+            {
+                if shape.shadow.is_visible() {
+                    let [x1, y1] = ndc_to_screen(self.size, shape.first);
+                    let [x2, y2] = ndc_to_screen(self.size, shape.last);
+                    let bounds = Rect {
+                        x: x1.min(x2),
+                        y: y1.min(y2),
+                        width: (x2 - x1).abs(),
+                        height: (y2 - y1).abs(),
+                    };
+                    self.shadow_vertices
+                        .extend(shape.shadow.quad(self.size, &bounds));
+                }
+            }
+
+            for table in self
+                .board
+                .tables
+                .iter_mut()
+                .filter(|table| table.layer == layer)
+            {
+                let [x, y] = table.position;
+                let row_height = table.font_size as f32 * LINE_HEIGHT + TABLE_CELL_PADDING * 2.0;
+                let columns = table.rows.iter().map(Vec::len).max().unwrap_or(0);
+                let mut column_widths = vec![0.0f32; columns];
+                let mut cells = Vec::new();
+
+                for (row, cells_in_row) in table.rows.iter().enumerate() {
+                    for (column, cell) in cells_in_row.iter().enumerate() {
+                        let cell_buffer = text_buffer(
+                            &mut self.font_system,
+                            cell,
+                            DEFAULT_FONT,
+                            table.font_size as f32,
+                            LINE_HEIGHT,
+                            physical_width,
+                            physical_height,
+                        );
+                        let width = cell_buffer
+                            .layout_runs()
+                            .map(|run| run.line_w)
+                            .fold(0.0, f32::max);
+                        column_widths[column] = column_widths[column].max(width);
+                        cells.push((row, column, cell_buffer));
+                    }
+                }
+
+                let mut column_offsets = vec![0.0f32];
+                for width in &column_widths {
+                    let last = *column_offsets.last().unwrap();
+                    column_offsets.push(last + width + TABLE_CELL_PADDING * 2.0);
+                }
+
+                for (row, column, cell_buffer) in cells {
+                    let left = x + column_offsets[column] + TABLE_CELL_PADDING;
+                    let top = y + row as f32 * row_height + TABLE_CELL_PADDING;
+                    buffers.push((cell_buffer, [left, top], table.color, None));
+                }
+
+                let color = rgba_to_normalized(table.color);
+                let width = *column_offsets.last().unwrap();
+                let height = row_height * table.rows.len() as f32;
+                table.bounds = Rect {
+                    x,
+                    y,
+                    width,
+                    height,
+                };
+                if table.shadow.is_visible() {
+                    self.shadow_vertices
+                        .extend(table.shadow.quad(self.size, &table.bounds));
+                }
+                for row in 0..=table.rows.len() {
+                    let top = y + row as f32 * row_height;
+                    self.object_lines.push(Vertex {
+                        position: screen_to_ndc(self.size, x, top),
+                        color,
+                    });
+                    self.object_lines.push(Vertex {
+                        position: screen_to_ndc(self.size, x + width, top),
+                        color,
+                    });
+                }
+                for offset in &column_offsets {
+                    self.object_lines.push(Vertex {
+                        position: screen_to_ndc(self.size, x + offset, y),
+                        color,
+                    });
+                    self.object_lines.push(Vertex {
+                        position: screen_to_ndc(self.size, x + offset, y + height),
+                        color,
+                    });
+                }
+            }
+
+            let code_blocks = self.board.code_blocks.iter_mut();
+            for code_block in code_blocks.filter(|code_block| code_block.layer == layer) {
+                if code_block.highlighted.is_empty() {
+                    code_block.highlighted =
+                        code::highlight(&code_block.code, &code_block.language);
+                }
+
+                let code_buffer = code_buffer(
+                    &mut self.font_system,
+                    &code_block.highlighted,
+                    code_block.font_size as f32,
+                    physical_width,
+                    physical_height,
+                );
+
+                let [x, y] = code_block.position;
+                let bounds = buffer_bounds(&code_buffer, [x, y], code_block.font_size as f32);
+                let (left, top) = (bounds.x - CODE_BLOCK_PADDING, bounds.y - CODE_BLOCK_PADDING);
+                let right = bounds.x + bounds.width + CODE_BLOCK_PADDING;
+                let bottom = bounds.y + bounds.height + CODE_BLOCK_PADDING;
+                code_block.bounds = Rect {
+                    x: left,
+                    y: top,
+                    width: right - left,
+                    height: bottom - top,
+                };
+                if code_block.shadow.is_visible() {
+                    self.shadow_vertices
+                        .extend(code_block.shadow.quad(self.size, &code_block.bounds));
+                }
+                let color = rgba_to_normalized(CODE_BORDER_COLOR);
+                for (from, to) in [
+                    ((left, top), (right, top)),
+                    ((right, top), (right, bottom)),
+                    ((right, bottom), (left, bottom)),
+                    ((left, bottom), (left, top)),
+                ] {
+                    self.object_lines.push(Vertex {
+                        position: screen_to_ndc(self.size, from.0, from.1),
+                        color,
+                    });
+                    self.object_lines.push(Vertex {
+                        position: screen_to_ndc(self.size, to.0, to.1),
+                        color,
+                    });
+                }
+
+                buffers.push((code_buffer, code_block.position, [0, 0, 0, 255], None));
+            }
+
+            for stencil in self
+                .board
+                .stencils
+                .iter_mut()
+                .filter(|stencil| stencil.layer == layer)
+            {
+                let bounds = stencil.bounds();
+                let color = rgba_to_normalized(stencil.color);
+                self.object_lines
+                    .extend(
+                        stencil
+                            .kind
+                            .outline(&bounds)
+                            .into_iter()
+                            .map(|[x, y]| Vertex {
+                                position: screen_to_ndc(self.size, x, y),
+                                color,
+                            }),
+                    );
+
+                let label = &mut stencil.label;
+                let font_size = label.font_size as f32;
+                let label_buffer = text_buffer(
+                    &mut self.font_system,
+                    &label.text,
+                    &label.font,
+                    font_size,
+                    LINE_HEIGHT,
+                    physical_width,
+                    physical_height,
+                );
+                let width = label_buffer
+                    .layout_runs()
+                    .map(|run| run.line_w)
+                    .fold(0.0, f32::max);
+                let height =
+                    label_buffer.layout_runs().count().max(1) as f32 * font_size * LINE_HEIGHT;
+
+                let area = stencil.kind.label_area(&bounds);
+                label.position = [
+                    area.x + (area.width - width) / 2.0,
+                    area.y + (area.height - height) / 2.0,
+                ];
+                label.bounds = Rect {
+                    x: label.position[0],
+                    y: label.position[1],
+                    width,
+                    height,
+                };
+                if label.pending {
+                    let caret = caret_vertices(&label_buffer, label, label.position, self.size);
+                    let position = ime_position(self.board.camera, self.size, &caret);
+                    self.window.set_ime_position(position);
+                    self.caret_position = Some(position);
+                    if self.cursor_visible {
+                        self.object_lines.extend(caret);
+                    }
+                }
+                buffers.push((label_buffer, label.position, label.color, None));
+            }
+
+            for connector in &self.board.connectors {
+                // Connectors sit on the layer of the stencil they start from.
+                let from_layer = self
+                    .board
+                    .stencils
+                    .get(connector.from)
+                    .map(|stencil| stencil.layer);
+                if from_layer != Some(layer) {
+                    continue;
+                }
+                let Some((from, to)) = connector.endpoints(&self.board.stencils) else {
+                    continue;
+                };
+                let color = rgba_to_normalized(connector.color);
+                let angle = (to[1] - from[1]).atan2(to[0] - from[0]);
+                let mut points = vec![from, to];
+                for side in [-1.0, 1.0] {
+                    let wing = angle + std::f32::consts::PI - side * ARROW_ANGLE;
+                    points.push(to);
+                    points.push([
+                        to[0] + ARROW_SIZE * wing.cos(),
+                        to[1] + ARROW_SIZE * wing.sin(),
+                    ]);
+                }
+                self.object_lines
+                    .extend(points.into_iter().map(|[x, y]| Vertex {
+                        position: screen_to_ndc(self.size, x, y),
+                        color,
+                    }));
+
+                if let Some(label) = &connector.label {
+                    let font_size = connector.font_size as f32;
+                    let label_buffer = text_buffer(
+                        &mut self.font_system,
+                        label,
+                        DEFAULT_FONT,
+                        font_size,
+                        LINE_HEIGHT,
+                        physical_width,
+                        physical_height,
+                    );
+                    let width = label_buffer
+                        .layout_runs()
+                        .map(|run| run.line_w)
+                        .fold(0.0, f32::max);
+                    let position = [
+                        (from[0] + to[0] - width) / 2.0,
+                        (from[1] + to[1]) / 2.0 - font_size * LINE_HEIGHT,
+                    ];
+                    buffers.push((label_buffer, position, connector.color, None));
+                }
+            }
+
+            let mut temp_shapes: Vec<Rectangle> = self
+                .board
+                .shapes
+                .iter()
+                .filter(|shape| shape.layer == layer)
+                .copied()
+                .collect();
+            if live && self.create_rect {
+                if let (Some(first), Some(last)) =
+                    (&self.shape_positions.first(), &self.shape_positions.last())
+                {
+                    temp_shapes.push(Rectangle {
+                        first: first.position,
+                        last: last.position,
+                        color: self.current_color,
+                        shadow: self.shadow_style(),
+                        filled: false,
+                        layer,
+                    });
+                }
+            }
+            for rect in temp_shapes.iter().filter(|rect| rect.filled) {
+                let (x1, y1) = (rect.first[0], rect.first[1]);
+                let (x2, y2) = (rect.last[0], rect.last[1]);
+                self.object_fills.extend(
+                    [(x1, y1), (x2, y1), (x1, y2), (x1, y2), (x2, y1), (x2, y2)].map(|(x, y)| {
+                        Vertex {
+                            position: [x, y],
+                            color: rect.color,
+                        }
+                    }),
+                );
+            }
+            self.shape_vertices
+                .extend(temp_shapes.iter().flat_map(|rect| rect.to_vertices()));
+            self.shape_vertices.extend_from_slice(&self.object_lines);
+
+            for image in self
+                .board
+                .images
+                .iter_mut()
+                .filter(|image| image.layer == layer)
+            {
+                // Pictures far from the camera give their textures back, and
+                // are uploaded again once a pan or zoom brings them near.
+                if !in_view(&image.bounds()) {
+                    image.texture = None;
+                    continue;
+                }
+                if image.texture.is_none() {
+                    image.texture = self
+                        .image_renderer
+                        .upload(&self.device, &self.queue, &image.data)
+                        .map(Arc::new);
+                }
+                let Some(texture) = &image.texture else {
+                    continue;
+                };
+                let [first, last] =
+                    [image.first, image.last].map(|[x, y]| screen_to_ndc(self.size, x, y));
+                self.image_vertices
+                    .extend(images::quad(first, last, &image.adjust));
+                self.image_textures.push(texture.clone());
+            }
+
+            self.layer_draws.push(LayerDraw {
+                images: images..self.image_textures.len(),
+                shadows: shadows..self.shadow_vertices.len() as u32,
+                fills: fills..self.object_fills.len() as u32,
+                shapes: shapes..self.shape_vertices.len() as u32,
+                strokes: strokes..self.stroke_buffers.len(),
+            });
+        }
+        if visible.is_some_and(|(_, finished)| finished) {
+            self.replay = None;
+        }
+        scene.push(self.blend_mode as u8);
+        scene.push(self.brush as u8);
+        for draw in &self.layer_draws {
+            let ends = [
+                draw.images.end as u32,
+                draw.shadows.end,
+                draw.fills.end,
+                draw.shapes.end,
+                draw.strokes.end as u32,
+            ];
+            scene.extend_from_slice(bytemuck::cast_slice(&ends));
+        }
+
+        if self
+            .split
+            .as_ref()
+            .is_some_and(|split| split.page >= self.board.pages.len())
+        {
+            self.split = None;
+        }
+        if let Some(split) = &mut self.split {
+            // The current page's slot is only written when leaving it, so it is
+            // laid out afresh while it is being worked on.
+            if split.page == self.board.current_page {
+                let scene = self.board.scene_actions();
+                split.drawing = Some(Drawing::new(&scene, self.size, &mut self.font_system));
+            } else if split.drawing.is_none() {
+                let scene = &self.board.pages[split.page].scene;
+                split.drawing = Some(Drawing::new(scene, self.size, &mut self.font_system));
+            }
+            let camera = split.camera.uniform(self.size);
+            self.queue
+                .write_buffer(&self.split_camera_buffer, 0, bytemuck::bytes_of(&camera));
+        }
+        let right = match &self.split {
+            Some(_) => split::divider(self.size) as i32,
+            None => self.size.width as i32,
+        };
+
+        for (buffer, position, color, halo) in &buffers {
+            let text_bounds = TextBounds {
+                left: 0,
+                top: 0,
+                right,
+                bottom: self.size.height as i32,
+            };
+
+            let zoom = self.board.camera.zoom;
+            let [left, top] = self.board.camera.to_screen(*position);
+            if let Some(halo) = halo {
+                for (dx, dy) in HALO_OFFSETS {
+                    text_areas.push(TextArea {
+                        buffer,
+                        left: left + dx * zoom,
+                        top: top + dy * zoom,
+                        scale: zoom,
+                        bounds: text_bounds,
+                        default_color: Color::rgba(halo[0], halo[1], halo[2], halo[3]),
+                        custom_glyphs: &[],
+                    });
+                }
+            }
+
+            let default_color = Color::rgba(color[0], color[1], color[2], color[3]);
+
+            text_areas.push(TextArea {
+                buffer,
+                left,
+                top,
+                scale: zoom,
+                bounds: text_bounds,
+                default_color,
+                custom_glyphs: &[],
+            });
+        }
+
+        if let Some(split) = &self.split {
+            let text_bounds = TextBounds {
+                left: right,
+                top: 0,
+                right: self.size.width as i32,
+                bottom: self.size.height as i32,
+            };
+            let texts = split.drawing.iter().flat_map(|drawing| &drawing.texts);
+            for (buffer, position, color) in texts {
+                let [left, top] = split.camera.to_screen(*position);
+                text_areas.push(TextArea {
+                    buffer,
+                    left,
+                    top,
+                    scale: split.camera.zoom,
+                    bounds: text_bounds,
+                    default_color: Color::rgba(color[0], color[1], color[2], color[3]),
+                    custom_glyphs: &[],
+                });
+            }
+        }
+
+        let _ = self.text_renderer.prepare(
+            &self.device,
+            &self.queue,
+            &mut self.font_system,
+            &mut self.atlas,
+            &self.viewport,
+            text_areas,
+            &mut self.swash_cache,
+        );
+
+        self.redaction_vertices.clear();
+        for area in &self.board.redactions {
+            let (left, top) = (area.x, area.y);
+            let (right, bottom) = (area.x + area.width, area.y + area.height);
+            self.redaction_vertices.extend(
+                [
+                    (left, top),
+                    (right, top),
+                    (left, bottom),
+                    (left, bottom),
+                    (right, top),
+                    (right, bottom),
+                ]
+                .map(|(x, y)| Vertex {
+                    position: screen_to_ndc(self.size, x, y),
+                    color: [0.0, 0.0, 0.0, 1.0],
+                }),
+            );
+        }
+
+        let onion = self
+            .onion_page
+            .filter(|&page| page != self.board.current_page)
+            .and_then(|page| self.board.pages.get(page));
+        match onion {
+            Some(page) => {
+                if self.onion_vertices.is_none() {
+                    self.onion_vertices = Some(onion::vertices(
+                        &page.scene,
+                        self.size,
+                        onion::GHOST_ALPHA,
+                        &mut self.font_system,
+                    ));
+                }
+            }
+            None => self.onion_vertices = None,
+        }
+        if let Some(vertices) = &self.onion_vertices {
+            scene.extend_from_slice(bytemuck::cast_slice(vertices));
+        }
+
+        scene.extend_from_slice(bytemuck::cast_slice(&self.image_vertices));
+        scene.extend_from_slice(bytemuck::cast_slice(&self.shape_vertices));
+        scene.extend_from_slice(bytemuck::cast_slice(&self.object_fills));
+        scene.extend_from_slice(bytemuck::cast_slice(&self.shadow_vertices));
+        scene.extend_from_slice(bytemuck::cast_slice(&self.redaction_vertices));
+        let camera = self.board.camera.uniform(self.size);
+        self.queue
+            .write_buffer(&self.camera_buffer, 0, bytemuck::bytes_of(&camera));
+        scene.extend_from_slice(bytemuck::bytes_of(&camera));
+        self.track_damage(scene);
+
+        Ok(())
+    }
+
+    pub(crate) fn track_damage(&mut self, scene: Vec<u8>) {
+        if scene != self.drawn_scene || self.current_stroke.len() < self.drawn_stroke_len {
+            self.drawn_scene = scene;
+            self.damage = Damage::Full;
+        } else if self.current_stroke.len() > self.drawn_stroke_len {
+            let start = self.drawn_stroke_len.saturating_sub(1);
+            let surface_size =
+                PhysicalSize::new(self.surface_config.width, self.surface_config.height);
+            let mut bounds = points_bounds(surface_size, &self.current_stroke[start..]);
+            let width = self.stroke_width();
+            bounds.x -= width / 2.0;
+            bounds.y -= width / 2.0;
+            bounds.width += width;
+            bounds.height += width;
+            let [x, y] = self.board.camera.to_screen([bounds.x, bounds.y]);
+            bounds = Rect {
+                x,
+                y,
+                width: bounds.width * self.board.camera.zoom,
+                height: bounds.height * self.board.camera.zoom,
+            };
+            self.damage = self.damage.union(Damage::region(surface_size, &bounds));
+        }
+        self.drawn_stroke_len = self.current_stroke.len();
+    }
+
+    // Grid lines or dots over the part of the board seen through `camera`.
+    pub(crate) fn paper_vertices(&self, camera: Camera) -> Vec<Vertex> {
+        let (width, height) = (self.size.width as f32, self.size.height as f32);
+        let area = [
+            camera.to_world([0.0, 0.0]),
+            camera.to_world([width, height]),
+        ];
+        let color = self.settings.background.pattern_color();
+        self.settings
+            .background
+            .pattern(area, camera.zoom)
+            .into_iter()
+            .map(|[x, y]| Vertex {
+                position: screen_to_ndc(self.size, x, y),
+                color,
+            })
+            .collect()
+    }
+
+    pub(crate) fn render(&mut self) -> Result<(), egui_wgpu::wgpu::SurfaceError> {
+        self.egui_context.begin_pass(self.raw_input.clone());
+        let output = self.surface.get_current_texture()?;
+        let view = output
+            .texture
+            .create_view(&egui_wgpu::wgpu::TextureViewDescriptor::default());
+
+        let mut encoder =
+            self.device
+                .create_command_encoder(&egui_wgpu::wgpu::CommandEncoderDescriptor {
+                    label: Some("Render Encoder"),
+                });
+
+        let canvas_view = self
+            .canvas
+            .as_ref()
+            .map(|canvas| canvas.create_view(&egui_wgpu::wgpu::TextureViewDescriptor::default()));
+        let mut damage = match canvas_view {
+            Some(_) => std::mem::replace(&mut self.damage, Damage::None),
+            None => Damage::Full,
+        };
+        // Both halves of a split view are clipped, so it is always redrawn whole.
+        if self.split.is_some() {
+            damage = Damage::Full;
+        }
+
+        if damage != Damage::None {
+            let encoder = encoder.borrow_mut();
+            let mut render_pass =
+                encoder
+                    .borrow_mut()
+                    .begin_render_pass(&egui_wgpu::wgpu::RenderPassDescriptor {
+                        label: Some("Strokes Render Pass"),
+                        color_attachments: &[Some(egui_wgpu::wgpu::RenderPassColorAttachment {
+                            view: canvas_view.as_ref().unwrap_or(&view),
+                            resolve_target: None,
+                            ops: egui_wgpu::wgpu::Operations {
+                                load: match damage {
+                                    Damage::Full => {
+                                        let [r, g, b, a] = self.settings.background.normalized();
+                                        egui_wgpu::wgpu::LoadOp::Clear(egui_wgpu::wgpu::Color {
+                                            r: r as f64,
+                                            g: g as f64,
+                                            b: b as f64,
+                                            a: a as f64,
+                                        })
+                                    }
+                                    _ => egui_wgpu::wgpu::LoadOp::Load,
+                                },
+                                store: egui_wgpu::wgpu::StoreOp::Store,
+                            },
+                        })],
+                        depth_stencil_attachment: None,
+                        timestamp_writes: None,
+                        occlusion_query_set: None,
+                    });
+            render_pass.set_bind_group(0, &self.camera_bind_group, &[]);
+            if self.split.is_some() {
+                render_pass.set_scissor_rect(0, 0, split::divider(self.size), self.size.height);
+            }
+
+            if let Damage::Region {
+                x,
+                y,
+                width,
+                height,
+            } = damage
+            {
+                render_pass.set_scissor_rect(x, y, width, height);
+
+                let background: Vec<Vertex> = [
+                    [-1.0, -1.0],
+                    [1.0, -1.0],
+                    [1.0, 1.0],
+                    [-1.0, -1.0],
+                    [1.0, 1.0],
+                    [-1.0, 1.0],
+                ]
+                .map(|position| Vertex {
+                    position: self.board.camera.unproject(self.size, position),
+                    color: self.settings.background.normalized(),
+                })
+                .into();
+                let background_buffer =
+                    self.device
+                        .create_buffer_init(&egui_wgpu::wgpu::util::BufferInitDescriptor {
+                            label: Some("Damage Background Buffer"),
+                            contents: bytemuck::cast_slice(&background),
+                            usage: egui_wgpu::wgpu::BufferUsages::VERTEX,
+                        });
+                render_pass.set_pipeline(&self.fill_pipeline);
+                render_pass.set_vertex_buffer(0, background_buffer.slice(..));
+                render_pass.draw(0..background.len() as u32, 0..1);
+            }
+
+            let paper = self.paper_vertices(self.board.camera);
+            if !paper.is_empty() {
+                let paper_buffer =
+                    self.device
+                        .create_buffer_init(&egui_wgpu::wgpu::util::BufferInitDescriptor {
+                            label: Some("Paper Vertex Buffer"),
+                            contents: bytemuck::cast_slice(&paper),
+                            usage: egui_wgpu::wgpu::BufferUsages::VERTEX,
+                        });
+                render_pass.set_pipeline(&self.fill_pipeline);
+                render_pass.set_vertex_buffer(0, paper_buffer.slice(..));
+                render_pass.draw(0..paper.len() as u32, 0..1);
+            }
+
+            if let Some(onion) = self
+                .onion_vertices
+                .as_ref()
+                .filter(|onion| !onion.is_empty())
+            {
+                let onion_buffer =
+                    self.device
+                        .create_buffer_init(&egui_wgpu::wgpu::util::BufferInitDescriptor {
+                            label: Some("Onion Vertex Buffer"),
+                            contents: bytemuck::cast_slice(onion),
+                            usage: egui_wgpu::wgpu::BufferUsages::VERTEX,
+                        });
+                render_pass.set_pipeline(&self.fill_pipeline);
+                render_pass.set_vertex_buffer(0, onion_buffer.slice(..));
+                render_pass.draw(0..onion.len() as u32, 0..1);
+            }
+
+            let image_buffer = (!self.image_vertices.is_empty()).then(|| {
+                self.device
+                    .create_buffer_init(&egui_wgpu::wgpu::util::BufferInitDescriptor {
+                        label: Some("Image Vertex Buffer"),
+                        contents: bytemuck::cast_slice(&self.image_vertices),
+                        usage: egui_wgpu::wgpu::BufferUsages::VERTEX,
+                    })
+            });
+            let shadow_vertex_buffer =
+                self.device
+                    .create_buffer_init(&egui_wgpu::wgpu::util::BufferInitDescriptor {
+                        label: Some("Shadow Vertex Buffer"),
+                        contents: bytemuck::cast_slice(&self.shadow_vertices),
+                        usage: egui_wgpu::wgpu::BufferUsages::VERTEX,
+                    });
+            let fill_vertex_buffer =
+                self.device
+                    .create_buffer_init(&egui_wgpu::wgpu::util::BufferInitDescriptor {
+                        label: Some("Fill Vertex Buffer"),
+                        contents: bytemuck::cast_slice(&self.object_fills),
+                        usage: egui_wgpu::wgpu::BufferUsages::VERTEX,
+                    });
+            let rectangle_vertex_buffer =
+                self.device
+                    .create_buffer_init(&egui_wgpu::wgpu::util::BufferInitDescriptor {
+                        label: Some("Rectangle Vertex Buffer"),
+                        contents: bytemuck::cast_slice(&self.shape_vertices),
+                        usage: egui_wgpu::wgpu::BufferUsages::VERTEX,
+                    });
+
+            for draw in &self.layer_draws {
+                if let Some(image_buffer) =
+                    image_buffer.as_ref().filter(|_| !draw.images.is_empty())
+                {
+                    render_pass.set_pipeline(&self.image_renderer.pipeline);
+                    render_pass.set_vertex_buffer(0, image_buffer.slice(..));
+                    for index in draw.images.clone() {
+                        let start = index as u32 * 6;
+                        render_pass.set_bind_group(1, &self.image_textures[index].bind_group, &[]);
+                        render_pass.draw(start..start + 6, 0..1);
+                    }
+                }
+
+                if !draw.shadows.is_empty() {
+                    render_pass.set_pipeline(&self.shadow_pipeline);
+                    render_pass.set_vertex_buffer(0, shadow_vertex_buffer.slice(..));
+                    render_pass.draw(draw.shadows.clone(), 0..1);
+                }
+
+                if !draw.fills.is_empty() {
+                    render_pass.set_pipeline(&self.fill_pipeline);
+                    render_pass.set_vertex_buffer(0, fill_vertex_buffer.slice(..));
+                    render_pass.draw(draw.fills.clone(), 0..1);
+                }
+
+                if let Some(rectangle_shader) = &self.rectangle_shader {
+                    if !draw.shapes.is_empty() {
+                        render_pass.set_pipeline(rectangle_shader);
+                        render_pass.set_vertex_buffer(0, rectangle_vertex_buffer.slice(..));
+                        render_pass.draw(draw.shapes.clone(), 0..1);
+                    }
+                }
+
+                for (blend_mode, vertex_buffer, count) in &self.stroke_buffers[draw.strokes.clone()]
+                {
+                    render_pass.set_pipeline(&self.stroke_pipelines[*blend_mode as usize]);
+                    render_pass.set_vertex_buffer(0, vertex_buffer.slice(..));
+                    render_pass.draw(0..*count, 0..1);
+                }
+            }
+
+            if !self.redaction_vertices.is_empty() {
+                let redaction_buffer =
+                    self.device
+                        .create_buffer_init(&egui_wgpu::wgpu::util::BufferInitDescriptor {
+                            label: Some("Redaction Vertex Buffer"),
+                            contents: bytemuck::cast_slice(&self.redaction_vertices),
+                            usage: egui_wgpu::wgpu::BufferUsages::VERTEX,
+                        });
+                render_pass.set_pipeline(&self.fill_pipeline);
+                render_pass.set_vertex_buffer(0, redaction_buffer.slice(..));
+                render_pass.draw(0..self.redaction_vertices.len() as u32, 0..1);
+            }
+
+            if let Some(split) = &self.split {
+                let divider = split::divider(self.size);
+                let vertices: Vec<Vertex> = self
+                    .paper_vertices(split.camera)
+                    .into_iter()
+                    .chain(
+                        split
+                            .drawing
+                            .iter()
+                            .flat_map(|drawing| drawing.vertices.clone()),
+                    )
+                    .collect();
+                if !vertices.is_empty() {
+                    let split_buffer = self.device.create_buffer_init(
+                        &egui_wgpu::wgpu::util::BufferInitDescriptor {
+                            label: Some("Split Vertex Buffer"),
+                            contents: bytemuck::cast_slice(&vertices),
+                            usage: egui_wgpu::wgpu::BufferUsages::VERTEX,
+                        },
+                    );
+                    render_pass.set_scissor_rect(
+                        divider,
+                        0,
+                        self.size.width - divider,
+                        self.size.height,
+                    );
+                    render_pass.set_bind_group(0, &self.split_camera_bind_group, &[]);
+                    render_pass.set_pipeline(&self.fill_pipeline);
+                    render_pass.set_vertex_buffer(0, split_buffer.slice(..));
+                    render_pass.draw(0..vertices.len() as u32, 0..1);
+                }
+            }
+        }
+
+        if let Some(canvas) = &self.canvas {
+            encoder.copy_texture_to_texture(
+                canvas.as_image_copy(),
+                output.texture.as_image_copy(),
+                canvas.size(),
+            );
+
+            if let Some(lens) = &mut self.magnifier {
+                if self
+                    .lens_texture
+                    .as_ref()
+                    .is_none_or(|texture| texture.size() != canvas.size())
+                {
+                    let texture = create_canvas(
+                        &self.device,
+                        &self.surface_config,
+                        TextureUsages::COPY_DST | TextureUsages::TEXTURE_BINDING,
+                    );
+                    let lens_view =
+                        texture.create_view(&egui_wgpu::wgpu::TextureViewDescriptor::default());
+                    match lens.texture {
+                        Some(id) => self.egui_renderer.update_egui_texture_from_wgpu_texture(
+                            &self.device,
+                            &lens_view,
+                            egui_wgpu::wgpu::FilterMode::Linear,
+                            id,
+                        ),
+                        None => {
+                            lens.texture = Some(self.egui_renderer.register_native_texture(
+                                &self.device,
+                                &lens_view,
+                                egui_wgpu::wgpu::FilterMode::Linear,
+                            ))
+                        }
+                    }
+                    self.lens_texture = Some(texture);
+                }
+
+                if let Some(texture) = &self.lens_texture {
+                    encoder.copy_texture_to_texture(
+                        canvas.as_image_copy(),
+                        texture.as_image_copy(),
+                        canvas.size(),
+                    );
+                    let lens_view =
+                        texture.create_view(&egui_wgpu::wgpu::TextureViewDescriptor::default());
+                    let mut render_pass =
+                        encoder.begin_render_pass(&egui_wgpu::wgpu::RenderPassDescriptor {
+                            label: Some("Lens Text Render Pass"),
+                            color_attachments: &[Some(
+                                egui_wgpu::wgpu::RenderPassColorAttachment {
+                                    view: &lens_view,
+                                    resolve_target: None,
+                                    ops: egui_wgpu::wgpu::Operations {
+                                        load: egui_wgpu::wgpu::LoadOp::Load,
+                                        store: egui_wgpu::wgpu::StoreOp::Store,
+                                    },
+                                },
+                            )],
+                            depth_stencil_attachment: None,
+                            timestamp_writes: None,
+                            occlusion_query_set: None,
+                        });
+                    self.text_renderer
+                        .render(&self.atlas, &self.viewport, &mut render_pass)
+                        .unwrap();
+                }
+            }
+        }
+
+        let screen_descriptor = ScreenDescriptor {
+            size_in_pixels: [self.surface_config.width, self.surface_config.height],
+            pixels_per_point: self.egui_context.pixels_per_point(),
+        };
+
+        self.interface();
+
+        let full_output = self.egui_context.end_pass();
+
+        let repaint_delay = full_output
+            .viewport_output
+            .get(&egui::ViewportId::ROOT)
+            .map_or(Duration::MAX, |viewport| viewport.repaint_delay);
+        if repaint_delay.is_zero() {
+            self.window.request_redraw();
+        } else if repaint_delay < Duration::MAX {
+            self.animations
+                .schedule(Animation::Interface, repaint_delay);
+        }
+
+        let tris = self
+            .egui_context
+            .tessellate(full_output.shapes, full_output.pixels_per_point);
+
+        for (id, image_delta) in &full_output.textures_delta.set {
+            self.egui_renderer
+                .update_texture(&self.device, &self.queue, *id, image_delta);
+        }
+
+        self.egui_renderer.update_buffers(
+            &self.device,
+            &self.queue,
+            &mut encoder,
+            &tris,
+            &screen_descriptor,
+        );
+
+        let rpass = encoder.begin_render_pass(&egui_wgpu::wgpu::RenderPassDescriptor {
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: &view,
+                resolve_target: None,
+                ops: egui_wgpu::wgpu::Operations {
+                    load: egui_wgpu::wgpu::LoadOp::Load,
+                    store: StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: None,
+            timestamp_writes: None,
+            label: Some("egui main render pass"),
+            occlusion_query_set: None,
+        });
+
+        self.egui_renderer
+            .render(&mut rpass.forget_lifetime(), &tris, &screen_descriptor);
+        for x in &full_output.textures_delta.free {
+            self.egui_renderer.free_texture(x);
+        }
+
+        {
+            let mut render_pass =
+                encoder.begin_render_pass(&egui_wgpu::wgpu::RenderPassDescriptor {
+                    label: Some("Text Render Pass"),
+                    color_attachments: &[Some(egui_wgpu::wgpu::RenderPassColorAttachment {
+                        view: &view,
+                        resolve_target: None,
+                        ops: egui_wgpu::wgpu::Operations {
+                            load: egui_wgpu::wgpu::LoadOp::Load,
+                            store: egui_wgpu::wgpu::StoreOp::Store,
+                        },
+                    })],
+                    depth_stencil_attachment: None,
+                    timestamp_writes: None,
+                    occlusion_query_set: None,
+                });
+
+            self.text_renderer
+                .render(&self.atlas, &self.viewport, &mut render_pass)
+                .unwrap();
+        }
+
+        self.queue.submit(std::iter::once(encoder.finish()));
+        output.present();
+
+        self.atlas.trim();
+
+        self.raw_input.events.clear();
+
+        Ok(())
+    }
+}
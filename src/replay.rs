@@ -0,0 +1,71 @@
+use crate::Stroke;
+use std::time::{Duration, Instant};
+
+pub const REPLAY_FRAME: Duration = Duration::from_millis(16);
+const POINT_INTERVAL_MS: f64 = 10.0;
+const MAX_STROKE_GAP_MS: f64 = 500.0;
+
+pub struct Replay {
+    pub speed: f32,
+    progress: Duration,
+    last_tick: Instant,
+    // Stroke indexes to replay, the rest stay fully drawn. Empty replays all.
+    only: Vec<usize>,
+}
+
+impl Replay {
+    pub fn new(only: Vec<usize>) -> Self {
+        Replay {
+            speed: 1.0,
+            progress: Duration::ZERO,
+            last_tick: Instant::now(),
+            only,
+        }
+    }
+
+    pub fn advance(&mut self) {
+        let now = Instant::now();
+        self.progress += now.duration_since(self.last_tick).mul_f32(self.speed);
+        self.last_tick = now;
+    }
+
+    pub fn visible_points(&self, strokes: &[Stroke]) -> (Vec<usize>, bool) {
+        let progress = self.progress.as_secs_f64() * 1000.0;
+        let mut start = 0.0;
+        let mut previous_end = None;
+        let mut visible = Vec::with_capacity(strokes.len());
+
+        for (index, stroke) in strokes.iter().enumerate() {
+            if !self.only.is_empty() && !self.only.contains(&index) {
+                visible.push(stroke.points.len());
+                continue;
+            }
+            let offsets = point_offsets(stroke);
+            if let (Some(end), Some(&first)) = (previous_end, stroke.timestamps.first()) {
+                start += (first.saturating_sub(end) as f64).min(MAX_STROKE_GAP_MS);
+            }
+            visible.push(
+                offsets
+                    .iter()
+                    .take_while(|offset| start + **offset <= progress)
+                    .count(),
+            );
+            start += offsets.last().copied().unwrap_or(0.0);
+            previous_end = stroke.timestamps.last().copied();
+        }
+        (visible, progress > start)
+    }
+}
+
+fn point_offsets(stroke: &Stroke) -> Vec<f64> {
+    match stroke.timestamps.first() {
+        Some(&first) if stroke.timestamps.len() == stroke.points.len() => stroke
+            .timestamps
+            .iter()
+            .map(|timestamp| timestamp.saturating_sub(first) as f64)
+            .collect(),
+        _ => (0..stroke.points.len())
+            .map(|index| index as f64 * POINT_INTERVAL_MS)
+            .collect(),
+    }
+}
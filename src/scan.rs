@@ -0,0 +1,96 @@
+use crate::images;
+use egui::{Color32, Pos2, Sense, Stroke, TextureHandle, TextureOptions, Vec2};
+
+// The longer side of the photo in the dialog, in points.
+const SIZE: f32 = 360.0;
+const HANDLE_RADIUS: f32 = 7.0;
+// Where the corners start, as a fraction of the photo in from each side.
+const CORNER_INSET: f32 = 0.05;
+const OUTLINE_COLOR: Color32 = Color32::from_rgb(30, 120, 230);
+
+pub enum ScanStep {
+    Open,
+    Cancel,
+    Straighten,
+}
+
+// A photo of a whiteboard or a sheet of paper on the board, with the four
+// corners of the page in it dragged into place before it is straightened.
+pub struct Scan {
+    pub index: usize,
+    // From the top left corner clockwise, as fractions of the photo.
+    pub corners: [[f32; 2]; 4],
+    texture: TextureHandle,
+}
+
+impl Scan {
+    pub fn new(context: &egui::Context, index: usize, data: &[u8]) -> Option<Self> {
+        let image = images::color_image(data)?;
+        let (near, far) = (CORNER_INSET, 1.0 - CORNER_INSET);
+        Some(Scan {
+            index,
+            corners: [[near, near], [far, near], [far, far], [near, far]],
+            texture: context.load_texture("Scan", image, TextureOptions::LINEAR),
+        })
+    }
+
+    pub fn show(&mut self, context: &egui::Context) -> ScanStep {
+        let mut open = true;
+        let mut step = ScanStep::Open;
+        egui::Window::new("Scan photo")
+            .collapsible(false)
+            .order(egui::Order::Foreground)
+            .resizable(false)
+            .open(&mut open)
+            .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+            .show(context, |ui| {
+                ui.label("Drag the corners onto the corners of the page.");
+                let size = self.texture.size_vec2();
+                let size = size * (SIZE / size.x.max(size.y));
+                let (rect, _) = ui.allocate_exact_size(size, Sense::hover());
+                ui.painter().image(
+                    self.texture.id(),
+                    rect,
+                    egui::Rect::from_min_max(Pos2::ZERO, Pos2::new(1.0, 1.0)),
+                    Color32::WHITE,
+                );
+                let to_screen = |[x, y]: [f32; 2]| rect.min + Vec2::new(x, y) * size;
+                for (index, corner) in self.corners.iter_mut().enumerate() {
+                    let handle = egui::Rect::from_center_size(
+                        to_screen(*corner),
+                        Vec2::splat(HANDLE_RADIUS * 2.0),
+                    );
+                    let response = ui.interact(handle, ui.id().with(index), Sense::drag());
+                    let delta = response.drag_delta() / size;
+                    *corner = [
+                        (corner[0] + delta.x).clamp(0.0, 1.0),
+                        (corner[1] + delta.y).clamp(0.0, 1.0),
+                    ];
+                }
+                let points: Vec<Pos2> = self
+                    .corners
+                    .iter()
+                    .map(|&corner| to_screen(corner))
+                    .collect();
+                let stroke = Stroke::new(2.0, OUTLINE_COLOR);
+                ui.painter()
+                    .add(egui::Shape::closed_line(points.clone(), stroke));
+                for point in points {
+                    ui.painter()
+                        .circle(point, HANDLE_RADIUS, Color32::WHITE, stroke);
+                }
+                ui.horizontal(|ui| {
+                    if ui.button("Straighten").clicked() {
+                        step = ScanStep::Straighten;
+                    }
+                    if ui.button("Cancel").clicked() {
+                        step = ScanStep::Cancel;
+                    }
+                });
+            });
+        match open {
+            true => step,
+            false => ScanStep::Cancel,
+        }
+    }
+}
@@ -0,0 +1,98 @@
+use crate::{ndc_to_screen, Action, Rect};
+use tao::dpi::PhysicalSize;
+
+pub fn hidden_layers(page: &[Action]) -> Vec<u32> {
+    page.iter()
+        .filter_map(|action| match action {
+            Action::Layers(edit) => Some(&edit.to),
+            _ => None,
+        })
+        .flatten()
+        .filter(|layer| !layer.visible)
+        .map(|layer| layer.id)
+        .collect()
+}
+
+pub fn layer(action: &Action) -> Option<u32> {
+    Some(match action {
+        Action::Stroke(stroke) => stroke.layer,
+        Action::Text(text) => text.layer,
+        Action::Shapes(rectangle) => rectangle.layer,
+        Action::Table(table) => table.layer,
+        Action::Code(code_block) => code_block.layer,
+        Action::Stencil(stencil) => stencil.layer,
+        Action::Image(image) => image.layer,
+        Action::Fill(fill) => fill.layer,
+        _ => return None,
+    })
+}
+
+pub fn action_bounds(action: &Action, size: PhysicalSize<u32>) -> Option<Rect> {
+    let corners = |[x1, y1]: [f32; 2], [x2, y2]: [f32; 2]| Rect {
+        x: x1.min(x2),
+        y: y1.min(y2),
+        width: (x2 - x1).abs(),
+        height: (y2 - y1).abs(),
+    };
+    let placed = |bounds: &Rect, [x, y]: [f32; 2]| match bounds.width > 0.0 {
+        true => bounds.clone(),
+        false => corners([x, y], [x, y]),
+    };
+    Some(match action {
+        Action::Stroke(stroke) => {
+            let points: Vec<_> = stroke
+                .points
+                .iter()
+                .map(|point| ndc_to_screen(size, point.position))
+                .collect();
+            let first = *points.first()?;
+            let (min, max) = points.iter().fold((first, first), |(min, max), point| {
+                (
+                    [min[0].min(point[0]), min[1].min(point[1])],
+                    [max[0].max(point[0]), max[1].max(point[1])],
+                )
+            });
+            corners(min, max)
+        }
+        Action::Shapes(rectangle) => corners(
+            ndc_to_screen(size, rectangle.first),
+            ndc_to_screen(size, rectangle.last),
+        ),
+        Action::Text(text) => placed(&text.bounds, text.position),
+        Action::Table(table) => placed(&table.bounds, table.position),
+        Action::Code(code_block) => placed(&code_block.bounds, code_block.position),
+        Action::Stencil(stencil) => Rect {
+            x: stencil.position[0],
+            y: stencil.position[1],
+            width: stencil.size[0],
+            height: stencil.size[1],
+        },
+        Action::Image(image) => corners(image.first, image.last),
+        Action::Fill(fill) => fill.bounds(),
+        Action::Redact(area) => area.clone(),
+        _ => return None,
+    })
+}
+
+// Objects on the page's hidden layers are left out of summaries and exports.
+pub fn is_visible(action: &Action, hidden: &[u32]) -> bool {
+    !matches!(action, Action::Layers(_) | Action::Label(_))
+        && layer(action).is_none_or(|layer| !hidden.contains(&layer))
+}
+
+pub fn bounds<'a>(
+    actions: impl Iterator<Item = &'a Action>,
+    size: PhysicalSize<u32>,
+) -> Option<Rect> {
+    actions
+        .filter_map(|action| action_bounds(action, size))
+        .reduce(|a, b| {
+            let (x, y) = (a.x.min(b.x), a.y.min(b.y));
+            Rect {
+                x,
+                y,
+                width: (a.x + a.width).max(b.x + b.width) - x,
+                height: (a.y + a.height).max(b.y + b.height) - y,
+            }
+        })
+}
@@ -0,0 +1,257 @@
+use crate::{
+    background::Background,
+    bridge::BridgeSettings,
+    commands::{Command, KeyBinding},
+    crash,
+    gestures::{self, Flick},
+    panels::PanelLayouts,
+};
+use serde::{Deserialize, Serialize};
+use std::{fs, path::PathBuf};
+use wgpu::Backends;
+
+pub const HISTORY_MAX_ENTRIES: usize = 500;
+pub const HISTORY_MAX_BYTES: usize = 64 * 1024 * 1024;
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    pub backend: Option<String>,
+    pub adapter: Option<String>,
+    pub history_max_entries: usize,
+    pub history_max_bytes: usize,
+    pub panels: PanelLayouts,
+    pub asset_folders: Vec<PathBuf>,
+    pub fonts_dir: Option<PathBuf>,
+    pub smooth_strokes: bool,
+    pub pen_offset: [f64; 2],
+    pub device_tools: DeviceTools,
+    pub scribble_erase: bool,
+    // How far, in board pixels, the eraser reaches at full pressure.
+    pub eraser_radius: f32,
+    // Fingers only pan and zoom while a pen is down or was just lifted.
+    pub pen_only: bool,
+    // Redactions coarsen pictures instead of covering the area in black.
+    pub pixelate_redactions: bool,
+    pub flicks: Vec<Flick>,
+    // A finger swiping in from the left or right edge turns the page, and
+    // arrows at the window's sides do the same for the mouse.
+    pub edge_swipes: bool,
+    pub edge_arrows: bool,
+    // Writing near the bottom or a side of the window slides the board along.
+    pub auto_scroll: bool,
+    // Run on a press and on a double tap of the extra mouse button numbered
+    // stylus_button_id.
+    pub stylus_button: Option<Command>,
+    pub stylus_double_tap: Option<Command>,
+    pub stylus_button_id: u16,
+    // Holding still this long at the end of a stroke straightens it, 0 disables.
+    pub snap_hold_millis: u64,
+    // A running lecture starts a new dated page this often, 0 disables.
+    pub lecture_page_minutes: u64,
+    // Frames per second when pages are played as a flipbook.
+    pub flipbook_fps: u32,
+    pub tapers: BrushTapers,
+    // Degrees counterclockwise from horizontal.
+    pub nib_angle: f32,
+    pub key_bindings: Vec<KeyBinding>,
+    // How far from white a pixel may be and still count as background.
+    pub background_threshold: u8,
+    pub background: Background,
+    // Exports draw the paper's grid or dots and a border around each page.
+    pub export_pattern: bool,
+    pub export_border: bool,
+    // Serves the remote-api endpoint on this localhost port when set.
+    pub api_port: Option<u16>,
+    pub bridge: BridgeSettings,
+}
+
+// The tool an input device switches to when it touches the board. `Toolbar`
+// leaves whatever the toolbar has selected.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DeviceTool {
+    Toolbar,
+    Pen,
+    Eraser,
+    Select,
+    Pan,
+}
+
+impl DeviceTool {
+    pub const ALL: [DeviceTool; 5] = [
+        DeviceTool::Toolbar,
+        DeviceTool::Pen,
+        DeviceTool::Eraser,
+        DeviceTool::Select,
+        DeviceTool::Pan,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            DeviceTool::Toolbar => "Toolbar tool",
+            DeviceTool::Pen => "Pen",
+            DeviceTool::Eraser => "Eraser",
+            DeviceTool::Select => "Select",
+            DeviceTool::Pan => "Pan",
+        }
+    }
+}
+
+// Styluses reach the window either as the mouse or as touch, depending on the
+// platform, so they follow whichever of these bindings their events arrive as.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DeviceTools {
+    pub mouse: DeviceTool,
+    pub touch: DeviceTool,
+}
+
+impl Default for DeviceTools {
+    fn default() -> Self {
+        DeviceTools {
+            mouse: DeviceTool::Toolbar,
+            touch: DeviceTool::Toolbar,
+        }
+    }
+}
+
+// How far, in board pixels, each brush's strokes take to swell to full width
+// at either end. 0 leaves the ends blunt.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BrushTapers {
+    pub pen: f32,
+    pub highlighter: f32,
+    pub calligraphy: f32,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            backend: None,
+            adapter: None,
+            history_max_entries: HISTORY_MAX_ENTRIES,
+            history_max_bytes: HISTORY_MAX_BYTES,
+            panels: PanelLayouts::default(),
+            asset_folders: Vec::new(),
+            fonts_dir: None,
+            smooth_strokes: true,
+            pen_offset: [0.0, 0.0],
+            device_tools: DeviceTools::default(),
+            scribble_erase: true,
+            eraser_radius: 12.0,
+            pen_only: false,
+            pixelate_redactions: false,
+            flicks: gestures::default_flicks(),
+            edge_swipes: false,
+            edge_arrows: false,
+            auto_scroll: false,
+            stylus_button: Some(Command::PreviousTool),
+            stylus_double_tap: None,
+            stylus_button_id: 8,
+            snap_hold_millis: 600,
+            lecture_page_minutes: 0,
+            flipbook_fps: 12,
+            tapers: BrushTapers::default(),
+            nib_angle: 45.0,
+            key_bindings: Vec::new(),
+            background_threshold: 24,
+            background: Background::default(),
+            export_pattern: false,
+            export_border: false,
+            api_port: None,
+            bridge: BridgeSettings::default(),
+        }
+    }
+}
+
+impl Settings {
+    pub fn path() -> Option<PathBuf> {
+        dirs::config_dir().map(|dir| dir.join("rust-whiteboard").join("settings.toml"))
+    }
+
+    pub fn fonts_dir(&self) -> Option<PathBuf> {
+        self.fonts_dir
+            .clone()
+            .or_else(|| Self::path().and_then(|path| Some(path.parent()?.join("fonts"))))
+    }
+
+    pub fn load() -> Self {
+        let Some(path) = Self::path() else {
+            return Settings::default();
+        };
+        let Ok(contents) = fs::read_to_string(&path) else {
+            return Settings::default();
+        };
+
+        toml::from_str(&contents).unwrap_or_else(|err| {
+            crash::log(format!("Invalid settings file {}: {}", path.display(), err));
+            Settings::default()
+        })
+    }
+
+    pub fn save(&self) {
+        let Some(path) = Self::path() else {
+            return;
+        };
+        if let Some(dir) = path.parent() {
+            let _ = fs::create_dir_all(dir);
+        }
+
+        match toml::to_string_pretty(self) {
+            Ok(contents) => {
+                if let Err(err) = fs::write(&path, contents) {
+                    crash::log(format!(
+                        "Unable to save settings {}: {}",
+                        path.display(),
+                        err
+                    ));
+                }
+            }
+            Err(err) => crash::log(format!("Unable to serialize settings: {}", err)),
+        }
+    }
+
+    pub fn apply_args(&mut self, args: impl IntoIterator<Item = String>) {
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            let (flag, value) = match arg.split_once('=') {
+                Some((flag, value)) => (flag.to_string(), Some(value.to_string())),
+                None => (arg, None),
+            };
+
+            match flag.as_str() {
+                "--backend" => self.backend = value.or_else(|| args.next()),
+                "--adapter" => self.adapter = value.or_else(|| args.next()),
+                "--api-port" => {
+                    let port = value.or_else(|| args.next());
+                    match port.as_deref().map(str::parse) {
+                        Some(Ok(port)) => self.api_port = Some(port),
+                        _ => crash::log(format!("Invalid API port: {:?}", port)),
+                    }
+                }
+                _ => crash::log(format!("Unknown argument: {}", flag)),
+            }
+        }
+    }
+
+    pub fn backends(&self) -> Backends {
+        let Some(name) = &self.backend else {
+            return Backends::all();
+        };
+
+        match name.to_lowercase().as_str() {
+            "vulkan" => Backends::VULKAN,
+            "dx12" => Backends::DX12,
+            "metal" => Backends::METAL,
+            "gl" => Backends::GL,
+            _ => {
+                crash::log(format!(
+                    "Unknown backend {}, expected vulkan, dx12, metal or gl",
+                    name
+                ));
+                Backends::all()
+            }
+        }
+    }
+}
@@ -1,5 +1,13 @@
 // shader.wgsl
 
+struct Camera {
+    translate: vec2<f32>, // Pan in clip space
+    scale: f32,           // Zoom factor
+};
+
+@group(0) @binding(0)
+var<uniform> camera: Camera;
+
 struct VertexInput {
     @location(0) position: vec2<f32>, // 2D position
     @location(1) color: vec4<f32>,    // RGBA color
@@ -16,8 +24,8 @@ struct VertexOutput {
 fn vs_main(input: VertexInput) -> VertexOutput {
     var output: VertexOutput;
     
-    // Transform 2D position to 4D clip space
-    output.position = vec4<f32>(input.position, 0.0, 1.0);
+    // Apply the camera, then transform 2D position to 4D clip space
+    output.position = vec4<f32>(input.position * camera.scale + camera.translate, 0.0, 1.0);
     
     // Pass color to fragment shader
     output.color = input.color;
@@ -0,0 +1,49 @@
+struct Camera {
+    translate: vec2<f32>, // Pan in clip space
+    scale: f32,           // Zoom factor
+};
+
+@group(0) @binding(0)
+var<uniform> camera: Camera;
+
+@group(1) @binding(0)
+var image_texture: texture_2d<f32>;
+@group(1) @binding(1)
+var image_sampler: sampler;
+
+struct VertexInput {
+    @location(0) position: vec2<f32>, // 2D position
+    @location(1) uv: vec2<f32>,       // Texture coordinate
+    @location(2) effect: vec4<f32>,   // Brightness, contrast, grayscale
+};
+
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+    @location(1) effect: vec4<f32>,
+};
+
+@vertex
+fn image_vs(input: VertexInput) -> VertexOutput {
+    var output: VertexOutput;
+
+    // Move the position into clip space through the camera
+    output.position = vec4<f32>(input.position * camera.scale + camera.translate, 0.0, 1.0);
+    output.uv = input.uv;
+    output.effect = input.effect;
+
+    return output;
+}
+
+@fragment
+fn image_fs(input: VertexOutput) -> @location(0) vec4<f32> {
+    let color = textureSample(image_texture, image_sampler, input.uv);
+    var rgb = color.rgb;
+
+    // Same weights as images::LUMA
+    let luma = dot(rgb, vec3<f32>(0.299, 0.587, 0.114));
+    rgb = mix(rgb, vec3<f32>(luma), input.effect.z);
+
+    rgb = (rgb - 0.5) * input.effect.y + 0.5 + input.effect.x;
+    return vec4<f32>(clamp(rgb, vec3<f32>(0.0), vec3<f32>(1.0)), color.a);
+}
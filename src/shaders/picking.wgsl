@@ -0,0 +1,33 @@
+struct PickUniform {
+    offset: vec2<f32>, // Clip space position of the cursor
+    scale: vec2<f32>,  // Window size divided by the picking region size
+};
+
+@group(0) @binding(0)
+var<uniform> pick: PickUniform;
+
+struct VertexInput {
+    @location(0) position: vec2<f32>, // 2D position
+    @location(1) id: u32,             // Object id, 0 means nothing
+};
+
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) @interpolate(flat) id: u32,
+};
+
+@vertex
+fn pick_vs(input: VertexInput) -> VertexOutput {
+    var output: VertexOutput;
+
+    // Zoom the region around the cursor onto the whole picking target
+    output.position = vec4<f32>((input.position - pick.offset) * pick.scale, 0.0, 1.0);
+    output.id = input.id;
+
+    return output;
+}
+
+@fragment
+fn pick_fs(input: VertexOutput) -> @location(0) u32 {
+    return input.id;
+}
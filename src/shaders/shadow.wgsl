@@ -0,0 +1,48 @@
+struct Camera {
+    translate: vec2<f32>, // Pan in clip space
+    scale: f32,           // Zoom factor
+};
+
+@group(0) @binding(0)
+var<uniform> camera: Camera;
+
+struct VertexInput {
+    @location(0) position: vec2<f32>,  // Clip space position
+    @location(1) local: vec2<f32>,     // Offset from the shadow center in pixels
+    @location(2) half_size: vec2<f32>, // Half size of the casting object in pixels
+    @location(3) blur: f32,            // Blur radius in pixels
+    @location(4) color: vec4<f32>,     // RGBA color
+};
+
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) local: vec2<f32>,
+    @location(1) half_size: vec2<f32>,
+    @location(2) blur: f32,
+    @location(3) color: vec4<f32>,
+};
+
+@vertex
+fn shadow_vs(input: VertexInput) -> VertexOutput {
+    var output: VertexOutput;
+
+    output.position = vec4<f32>(input.position * camera.scale + camera.translate, 0.0, 1.0);
+    output.local = input.local;
+    output.half_size = input.half_size;
+    output.blur = input.blur;
+    output.color = input.color;
+
+    return output;
+}
+
+@fragment
+fn shadow_fs(input: VertexOutput) -> @location(0) vec4<f32> {
+    // Signed distance to the box edge, negative inside
+    let q = abs(input.local) - input.half_size;
+    let distance = length(max(q, vec2<f32>(0.0))) + min(max(q.x, q.y), 0.0);
+
+    let blur = max(input.blur, 0.5);
+    let alpha = 1.0 - smoothstep(-blur, blur, distance);
+
+    return vec4<f32>(input.color.rgb, input.color.a * alpha);
+}
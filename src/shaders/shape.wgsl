@@ -1,3 +1,11 @@
+struct Camera {
+    translate: vec2<f32>, // Pan in clip space
+    scale: f32,           // Zoom factor
+};
+
+@group(0) @binding(0)
+var<uniform> camera: Camera;
+
 // Shared Input and Output Structures
 struct VertexInput {
     @location(0) position: vec2<f32>, // 2D position
@@ -16,8 +24,8 @@ struct VertexOutput {
 fn triangle_vs(input: VertexInput) -> VertexOutput {
     var output: VertexOutput;
 
-    // Pass-through vertex position and color
-    output.position = vec4<f32>(input.position, 0.0, 1.0);
+    // Apply the camera and pass the color through
+    output.position = vec4<f32>(input.position * camera.scale + camera.translate, 0.0, 1.0);
     output.color = input.color;
 
     return output;
@@ -28,8 +36,8 @@ fn triangle_vs(input: VertexInput) -> VertexOutput {
 fn rectangle_vs(input: VertexInput) -> VertexOutput {
     var output: VertexOutput;
 
-    // Pass the position to clip space
-    output.position = vec4<f32>(input.position, 0.0, 1.0);
+    // Move the position into clip space through the camera
+    output.position = vec4<f32>(input.position * camera.scale + camera.translate, 0.0, 1.0);
 
     // Pass the color to the fragment shader
     output.color = input.color;
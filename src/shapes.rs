@@ -0,0 +1,467 @@
+use crate::{
+    diagram, fill, ndc_to_screen,
+    picking::{PickVertex, PICK_RADIUS},
+    points_bounds, screen_to_ndc, Action, BoardImage, Damage, Erased, Fill, ObjectMove, ObjectRef,
+    ObjectResize, Rect, Rectangle, SelectDrag, Stencil, StencilMove, Stroke, Vertex, WindowState,
+    SELECTION_HANDLE,
+};
+
+impl WindowState<'_> {
+    pub(crate) fn finish_rectangle(&mut self) {
+        if let (Some(first), Some(last)) =
+            (self.shape_positions.first(), self.shape_positions.last())
+        {
+            if self.redacting {
+                let [x1, y1] = ndc_to_screen(self.size, first.position);
+                let [x2, y2] = ndc_to_screen(self.size, last.position);
+                let area = Rect {
+                    x: x1.min(x2),
+                    y: y1.min(y2),
+                    width: (x2 - x1).abs(),
+                    height: (y2 - y1).abs(),
+                };
+                // A pixelated redaction leaves no box behind, only its effect.
+                self.board.redo_actions.clear();
+                self.board.redact(area, self.settings.pixelate_redactions);
+                self.damage = Damage::Full;
+            } else {
+                let rectangle = Rectangle {
+                    first: first.position,
+                    last: last.position,
+                    color: self.current_color,
+                    shadow: self.shadow_style(),
+                    filled: false,
+                    layer: self.board.current_layer,
+                };
+
+                self.board.push_action(Action::Shapes(rectangle));
+            }
+        }
+
+        self.redacting = false;
+        self.shape_positions.clear();
+    }
+
+    pub(crate) fn arrange_stencils(&mut self) {
+        let mut members: Vec<usize> = self
+            .board
+            .connectors
+            .iter()
+            .flat_map(|connector| [connector.from, connector.to])
+            .filter(|index| *index < self.board.stencils.len())
+            .collect();
+        members.sort_unstable();
+        members.dedup();
+
+        // With stencils selected, only the diagrams they belong to move.
+        let mut reached: Vec<usize> = self
+            .selection
+            .iter()
+            .filter_map(|object| match object {
+                ObjectRef::Stencil(index) => Some(*index),
+                _ => None,
+            })
+            .collect();
+        if !reached.is_empty() {
+            let mut grown = true;
+            while grown {
+                grown = false;
+                for connector in &self.board.connectors {
+                    for (a, b) in [
+                        (connector.from, connector.to),
+                        (connector.to, connector.from),
+                    ] {
+                        if reached.contains(&a) && !reached.contains(&b) {
+                            reached.push(b);
+                            grown = true;
+                        }
+                    }
+                }
+            }
+            members.retain(|index| reached.contains(index));
+        }
+        if members.is_empty() {
+            return;
+        }
+
+        let local = |index: usize| members.binary_search(&index).ok();
+        let edges: Vec<_> = self
+            .board
+            .connectors
+            .iter()
+            .filter_map(|connector| Some((local(connector.from)?, local(connector.to)?)))
+            .collect();
+
+        let bounds: Vec<Rect> = members
+            .iter()
+            .map(|&index| self.board.stencils[index].bounds())
+            .collect();
+        let left = bounds.iter().map(|b| b.x).fold(f32::MAX, f32::min);
+        let right = bounds
+            .iter()
+            .map(|b| b.x + b.width)
+            .fold(f32::MIN, f32::max);
+        let top = bounds.iter().map(|b| b.y).fold(f32::MAX, f32::min);
+        let tallest = bounds.iter().map(|b| b.height).fold(0.0, f32::max);
+        let origin = [(left + right) / 2.0, top + tallest / 2.0];
+
+        let centers = diagram::arrange(members.len(), &edges, origin);
+        let moves = members
+            .iter()
+            .zip(centers)
+            .map(|(&index, center)| {
+                let stencil = &self.board.stencils[index];
+                StencilMove {
+                    index,
+                    from: stencil.position,
+                    to: [
+                        center[0] - stencil.size[0] / 2.0,
+                        center[1] - stencil.size[1] / 2.0,
+                    ],
+                }
+            })
+            .collect();
+        self.board.push_action(Action::Arrange(moves));
+    }
+
+    pub(crate) fn pick(&self, position: [f32; 2]) -> Option<ObjectRef> {
+        let radius = PICK_RADIUS as f32;
+        let near = |bounds: &Rect| {
+            position[0] >= bounds.x - radius
+                && position[0] <= bounds.x + bounds.width + radius
+                && position[1] >= bounds.y - radius
+                && position[1] <= bounds.y + bounds.height + radius
+        };
+
+        let mut candidates = Vec::new();
+        let mut triangles = Vec::new();
+        let mut lines = Vec::new();
+
+        // Pictures sit beneath everything else.
+        let image_bounds: Vec<_> = self.board.images.iter().map(BoardImage::bounds).collect();
+        let quads = image_bounds
+            .iter()
+            .enumerate()
+            .map(|(index, bounds)| (ObjectRef::Image(index), bounds))
+            .chain(
+                self.board
+                    .texts
+                    .iter()
+                    .enumerate()
+                    .map(|(index, text)| (ObjectRef::Text(index), &text.bounds)),
+            )
+            .chain(
+                self.board
+                    .tables
+                    .iter()
+                    .enumerate()
+                    .map(|(index, table)| (ObjectRef::Table(index), &table.bounds)),
+            )
+            .chain(
+                self.board
+                    .code_blocks
+                    .iter()
+                    .enumerate()
+                    .map(|(index, code_block)| (ObjectRef::Code(index), &code_block.bounds)),
+            );
+        let fill_rects = self
+            .board
+            .fills
+            .iter()
+            .enumerate()
+            .flat_map(|(index, fill)| {
+                let object = ObjectRef::Fill(index);
+                fill.rects.iter().map(move |rect| (object, rect))
+            });
+        let quads = quads.chain(fill_rects);
+        let stencil_bounds: Vec<_> = self.board.stencils.iter().map(Stencil::bounds).collect();
+        let quads = quads.chain(
+            stencil_bounds
+                .iter()
+                .enumerate()
+                .map(|(index, bounds)| (ObjectRef::Stencil(index), bounds)),
+        );
+        for (object, bounds) in quads {
+            if !near(bounds) || self.board.layer_rank(object).is_none() {
+                continue;
+            }
+            // A fill is covered by many rectangles that all answer for it.
+            if candidates.last() != Some(&object) {
+                candidates.push(object);
+            }
+            let id = candidates.len() as u32;
+            let (left, top) = (bounds.x, bounds.y);
+            let (right, bottom) = (bounds.x + bounds.width, bounds.y + bounds.height);
+            for (x, y) in [
+                (left, top),
+                (right, top),
+                (right, bottom),
+                (left, top),
+                (right, bottom),
+                (left, bottom),
+            ] {
+                triangles.push(PickVertex {
+                    position: screen_to_ndc(self.size, x, y),
+                    id,
+                });
+            }
+        }
+
+        let outlines =
+            self.board
+                .shapes
+                .iter()
+                .enumerate()
+                .map(|(index, shape)| (ObjectRef::Shape(index), shape.to_vertices()))
+                .chain(
+                    self.board
+                        .strokes
+                        .iter()
+                        .enumerate()
+                        .map(|(index, stroke)| {
+                            let mut vertices = Vec::new();
+                            Stroke::line_vertices(&stroke.points, &mut vertices);
+                            (ObjectRef::Stroke(index), vertices)
+                        }),
+                )
+                .chain(self.board.connectors.iter().enumerate().filter_map(
+                    |(index, connector)| {
+                        let (from, to) = connector.endpoints(&self.board.stencils)?;
+                        let vertices = [from, to]
+                            .map(|[x, y]| Vertex {
+                                position: screen_to_ndc(self.size, x, y),
+                                color: [0.0; 4],
+                            })
+                            .to_vec();
+                        Some((ObjectRef::Connector(index), vertices))
+                    },
+                ));
+        for (object, vertices) in outlines {
+            if !near(&points_bounds(self.size, &vertices))
+                || self.board.layer_rank(object).is_none()
+            {
+                continue;
+            }
+            candidates.push(object);
+            let id = candidates.len() as u32;
+            lines.extend(vertices.iter().map(|vertex| PickVertex {
+                position: vertex.position,
+                id,
+            }));
+        }
+
+        if !self.gpu_picking {
+            return candidates
+                .iter()
+                .copied()
+                .max_by_key(|&object| self.board.layer_rank(object));
+        }
+
+        let id = self.picker.pick(
+            &self.device,
+            &self.queue,
+            [self.size.width as f32, self.size.height as f32],
+            position,
+            &triangles,
+            &lines,
+        )?;
+        candidates.get(id as usize - 1).copied()
+    }
+
+    pub(crate) fn start_selection_drag(&mut self, position: [f32; 2]) {
+        if let [object] = self.selection[..] {
+            if let Some(corners) = self.board.object_corners(object) {
+                let handle = corners.iter().position(|&[x, y]| {
+                    let distance = ((x - position[0]).powi(2) + (y - position[1]).powi(2)).sqrt();
+                    distance * self.board.camera.zoom <= SELECTION_HANDLE
+                });
+                if let Some(corner) = handle {
+                    self.select_drag = Some(SelectDrag::Resize {
+                        object,
+                        corner,
+                        from: corners,
+                    });
+                    return;
+                }
+            }
+        }
+
+        match self.pick(position) {
+            Some(object) => {
+                if !self.selection.contains(&object) {
+                    self.selection = vec![object];
+                }
+                self.select_drag = Some(SelectDrag::Move {
+                    last: position,
+                    total: [0.0, 0.0],
+                });
+            }
+            None => {
+                self.selection.clear();
+                self.select_drag = Some(SelectDrag::Band {
+                    start: position,
+                    end: position,
+                });
+            }
+        }
+    }
+
+    pub(crate) fn drag_selection(&mut self, position: [f32; 2]) {
+        match &mut self.select_drag {
+            Some(SelectDrag::Move { last, total }) => {
+                let delta = [position[0] - last[0], position[1] - last[1]];
+                *last = position;
+                total[0] += delta[0];
+                total[1] += delta[1];
+                for object in self.selection.clone() {
+                    self.board.translate_object(object, delta);
+                }
+            }
+            Some(SelectDrag::Resize { object, corner, .. }) => {
+                let (object, corner) = (*object, *corner);
+                if let Some(mut corners) = self.board.object_corners(object) {
+                    corners[corner] = position;
+                    self.board.set_object_corners(object, corners);
+                }
+            }
+            Some(SelectDrag::Band { end, .. }) => *end = position,
+            None => {}
+        }
+    }
+
+    pub(crate) fn finish_selection_drag(&mut self) {
+        match self.select_drag.take() {
+            Some(SelectDrag::Move { total, .. }) if total != [0.0, 0.0] => {
+                let objects = self.selection.clone();
+                for &object in &objects {
+                    self.board.translate_object(object, [-total[0], -total[1]]);
+                }
+                self.board.push_action(Action::Move(ObjectMove {
+                    objects,
+                    delta: total,
+                }));
+            }
+            Some(SelectDrag::Resize { object, from, .. }) => {
+                let Some(to) = self.board.object_corners(object) else {
+                    return;
+                };
+                self.board.set_object_corners(object, from);
+                if to != from {
+                    self.board
+                        .push_action(Action::Resize(ObjectResize { object, from, to }));
+                }
+            }
+            Some(SelectDrag::Band { start, end }) => {
+                let band = Rect {
+                    x: start[0].min(end[0]),
+                    y: start[1].min(end[1]),
+                    width: (start[0] - end[0]).abs(),
+                    height: (start[1] - end[1]).abs(),
+                };
+                let objects = (0..self.board.strokes.len())
+                    .map(ObjectRef::Stroke)
+                    .chain((0..self.board.shapes.len()).map(ObjectRef::Shape))
+                    .chain((0..self.board.images.len()).map(ObjectRef::Image))
+                    .chain((0..self.board.fills.len()).map(ObjectRef::Fill))
+                    .chain((0..self.board.texts.len()).map(ObjectRef::Text))
+                    .chain((0..self.board.tables.len()).map(ObjectRef::Table))
+                    .chain((0..self.board.code_blocks.len()).map(ObjectRef::Code))
+                    .chain((0..self.board.stencils.len()).map(ObjectRef::Stencil));
+                self.selection = objects
+                    .filter(|&object| {
+                        self.board.layer_rank(object).is_some()
+                            && self
+                                .board
+                                .object_bounds(object)
+                                .is_some_and(|bounds| bounds.intersects(&band))
+                    })
+                    .collect();
+            }
+            _ => {}
+        }
+    }
+
+    pub(crate) fn erase_at(&mut self, position: [f32; 2]) -> bool {
+        let hit = self.pick(position);
+        if let Some(object) = hit {
+            self.selection.clear();
+            self.board.push_action(Action::Erase(Erased {
+                object,
+                content: None,
+            }));
+        }
+        hit.is_some()
+    }
+
+    pub(crate) fn eraser_reach(&self) -> f32 {
+        (self.settings.eraser_radius * self.eraser_pressure).max(PICK_RADIUS as f32)
+    }
+
+    // Picks across the whole eraser circle, a pick radius apart, so anything
+    // under it goes at once.
+    pub(crate) fn erase_around(&mut self, position: [f32; 2]) -> bool {
+        let reach = self.eraser_reach();
+        let step = (PICK_RADIUS * 2) as f32;
+        let count = (reach / step) as i32;
+        let mut erased = false;
+        for row in -count..=count {
+            for column in -count..=count {
+                let [dx, dy] = [column as f32 * step, row as f32 * step];
+                if dx * dx + dy * dy <= reach * reach {
+                    erased |= self.erase_at([position[0] + dx, position[1] + dy]);
+                }
+            }
+        }
+        erased
+    }
+
+    // Floods the region under `screen` that visible strokes and rectangles
+    // enclose at the current view.
+    pub(crate) fn bucket_fill(&mut self, screen: [f32; 2]) {
+        let mut mask = fill::Mask::new(self.size.width as f32, self.size.height as f32);
+        let zoom = self.board.camera.zoom;
+        let to_screen = |position: [f32; 2]| {
+            self.board
+                .camera
+                .to_screen(ndc_to_screen(self.size, position))
+        };
+        for (index, stroke) in self.board.strokes.iter().enumerate() {
+            if self.board.layer_rank(ObjectRef::Stroke(index)).is_none() {
+                continue;
+            }
+            let points: Vec<_> = stroke
+                .points
+                .iter()
+                .map(|point| to_screen(point.position))
+                .collect();
+            mask.add_line(&points, stroke.width * zoom / 2.0);
+        }
+        for (index, shape) in self.board.shapes.iter().enumerate() {
+            if self.board.layer_rank(ObjectRef::Shape(index)).is_none() {
+                continue;
+            }
+            let ([x1, y1], [x2, y2]) = (to_screen(shape.first), to_screen(shape.last));
+            mask.add_line(&[[x1, y1], [x2, y1], [x2, y2], [x1, y2], [x1, y1]], 0.0);
+        }
+        let Some(rects) = mask.flood(screen) else {
+            return;
+        };
+        let rects = rects
+            .into_iter()
+            .map(|[left, top, right, bottom]| {
+                let [x, y] = self.board.camera.to_world([left, top]);
+                Rect {
+                    x,
+                    y,
+                    width: (right - left) / zoom,
+                    height: (bottom - top) / zoom,
+                }
+            })
+            .collect();
+        self.board.push_object(Action::Fill(Fill {
+            rects,
+            color: self.current_color,
+            layer: self.board.current_layer,
+        }));
+    }
+}
@@ -0,0 +1,269 @@
+use crate::{
+    board::Board,
+    commands::{Binding, Command, Context, Keymap},
+    ndc_to_screen, screen_to_ndc, smoothing, timestamp_millis, Action, BlendMode, Brush, Erased,
+    ObjectRef, Rect, Rectangle, Shadow, Stroke, StrokeLod, Vertex,
+};
+use tao::{
+    dpi::{PhysicalPosition, PhysicalSize},
+    event::{DeviceId, ElementState, MouseButton, WindowEvent},
+    keyboard::{Key, ModifiersState},
+};
+
+// Plays window events and key presses into a board the way the window does,
+// without a window or a GPU, so whole interactions can be checked against the
+// scene they leave. The left button draws with the pen, or a rectangle or a
+// redaction while its key is held, and keys run their board commands. Other
+// tools and the interface are not simulated.
+pub struct Driver {
+    pub board: Board,
+    pub color: [f32; 4],
+    pub width: f32,
+    pub smooth_strokes: bool,
+    keymap: Keymap,
+    pressed: bool,
+    held_shape: Option<Command>,
+    points: Vec<Vertex>,
+    timestamps: Vec<u64>,
+}
+
+impl Driver {
+    pub fn new(size: PhysicalSize<u32>) -> Self {
+        Driver {
+            board: Board::new(size),
+            color: [0.0, 0.0, 0.0, 1.0],
+            width: 2.0,
+            smooth_strokes: false,
+            keymap: Keymap::new(&[]),
+            pressed: false,
+            held_shape: None,
+            points: Vec::new(),
+            timestamps: Vec::new(),
+        }
+    }
+
+    // Returns whether the event was one the driver plays.
+    pub fn window_event(&mut self, event: &WindowEvent) -> bool {
+        match event {
+            WindowEvent::CursorMoved { position, .. } => {
+                if self.pressed {
+                    self.track(*position);
+                }
+                true
+            }
+            WindowEvent::MouseInput {
+                state,
+                button: MouseButton::Left,
+                ..
+            } => {
+                match state {
+                    ElementState::Pressed => {
+                        self.pressed = true;
+                        self.points.clear();
+                        self.timestamps.clear();
+                    }
+                    _ => self.release_button(),
+                }
+                true
+            }
+            _ => false,
+        }
+    }
+
+    // Moves to the first point, presses the left button, follows the rest of
+    // the path and lets go.
+    pub fn drag(&mut self, path: &[[f64; 2]]) {
+        let Some(first) = path.first() else {
+            return;
+        };
+        self.window_event(&cursor_moved(*first));
+        self.window_event(&mouse_input(ElementState::Pressed));
+        for point in path {
+            self.window_event(&cursor_moved(*point));
+        }
+        self.window_event(&mouse_input(ElementState::Released));
+    }
+
+    // Presses each space-separated key combination in turn, like "S" or
+    // "Ctrl+Z", and keeps the last one held until `release_keys`.
+    pub fn press_keys(&mut self, keys: &str) {
+        for keys in keys.split_whitespace() {
+            let Some(binding) = Binding::parse(keys) else {
+                continue;
+            };
+            let key = Key::Character(&binding.key);
+            let command = self.keymap.lookup(
+                Context::Board,
+                &key,
+                binding.ctrl,
+                binding.alt,
+                binding.shift,
+            );
+            if let Some(command) = command {
+                self.run_command(command);
+            }
+        }
+    }
+
+    // Letting go of a shape key in the middle of a drag finishes the shape.
+    pub fn release_keys(&mut self) {
+        self.finish_rectangle();
+        self.held_shape = None;
+    }
+
+    fn run_command(&mut self, command: Command) {
+        match command {
+            Command::Undo => self.board.undo(),
+            Command::Redo => self.board.redo(),
+            Command::EraseLastStroke => {
+                if let Some(index) = self.board.strokes.len().checked_sub(1) {
+                    self.board.push_action(Action::Erase(Erased {
+                        object: ObjectRef::Stroke(index),
+                        content: None,
+                    }));
+                }
+            }
+            Command::HoldRectangle | Command::HoldRedaction => self.held_shape = Some(command),
+            Command::PreviousPage => {
+                let index = self.board.current_page.saturating_sub(1);
+                self.board.show_page(index);
+            }
+            Command::NextPage => {
+                self.board.show_page(self.board.current_page + 1);
+            }
+            Command::NewPage => self.board.new_page(),
+            _ => {}
+        }
+    }
+
+    // A held shape key keeps the drag's first and latest points, the pen every
+    // point.
+    fn track(&mut self, position: PhysicalPosition<f64>) {
+        let [x, y] = self
+            .board
+            .camera
+            .to_world([position.x as f32, position.y as f32]);
+        let point = Vertex {
+            position: screen_to_ndc(self.board.size, x, y),
+            color: self.color,
+        };
+        if self.held_shape.is_some() && self.points.len() > 1 {
+            self.points.pop();
+        }
+        self.points.push(point);
+        self.timestamps.push(timestamp_millis());
+    }
+
+    fn release_button(&mut self) {
+        self.pressed = false;
+        if self.held_shape.is_some() {
+            self.finish_rectangle();
+            return;
+        }
+        let mut points = std::mem::take(&mut self.points);
+        let mut timestamps = std::mem::take(&mut self.timestamps);
+        if points.is_empty() {
+            return;
+        }
+        if self.smooth_strokes {
+            (points, timestamps) = smoothing::smooth(&points, &timestamps, self.board.size);
+        }
+        let stroke = Stroke {
+            points,
+            blend: BlendMode::Normal,
+            brush: Brush::Pen,
+            layer: self.board.current_layer,
+            timestamps,
+            width: self.width,
+            lod: StrokeLod::default(),
+        };
+        self.board.push_action(Action::Stroke(stroke));
+    }
+
+    fn finish_rectangle(&mut self) {
+        if self.held_shape.is_none() {
+            return;
+        }
+        let points = std::mem::take(&mut self.points);
+        self.timestamps.clear();
+        let (Some(first), Some(last)) = (points.first(), points.last()) else {
+            return;
+        };
+        if self.held_shape == Some(Command::HoldRedaction) {
+            let [x1, y1] = ndc_to_screen(self.board.size, first.position);
+            let [x2, y2] = ndc_to_screen(self.board.size, last.position);
+            let area = Rect {
+                x: x1.min(x2),
+                y: y1.min(y2),
+                width: (x2 - x1).abs(),
+                height: (y2 - y1).abs(),
+            };
+            self.board.redo_actions.clear();
+            self.board.redact(area, false);
+        } else {
+            self.board.push_action(Action::Shapes(Rectangle {
+                first: first.position,
+                last: last.position,
+                color: self.color,
+                shadow: Shadow::default(),
+                filled: false,
+                layer: self.board.current_layer,
+            }));
+        }
+    }
+}
+
+// The events are only built to be handed to the driver; the dummy device is
+// never passed to tao.
+#[allow(deprecated)]
+pub fn cursor_moved([x, y]: [f64; 2]) -> WindowEvent<'static> {
+    WindowEvent::CursorMoved {
+        device_id: unsafe { DeviceId::dummy() },
+        position: PhysicalPosition::new(x, y),
+        modifiers: ModifiersState::empty(),
+    }
+}
+
+#[allow(deprecated)]
+pub fn mouse_input(state: ElementState) -> WindowEvent<'static> {
+    WindowEvent::MouseInput {
+        device_id: unsafe { DeviceId::dummy() },
+        state,
+        button: MouseButton::Left,
+        modifiers: ModifiersState::empty(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn the_rectangle_key_and_a_drag_make_one_rectangle() {
+        let mut driver = Driver::new(PhysicalSize::new(800, 600));
+        driver.press_keys("S");
+        driver.drag(&[[100.0, 100.0], [180.0, 150.0], [300.0, 200.0]]);
+        driver.release_keys();
+
+        assert_eq!(driver.board.shapes.len(), 1);
+        assert!(driver.board.strokes.is_empty());
+        let rectangle = &driver.board.shapes[0];
+        let size = driver.board.size;
+        let corner = |ndc| ndc_to_screen(size, ndc).map(f32::round);
+        assert_eq!(corner(rectangle.first), [100.0, 100.0]);
+        assert_eq!(corner(rectangle.last), [300.0, 200.0]);
+    }
+
+    #[test]
+    fn a_drag_draws_a_stroke_that_undo_takes_back() {
+        let mut driver = Driver::new(PhysicalSize::new(800, 600));
+        driver.drag(&[[10.0, 10.0], [20.0, 40.0], [60.0, 50.0]]);
+        assert_eq!(driver.board.strokes.len(), 1);
+        assert_eq!(driver.board.strokes[0].points.len(), 3);
+
+        driver.press_keys("Ctrl+Z");
+        assert!(driver.board.strokes.is_empty());
+        driver.press_keys("Ctrl+Y");
+        assert_eq!(driver.board.strokes.len(), 1);
+    }
+}
@@ -0,0 +1,92 @@
+use crate::Vertex;
+use tao::dpi::PhysicalSize;
+
+const PIXELS_PER_STEP: f32 = 4.0;
+const MAX_STEPS: usize = 16;
+
+// Resamples a captured stroke along a Catmull-Rom spline through its points,
+// interpolating colors and timestamps so replay keeps its pacing.
+pub fn smooth(
+    points: &[Vertex],
+    timestamps: &[u64],
+    size: PhysicalSize<u32>,
+) -> (Vec<Vertex>, Vec<u64>) {
+    if points.len() < 3 {
+        return (points.to_vec(), timestamps.to_vec());
+    }
+    let timed = timestamps.len() == points.len();
+    let (half_width, half_height) = (size.width as f32 / 2.0, size.height as f32 / 2.0);
+
+    let mut smoothed = vec![points[0]];
+    let mut times = Vec::new();
+    if timed {
+        times.push(timestamps[0]);
+    }
+    for index in 0..points.len() - 1 {
+        let p0 = points[index.saturating_sub(1)].position;
+        let p1 = points[index].position;
+        let p2 = points[index + 1].position;
+        let p3 = points[(index + 2).min(points.len() - 1)].position;
+
+        let dx = (p2[0] - p1[0]) * half_width;
+        let dy = (p2[1] - p1[1]) * half_height;
+        let length = (dx * dx + dy * dy).sqrt();
+        let steps = ((length / PIXELS_PER_STEP).ceil() as usize).clamp(1, MAX_STEPS);
+
+        for step in 1..=steps {
+            let t = step as f32 / steps as f32;
+            let (t2, t3) = (t * t, t * t * t);
+            let axis = |axis: usize| {
+                0.5 * (2.0 * p1[axis]
+                    + (p2[axis] - p0[axis]) * t
+                    + (2.0 * p0[axis] - 5.0 * p1[axis] + 4.0 * p2[axis] - p3[axis]) * t2
+                    + (3.0 * p1[axis] - p0[axis] - 3.0 * p2[axis] + p3[axis]) * t3)
+            };
+            let (from, to) = (points[index].color, points[index + 1].color);
+            smoothed.push(Vertex {
+                position: [axis(0), axis(1)],
+                color: std::array::from_fn(|channel| {
+                    from[channel] + (to[channel] - from[channel]) * t
+                }),
+            });
+            if timed {
+                let (from, to) = (timestamps[index], timestamps[index + 1]);
+                times.push(from + ((to.saturating_sub(from)) as f32 * t) as u64);
+            }
+        }
+    }
+    (smoothed, times)
+}
+
+// Douglas-Peucker: the indices of the points to keep so that the polyline
+// through them stays within `tolerance` of every point left out. The ends are
+// always kept.
+pub fn simplify(points: &[[f32; 2]], tolerance: f32) -> Vec<u32> {
+    if points.len() < 3 {
+        return (0..points.len() as u32).collect();
+    }
+    let mut keep = vec![false; points.len()];
+    keep[0] = true;
+    keep[points.len() - 1] = true;
+    let mut spans = vec![(0, points.len() - 1)];
+    while let Some((start, end)) = spans.pop() {
+        let ([x1, y1], [x2, y2]) = (points[start], points[end]);
+        let (dx, dy) = (x2 - x1, y2 - y1);
+        let length = dx.hypot(dy);
+        let distance = |[x, y]: [f32; 2]| match length > f32::EPSILON {
+            true => (dy * (x - x1) - dx * (y - y1)).abs() / length,
+            false => (x - x1).hypot(y - y1),
+        };
+        let farthest = (start + 1..end)
+            .map(|index| (index, distance(points[index])))
+            .max_by(|a, b| a.1.total_cmp(&b.1));
+        if let Some((index, _)) = farthest.filter(|(_, distance)| *distance > tolerance) {
+            keep[index] = true;
+            spans.push((start, index));
+            spans.push((index, end));
+        }
+    }
+    (0..points.len() as u32)
+        .filter(|&index| keep[index as usize])
+        .collect()
+}
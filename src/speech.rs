@@ -0,0 +1,70 @@
+use crate::crash;
+use std::{
+    io::Write,
+    process::{Child, Command, Stdio},
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Language {
+    Persian,
+    English,
+}
+
+impl Language {
+    pub fn detect(text: &str) -> Self {
+        let letters = text.chars().filter(|c| c.is_alphabetic());
+        let (persian, total) = letters.fold((0, 0), |(persian, total), c| {
+            let arabic_script = matches!(c, '\u{0600}'..='\u{06FF}' | '\u{FB50}'..='\u{FDFF}');
+            (persian + arabic_script as usize, total + 1)
+        });
+        if total > 0 && persian * 2 >= total {
+            Language::Persian
+        } else {
+            Language::English
+        }
+    }
+
+    fn code(self) -> &'static str {
+        match self {
+            Language::Persian => "fa",
+            Language::English => "en",
+        }
+    }
+}
+
+pub fn speak(text: &str) -> Option<Child> {
+    let language = Language::detect(text);
+    let mut command = if cfg!(target_os = "macos") {
+        let mut command = Command::new("say");
+        command.arg(text);
+        command
+    } else if cfg!(target_os = "windows") {
+        let mut command = Command::new("powershell");
+        command.args([
+            "-NoProfile",
+            "-Command",
+            "Add-Type -AssemblyName System.Speech; \
+             $speaker = New-Object System.Speech.Synthesis.SpeechSynthesizer; \
+             $speaker.Speak([Console]::In.ReadToEnd())",
+        ]);
+        command.stdin(Stdio::piped());
+        command
+    } else {
+        let mut command = Command::new("espeak-ng");
+        command.args(["-v", language.code(), text]);
+        command
+    };
+
+    match command.spawn() {
+        Ok(mut child) => {
+            if let Some(mut stdin) = child.stdin.take() {
+                let _ = stdin.write_all(text.as_bytes());
+            }
+            Some(child)
+        }
+        Err(err) => {
+            crash::log(format!("Unable to start text to speech: {}", err));
+            None
+        }
+    }
+}
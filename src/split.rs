@@ -0,0 +1,57 @@
+use crate::{camera::Camera, export::paint, onion, text_layout, Action, Vertex};
+use glyphon::{Buffer, FontSystem};
+use tao::dpi::PhysicalSize;
+
+// A second page shown in the right half of the window with a camera of its
+// own, so a solution can be worked out beside the problem it answers.
+pub struct Split {
+    pub page: usize,
+    pub camera: Camera,
+    // Laid out on first draw and again after the window or the page changes.
+    pub drawing: Option<Drawing>,
+}
+
+impl Split {
+    // The page starts out with its top left corner at the divider.
+    pub fn new(page: usize, size: PhysicalSize<u32>) -> Self {
+        Split {
+            page,
+            camera: Camera {
+                pan: [divider(size) as f32, 0.0],
+                zoom: 1.0,
+            },
+            drawing: None,
+        }
+    }
+}
+
+// Where the right half starts, in window pixels.
+pub fn divider(size: PhysicalSize<u32>) -> u32 {
+    size.width / 2
+}
+
+// Everything but text is drawn with the fill pipeline, as for a ghost page,
+// and text is handed to glyphon clipped to the right half. Pictures are only
+// outlined.
+pub struct Drawing {
+    pub vertices: Vec<Vertex>,
+    pub texts: Vec<(Buffer, [f32; 2], [u8; 4])>,
+}
+
+impl Drawing {
+    pub fn new(page: &[Action], size: PhysicalSize<u32>, font_system: &mut FontSystem) -> Self {
+        let vertices = onion::vertices(page, size, 1.0, font_system);
+        let (visible, _) = paint::visible(page, size);
+        let texts = visible
+            .into_iter()
+            .filter_map(|action| match action {
+                Action::Text(text) => {
+                    let (buffer, origin) = text_layout(font_system, text, size);
+                    Some((buffer, origin, text.color))
+                }
+                _ => None,
+            })
+            .collect();
+        Drawing { vertices, texts }
+    }
+}
@@ -0,0 +1,142 @@
+use crate::{Action, Brush};
+use std::{cmp::Reverse, collections::HashMap, time::Duration};
+
+const MAX_DAYS: usize = 14;
+const TOP_COLORS: usize = 5;
+pub const MILLIS_PER_DAY: u64 = 24 * 60 * 60 * 1000;
+
+pub struct Statistics {
+    pub days: Vec<(String, Duration)>,
+    pub tools: Vec<(&'static str, usize)>,
+    pub colors: Vec<([u8; 4], usize)>,
+    pub pages_created: usize,
+}
+
+// Grouped edits count as the edits they are made of.
+fn flatten<'a>(actions: &'a [Action], parts: &mut Vec<&'a Action>) {
+    for action in actions {
+        match action {
+            Action::Group(group) => flatten(group, parts),
+            _ => parts.push(action),
+        }
+    }
+}
+
+impl Statistics {
+    pub fn compute(actions: &[Action], pages_created: usize) -> Self {
+        let mut days: HashMap<u64, u64> = HashMap::new();
+        let mut tools: HashMap<&'static str, usize> = HashMap::new();
+        let mut colors: HashMap<[u8; 4], usize> = HashMap::new();
+
+        let mut parts = Vec::new();
+        flatten(actions, &mut parts);
+        for action in parts {
+            let (tool, color) = match action {
+                Action::Stroke(stroke) => {
+                    if let (Some(first), Some(last)) =
+                        (stroke.timestamps.first(), stroke.timestamps.last())
+                    {
+                        *days.entry(first / MILLIS_PER_DAY).or_default() += last - first;
+                    }
+                    (
+                        match stroke.brush {
+                            Brush::Pen => "Pen",
+                            Brush::Highlighter => "Highlighter",
+                            Brush::Calligraphy => "Calligraphy",
+                        },
+                        stroke.points.first().map(|point| to_rgba(point.color)),
+                    )
+                }
+                Action::Text(text) => ("Text", Some(text.color)),
+                Action::Retext(_) => ("Text", None),
+                Action::Shapes(rectangle) => ("Rectangle", Some(to_rgba(rectangle.color))),
+                Action::Image(_) | Action::Retouch(_) | Action::Adjust(_) => ("Image", None),
+                Action::Fill(fill) => ("Bucket", Some(to_rgba(fill.color))),
+                Action::Table(table) => ("Table", Some(table.color)),
+                Action::Code(_) => ("Code", None),
+                Action::Stencil(stencil) => ("Stencil", Some(stencil.color)),
+                Action::Connector(connector) => ("Connector", Some(connector.color)),
+                Action::Arrange(_) => ("Auto arrange", None),
+                Action::Restyle(_) => ("Style", None),
+                Action::Redact(_) => ("Redact", None),
+                Action::Erase(_) => ("Eraser", None),
+                Action::Move(_) => ("Move", None),
+                Action::Resize(_) => ("Resize", None),
+                Action::Layers(_) | Action::Relayer(_) => ("Layers", None),
+                Action::Label(_) => ("Pages", None),
+                Action::Group(_) => continue,
+            };
+            *tools.entry(tool).or_default() += 1;
+            if let Some(color) = color {
+                *colors.entry(color).or_default() += 1;
+            }
+        }
+
+        let mut days: Vec<_> = days.into_iter().collect();
+        days.sort_by_key(|&(day, _)| Reverse(day));
+        days.truncate(MAX_DAYS);
+
+        let mut tools: Vec<_> = tools.into_iter().collect();
+        tools.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+
+        let mut colors: Vec<_> = colors.into_iter().collect();
+        colors.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+        colors.truncate(TOP_COLORS);
+
+        Statistics {
+            days: days
+                .into_iter()
+                .map(|(day, millis)| (date(day), Duration::from_millis(millis)))
+                .collect(),
+            tools,
+            colors,
+            pages_created,
+        }
+    }
+}
+
+fn to_rgba(color: [f32; 4]) -> [u8; 4] {
+    color.map(|channel| (channel.clamp(0.0, 1.0) * 255.0).round() as u8)
+}
+
+// The day count of the local calendar date at `millis` since the epoch.
+pub fn local_day(millis: u64) -> u64 {
+    (millis as i64 + utc_offset_millis(millis)).max(0) as u64 / MILLIS_PER_DAY
+}
+
+#[cfg(unix)]
+fn utc_offset_millis(millis: u64) -> i64 {
+    let seconds = (millis / 1000) as libc::time_t;
+    // SAFETY: `tm` is plain data that localtime_r fills in, and both pointers
+    // outlive the call.
+    let mut tm: libc::tm = unsafe { std::mem::zeroed() };
+    if unsafe { libc::localtime_r(&seconds, &mut tm) }.is_null() {
+        return 0;
+    }
+    tm.tm_gmtoff as i64 * 1000
+}
+
+// Elsewhere the zone is not read and dates stay in UTC.
+#[cfg(not(unix))]
+fn utc_offset_millis(_millis: u64) -> i64 {
+    0
+}
+
+pub fn date(days_since_epoch: u64) -> String {
+    // Civil date from a day count, after Howard Hinnant's days_from_civil inverse.
+    let z = days_since_epoch as i64 + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
@@ -0,0 +1,169 @@
+use crate::{rounded_rect_outline, Rect};
+use serde::{Deserialize, Serialize};
+
+const ACTOR_HEAD_SEGMENTS: usize = 16;
+const ACTOR_LABEL_HEIGHT: f32 = 28.0;
+const CLASS_HEADER: f32 = 0.3;
+const CLASS_ATTRIBUTES: f32 = 0.65;
+const NOTE_FOLD: f32 = 18.0;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum StencilKind {
+    Process,
+    Decision,
+    Terminator,
+    ClassBox,
+    Actor,
+    Note,
+}
+
+impl StencilKind {
+    pub const FLOWCHART: [StencilKind; 3] = [
+        StencilKind::Process,
+        StencilKind::Decision,
+        StencilKind::Terminator,
+    ];
+    pub const UML: [StencilKind; 3] =
+        [StencilKind::ClassBox, StencilKind::Actor, StencilKind::Note];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            StencilKind::Process => "Process",
+            StencilKind::Decision => "Decision",
+            StencilKind::Terminator => "Terminator",
+            StencilKind::ClassBox => "Class",
+            StencilKind::Actor => "Actor",
+            StencilKind::Note => "Note",
+        }
+    }
+
+    pub fn size(self) -> [f32; 2] {
+        match self {
+            StencilKind::Process => [140.0, 70.0],
+            StencilKind::Decision => [150.0, 90.0],
+            StencilKind::Terminator => [140.0, 56.0],
+            StencilKind::ClassBox => [160.0, 120.0],
+            StencilKind::Actor => [50.0, 100.0],
+            StencilKind::Note => [140.0, 90.0],
+        }
+    }
+
+    pub fn outline(self, bounds: &Rect) -> Vec<[f32; 2]> {
+        let (left, top) = (bounds.x, bounds.y);
+        let (right, bottom) = (bounds.x + bounds.width, bounds.y + bounds.height);
+        let (center_x, center_y) = (left + bounds.width / 2.0, top + bounds.height / 2.0);
+
+        let mut lines = Vec::new();
+        match self {
+            StencilKind::Process => polyline(
+                &[[left, top], [right, top], [right, bottom], [left, bottom]],
+                true,
+                &mut lines,
+            ),
+            StencilKind::Decision => polyline(
+                &[
+                    [center_x, top],
+                    [right, center_y],
+                    [center_x, bottom],
+                    [left, center_y],
+                ],
+                true,
+                &mut lines,
+            ),
+            StencilKind::Terminator => polyline(
+                &rounded_rect_outline(left, top, right, bottom, bounds.height / 2.0),
+                true,
+                &mut lines,
+            ),
+            StencilKind::ClassBox => {
+                polyline(
+                    &[[left, top], [right, top], [right, bottom], [left, bottom]],
+                    true,
+                    &mut lines,
+                );
+                for fraction in [CLASS_HEADER, CLASS_ATTRIBUTES] {
+                    let y = top + bounds.height * fraction;
+                    lines.extend([[left, y], [right, y]]);
+                }
+            }
+            StencilKind::Actor => {
+                let radius = bounds.width.min(bounds.height) * 0.25;
+                let neck = top + radius * 2.0;
+                let head: Vec<_> = (0..ACTOR_HEAD_SEGMENTS)
+                    .map(|step| {
+                        let angle =
+                            std::f32::consts::TAU * step as f32 / ACTOR_HEAD_SEGMENTS as f32;
+                        [
+                            center_x + radius * angle.cos(),
+                            top + radius + radius * angle.sin(),
+                        ]
+                    })
+                    .collect();
+                polyline(&head, true, &mut lines);
+
+                let hip = top + bounds.height * 0.65;
+                let shoulders = neck + (hip - neck) * 0.3;
+                lines.extend([
+                    [center_x, neck],
+                    [center_x, hip],
+                    [left, shoulders],
+                    [right, shoulders],
+                    [center_x, hip],
+                    [left, bottom],
+                    [center_x, hip],
+                    [right, bottom],
+                ]);
+            }
+            StencilKind::Note => {
+                let fold = NOTE_FOLD.min(bounds.width / 2.0).min(bounds.height / 2.0);
+                polyline(
+                    &[
+                        [left, top],
+                        [right - fold, top],
+                        [right, top + fold],
+                        [right, bottom],
+                        [left, bottom],
+                    ],
+                    true,
+                    &mut lines,
+                );
+                polyline(
+                    &[
+                        [right - fold, top],
+                        [right - fold, top + fold],
+                        [right, top + fold],
+                    ],
+                    false,
+                    &mut lines,
+                );
+            }
+        }
+        lines
+    }
+
+    pub fn label_area(self, bounds: &Rect) -> Rect {
+        match self {
+            StencilKind::ClassBox => Rect {
+                height: bounds.height * CLASS_HEADER,
+                ..bounds.clone()
+            },
+            StencilKind::Actor => Rect {
+                y: bounds.y + bounds.height,
+                height: ACTOR_LABEL_HEIGHT,
+                ..bounds.clone()
+            },
+            _ => bounds.clone(),
+        }
+    }
+}
+
+fn polyline(points: &[[f32; 2]], closed: bool, lines: &mut Vec<[f32; 2]>) {
+    for pair in points.windows(2) {
+        lines.extend([pair[0], pair[1]]);
+    }
+    if closed {
+        if let (Some(first), Some(last)) = (points.first(), points.last()) {
+            lines.extend([*last, *first]);
+        }
+    }
+}
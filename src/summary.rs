@@ -0,0 +1,140 @@
+use crate::{ndc_to_screen, scene, screen_to_ndc, Action, Rect, Rectangle, TextEntries};
+use tao::dpi::PhysicalSize;
+
+const MARGIN: f32 = 32.0;
+const LABEL_HEIGHT: f32 = 28.0;
+const LABEL_FONT_SIZE: i32 = 16;
+const FRAME_COLOR: [f32; 4] = [0.5, 0.5, 0.5, 1.0];
+const LABEL_COLOR: [u8; 4] = [128, 128, 128, 255];
+
+// A contact sheet of `pages`: each page's visible objects scaled down into one
+// cell of a grid that fills the window, framed and labelled with its number.
+pub fn contact_sheet(pages: &[Vec<Action>], size: PhysicalSize<u32>) -> Vec<Action> {
+    let (width, height) = (size.width as f32, size.height as f32);
+    let columns = (pages.len() as f32).sqrt().ceil().max(1.0) as usize;
+    let rows = pages.len().div_ceil(columns).max(1);
+    let cell_width = ((width - MARGIN) / columns as f32 - MARGIN).max(1.0);
+    let cell_height = ((height - MARGIN) / rows as f32 - MARGIN - LABEL_HEIGHT).max(1.0);
+
+    let mut sheet = Vec::new();
+    let mut stencils = 0;
+    for (index, page) in pages.iter().enumerate() {
+        let cell = Rect {
+            x: MARGIN + (index % columns) as f32 * (cell_width + MARGIN),
+            y: MARGIN + (index / columns) as f32 * (cell_height + MARGIN + LABEL_HEIGHT),
+            width: cell_width,
+            height: cell_height,
+        };
+        sheet.push(Action::Shapes(Rectangle {
+            first: screen_to_ndc(size, cell.x, cell.y),
+            last: screen_to_ndc(size, cell.x + cell.width, cell.y + cell.height),
+            color: FRAME_COLOR,
+            shadow: Default::default(),
+            filled: false,
+            layer: 0,
+        }));
+        let mut label = TextEntries::null(LABEL_COLOR, LABEL_FONT_SIZE);
+        label.text = format!("Page {}", index + 1);
+        label.position = [cell.x, cell.y + cell.height + LABEL_HEIGHT * 0.75];
+        label.pending = false;
+        sheet.push(Action::Text(label));
+
+        let hidden = scene::hidden_layers(page);
+        let visible = |action: &&Action| scene::is_visible(action, &hidden);
+        let Some(bounds) = scene::bounds(page.iter().filter(visible), size) else {
+            continue;
+        };
+        let factor = (cell.width / bounds.width.max(1.0))
+            .min(cell.height / bounds.height.max(1.0))
+            .min(1.0);
+        let offset = [
+            cell.x + (cell.width - bounds.width * factor) / 2.0 - bounds.x * factor,
+            cell.y + (cell.height - bounds.height * factor) / 2.0 - bounds.y * factor,
+        ];
+
+        // Connectors point at stencils by index, so they are renumbered to the
+        // stencils that made it onto the sheet.
+        let mut stencil_map = Vec::new();
+        for action in page {
+            if let Action::Stencil(_) = action {
+                stencil_map.push(visible(&action).then_some(stencils));
+            }
+            if !visible(&action) {
+                continue;
+            }
+            let mut action = action.clone();
+            match &mut action {
+                Action::Stencil(_) => stencils += 1,
+                Action::Connector(connector) => {
+                    let ends = (
+                        stencil_map.get(connector.from).copied().flatten(),
+                        stencil_map.get(connector.to).copied().flatten(),
+                    );
+                    let (Some(from), Some(to)) = ends else {
+                        continue;
+                    };
+                    (connector.from, connector.to) = (from, to);
+                }
+                _ => {}
+            }
+            scale_action(&mut action, size, factor, offset);
+            sheet.push(action);
+        }
+    }
+    sheet
+}
+
+fn scale_action(action: &mut Action, size: PhysicalSize<u32>, factor: f32, offset: [f32; 2]) {
+    let place = |[x, y]: [f32; 2]| [x * factor + offset[0], y * factor + offset[1]];
+    let place_ndc = |position: [f32; 2]| {
+        let [x, y] = place(ndc_to_screen(size, position));
+        screen_to_ndc(size, x, y)
+    };
+    let place_rect = |rect: &mut Rect| {
+        [rect.x, rect.y] = place([rect.x, rect.y]);
+        rect.width *= factor;
+        rect.height *= factor;
+    };
+    let scale_font = |font_size: &mut i32| {
+        *font_size = ((*font_size as f32 * factor).round() as i32).max(1);
+    };
+    match action {
+        Action::Stroke(stroke) => {
+            for point in &mut stroke.points {
+                point.position = place_ndc(point.position);
+            }
+            stroke.width *= factor;
+        }
+        Action::Shapes(rectangle) => {
+            rectangle.first = place_ndc(rectangle.first);
+            rectangle.last = place_ndc(rectangle.last);
+        }
+        Action::Text(text) => {
+            text.position = place(text.position);
+            place_rect(&mut text.bounds);
+            scale_font(&mut text.font_size);
+            text.wrap_width = text.wrap_width.map(|width| width * factor);
+        }
+        Action::Table(table) => {
+            table.position = place(table.position);
+            scale_font(&mut table.font_size);
+        }
+        Action::Code(code_block) => {
+            code_block.position = place(code_block.position);
+            scale_font(&mut code_block.font_size);
+        }
+        Action::Stencil(stencil) => {
+            stencil.position = place(stencil.position);
+            stencil.size = stencil.size.map(|side| side * factor);
+            scale_font(&mut stencil.label.font_size);
+        }
+        Action::Connector(connector) => scale_font(&mut connector.font_size),
+        Action::Image(image) => {
+            image.first = place(image.first);
+            image.last = place(image.last);
+        }
+        Action::Fill(fill) => fill.rects.iter_mut().for_each(place_rect),
+        Action::Redact(area) => place_rect(area),
+        _ => {}
+    }
+}
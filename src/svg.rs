@@ -0,0 +1,220 @@
+use usvg::{
+    tiny_skia_path::{PathSegment, Point},
+    utils::view_box_to_transform,
+    NodeKind, Opacity, Paint, Transform, TreeParsing,
+};
+
+const CURVE_STEPS: usize = 12;
+const MIN_WIDTH: f32 = 1.0;
+
+pub enum Element {
+    Polyline {
+        points: Vec<[f32; 2]>,
+        color: [u8; 4],
+        width: f32,
+    },
+    Rect {
+        min: [f32; 2],
+        max: [f32; 2],
+        color: [u8; 4],
+        filled: bool,
+    },
+    Text {
+        position: [f32; 2],
+        text: String,
+        color: [u8; 4],
+        font_size: f32,
+    },
+}
+
+pub struct Drawing {
+    pub elements: Vec<Element>,
+    pub unsupported: usize,
+}
+
+pub fn looks_like_svg(text: &str) -> bool {
+    let text = text.trim_start();
+    text.starts_with("<svg") || (text.starts_with("<?xml") && text.contains("<svg"))
+}
+
+pub fn parse(text: &str, fallback: [u8; 4]) -> Result<Drawing, String> {
+    let tree =
+        usvg::Tree::from_str(text, &usvg::Options::default()).map_err(|err| err.to_string())?;
+    let view = view_box_to_transform(tree.view_box.rect, tree.view_box.aspect, tree.size);
+
+    let mut drawing = Drawing {
+        elements: Vec::new(),
+        unsupported: 0,
+    };
+    for node in tree.root.descendants() {
+        let transform = node
+            .parent()
+            .and_then(|parent| match &*parent.borrow() {
+                NodeKind::Group(group) => Some(group.abs_transform),
+                _ => None,
+            })
+            .unwrap_or_default()
+            .post_concat(view);
+
+        match &*node.borrow() {
+            NodeKind::Group(_) => {}
+            NodeKind::Path(path) => {
+                let (color, width, filled) = match (&path.stroke, &path.fill) {
+                    (Some(stroke), _) => (
+                        color(&stroke.paint, stroke.opacity, fallback),
+                        stroke.width.get() * scale(transform),
+                        false,
+                    ),
+                    (None, Some(fill)) => {
+                        (color(&fill.paint, fill.opacity, fallback), MIN_WIDTH, true)
+                    }
+                    (None, None) => continue,
+                };
+                let polylines = flatten(&path.data, transform);
+                if let [points] = polylines.as_slice() {
+                    if let Some((min, max)) = axis_aligned_rect(points) {
+                        drawing.elements.push(Element::Rect {
+                            min,
+                            max,
+                            color,
+                            filled,
+                        });
+                        continue;
+                    }
+                }
+                for points in polylines {
+                    drawing.elements.push(Element::Polyline {
+                        points,
+                        color,
+                        width: width.max(MIN_WIDTH),
+                    });
+                }
+            }
+            NodeKind::Text(text) => {
+                for chunk in &text.chunks {
+                    let Some(span) = chunk.spans.first() else {
+                        continue;
+                    };
+                    let mut origin = Point::from_xy(chunk.x.unwrap_or(0.0), chunk.y.unwrap_or(0.0));
+                    transform.map_point(&mut origin);
+                    let font_size = span.font_size.get() * scale(transform);
+                    drawing.elements.push(Element::Text {
+                        // SVG positions text by its baseline, the board by its top edge.
+                        position: [origin.x, origin.y - font_size],
+                        text: chunk.text.clone(),
+                        color: span
+                            .fill
+                            .as_ref()
+                            .map_or(fallback, |fill| color(&fill.paint, fill.opacity, fallback)),
+                        font_size,
+                    });
+                }
+            }
+            NodeKind::Image(_) => drawing.unsupported += 1,
+        }
+    }
+
+    if drawing.elements.is_empty() {
+        return Err("no supported shapes".to_string());
+    }
+    Ok(drawing)
+}
+
+fn color(paint: &Paint, opacity: Opacity, fallback: [u8; 4]) -> [u8; 4] {
+    match paint {
+        Paint::Color(color) => [
+            color.red,
+            color.green,
+            color.blue,
+            (opacity.get() * 255.0).round() as u8,
+        ],
+        _ => fallback,
+    }
+}
+
+fn scale(transform: Transform) -> f32 {
+    let (sx, sy) = transform.get_scale();
+    (sx * sy).sqrt()
+}
+
+fn flatten(path: &usvg::tiny_skia_path::Path, transform: Transform) -> Vec<Vec<[f32; 2]>> {
+    let mut polylines = Vec::new();
+    let mut points: Vec<Point> = Vec::new();
+    let mut last = Point::zero();
+    for segment in path.segments() {
+        match segment {
+            PathSegment::MoveTo(point) => {
+                if points.len() > 1 {
+                    polylines.push(std::mem::take(&mut points));
+                }
+                points.clear();
+                points.push(point);
+                last = point;
+            }
+            PathSegment::LineTo(point) => {
+                points.push(point);
+                last = point;
+            }
+            PathSegment::QuadTo(control, point) => {
+                for step in 1..=CURVE_STEPS {
+                    let t = step as f32 / CURVE_STEPS as f32;
+                    let u = 1.0 - t;
+                    points.push(Point::from_xy(
+                        u * u * last.x + 2.0 * u * t * control.x + t * t * point.x,
+                        u * u * last.y + 2.0 * u * t * control.y + t * t * point.y,
+                    ));
+                }
+                last = point;
+            }
+            PathSegment::CubicTo(first, second, point) => {
+                for step in 1..=CURVE_STEPS {
+                    let t = step as f32 / CURVE_STEPS as f32;
+                    let u = 1.0 - t;
+                    let (a, b, c, d) = (u * u * u, 3.0 * u * u * t, 3.0 * u * t * t, t * t * t);
+                    points.push(Point::from_xy(
+                        a * last.x + b * first.x + c * second.x + d * point.x,
+                        a * last.y + b * first.y + c * second.y + d * point.y,
+                    ));
+                }
+                last = point;
+            }
+            PathSegment::Close => {
+                if let Some(&first) = points.first() {
+                    points.push(first);
+                    last = first;
+                }
+            }
+        }
+    }
+    if points.len() > 1 {
+        polylines.push(points);
+    }
+
+    for points in &mut polylines {
+        transform.map_points(points);
+    }
+    polylines
+        .into_iter()
+        .map(|points| points.into_iter().map(|point| [point.x, point.y]).collect())
+        .collect()
+}
+
+fn axis_aligned_rect(points: &[[f32; 2]]) -> Option<([f32; 2], [f32; 2])> {
+    if points.len() != 5 || points[0] != points[4] {
+        return None;
+    }
+    let axis_aligned = points
+        .windows(2)
+        .all(|pair| pair[0][0] == pair[1][0] || pair[0][1] == pair[1][1]);
+    if !axis_aligned {
+        return None;
+    }
+
+    let (xs, ys) = (points.iter().map(|p| p[0]), points.iter().map(|p| p[1]));
+    let min = [
+        xs.clone().fold(f32::MAX, f32::min),
+        ys.clone().fold(f32::MAX, f32::min),
+    ];
+    let max = [xs.fold(f32::MIN, f32::max), ys.fold(f32::MIN, f32::max)];
+    Some((min, max))
+}
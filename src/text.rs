@@ -0,0 +1,123 @@
+use crate::{
+    crash, normalized_to_rgba, ocr::Recognition, Action, BoardImage, ObjectRef, TextEdit,
+    TextEntries, WindowState, OCR_MARGIN,
+};
+
+impl WindowState<'_> {
+    pub(crate) fn extract_text(&mut self, index: usize) {
+        let Some(image) = self.board.images.get(index) else {
+            return;
+        };
+        if self.ocr.is_none() {
+            match Recognition::start(&image.data, index) {
+                Ok(recognition) => self.ocr = Some(recognition),
+                Err(err) => crash::log(err),
+            }
+        }
+    }
+
+    // Recognized text lands as a plain text object beside the picture it came
+    // from, so it can be edited and searched.
+    pub(crate) fn poll_ocr(&mut self) {
+        let Some(result) = self.ocr.as_mut().and_then(Recognition::poll) else {
+            return;
+        };
+        let Some(recognition) = self.ocr.take() else {
+            return;
+        };
+        let text = match result {
+            Ok(text) if !text.is_empty() => text,
+            Ok(_) => {
+                crash::log("No text found in the image".to_string());
+                return;
+            }
+            Err(err) => {
+                crash::log(format!("Unable to extract text: {}", err));
+                return;
+            }
+        };
+        // The picture may have been erased, undone or swapped for another
+        // page's while tesseract ran.
+        let Some(bounds) = self
+            .board
+            .images
+            .get(recognition.image)
+            .filter(|image| recognition.reads(&image.data))
+            .map(BoardImage::bounds)
+        else {
+            crash::log("The image changed before its text was read".to_string());
+            return;
+        };
+        let mut entry = TextEntries::null(normalized_to_rgba(self.current_color), self.font_size);
+        entry.position = [bounds.x + bounds.width + OCR_MARGIN, bounds.y];
+        entry.text = text;
+        entry.pending = false;
+        entry.font = self.font_family.clone();
+        self.commit_text();
+        self.board.push_object(Action::Text(entry));
+    }
+
+    pub(crate) fn active_text_mut(&mut self) -> Option<&mut TextEntries> {
+        if let Some(index) = self.editing_stencil_index {
+            return self
+                .board
+                .stencils
+                .get_mut(index)
+                .map(|stencil| &mut stencil.label);
+        }
+        match self.editing_text_index {
+            Some(index) => self.board.texts.get_mut(index),
+            None => self.board.texts.last_mut().filter(|text| text.pending),
+        }
+    }
+
+    pub(crate) fn commit_text(&mut self) {
+        self.start_typing = false;
+        let edited = match (
+            self.editing_stencil_index.take(),
+            self.editing_text_index.take(),
+        ) {
+            (Some(index), _) => self
+                .board
+                .stencils
+                .get_mut(index)
+                .map(|stencil| (ObjectRef::Stencil(index), &mut stencil.label)),
+            (None, Some(index)) => self
+                .board
+                .texts
+                .get_mut(index)
+                .map(|text| (ObjectRef::Text(index), text)),
+            (None, None) => None,
+        };
+        let from = self.edited_text.take();
+        if let Some((object, entry)) = edited {
+            entry.pending = false;
+            let to = entry.text.clone();
+            if let Some(from) = from.filter(|from| *from != to) {
+                self.board
+                    .push_action(Action::Retext(TextEdit { object, from, to }));
+            }
+        } else if let Some(text) = self.board.texts.last_mut() {
+            if text.pending {
+                text.pending = false;
+                let text = text.clone();
+                self.board.redo_actions.clear();
+                self.board.actions.push(Action::Text(text));
+                self.board.compact_history();
+            }
+        }
+    }
+
+    pub(crate) fn discard_empty_text(&mut self) {
+        if self.editing_text_index.is_none()
+            && self
+                .board
+                .texts
+                .last()
+                .is_some_and(|text| text.pending && text.text.is_empty())
+        {
+            self.board.texts.pop();
+            self.start_typing = false;
+        }
+    }
+}
@@ -0,0 +1,102 @@
+use crate::{camera::Camera, Rect};
+use tao::dpi::PhysicalSize;
+
+// Board pixels along a tile's side. The board is split into a grid of these,
+// and only what touches the tiles around the camera is built each frame.
+pub const TILE_SIZE: f32 = 1024.0;
+
+// The tiles from `min` to `max`, both included.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TileRange {
+    pub min: [i32; 2],
+    pub max: [i32; 2],
+}
+
+impl TileRange {
+    pub fn covering(bounds: &Rect) -> Self {
+        let tile = |value: f32| (value / TILE_SIZE).floor() as i32;
+        TileRange {
+            min: [tile(bounds.x), tile(bounds.y)],
+            max: [
+                tile(bounds.x + bounds.width),
+                tile(bounds.y + bounds.height),
+            ],
+        }
+    }
+
+    // The tiles the window shows, with a tile more on every side, so objects
+    // just out of view are ready before a pan brings them in.
+    pub fn in_view(camera: Camera, size: PhysicalSize<u32>) -> Self {
+        let [left, top] = camera.to_world([0.0, 0.0]);
+        let [right, bottom] = camera.to_world([size.width as f32, size.height as f32]);
+        let shown = TileRange::covering(&Rect {
+            x: left,
+            y: top,
+            width: right - left,
+            height: bottom - top,
+        });
+        TileRange {
+            min: shown.min.map(|tile| tile - 1),
+            max: shown.max.map(|tile| tile + 1),
+        }
+    }
+
+    pub fn overlaps(&self, other: &TileRange) -> bool {
+        (0..2).all(|axis| self.min[axis] <= other.max[axis] && other.min[axis] <= self.max[axis])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn the_view_follows_pan_and_zoom() {
+        let size = PhysicalSize::new(800, 600);
+        let view = TileRange::in_view(Camera::default(), size);
+        assert_eq!(
+            view,
+            TileRange {
+                min: [-1, -1],
+                max: [1, 1]
+            }
+        );
+
+        let far = Rect {
+            x: 5000.0,
+            y: 100.0,
+            width: 10.0,
+            height: 10.0,
+        };
+        assert!(!view.overlaps(&TileRange::covering(&far)));
+
+        let panned = Camera {
+            pan: [-4800.0, 0.0],
+            zoom: 1.0,
+        };
+        assert!(TileRange::in_view(panned, size).overlaps(&TileRange::covering(&far)));
+
+        let zoomed_out = Camera {
+            pan: [0.0, 0.0],
+            zoom: 0.1,
+        };
+        assert!(TileRange::in_view(zoomed_out, size).overlaps(&TileRange::covering(&far)));
+    }
+
+    #[test]
+    fn objects_left_of_the_origin_land_in_negative_tiles() {
+        let bounds = Rect {
+            x: -10.0,
+            y: -2000.0,
+            width: 20.0,
+            height: 1000.0,
+        };
+        assert_eq!(
+            TileRange::covering(&bounds),
+            TileRange {
+                min: [-1, -2],
+                max: [0, -1]
+            }
+        );
+    }
+}
@@ -0,0 +1,2015 @@
+use crate::{
+    animation::PAGE_TURN_DURATION,
+    background::{self, Background},
+    calibration::{self, TARGET_RADIUS},
+    code,
+    commands::{Command, Context as CommandContext, COMMANDS},
+    contrasting_color, convert_to_buffer, crash,
+    export::{self, paint::Backdrop, preview::Preview},
+    icons::{self, ICON_SIZE},
+    images, layers,
+    magnifier::{self, LENS_RADIUS, ZOOM_LEVELS},
+    normalized_to_rgba,
+    pages::{self, PageLabel, PageTag, TAG_COLORS},
+    paste::{PasteFormat, PasteSpecial},
+    picking::PICK_RADIUS,
+    project::{self, ProjectDialog, ProjectMode},
+    reference::Reference,
+    rgba_to_normalized,
+    scan::{Scan, ScanStep},
+    settings::{DeviceTool, Settings},
+    split::{self, Split},
+    stats::Statistics,
+    stencil::StencilKind,
+    Action, Brush, Damage, ImageAdjust, ObjectRef, SelectDrag, Stencil, WindowState,
+    BRUSH_INDICATOR_DURATION, CROSSHAIR_ARM, CROSSHAIR_GAP, EDGE_ARROW_SIZE, LASER_COLOR,
+    LASER_FADE, LASER_WIDTH, MAX_BRUSH_WIDTH, MIN_CROP, NARROW_TOOLBAR_WIDTH, PAGE_TURN_ALPHA,
+    PALETTE, SELECTION_COLOR, SELECTION_HANDLE, TOOLBAR_SPACING,
+};
+use egui::{Align2, Color32, Image, ImageButton};
+use std::path::PathBuf;
+use tao::dpi::PhysicalPosition;
+
+impl WindowState<'_> {
+    // The header toolbar, panels and dialogs for this frame.
+    pub(crate) fn interface(&mut self) {
+        let header_height = self.surface_config.height as f32;
+        let header_width = (self.surface_config.width as f64 * self.scale_factor) as f32;
+
+        let menu_color = egui::Color32::from_hex("#5C5C5C").expect("unable to get color");
+
+        let sized = vec![10, 12, 14, 16, 18, 20, 24, 28, 32];
+
+        let panels = self.settings.panels.clone();
+        let mut select_color = None;
+        let mut select_font = None;
+        let mut fonts_dir = None;
+        self.settings
+            .panels
+            .colors
+            .show(&self.egui_context, "Colors", "رنگ قلم", |ui| {
+                ui.horizontal_wrapped(|ui| {
+                    for (slot, &color) in PALETTE.iter().enumerate() {
+                        let size = egui::Vec2::splat(30.0);
+                        if ui
+                            .add(egui::Button::new("").fill(color).min_size(size))
+                            .on_hover_text(format!("{}", slot + 1))
+                            .clicked()
+                        {
+                            select_color = Some(color);
+                            self.egui_context.request_repaint();
+                        }
+                    }
+                });
+                if ui
+                    .checkbox(&mut self.settings.smooth_strokes, "Smooth strokes")
+                    .changed()
+                {
+                    self.panels_dirty = true;
+                }
+                if ui
+                    .checkbox(&mut self.settings.scribble_erase, "Scribble to erase")
+                    .changed()
+                {
+                    self.panels_dirty = true;
+                }
+                if ui
+                    .checkbox(
+                        &mut self.settings.pixelate_redactions,
+                        "Pixelate redactions",
+                    )
+                    .changed()
+                {
+                    self.panels_dirty = true;
+                }
+                ui.horizontal(|ui| {
+                    ui.label("Eraser radius");
+                    if ui
+                        .add(
+                            egui::DragValue::new(&mut self.settings.eraser_radius)
+                                .range(PICK_RADIUS as f32..=200.0)
+                                .suffix(" px"),
+                        )
+                        .on_hover_text("Stylus pressure shrinks it")
+                        .changed()
+                    {
+                        self.panels_dirty = true;
+                    }
+                });
+                ui.horizontal(|ui| {
+                    ui.label("Lecture page every");
+                    if ui
+                        .add(
+                            egui::DragValue::new(&mut self.settings.lecture_page_minutes)
+                                .range(0..=240)
+                                .suffix(" min"),
+                        )
+                        .on_hover_text("0 turns it off")
+                        .changed()
+                    {
+                        self.panels_dirty = true;
+                    }
+                });
+                ui.horizontal(|ui| {
+                    ui.label("Hold to straighten");
+                    if ui
+                        .add(
+                            egui::DragValue::new(&mut self.settings.snap_hold_millis)
+                                .range(0..=3000)
+                                .suffix(" ms"),
+                        )
+                        .on_hover_text("0 turns it off")
+                        .changed()
+                    {
+                        self.panels_dirty = true;
+                    }
+                });
+                ui.horizontal(|ui| {
+                    let (label, taper) = match self.brush {
+                        Brush::Pen => ("Pen taper", &mut self.settings.tapers.pen),
+                        Brush::Highlighter => {
+                            ("Highlighter taper", &mut self.settings.tapers.highlighter)
+                        }
+                        Brush::Calligraphy => {
+                            ("Calligraphy taper", &mut self.settings.tapers.calligraphy)
+                        }
+                    };
+                    ui.label(label);
+                    if ui
+                        .add(egui::DragValue::new(taper).range(0.0..=100.0).suffix(" px"))
+                        .on_hover_text("0 turns it off")
+                        .changed()
+                    {
+                        self.panels_dirty = true;
+                    }
+                });
+                if self.brush == Brush::Calligraphy {
+                    ui.horizontal(|ui| {
+                        ui.label("Nib angle");
+                        if ui
+                            .add(
+                                egui::DragValue::new(&mut self.settings.nib_angle)
+                                    .range(0.0..=180.0)
+                                    .suffix("°"),
+                            )
+                            .changed()
+                        {
+                            self.panels_dirty = true;
+                        }
+                    });
+                }
+            });
+
+        self.settings
+            .panels
+            .fonts
+            .show(&self.egui_context, "Fonts", "Font size", |ui| {
+                ui.horizontal(|ui| {
+                    for size in sized {
+                        if ui
+                            .selectable_label(self.font_size == size, format!("{} px", size))
+                            .clicked()
+                        {
+                            self.font_size = size;
+                            self.window.request_redraw();
+                        }
+                    }
+                });
+                egui::ComboBox::from_label("Font")
+                    .selected_text(self.font_family.as_str())
+                    .height(300.0)
+                    .show_ui(ui, |ui| {
+                        for family in &self.fonts.families {
+                            if ui
+                                .selectable_label(self.font_family == *family, family)
+                                .clicked()
+                            {
+                                select_font = Some(family.clone());
+                            }
+                        }
+                    });
+                ui.horizontal(|ui| {
+                    let dir = self.fonts.dir.as_ref();
+                    ui.label(dir.map_or("No fonts folder".to_string(), |dir| {
+                        format!("Fonts folder: {}", dir.display())
+                    }));
+                });
+                ui.horizontal(|ui| {
+                    ui.text_edit_singleline(&mut self.new_fonts_dir);
+                    if ui.button("Use folder").clicked() && !self.new_fonts_dir.is_empty() {
+                        fonts_dir = Some(std::mem::take(&mut self.new_fonts_dir));
+                    }
+                });
+            });
+
+        if let Some(family) = select_font {
+            if let Some(text) = self.active_text_mut() {
+                text.font = family.clone();
+            }
+            self.font_family = family;
+            self.window.request_redraw();
+        }
+        if let Some(dir) = fonts_dir {
+            self.settings.fonts_dir = Some(dir.into());
+            self.fonts.dir = self.settings.fonts_dir();
+            self.fonts.scan(&mut self.font_system);
+            self.panels_dirty = true;
+            self.window.request_redraw();
+        }
+
+        let mut insert_stencil = None;
+        let mut arrange = false;
+        self.settings
+            .panels
+            .stencils
+            .show(&self.egui_context, "Stencils", "Stencils", |ui| {
+                for (group, kinds) in [
+                    ("Flowchart", StencilKind::FLOWCHART),
+                    ("UML", StencilKind::UML),
+                ] {
+                    ui.label(group);
+                    ui.horizontal(|ui| {
+                        for kind in kinds {
+                            let response = ui.add(
+                                egui::Button::new(kind.name()).sense(egui::Sense::click_and_drag()),
+                            );
+                            if response.drag_started() {
+                                self.dragging_stencil = Some(kind);
+                            } else if response.clicked() {
+                                insert_stencil = Some((
+                                    kind,
+                                    [self.size.width as f32 / 2.0, self.size.height as f32 / 2.0],
+                                ));
+                            }
+                        }
+                    });
+                }
+                ui.separator();
+                if ui.button("Auto arrange").clicked() {
+                    arrange = true;
+                }
+            });
+
+        if arrange {
+            self.arrange_stencils();
+            self.window.request_redraw();
+        }
+
+        if let Some(kind) = self.dragging_stencil {
+            let pointer = self.egui_context.pointer_latest_pos();
+            if let Some(pointer) = pointer {
+                let [width, height] = kind.size();
+                self.egui_context
+                    .layer_painter(egui::LayerId::new(
+                        egui::Order::Tooltip,
+                        egui::Id::new("Stencil preview"),
+                    ))
+                    .rect_stroke(
+                        egui::Rect::from_center_size(pointer, egui::vec2(width, height)),
+                        0.0,
+                        egui::Stroke::new(1.0, menu_color),
+                    );
+            }
+            if self.egui_context.input(|i| i.pointer.any_released()) {
+                self.dragging_stencil = None;
+                if let Some(pointer) = pointer.filter(|_| !self.egui_context.is_pointer_over_area())
+                {
+                    insert_stencil =
+                        Some((kind, self.board.camera.to_world([pointer.x, pointer.y])));
+                }
+            }
+        }
+
+        if let Some((kind, center)) = insert_stencil {
+            self.commit_text();
+            self.board.push_object(Action::Stencil(Stencil::new(
+                kind,
+                center,
+                normalized_to_rgba(self.current_color),
+                self.font_size,
+            )));
+            self.window.request_redraw();
+        }
+
+        let mut layers = self.board.layers.clone();
+        let mut current_layer = self.board.current_layer;
+        self.settings
+            .panels
+            .layers
+            .show(&self.egui_context, "Layers", "Layers", |ui| {
+                let count = layers.len();
+                let mut moved = None;
+                let mut deleted = None;
+                // Listed top first, the way they stack on the board.
+                for index in (0..count).rev() {
+                    let layer = &mut layers[index];
+                    ui.horizontal(|ui| {
+                        ui.checkbox(&mut layer.visible, "").on_hover_text("Visible");
+                        let active = layer.id == current_layer;
+                        if ui
+                            .selectable_label(active, "✏")
+                            .on_hover_text("Draw on this layer")
+                            .clicked()
+                        {
+                            current_layer = layer.id;
+                            layer.visible = true;
+                        }
+                        ui.add(egui::TextEdit::singleline(&mut layer.name).desired_width(100.0));
+                        if ui
+                            .add_enabled(index + 1 < count, egui::Button::new("⏶"))
+                            .clicked()
+                        {
+                            moved = Some((index, index + 1));
+                        }
+                        if ui.add_enabled(index > 0, egui::Button::new("⏷")).clicked() {
+                            moved = Some((index, index - 1));
+                        }
+                        if ui.add_enabled(count > 1, egui::Button::new("🗑")).clicked() {
+                            deleted = Some(index);
+                        }
+                    });
+                }
+                if let Some((from, to)) = moved {
+                    layers.swap(from, to);
+                }
+                if let Some(index) = deleted {
+                    layers.remove(index);
+                }
+                ui.separator();
+                if ui.button("New layer").clicked() {
+                    current_layer = layers::add_layer(&mut layers);
+                }
+            });
+
+        self.board.current_layer = current_layer;
+        if layers != self.board.layers {
+            // Typing a name would otherwise fill the history one letter at a time.
+            let renamed = layers.len() == self.board.layers.len()
+                && layers
+                    .iter()
+                    .zip(&self.board.layers)
+                    .all(|(layer, old)| layer.id == old.id && layer.visible == old.visible);
+            if renamed {
+                self.board.layers = layers;
+            } else {
+                self.board.edit_layers(layers);
+            }
+            self.window.request_redraw();
+        }
+
+        let mut insert_icon = None;
+        let mut rescan_icons = false;
+        self.settings
+            .panels
+            .icons
+            .show(&self.egui_context, "Icons", "Icons", |ui| {
+                ui.add(egui::TextEdit::singleline(&mut self.icon_query).hint_text("Search"));
+                egui::ScrollArea::vertical()
+                    .max_height(240.0)
+                    .show(ui, |ui| {
+                        ui.horizontal_wrapped(|ui| {
+                            for (index, icon) in self.icons.iter().enumerate() {
+                                if !icon.matches(&self.icon_query) {
+                                    continue;
+                                }
+                                let response = ui
+                                    .add(
+                                        egui::Button::new(icon.name.as_str())
+                                            .sense(egui::Sense::click_and_drag()),
+                                    )
+                                    .on_hover_text(icon.category.as_str());
+                                if response.drag_started() {
+                                    self.dragging_icon = Some(index);
+                                } else if response.clicked() {
+                                    insert_icon = Some((
+                                        index,
+                                        [
+                                            self.size.width as f32 / 2.0,
+                                            self.size.height as f32 / 2.0,
+                                        ],
+                                    ));
+                                }
+                            }
+                        });
+                    });
+                ui.separator();
+                ui.label("Asset folders");
+                let mut remove = None;
+                for (index, folder) in self.settings.asset_folders.iter().enumerate() {
+                    ui.horizontal(|ui| {
+                        ui.label(folder.display().to_string());
+                        if ui.small_button("Remove").clicked() {
+                            remove = Some(index);
+                        }
+                    });
+                }
+                if let Some(index) = remove {
+                    self.settings.asset_folders.remove(index);
+                    rescan_icons = true;
+                }
+                ui.horizontal(|ui| {
+                    ui.text_edit_singleline(&mut self.new_asset_folder);
+                    if ui.button("Add folder").clicked() && !self.new_asset_folder.is_empty() {
+                        self.settings
+                            .asset_folders
+                            .push(std::mem::take(&mut self.new_asset_folder).into());
+                        rescan_icons = true;
+                    }
+                    if ui.button("Rescan").clicked() {
+                        rescan_icons = true;
+                    }
+                });
+            });
+
+        if rescan_icons {
+            self.icons = icons::library(&self.settings.asset_folders);
+            self.dragging_icon = None;
+            self.panels_dirty = true;
+        }
+
+        if let Some(index) = self.dragging_icon {
+            let pointer = self.egui_context.pointer_latest_pos();
+            if let Some(pointer) = pointer {
+                self.egui_context
+                    .layer_painter(egui::LayerId::new(
+                        egui::Order::Tooltip,
+                        egui::Id::new("Icon preview"),
+                    ))
+                    .rect_stroke(
+                        egui::Rect::from_center_size(pointer, egui::vec2(ICON_SIZE, ICON_SIZE)),
+                        0.0,
+                        egui::Stroke::new(1.0, menu_color),
+                    );
+            }
+            if self.egui_context.input(|i| i.pointer.any_released()) {
+                self.dragging_icon = None;
+                if let Some(pointer) = pointer.filter(|_| !self.egui_context.is_pointer_over_area())
+                {
+                    insert_icon = Some((index, self.board.camera.to_world([pointer.x, pointer.y])));
+                }
+            }
+        }
+
+        if let Some((index, center)) = insert_icon {
+            self.commit_text();
+            let [r, g, b, _] = normalized_to_rgba(self.current_color);
+            let svg = self.icons[index]
+                .svg
+                .replace("currentColor", &format!("#{:02x}{:02x}{:02x}", r, g, b));
+            let mut paste = PasteSpecial::new(
+                svg,
+                [center[0] - ICON_SIZE / 2.0, center[1] - ICON_SIZE / 2.0],
+            );
+            paste.format = PasteFormat::Svg;
+            match paste.to_actions(
+                normalized_to_rgba(self.current_color),
+                self.font_size,
+                self.size,
+            ) {
+                Ok(actions) => {
+                    for action in actions {
+                        self.board.push_object(action);
+                    }
+                }
+                Err(err) => crash::log(format!("Unable to insert icon: {}", err)),
+            }
+            self.window.request_redraw();
+        }
+
+        if let Some(color) = select_color {
+            self.set_color(convert_to_buffer(color));
+        }
+
+        if panels != self.settings.panels {
+            self.panels_dirty = true;
+        }
+        if self.panels_dirty && !self.egui_context.input(|i| i.pointer.any_down()) {
+            self.settings.save();
+            self.panels_dirty = false;
+        }
+
+        if let Some(shown) = self.brush_indicator {
+            if shown.elapsed() < BRUSH_INDICATOR_DURATION {
+                let scale = self.scale_factor as f32;
+                let position = self.last_cursor_position;
+                let radius = self.brush_width / 2.0 / scale;
+                let [r, g, b, _] = normalized_to_rgba(self.current_color);
+                let color = Color32::from_rgb(r, g, b);
+                egui::Area::new("Brush size".into())
+                    .fixed_pos([
+                        position.x as f32 / scale + 16.0,
+                        position.y as f32 / scale + 16.0,
+                    ])
+                    .order(egui::Order::Tooltip)
+                    .interactable(false)
+                    .show(&self.egui_context, |ui| {
+                        egui::Frame::popup(ui.style()).show(ui, |ui| {
+                            ui.horizontal(|ui| {
+                                let side = MAX_BRUSH_WIDTH / scale;
+                                let (rect, _) = ui.allocate_exact_size(
+                                    egui::vec2(side, side),
+                                    egui::Sense::hover(),
+                                );
+                                ui.painter().circle_filled(rect.center(), radius, color);
+                                ui.label(format!("{} px", self.brush_width));
+                            });
+                        });
+                    });
+            } else {
+                self.brush_indicator = None;
+            }
+        }
+
+        if let Some(lens) = &mut self.magnifier {
+            if let Some(texture) = lens.texture {
+                let pixels_per_point = self.egui_context.pixels_per_point();
+                let screen = egui::vec2(
+                    self.surface_config.width as f32 / pixels_per_point,
+                    self.surface_config.height as f32 / pixels_per_point,
+                );
+                egui::Area::new("Magnifier".into())
+                    .default_pos([screen.x / 2.0 - LENS_RADIUS, screen.y / 2.0 - LENS_RADIUS])
+                    .movable(true)
+                    .show(&self.egui_context, |ui| {
+                        let (rect, _) = ui.allocate_exact_size(
+                            egui::Vec2::splat(LENS_RADIUS * 2.0),
+                            egui::Sense::drag(),
+                        );
+                        let center = rect.center();
+                        ui.painter().add(egui::Shape::mesh(magnifier::lens_mesh(
+                            texture, center, lens.zoom, screen,
+                        )));
+                        ui.painter().circle_stroke(
+                            center,
+                            LENS_RADIUS,
+                            egui::Stroke::new(3.0, menu_color),
+                        );
+                        ui.horizontal(|ui| {
+                            for zoom in ZOOM_LEVELS {
+                                ui.selectable_value(&mut lens.zoom, zoom, format!("{}x", zoom));
+                            }
+                        });
+                    });
+            }
+        }
+
+        if self.selecting {
+            let scale = self.scale_factor as f32;
+            let to_points = |point: [f32; 2]| {
+                let [x, y] = self.board.camera.to_screen(point);
+                egui::pos2(x / scale, y / scale)
+            };
+            let painter = self.egui_context.layer_painter(egui::LayerId::new(
+                egui::Order::Background,
+                "Selection".into(),
+            ));
+            let stroke = egui::Stroke::new(1.0, SELECTION_COLOR);
+            for &object in &self.selection {
+                let Some(bounds) = self.board.object_bounds(object) else {
+                    continue;
+                };
+                let rect = egui::Rect::from_two_pos(
+                    to_points([bounds.x, bounds.y]),
+                    to_points([bounds.x + bounds.width, bounds.y + bounds.height]),
+                );
+                painter.rect_stroke(rect.expand(2.0), 0.0, stroke);
+            }
+            if let [object] = self.selection[..] {
+                if let Some(corners) = self.board.object_corners(object) {
+                    for corner in corners {
+                        let center = to_points(corner);
+                        painter.rect_filled(
+                            egui::Rect::from_center_size(
+                                center,
+                                egui::Vec2::splat(SELECTION_HANDLE / scale),
+                            ),
+                            0.0,
+                            SELECTION_COLOR,
+                        );
+                    }
+                }
+            }
+            if let Some(SelectDrag::Band { start, end }) = self.select_drag {
+                painter.rect_stroke(
+                    egui::Rect::from_two_pos(to_points(start), to_points(end)),
+                    0.0,
+                    stroke,
+                );
+            }
+
+            let bottom_left = self
+                .selection
+                .iter()
+                .filter_map(|&object| self.board.object_bounds(object))
+                .map(|bounds| to_points([bounds.x, bounds.y + bounds.height]))
+                .reduce(|a, b| egui::pos2(a.x.min(b.x), a.y.max(b.y)));
+            if let (Some(anchor), None) = (bottom_left, &self.select_drag) {
+                let mut to_layer = None;
+                let mut to_page = None;
+                let mut key_out = false;
+                let mut extract = false;
+                let mut pin = false;
+                let mut adjust = false;
+                let mut scan = false;
+                let has_images = self
+                    .selection
+                    .iter()
+                    .any(|object| matches!(object, ObjectRef::Image(_)));
+                egui::Area::new("Selection menu".into())
+                    .fixed_pos(anchor + egui::vec2(0.0, 8.0))
+                    .order(egui::Order::Foreground)
+                    .show(&self.egui_context, |ui| {
+                        ui.menu_button("Move to", |ui| {
+                            ui.menu_button("Layer", |ui| {
+                                for layer in self.board.layers.iter().rev() {
+                                    if ui.button(&layer.name).clicked() {
+                                        to_layer = Some(layer.id);
+                                        ui.close_menu();
+                                    }
+                                }
+                            });
+                            ui.menu_button("Page", |ui| {
+                                for index in 0..self.board.pages.len() {
+                                    let label = format!("Page {}", index + 1);
+                                    let button = egui::Button::new(label);
+                                    if ui
+                                        .add_enabled(index != self.board.current_page, button)
+                                        .clicked()
+                                    {
+                                        to_page = Some(Some(index));
+                                        ui.close_menu();
+                                    }
+                                }
+                                ui.separator();
+                                if ui.button("New page").clicked() {
+                                    to_page = Some(None);
+                                    ui.close_menu();
+                                }
+                            });
+                        });
+                        if has_images && ui.button("Make background transparent").clicked() {
+                            key_out = true;
+                        }
+                        let button = egui::Button::new("Extract text");
+                        if has_images && ui.add_enabled(self.ocr.is_none(), button).clicked() {
+                            extract = true;
+                        }
+                        if has_images && ui.button("Pin as reference").clicked() {
+                            pin = true;
+                        }
+                        if has_images && ui.button("Adjust picture").clicked() {
+                            adjust = true;
+                        }
+                        if has_images && ui.button("Scan photo").clicked() {
+                            scan = true;
+                        }
+                    });
+                if key_out {
+                    for object in self.selection.clone() {
+                        if let ObjectRef::Image(index) = object {
+                            self.key_out_background(index);
+                        }
+                    }
+                }
+                let first_image = self.selection.iter().find_map(|object| match object {
+                    ObjectRef::Image(index) => Some(*index),
+                    _ => None,
+                });
+                if let (true, Some(index)) = (extract, first_image) {
+                    self.extract_text(index);
+                }
+                if let (true, Some(index)) = (adjust, first_image) {
+                    self.finish_adjusting();
+                    let image = &self.board.images[index];
+                    self.adjusting = Some((index, image.adjust, [image.first, image.last]));
+                }
+                if let (true, Some(index)) = (scan, first_image) {
+                    let data = &self.board.images[index].data;
+                    self.scan = Scan::new(&self.egui_context, index, data);
+                }
+                if let (true, Some(index)) = (pin, first_image) {
+                    let data = &self.board.images[index].data;
+                    self.reference = Reference::new(&self.egui_context, data);
+                }
+                if let Some(layer) = to_layer {
+                    self.move_selection_to_layer(layer);
+                }
+                if let Some(page) = to_page {
+                    self.move_selection_to_page(page);
+                }
+            }
+        }
+
+        let crosshair =
+            self.cursor_inside && !self.start_typing && !self.egui_context.is_pointer_over_area();
+        if crosshair != self.crosshair_shown {
+            self.window.set_cursor_visible(!crosshair);
+            self.crosshair_shown = crosshair;
+        }
+        if crosshair && self.laser.is_none() {
+            let scale = self.scale_factor as f32;
+            let position = self.last_cursor_position;
+            let center = egui::pos2(position.x as f32 / scale, position.y as f32 / scale);
+            let reach = match self.erasing {
+                true => self.eraser_reach(),
+                false => self.stroke_width() / 2.0,
+            };
+            let radius = (reach * self.board.camera.zoom / scale).max(CROSSHAIR_GAP);
+            let [r, g, b, _] = normalized_to_rgba(self.current_color);
+            let painter = self
+                .egui_context
+                .layer_painter(egui::LayerId::new(egui::Order::Tooltip, "Crosshair".into()));
+            let stroke = egui::Stroke::new(1.0, menu_color);
+            for direction in [egui::Vec2::X, -egui::Vec2::X, egui::Vec2::Y, -egui::Vec2::Y] {
+                painter.line_segment(
+                    [
+                        center + direction * (radius + 2.0),
+                        center + direction * (radius + CROSSHAIR_ARM),
+                    ],
+                    stroke,
+                );
+            }
+            painter.circle_stroke(center, radius, stroke);
+            painter.circle_filled(center, 2.5, Color32::from_rgb(r, g, b));
+        }
+
+        if let Some(trail) = &mut self.laser {
+            while trail
+                .front()
+                .is_some_and(|(_, time)| time.elapsed() > LASER_FADE)
+            {
+                trail.pop_front();
+            }
+            let scale = self.scale_factor as f32;
+            let to_points = |position: PhysicalPosition<f64>| {
+                egui::pos2(position.x as f32 / scale, position.y as f32 / scale)
+            };
+            let painter = self
+                .egui_context
+                .layer_painter(egui::LayerId::new(egui::Order::Tooltip, "Laser".into()));
+            let points: Vec<_> = trail.iter().collect();
+            for pair in points.windows(2) {
+                let [(from, _), (to, time)] = [pair[0], pair[1]];
+                let fade = 1.0 - time.elapsed().as_secs_f32() / LASER_FADE.as_secs_f32();
+                let segment = [to_points(*from), to_points(*to)];
+                painter.line_segment(
+                    segment,
+                    egui::Stroke::new(LASER_WIDTH * 3.0, LASER_COLOR.gamma_multiply(fade * 0.25)),
+                );
+                painter.line_segment(
+                    segment,
+                    egui::Stroke::new(LASER_WIDTH, LASER_COLOR.gamma_multiply(fade)),
+                );
+            }
+            if self.cursor_inside {
+                let head = to_points(self.last_cursor_position);
+                painter.circle_filled(head, LASER_WIDTH * 2.0, LASER_COLOR.gamma_multiply(0.3));
+                painter.circle_filled(head, LASER_WIDTH, LASER_COLOR);
+            }
+        }
+
+        let pasted = self
+            .pasted_image
+            .and_then(|index| Some((index, self.board.images.get(index)?.bounds())));
+        if let Some((index, bounds)) = pasted {
+            let scale = self.scale_factor as f32;
+            let [x, y] = self
+                .board
+                .camera
+                .to_screen([bounds.x, bounds.y + bounds.height]);
+            let (mut key_out, mut dismiss) = (false, false);
+            egui::Area::new("Pasted image".into())
+                .fixed_pos([x / scale, y / scale + 8.0])
+                .order(egui::Order::Foreground)
+                .show(&self.egui_context, |ui| {
+                    egui::Frame::popup(ui.style()).show(ui, |ui| {
+                        ui.horizontal(|ui| {
+                            key_out = ui.button("Make background transparent").clicked();
+                            ui.label("Threshold");
+                            if ui
+                                .add(
+                                    egui::DragValue::new(&mut self.settings.background_threshold)
+                                        .range(0..=128),
+                                )
+                                .changed()
+                            {
+                                self.panels_dirty = true;
+                            }
+                            dismiss = ui.button("✖").clicked();
+                        });
+                    });
+                });
+            if key_out {
+                self.key_out_background(index);
+            }
+            if key_out || dismiss {
+                self.pasted_image = None;
+            }
+        }
+
+        if let Some(started) = self.lecture_started {
+            let elapsed = started.elapsed().as_secs();
+            egui::Area::new("Session timer".into())
+                .anchor(Align2::RIGHT_TOP, [-16.0, 64.0])
+                .order(egui::Order::Foreground)
+                .interactable(false)
+                .show(&self.egui_context, |ui| {
+                    egui::Frame::popup(ui.style()).show(ui, |ui| {
+                        ui.label(
+                            egui::RichText::new(format!(
+                                "{:02}:{:02}:{:02}",
+                                elapsed / 3600,
+                                elapsed / 60 % 60,
+                                elapsed % 60
+                            ))
+                            .monospace()
+                            .size(18.0),
+                        );
+                    });
+                });
+        }
+
+        let adjusted = self
+            .adjusting
+            .and_then(|(index, ..)| self.board.images.get_mut(index));
+        if let Some(image) = adjusted {
+            let mut adjust = image.adjust;
+            let mut open = true;
+            let mut done = false;
+            egui::Window::new("Adjust picture")
+                .collapsible(false)
+                .order(egui::Order::Foreground)
+                .resizable(false)
+                .open(&mut open)
+                .anchor(Align2::RIGHT_TOP, [-16.0, 64.0])
+                .show(&self.egui_context, |ui| {
+                    let [[left, top], [right, bottom]] = &mut adjust.crop;
+                    egui::Grid::new("Adjust picture grid").show(ui, |ui| {
+                        ui.label("Crop left");
+                        ui.add(egui::Slider::new(left, 0.0..=*right - MIN_CROP));
+                        ui.end_row();
+                        ui.label("Crop right");
+                        ui.add(egui::Slider::new(right, *left + MIN_CROP..=1.0));
+                        ui.end_row();
+                        ui.label("Crop top");
+                        ui.add(egui::Slider::new(top, 0.0..=*bottom - MIN_CROP));
+                        ui.end_row();
+                        ui.label("Crop bottom");
+                        ui.add(egui::Slider::new(bottom, *top + MIN_CROP..=1.0));
+                        ui.end_row();
+                        ui.label("Brightness");
+                        ui.add(egui::Slider::new(&mut adjust.brightness, -0.5..=0.5));
+                        ui.end_row();
+                        ui.label("Contrast");
+                        ui.add(egui::Slider::new(&mut adjust.contrast, 0.0..=2.0));
+                        ui.end_row();
+                    });
+                    ui.checkbox(&mut adjust.grayscale, "Grayscale");
+                    ui.horizontal(|ui| {
+                        if ui.button("Rotate ⟳").clicked() {
+                            adjust.turns = (adjust.turns + 1) % 4;
+                        }
+                        if ui.button("Reset").clicked() {
+                            adjust = ImageAdjust::default();
+                        }
+                        if ui.button("Done").clicked() {
+                            done = true;
+                        }
+                    });
+                });
+            if adjust != image.adjust {
+                let bounds = images::adjusted_bounds(&image.bounds(), &image.adjust, &adjust);
+                image.first = [bounds.x, bounds.y];
+                image.last = [bounds.x + bounds.width, bounds.y + bounds.height];
+                image.adjust = adjust;
+                self.window.request_redraw();
+            }
+            if !open || done {
+                self.finish_adjusting();
+                self.window.request_redraw();
+            }
+        }
+
+        if let Some(scan) = &mut self.scan {
+            match scan.show(&self.egui_context) {
+                ScanStep::Open => {}
+                ScanStep::Cancel => self.scan = None,
+                ScanStep::Straighten => {
+                    let (index, corners) = (scan.index, scan.corners);
+                    self.scan = None;
+                    self.scan_image(index, corners);
+                    self.window.request_redraw();
+                }
+            }
+        }
+
+        if let Some(reference) = &mut self.reference {
+            if !reference.show(&self.egui_context) {
+                self.reference = None;
+            }
+        }
+
+        let mut stop_replay = false;
+        if let Some(replay) = &mut self.replay {
+            egui::Window::new("Replay")
+                .collapsible(false)
+                .order(egui::Order::Foreground)
+                .resizable(false)
+                .anchor(Align2::CENTER_BOTTOM, [0.0, -16.0])
+                .show(&self.egui_context, |ui| {
+                    ui.horizontal(|ui| {
+                        ui.add(egui::Slider::new(&mut replay.speed, 0.25..=4.0).text("Speed"));
+                        if ui.button("Stop").clicked() {
+                            stop_replay = true;
+                        }
+                    });
+                });
+        }
+        if stop_replay {
+            self.replay = None;
+            self.window.request_redraw();
+        }
+
+        if self.show_shortcuts {
+            egui::Window::new("Keyboard shortcuts")
+                .collapsible(false)
+                .order(egui::Order::Foreground)
+                .resizable(false)
+                .open(&mut self.show_shortcuts)
+                .anchor(Align2::CENTER_CENTER, [0.0, 0.0])
+                .show(&self.egui_context, |ui| {
+                    let mut categories: Vec<&str> = Vec::new();
+                    for info in &COMMANDS {
+                        if !categories.contains(&info.category) {
+                            categories.push(info.category);
+                        }
+                    }
+                    for category in categories {
+                        ui.strong(category);
+                        egui::Grid::new(category).num_columns(2).show(ui, |ui| {
+                            for info in COMMANDS.iter().filter(|info| info.category == category) {
+                                let binding = self
+                                    .keymap
+                                    .binding(info.command)
+                                    .map(|binding| binding.label())
+                                    .unwrap_or_default();
+                                ui.monospace(binding);
+                                ui.label(info.description);
+                                ui.end_row();
+                            }
+                        });
+                        ui.add_space(6.0);
+                    }
+                });
+        }
+
+        let mut refresh_statistics = false;
+        if let Some(statistics) = &self.statistics {
+            let mut open = true;
+            egui::Window::new("Usage statistics")
+                .collapsible(false)
+                .order(egui::Order::Foreground)
+                .resizable(false)
+                .open(&mut open)
+                .anchor(Align2::CENTER_CENTER, [0.0, 0.0])
+                .show(&self.egui_context, |ui| {
+                    ui.label("Computed locally from this board. Nothing is sent anywhere.");
+                    ui.separator();
+                    ui.strong("Drawing time per day (UTC)");
+                    if statistics.days.is_empty() {
+                        ui.label("No timed strokes yet");
+                    }
+                    egui::Grid::new("Statistics days").show(ui, |ui| {
+                        for (day, drawn) in &statistics.days {
+                            let seconds = drawn.as_secs();
+                            ui.label(day);
+                            ui.label(format!("{}m {:02}s", seconds / 60, seconds % 60));
+                            ui.end_row();
+                        }
+                    });
+                    ui.add_space(6.0);
+                    ui.strong("Most used tools");
+                    egui::Grid::new("Statistics tools").show(ui, |ui| {
+                        for (tool, count) in &statistics.tools {
+                            ui.label(*tool);
+                            ui.label(count.to_string());
+                            ui.end_row();
+                        }
+                    });
+                    ui.add_space(6.0);
+                    ui.strong("Most used colors");
+                    egui::Grid::new("Statistics colors").show(ui, |ui| {
+                        for ([r, g, b, a], count) in &statistics.colors {
+                            egui::widgets::color_picker::show_color(
+                                ui,
+                                Color32::from_rgba_unmultiplied(*r, *g, *b, *a),
+                                egui::vec2(16.0, 16.0),
+                            );
+                            ui.label(count.to_string());
+                            ui.end_row();
+                        }
+                    });
+                    ui.add_space(6.0);
+                    ui.strong("Pages created");
+                    ui.label(statistics.pages_created.to_string());
+                    ui.separator();
+                    if ui.button("Refresh").clicked() {
+                        refresh_statistics = true;
+                    }
+                });
+            if !open {
+                self.statistics = None;
+            }
+        }
+        if refresh_statistics {
+            self.statistics = Some(Statistics::compute(
+                &self.board.scene_actions(),
+                self.pages_created,
+            ));
+        }
+
+        if self.show_about {
+            const MIB: f64 = 1024.0 * 1024.0;
+            let history_bytes = self.board.history_bytes();
+            let selected = &self.adapter_info;
+            egui::Window::new("About")
+                .collapsible(false)
+                .order(egui::Order::Foreground)
+                .resizable(false)
+                .open(&mut self.show_about)
+                .anchor(Align2::CENTER_CENTER, [0.0, 0.0])
+                .show(&self.egui_context, |ui| {
+                    ui.label(format!("Version {}", env!("CARGO_PKG_VERSION")));
+                    ui.separator();
+                    egui::Grid::new("Adapter").num_columns(2).show(ui, |ui| {
+                        ui.label("Adapter");
+                        ui.label(&selected.name);
+                        ui.end_row();
+                        ui.label("Backend");
+                        ui.label(format!("{:?}", selected.backend));
+                        ui.end_row();
+                        ui.label("Device type");
+                        ui.label(format!("{:?}", selected.device_type));
+                        ui.end_row();
+                        ui.label("Driver");
+                        ui.label(format!("{} {}", selected.driver, selected.driver_info));
+                        ui.end_row();
+                    });
+                    ui.separator();
+                    ui.label("Available adapters (--adapter <index|name>):");
+                    for (index, info) in self.adapters.iter().enumerate() {
+                        ui.label(format!("{}: {} ({:?})", index, info.name, info.backend));
+                    }
+                    ui.separator();
+                    ui.label(format!(
+                        "Undo history: {} entries, {:.1} / {:.1} MiB",
+                        self.board.actions.len(),
+                        history_bytes as f64 / MIB,
+                        self.settings.history_max_bytes as f64 / MIB,
+                    ));
+                    if let Some(path) = Settings::path() {
+                        ui.separator();
+                        ui.label(format!("Settings: {}", path.display()));
+                    }
+                });
+        }
+
+        if self.show_devices {
+            let mut changed = false;
+            egui::Window::new("Input devices")
+                .collapsible(false)
+                .order(egui::Order::Foreground)
+                .resizable(false)
+                .open(&mut self.show_devices)
+                .anchor(Align2::CENTER_CENTER, [0.0, 0.0])
+                .show(&self.egui_context, |ui| {
+                    ui.label("Each device switches to its tool when it touches the board.");
+                    let tools = &mut self.settings.device_tools;
+                    egui::Grid::new("Input devices").num_columns(2).show(ui, |ui| {
+                        for (name, binding) in
+                            [("Mouse", &mut tools.mouse), ("Touch", &mut tools.touch)]
+                        {
+                            ui.label(name);
+                            egui::ComboBox::from_id_salt(name)
+                                .selected_text(binding.label())
+                                .show_ui(ui, |ui| {
+                                    for tool in DeviceTool::ALL {
+                                        changed |= ui
+                                            .selectable_value(binding, tool, tool.label())
+                                            .changed();
+                                    }
+                                });
+                            ui.end_row();
+                        }
+                        let describe = |command: Option<Command>| {
+                            COMMANDS
+                                .iter()
+                                .find(|info| Some(info.command) == command)
+                                .map_or("Nothing", |info| info.description)
+                        };
+                        for (name, button) in [
+                            ("Stylus button", &mut self.settings.stylus_button),
+                            ("Stylus double tap", &mut self.settings.stylus_double_tap),
+                        ] {
+                            ui.label(name);
+                            egui::ComboBox::from_id_salt(name)
+                                .selected_text(describe(*button))
+                                .show_ui(ui, |ui| {
+                                    changed |=
+                                        ui.selectable_value(button, None, "Nothing").changed();
+                                    for info in COMMANDS
+                                        .iter()
+                                        .filter(|info| info.context == CommandContext::Board)
+                                    {
+                                        changed |= ui
+                                            .selectable_value(
+                                                button,
+                                                Some(info.command),
+                                                info.description,
+                                            )
+                                            .changed();
+                                    }
+                                });
+                            ui.end_row();
+                        }
+                        ui.label("Stylus button number");
+                        changed |= ui
+                            .add(egui::DragValue::new(&mut self.settings.stylus_button_id))
+                            .changed();
+                        ui.end_row();
+                    });
+                    changed |= ui
+                        .checkbox(&mut self.settings.pen_only, "Fingers only pan and zoom while a pen is in use")
+                        .changed();
+                    changed |= ui
+                        .checkbox(&mut self.settings.edge_swipes, "Swipe in from the left or right edge to turn pages")
+                        .changed();
+                    changed |= ui
+                        .checkbox(&mut self.settings.edge_arrows, "Page arrows at the sides of the window")
+                        .changed();
+                    changed |= ui
+                        .checkbox(&mut self.settings.auto_scroll, "Scroll on while writing near the bottom or a side")
+                        .changed();
+                    ui.label("A stylus follows the mouse or touch binding, depending on how the system reports it.");
+                });
+            if changed {
+                self.settings.save();
+            }
+        }
+
+        let mut recover = None;
+        if let Some(pages) = &self.recovery {
+            egui::Window::new("Recover board")
+                .collapsible(false)
+                .order(egui::Order::Foreground)
+                .resizable(false)
+                .anchor(Align2::CENTER_CENTER, [0.0, 0.0])
+                .show(&self.egui_context, |ui| {
+                    let objects: usize = pages.iter().map(Vec::len).sum();
+                    ui.label(format!(
+                        "The whiteboard closed unexpectedly. {} objects on {} pages can be recovered.",
+                        objects,
+                        pages.len()
+                    ));
+                    if let Some(dir) = crash::dir() {
+                        ui.label(format!("Crash reports: {}", dir.display()));
+                    }
+                    ui.horizontal(|ui| {
+                        if ui.button("Recover").clicked() {
+                            recover = Some(true);
+                        }
+                        if ui.button("Discard").clicked() {
+                            recover = Some(false);
+                        }
+                    });
+                });
+        }
+        if let Some(recover) = recover {
+            crash::discard_recovery();
+            if let Some(pages) = self.recovery.take() {
+                if recover {
+                    self.recover_pages(pages);
+                }
+            }
+            self.window.request_redraw();
+        }
+
+        let mut close_calibration = false;
+        if let Some(calibration) = &self.calibration {
+            egui::Window::new("Pen calibration")
+                .collapsible(false)
+                .order(egui::Order::Foreground)
+                .resizable(false)
+                .anchor(Align2::CENTER_TOP, [0.0, 16.0])
+                .show(&self.egui_context, |ui| {
+                    ui.label(format!(
+                        "Tap the center of each target with the pen ({} of {}).",
+                        calibration.step() + 1,
+                        calibration::TARGETS.len()
+                    ));
+                    let [dx, dy] = self.settings.pen_offset;
+                    ui.label(format!("Current offset: {:.1}, {:.1} px", dx, dy));
+                    ui.horizontal(|ui| {
+                        if ui.button("Reset offset").clicked() {
+                            self.settings.pen_offset = [0.0, 0.0];
+                            self.settings.save();
+                        }
+                        if ui.button("Cancel").clicked() {
+                            close_calibration = true;
+                        }
+                    });
+                });
+            if let Some(target) = calibration.target(self.size) {
+                let scale = self.scale_factor as f32;
+                let center = egui::pos2(target.x as f32 / scale, target.y as f32 / scale);
+                let painter = self.egui_context.layer_painter(egui::LayerId::new(
+                    egui::Order::Foreground,
+                    "Calibration target".into(),
+                ));
+                let stroke = egui::Stroke::new(2.0, Color32::RED);
+                painter.circle_stroke(center, TARGET_RADIUS, stroke);
+                painter.line_segment(
+                    [
+                        center - egui::vec2(TARGET_RADIUS * 1.5, 0.0),
+                        center + egui::vec2(TARGET_RADIUS * 1.5, 0.0),
+                    ],
+                    stroke,
+                );
+                painter.line_segment(
+                    [
+                        center - egui::vec2(0.0, TARGET_RADIUS * 1.5),
+                        center + egui::vec2(0.0, TARGET_RADIUS * 1.5),
+                    ],
+                    stroke,
+                );
+            }
+        }
+        if close_calibration {
+            self.calibration = None;
+        }
+
+        let mut project_action = None;
+        let backdrop = self.export_backdrop();
+        if let Some(dialog) = &mut self.project_dialog {
+            if dialog.is_export()
+                && dialog
+                    .preview
+                    .as_ref()
+                    .is_none_or(|preview| preview.backdrop != backdrop)
+            {
+                dialog.preview = Some(Preview::new(
+                    &self.board.scene_actions(),
+                    self.size,
+                    backdrop,
+                    &mut self.font_system,
+                ));
+            }
+            let mut open = true;
+            let mut export_changed = false;
+            egui::Window::new(dialog.title())
+                .collapsible(false)
+                .order(egui::Order::Foreground)
+                .resizable(false)
+                .anchor(Align2::CENTER_CENTER, [0.0, 0.0])
+                .open(&mut open)
+                .show(&self.egui_context, |ui| {
+                    ui.horizontal(|ui| {
+                        ui.label("File");
+                        ui.add(egui::TextEdit::singleline(&mut dialog.path).desired_width(320.0));
+                    });
+                    if let Some(preview) = &dialog.preview {
+                        export_changed |= ui
+                            .checkbox(&mut self.settings.export_pattern, "Grid or dots")
+                            .changed();
+                        export_changed |= ui
+                            .checkbox(&mut self.settings.export_border, "Page border")
+                            .changed();
+                        preview.show(ui);
+                    }
+                    if let Some(error) = &dialog.error {
+                        ui.colored_label(Color32::RED, error);
+                    }
+                    let label = match dialog.mode {
+                        ProjectMode::Save => "Save",
+                        ProjectMode::Open => "Open",
+                        ProjectMode::ExportPdf
+                        | ProjectMode::ExportSvg
+                        | ProjectMode::ExportHtml
+                        | ProjectMode::ExportGif => "Export",
+                    };
+                    if ui.button(label).clicked() && !dialog.path.trim().is_empty() {
+                        project_action = Some((dialog.mode, PathBuf::from(dialog.path.trim())));
+                    }
+                });
+            if export_changed {
+                self.settings.save();
+            }
+            if !open {
+                self.project_dialog = None;
+            }
+        }
+        if let Some((mode, path)) = project_action {
+            let backdrop = self.export_backdrop();
+            let result = match mode {
+                ProjectMode::Save => {
+                    project::save(&path, self.board.page_scenes(), self.board.size)
+                }
+                ProjectMode::Open => project::load(&path, self.board.size).map(|pages| {
+                    self.replace_pages(pages);
+                    path
+                }),
+                ProjectMode::ExportPdf => export::pdf::export(
+                    &path,
+                    &self.board.page_scenes(),
+                    self.size,
+                    &backdrop,
+                    &mut self.font_system,
+                ),
+                ProjectMode::ExportSvg => export::svg::export(
+                    &path,
+                    &self.board.scene_actions(),
+                    self.size,
+                    &backdrop,
+                    &mut self.font_system,
+                ),
+                ProjectMode::ExportHtml => export::html::export(
+                    &path,
+                    &self.board.page_scenes(),
+                    self.size,
+                    &backdrop,
+                    &mut self.font_system,
+                ),
+                ProjectMode::ExportGif => export::gif::export(
+                    &path,
+                    &self.board.page_scenes(),
+                    self.size,
+                    &backdrop,
+                    self.settings.flipbook_fps,
+                    &mut self.font_system,
+                ),
+            };
+            match result {
+                Ok(path) => {
+                    if mode == ProjectMode::Save || mode == ProjectMode::Open {
+                        self.project_path = Some(path);
+                    }
+                    self.project_dialog = None;
+                }
+                Err(err) => {
+                    if let Some(dialog) = &mut self.project_dialog {
+                        dialog.error = Some(err);
+                    }
+                }
+            }
+            self.window.request_redraw();
+        }
+
+        let mut insert_paste = false;
+        if let Some(paste_special) = &mut self.paste_special {
+            let mut open = true;
+            egui::Window::new("Paste special")
+                .collapsible(false)
+                .order(egui::Order::Foreground)
+                .resizable(false)
+                .open(&mut open)
+                .anchor(Align2::CENTER_TOP, [0.0, 0.0])
+                .show(&self.egui_context, |ui| {
+                    ui.horizontal(|ui| {
+                        for format in PasteFormat::ALL {
+                            ui.radio_value(&mut paste_special.format, format, format.label());
+                        }
+                    });
+                    if paste_special.format == PasteFormat::Code {
+                        egui::ComboBox::from_label("Language")
+                            .selected_text(paste_special.language.as_str())
+                            .show_ui(ui, |ui| {
+                                for language in code::LANGUAGES {
+                                    ui.selectable_value(
+                                        &mut paste_special.language,
+                                        language.to_string(),
+                                        language,
+                                    );
+                                }
+                            });
+                    }
+                    ui.label(paste_special.summary());
+                    if ui.button("Insert").clicked() {
+                        insert_paste = true;
+                    }
+                });
+            if !open {
+                self.paste_special = None;
+            }
+        }
+
+        if insert_paste {
+            self.commit_text();
+            if let Some(paste_special) = self.paste_special.take() {
+                match paste_special.to_actions(
+                    normalized_to_rgba(self.current_color),
+                    self.font_size,
+                    self.size,
+                ) {
+                    Ok(actions) => {
+                        let shadow = self.shadow_style();
+                        let first_stencil = self.board.stencils.len();
+                        for mut action in actions {
+                            match &mut action {
+                                Action::Table(table) => table.shadow = shadow,
+                                Action::Code(code_block) => code_block.shadow = shadow,
+                                Action::Connector(connector) => {
+                                    connector.from += first_stencil;
+                                    connector.to += first_stencil;
+                                }
+                                Action::Layers(_) => continue,
+                                _ => {}
+                            }
+                            self.board.push_object(action);
+                        }
+                    }
+                    Err(err) => crash::log(format!("Unable to paste: {}", err)),
+                }
+            }
+            self.window.request_redraw();
+        }
+
+        let mut undo = false;
+        let mut tool = None;
+        let mut onion_page = self.onion_page;
+        let mut split_page = self.split.as_ref().map(|split| split.page);
+        let labels = self.board.page_labels();
+        let mut label = self.board.label.clone();
+        let mut page_filter = self.page_filter.clone();
+        let mut new_page_tag = std::mem::take(&mut self.new_page_tag);
+        let mut go_to_page = None;
+        let mut flipbook_fps = self.settings.flipbook_fps;
+        let narrow = header_width < NARROW_TOOLBAR_WIDTH;
+        let spacing = if narrow {
+            TOOLBAR_SPACING
+        } else {
+            header_width * 0.03
+        };
+        let mut background = self.settings.background;
+        let header = egui::Area::new("Header".into())
+            .fixed_pos([0.0, 0.0])
+            .movable(false)
+            .order(egui::Order::Background)
+            .default_size([header_width, header_height * 10.0])
+            .show(&self.egui_context, |ui| {
+                let custom_frame = egui::Frame::none()
+                    .fill(menu_color)
+                    .stroke(egui::Stroke::new(1.0, menu_color));
+                custom_frame.show(ui, |ui| {
+                    ui.set_min_width(header_width);
+                    ui.vertical(|ui| {
+                        ui.add_space(10.0);
+                        ui.horizontal_wrapped(|ui| {
+                            ui.set_width(header_width);
+
+                            ui.add_space(if narrow {
+                                TOOLBAR_SPACING
+                            } else {
+                                header_width * 0.4
+                            });
+                            if ui.button("◀").clicked() {
+                                tool = Some(Command::PreviousPage);
+                            }
+                            ui.label(format!(
+                                "Page {} / {}",
+                                self.board.current_page + 1,
+                                self.board.pages.len()
+                            ));
+                            if ui.button("▶").clicked() {
+                                tool = Some(Command::NextPage);
+                            }
+                            if ui.button("+").on_hover_text("New page").clicked() {
+                                tool = Some(Command::NewPage);
+                            }
+                            if ui.button("Summary").on_hover_text("Summary page").clicked() {
+                                tool = Some(Command::SummaryPage);
+                            }
+                            ui.menu_button("Pages", |ui| {
+                                let current = self.board.current_page;
+                                ui.horizontal(|ui| {
+                                    ui.label("Name");
+                                    ui.add(
+                                        egui::TextEdit::singleline(&mut label.name)
+                                            .hint_text(format!("Page {}", current + 1))
+                                            .desired_width(140.0),
+                                    );
+                                });
+                                ui.horizontal_wrapped(|ui| {
+                                    let mut removed = None;
+                                    for (index, tag) in label.tags.iter().enumerate() {
+                                        let chip = egui::Button::new(
+                                            egui::RichText::new(format!("{} ×", tag.name))
+                                                .color(Color32::WHITE),
+                                        )
+                                        .fill(tag_color(tag.color));
+                                        if ui.add(chip).on_hover_text("Remove tag").clicked() {
+                                            removed = Some(index);
+                                        }
+                                    }
+                                    if let Some(index) = removed {
+                                        label.tags.remove(index);
+                                    }
+                                });
+                                ui.horizontal(|ui| {
+                                    ui.add(
+                                        egui::TextEdit::singleline(&mut new_page_tag)
+                                            .hint_text("New tag")
+                                            .desired_width(90.0),
+                                    );
+                                    let name = new_page_tag.trim().to_string();
+                                    for color in TAG_COLORS {
+                                        let swatch = egui::Button::new("")
+                                            .fill(tag_color(color))
+                                            .min_size(egui::vec2(16.0, 16.0));
+                                        let enabled = !name.is_empty() && !label.has_tag(&name);
+                                        if ui.add_enabled(enabled, swatch).clicked() {
+                                            label.tags.push(PageTag {
+                                                name: name.clone(),
+                                                color,
+                                            });
+                                            new_page_tag.clear();
+                                        }
+                                    }
+                                });
+                                ui.separator();
+                                let tags = pages::tags(&labels);
+                                egui::ComboBox::from_label("Show")
+                                    .selected_text(page_filter.as_deref().unwrap_or("All pages"))
+                                    .show_ui(ui, |ui| {
+                                        ui.selectable_value(&mut page_filter, None, "All pages");
+                                        for tag in &tags {
+                                            ui.selectable_value(
+                                                &mut page_filter,
+                                                Some(tag.name.clone()),
+                                                &tag.name,
+                                            );
+                                        }
+                                    });
+                                for (index, page_label) in labels.iter().enumerate() {
+                                    if page_filter
+                                        .as_ref()
+                                        .is_some_and(|tag| !page_label.has_tag(tag))
+                                    {
+                                        continue;
+                                    }
+                                    if page_entry(ui, index, page_label, index == current).clicked()
+                                    {
+                                        go_to_page = Some(index);
+                                        ui.close_menu();
+                                    }
+                                }
+                                ui.separator();
+                                if ui.button("Find page…").clicked() {
+                                    tool = Some(Command::FindPage);
+                                    ui.close_menu();
+                                }
+                            })
+                            .response
+                            .on_hover_text("Name, tag and list pages");
+                            ui.menu_button("Flipbook", |ui| {
+                                let label = match self.flipbook {
+                                    Some(_) => "⏹ Stop",
+                                    None => "▶ Play pages",
+                                };
+                                if ui.button(label).clicked() {
+                                    tool = Some(Command::Flipbook);
+                                    ui.close_menu();
+                                }
+                                ui.horizontal(|ui| {
+                                    ui.label("Speed");
+                                    if ui
+                                        .add(
+                                            egui::DragValue::new(&mut flipbook_fps)
+                                                .range(1..=60)
+                                                .suffix(" fps"),
+                                        )
+                                        .changed()
+                                    {
+                                        self.panels_dirty = true;
+                                    }
+                                });
+                                if ui.button("Export GIF").clicked() {
+                                    tool = Some(Command::ExportGif);
+                                    ui.close_menu();
+                                }
+                            })
+                            .response
+                            .on_hover_text("Play the pages as the frames of an animation");
+                            ui.menu_button("Ghost", |ui| {
+                                ui.radio_value(&mut onion_page, None, "None");
+                                for (index, page_label) in labels.iter().enumerate() {
+                                    if index != self.board.current_page {
+                                        let title = page_label.title(index);
+                                        ui.radio_value(&mut onion_page, Some(index), title);
+                                    }
+                                }
+                            })
+                            .response
+                            .on_hover_text("Show another page faded under this one");
+                            ui.menu_button("Split", |ui| {
+                                ui.radio_value(&mut split_page, None, "None");
+                                for (index, page_label) in labels.iter().enumerate() {
+                                    let title = page_label.title(index);
+                                    ui.radio_value(&mut split_page, Some(index), title);
+                                }
+                            })
+                            .response
+                            .on_hover_text("Show a page in the right half of the window");
+                            ui.add_space(spacing);
+
+                            let prev = ImageButton::new(Image::new(self.prev.clone())).frame(false);
+                            let prev_button = ui.add(prev);
+                            if prev_button.clicked() {
+                                undo = true;
+                            }
+                            ui.add_space(spacing);
+
+                            if ui.selectable_label(self.selecting, "Select").clicked() {
+                                tool = Some(Command::ToggleSelect);
+                            }
+                            ui.add_space(spacing);
+                            if ui.selectable_label(self.erasing, "Eraser").clicked() {
+                                tool = Some(Command::ToggleEraser);
+                            }
+                            ui.add_space(spacing);
+                            let highlighter = self.brush == Brush::Highlighter;
+                            if ui.selectable_label(highlighter, "Highlighter").clicked() {
+                                tool = Some(Command::ToggleHighlighter);
+                            }
+                            ui.add_space(spacing);
+                            if ui.selectable_label(self.bucket, "Bucket").clicked() {
+                                tool = Some(Command::PaintBucket);
+                            }
+                            ui.add_space(spacing);
+                            let calligraphy = self.brush == Brush::Calligraphy;
+                            if ui.selectable_label(calligraphy, "Calligraphy").clicked() {
+                                tool = Some(Command::ToggleCalligraphy);
+                            }
+                            ui.add_space(spacing);
+                            if ui.selectable_label(self.laser.is_some(), "Laser").clicked() {
+                                tool = Some(Command::LaserPointer);
+                            }
+                            ui.add_space(spacing);
+
+                            let sqaure =
+                                ImageButton::new(Image::new(self.rect.clone())).frame(false);
+                            let sqaure_button = ui.add(sqaure);
+                            if sqaure_button.clicked() {
+                                self.create_rect = true;
+                            }
+                            ui.add_space(spacing);
+
+                            let font = ImageButton::new(Image::new(self.font.clone())).frame(false);
+                            let font_button = ui.add(font);
+                            if font_button.clicked() {
+                                self.settings.panels.fonts.open = true;
+                                self.panels_dirty = true;
+                                self.egui_context.request_repaint();
+                                self.window.request_redraw();
+                            }
+
+                            ui.add_space(spacing);
+
+                            let color_picker =
+                                ImageButton::new(Image::new(self.color.clone())).frame(false);
+                            let color_picker_button = ui.add(color_picker);
+                            if color_picker_button.clicked() {
+                                self.settings.panels.colors.open = true;
+                                self.panels_dirty = true;
+                                self.egui_context.request_repaint();
+                                self.window.request_redraw();
+                            }
+
+                            ui.add_space(spacing);
+
+                            let mut open_stencils = false;
+                            let mut open_icons = false;
+                            let mut open_layers = false;
+                            let mut project_mode = None;
+                            if narrow {
+                                ui.menu_button("⋯", |ui| {
+                                    if ui.button("Save").clicked() {
+                                        project_mode = Some(ProjectMode::Save);
+                                        ui.close_menu();
+                                    }
+                                    if ui.button("Open").clicked() {
+                                        project_mode = Some(ProjectMode::Open);
+                                        ui.close_menu();
+                                    }
+                                    if ui.button("Export PDF").clicked() {
+                                        project_mode = Some(ProjectMode::ExportPdf);
+                                        ui.close_menu();
+                                    }
+                                    if ui.button("Export SVG").clicked() {
+                                        project_mode = Some(ProjectMode::ExportSvg);
+                                        ui.close_menu();
+                                    }
+                                    if ui.button("Export HTML viewer").clicked() {
+                                        project_mode = Some(ProjectMode::ExportHtml);
+                                        ui.close_menu();
+                                    }
+                                    if ui.button("Export flipbook GIF").clicked() {
+                                        project_mode = Some(ProjectMode::ExportGif);
+                                        ui.close_menu();
+                                    }
+                                    if ui.button("Stencils").clicked() {
+                                        open_stencils = true;
+                                        ui.close_menu();
+                                    }
+                                    if ui.button("Icons").clicked() {
+                                        open_icons = true;
+                                        ui.close_menu();
+                                    }
+                                    if ui.button("Layers").clicked() {
+                                        open_layers = true;
+                                        ui.close_menu();
+                                    }
+                                    ui.menu_button("Background", |ui| {
+                                        background::menu(ui, &mut background);
+                                    });
+                                });
+                            } else {
+                                if ui.button("Save").clicked() {
+                                    project_mode = Some(ProjectMode::Save);
+                                }
+                                ui.add_space(spacing);
+                                if ui.button("Open").clicked() {
+                                    project_mode = Some(ProjectMode::Open);
+                                }
+                                ui.add_space(spacing);
+                                ui.menu_button("Export", |ui| {
+                                    if ui.button("PDF").clicked() {
+                                        project_mode = Some(ProjectMode::ExportPdf);
+                                        ui.close_menu();
+                                    }
+                                    if ui.button("SVG (this page)").clicked() {
+                                        project_mode = Some(ProjectMode::ExportSvg);
+                                        ui.close_menu();
+                                    }
+                                    if ui.button("HTML viewer").clicked() {
+                                        project_mode = Some(ProjectMode::ExportHtml);
+                                        ui.close_menu();
+                                    }
+                                    if ui.button("Flipbook GIF").clicked() {
+                                        project_mode = Some(ProjectMode::ExportGif);
+                                        ui.close_menu();
+                                    }
+                                });
+                                ui.add_space(spacing);
+                                open_stencils = ui.button("Stencils").clicked();
+                                ui.add_space(spacing);
+                                open_icons = ui.button("Icons").clicked();
+                                ui.add_space(spacing);
+                                open_layers = ui.button("Layers").clicked();
+                                ui.add_space(spacing);
+                                ui.menu_button("Background", |ui| {
+                                    background::menu(ui, &mut background);
+                                });
+                            }
+                            if let Some(mode) = project_mode {
+                                self.project_dialog =
+                                    Some(ProjectDialog::new(mode, self.project_path.as_deref()));
+                                self.window.request_redraw();
+                            }
+                            if open_stencils {
+                                self.settings.panels.stencils.open = true;
+                                self.panels_dirty = true;
+                                self.window.request_redraw();
+                            }
+                            if open_icons {
+                                self.settings.panels.icons.open = true;
+                                self.panels_dirty = true;
+                                self.window.request_redraw();
+                            }
+                            if open_layers {
+                                self.settings.panels.layers.open = true;
+                                self.panels_dirty = true;
+                                self.window.request_redraw();
+                            }
+                        });
+
+                        ui.add_space(10.0);
+                    });
+                });
+            });
+
+        if undo {
+            self.undo();
+            self.window.request_redraw();
+        }
+        if background != self.settings.background {
+            self.set_background(background);
+            self.window.request_redraw();
+        }
+        if self.settings.edge_arrows {
+            let last = self.board.pages.len().saturating_sub(1);
+            for (align, label, command, enabled) in [
+                (
+                    Align2::LEFT_CENTER,
+                    "‹",
+                    Command::PreviousPage,
+                    self.board.current_page > 0,
+                ),
+                (
+                    Align2::RIGHT_CENTER,
+                    "›",
+                    Command::NextPage,
+                    self.board.current_page < last,
+                ),
+            ] {
+                egui::Area::new(egui::Id::new(("Edge arrow", label)))
+                    .anchor(align, [0.0, 0.0])
+                    .order(egui::Order::Foreground)
+                    .show(&self.egui_context, |ui| {
+                        let arrow =
+                            egui::Button::new(egui::RichText::new(label).size(EDGE_ARROW_SIZE))
+                                .frame(false);
+                        if ui.add_enabled(enabled, arrow).clicked() {
+                            tool = Some(command);
+                        }
+                    });
+            }
+        }
+        if let Some((started, forward)) = self.page_turn {
+            let progress = started.elapsed().as_secs_f32() / PAGE_TURN_DURATION.as_secs_f32();
+            if progress < 1.0 {
+                // The page left behind slides off and fades, uncovering the new
+                // one.
+                let screen = self.egui_context.screen_rect();
+                let direction = if forward { -1.0 } else { 1.0 };
+                let [r, g, b, _] = self.settings.background.color;
+                let alpha = ((1.0 - progress) * PAGE_TURN_ALPHA * 255.0) as u8;
+                self.egui_context
+                    .layer_painter(egui::LayerId::new(
+                        egui::Order::Background,
+                        egui::Id::new("Page turn"),
+                    ))
+                    .rect_filled(
+                        screen.translate(egui::vec2(screen.width() * progress * direction, 0.0)),
+                        0.0,
+                        Color32::from_rgba_unmultiplied(r, g, b, alpha),
+                    );
+            } else {
+                self.page_turn = None;
+            }
+        }
+        if split_page != self.split.as_ref().map(|split| split.page) {
+            self.split = split_page.map(|page| Split::new(page, self.size));
+            self.window.request_redraw();
+        }
+        if let Some(split) = &mut self.split {
+            let pixels_per_point = self.egui_context.pixels_per_point();
+            // The right half takes the pointer, so dragging or scrolling there
+            // moves its own camera and never draws on the board.
+            let divider = split::divider(self.size) as f32 / pixels_per_point;
+            let top = header.response.rect.bottom();
+            let rect = egui::Rect::from_min_max(
+                egui::pos2(divider, top),
+                egui::pos2(
+                    self.size.width as f32 / pixels_per_point,
+                    self.size.height as f32 / pixels_per_point,
+                ),
+            );
+            egui::Area::new("Split".into())
+                .fixed_pos(rect.min)
+                .movable(false)
+                .order(egui::Order::Background)
+                .show(&self.egui_context, |ui| {
+                    let response = ui.allocate_rect(
+                        egui::Rect::from_min_size(rect.min, rect.size()),
+                        egui::Sense::drag(),
+                    );
+                    let (scroll, zoom) =
+                        ui.input(|input| (input.smooth_scroll_delta, input.zoom_delta()));
+                    let mut moved = response.drag_delta() + scroll;
+                    if !response.hovered() {
+                        moved = egui::Vec2::ZERO;
+                    }
+                    if moved != egui::Vec2::ZERO {
+                        split
+                            .camera
+                            .scroll(moved.x * pixels_per_point, moved.y * pixels_per_point);
+                        self.window.request_redraw();
+                    }
+                    if let Some(pointer) = response.hover_pos().filter(|_| zoom != 1.0) {
+                        let center = pointer * pixels_per_point;
+                        split.camera.zoom_at([center.x, center.y], zoom);
+                        self.window.request_redraw();
+                    }
+                    ui.painter()
+                        .vline(divider, rect.y_range(), egui::Stroke::new(1.0, menu_color));
+                    ui.painter().text(
+                        rect.min + egui::vec2(8.0, 8.0),
+                        Align2::LEFT_TOP,
+                        labels
+                            .get(split.page)
+                            .map_or_else(String::new, |label| label.title(split.page)),
+                        egui::FontId::proportional(14.0),
+                        ui.visuals().weak_text_color(),
+                    );
+                });
+        }
+        if onion_page != self.onion_page {
+            self.onion_page = onion_page;
+            self.onion_vertices = None;
+            self.window.request_redraw();
+        }
+        self.settings.flipbook_fps = flipbook_fps;
+        self.page_filter = page_filter;
+        self.new_page_tag = new_page_tag;
+        if label != self.board.label {
+            // Typing a name would otherwise fill the history one letter at a time.
+            if label.tags == self.board.label.tags {
+                self.board.label = label;
+            } else {
+                self.board.edit_label(label);
+            }
+        }
+
+        let mut search = self.page_search.take();
+        let mut open = search.is_some();
+        if let Some(query) = &mut search {
+            egui::Window::new("Find page")
+                .collapsible(false)
+                .order(egui::Order::Foreground)
+                .resizable(false)
+                .open(&mut open)
+                .anchor(Align2::CENTER_TOP, [0.0, 80.0])
+                .show(&self.egui_context, |ui| {
+                    let field = ui.add(
+                        egui::TextEdit::singleline(query)
+                            .hint_text("Page name or tag")
+                            .desired_width(240.0),
+                    );
+                    field.request_focus();
+                    let found: Vec<usize> = (0..labels.len())
+                        .filter(|&index| labels[index].matches(index, query))
+                        .collect();
+                    if found.is_empty() {
+                        ui.label("No matching pages");
+                    }
+                    for &index in &found {
+                        let current = index == self.board.current_page;
+                        if page_entry(ui, index, &labels[index], current).clicked() {
+                            go_to_page = Some(index);
+                        }
+                    }
+                    // Enter goes to the first match.
+                    if ui.input(|input| input.key_pressed(egui::Key::Enter)) {
+                        go_to_page = found.first().copied().or(go_to_page);
+                    }
+                });
+        }
+        if open && go_to_page.is_none() {
+            self.page_search = search;
+        }
+        if let Some(index) = go_to_page {
+            self.show_page(index);
+            self.window.request_redraw();
+        }
+        if let Some(command) = tool {
+            self.run_command(command);
+        }
+    }
+
+    // The pen follows a switch between light and dark backgrounds while it
+    // still has its default black or white ink.
+    pub(crate) fn set_background(&mut self, background: Background) {
+        self.settings.background = background;
+        let ink = normalized_to_rgba(self.current_color);
+        if ink == [0, 0, 0, 255] || ink == [255, 255, 255, 255] {
+            self.current_color = rgba_to_normalized(contrasting_color(background.color));
+        }
+        self.damage = Damage::Full;
+        self.panels_dirty = true;
+    }
+
+    fn export_backdrop(&self) -> Backdrop {
+        Backdrop {
+            background: self.settings.background,
+            pattern: self.settings.export_pattern,
+            border: self.settings.export_border,
+        }
+    }
+}
+
+fn tag_color([r, g, b, a]: [u8; 4]) -> Color32 {
+    Color32::from_rgba_unmultiplied(r, g, b, a)
+}
+
+// A page's title followed by a dot in each of its tags' colors.
+fn page_entry(ui: &mut egui::Ui, index: usize, label: &PageLabel, current: bool) -> egui::Response {
+    ui.horizontal(|ui| {
+        let response = ui.selectable_label(current, label.title(index));
+        for tag in &label.tags {
+            ui.colored_label(tag_color(tag.color), "●")
+                .on_hover_text(&tag.name);
+        }
+        response
+    })
+    .inner
+}